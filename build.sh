@@ -1,4 +1,5 @@
 #!/bin/bash
 cargo clean
-cargo build --release --workspace
+cargo build --release --workspace --exclude fs-ffi --exclude fs-py
+cargo build --profile release-lib -p fs-ffi -p fs-py
 7z a ./build/fs-utils-windows-x64.zip ./target/release/*.exe ./bin
\ No newline at end of file
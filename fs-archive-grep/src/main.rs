@@ -0,0 +1,142 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    archive::ArchiveFs,
+    codec,
+    report::{self, Summary},
+};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use regex::bytes::{Regex, RegexBuilder};
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Search .gar/.dlc archive entries without extracting, strings inside .l64
+/// bytecode are searched too
+struct Cmd {
+    /// case insensitive search
+    #[argh(switch, short = 'i')]
+    ignore_case: bool,
+
+    /// pattern is a plain string, not a regular expression
+    #[argh(switch, short = 'F')]
+    fixed_strings: bool,
+
+    /// only search entries with this extension, can be repeated
+    #[argh(option, short = 'e')]
+    extension: Vec<String>,
+
+    /// search entry names only, entries are not read
+    #[argh(switch, short = 'n')]
+    names: bool,
+
+    /// search pattern
+    #[argh(positional)]
+    pattern: String,
+
+    /// path to .gar/.dlc archives
+    #[argh(positional)]
+    archives: Vec<PathBuf>,
+}
+
+/// Number of matches in the entry data, or in the decoded string table for
+/// Luau bytecode
+fn count_matches(regex: &Regex, name: &str, data: &[u8]) -> usize {
+    if name.ends_with(".l64")
+        && let Ok(strings) = codec::decode_string_table(data)
+    {
+        return strings
+            .iter()
+            .map(|(_, string)| regex.find_iter(string).count())
+            .sum();
+    }
+
+    regex.find_iter(data).count()
+}
+
+fn search_archive(cli: &Cmd, regex: &Regex, input: &PathBuf) -> Result<usize> {
+    let archive = ArchiveFs::open(input)?;
+
+    let files: Vec<&str> = archive
+        .list_files("", true)
+        .into_iter()
+        .filter(|name| {
+            cli.extension.is_empty()
+                || cli
+                    .extension
+                    .iter()
+                    .any(|ext| name.ends_with(&format!(".{}", ext)))
+        })
+        .collect();
+
+    let mut matches: Vec<(&str, usize)> = match cli.names {
+        true => files
+            .into_iter()
+            .filter(|name| regex.is_match(name.as_bytes()))
+            .map(|name| (name, 0))
+            .collect(),
+        false => files
+            .into_par_iter()
+            .filter_map(|name| match archive.read(name) {
+                Ok(data) => match count_matches(regex, name, &data) {
+                    0 => None,
+                    count => Some((name, count)),
+                },
+                Err(e) => {
+                    eprintln!("{}: {}: {}", input.display(), name, e);
+                    None
+                }
+            })
+            .collect(),
+    };
+
+    matches.sort();
+
+    for (name, count) in matches.iter() {
+        match cli.names {
+            true => println!("{}: {}", input.display(), name),
+            false => println!("{}: {} ({} matches)", input.display(), name, count),
+        }
+    }
+
+    Ok(matches.len())
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    if cli.archives.is_empty() {
+        bail!(report::usage("No archives given"));
+    }
+
+    let pattern = match cli.fixed_strings {
+        true => regex::escape(&cli.pattern),
+        false => cli.pattern.clone(),
+    };
+
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(cli.ignore_case)
+        .build()
+        .map_err(|e| report::usage(e.to_string()))?;
+
+    let mut entry_count = 0;
+
+    for input in cli.archives.iter() {
+        match search_archive(&cli, &regex, input) {
+            Ok(count) => {
+                entry_count += count;
+                summary.processed();
+            }
+            Err(e) => summary.error(input.display(), &e),
+        }
+    }
+
+    println!("\n{} matching entries", entry_count);
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
@@ -0,0 +1,260 @@
+use std::{
+    env::consts::EXE_SUFFIX,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    BYTECODE_EXTENSIONS,
+    buffer::BufferExtension,
+    cache::Cache,
+    cmd::{CommandLimits, run_command_output},
+    codec::{self, Generation},
+    deny::DenyList,
+    has_extension, list_input_files,
+    path::{OutputMapper, check_unique_outputs, is_glob_pattern},
+    report::{self, Summary},
+};
+use rayon::{
+    ThreadPoolBuilder,
+    iter::{IntoParallelIterator, ParallelIterator},
+};
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Decompile LuaJIT and Luau .l64 bytecode files, each file is passed to
+/// fs-luajit-decompile or fs-luau-decompile by its header
+pub struct Cmd {
+    /// recursive mode if folder input
+    #[argh(switch, short = 'r')]
+    recursive: bool,
+
+    /// enter directory symlinks and junctions in recursive mode
+    #[argh(switch)]
+    follow_symlinks: bool,
+
+    /// suppress output
+    #[argh(switch, short = 's')]
+    silent: bool,
+
+    /// set thread pool size when processing folders (0 = auto)
+    #[argh(option, default = "0")]
+    num_threads: u8,
+
+    /// process all files, even if unchanged since previous run
+    #[argh(switch, short = 'f')]
+    force: bool,
+
+    /// print planned input -> output mapping and the tool of each file
+    /// without processing files
+    #[argh(switch)]
+    dry_run: bool,
+
+    /// extension of decompiled files (default lua)
+    #[argh(option, default = "String::from(\"lua\")")]
+    output_ext: String,
+
+    /// option passed to fs-luau-decompile, can be repeated, e.g.
+    /// --luau-arg --deobfuscate
+    #[argh(option)]
+    luau_arg: Vec<String>,
+
+    /// option passed to fs-luajit-decompile, can be repeated
+    #[argh(option)]
+    luajit_arg: Vec<String>,
+
+    /// kill a decompiler that runs longer than this many seconds and fail
+    /// the file (default 0 = no limit)
+    #[argh(option, default = "0")]
+    timeout: u64,
+
+    /// kill a decompiler that prints more than this many MiB and fail the
+    /// file (default 0 = no limit)
+    #[argh(option, default = "0")]
+    max_output: usize,
+
+    /// path to input file/folder or glob pattern
+    #[argh(positional)]
+    input: PathBuf,
+
+    /// path to output file/folder (optional)
+    #[argh(positional)]
+    output: Option<PathBuf>,
+}
+
+impl Cmd {
+    fn tool_args(&self, generation: Generation) -> &[String] {
+        match generation {
+            Generation::LuaJit => &self.luajit_arg,
+            Generation::Luau => &self.luau_arg,
+        }
+    }
+}
+
+/// Decompiled bytecode files are written with --output-ext
+fn output_extension<P: AsRef<Path>>(cli: &Cmd, file: P) -> Option<&str> {
+    has_extension(file, BYTECODE_EXTENSIONS).then_some(&cli.output_ext)
+}
+
+fn detect_generation(file: &Path) -> Result<Generation> {
+    match codec::identify(&Vec::read_from_file(file)?, &[]) {
+        Some(info) => Ok(info.generation),
+        None => bail!("Unsupported/unknown bytecode"),
+    }
+}
+
+/// Messages printed by a tool on stderr, without the summary line
+fn tool_messages(stderr: &[String]) -> Vec<&str> {
+    stderr
+        .iter()
+        .filter(|line| !line.starts_with("Summary: "))
+        .map(|line| line.strip_prefix("Error: ").unwrap_or(line))
+        .collect()
+}
+
+/// Runs the decompiler of a file on it, errors printed by the tool are
+/// returned as the error and warnings are passed on to stderr
+fn decompile(cli: &Cmd, generation: Generation, file: &Path, output_file: &Path) -> Result<()> {
+    let tool = generation.decompiler();
+
+    let mut args: Vec<OsString> = cli.tool_args(generation).iter().map(Into::into).collect();
+    args.extend(["-s".into(), file.into(), output_file.into()]);
+
+    let limits = CommandLimits::new(cli.timeout, cli.max_output);
+    let output = run_command_output(&format!("{}{}", tool, EXE_SUFFIX), args, &limits)?;
+    let stderr = output.stderr_lines();
+    let messages = tool_messages(&stderr);
+
+    if output.code == Some(0) {
+        for message in messages {
+            eprintln!("{}: {}", file.display(), message);
+        }
+
+        return Ok(());
+    }
+
+    match (messages.is_empty(), output.code) {
+        (true, Some(code)) => bail!("{} exited with code {}", tool, code),
+        (true, None) => bail!("{} was terminated", tool),
+        (false, _) => bail!("{}: {}", tool, messages.join("; ")),
+    }
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+    let deny = DenyList::load()?;
+
+    if !cli.input.is_dir() && !is_glob_pattern(&cli.input) {
+        let output_file = match &cli.output {
+            Some(output) => output.clone(),
+            None => cli.input.with_extension(&cli.output_ext),
+        };
+
+        if deny.check(&cli.input, &summary) {
+            return Ok(summary);
+        }
+
+        let generation = detect_generation(&cli.input)?;
+
+        if cli.dry_run {
+            println!(
+                "{} -> {} ({})",
+                cli.input.display(),
+                output_file.display(),
+                generation.decompiler()
+            );
+            summary.processed();
+            return Ok(summary);
+        }
+
+        decompile(&cli, generation, &cli.input, &output_file)?;
+
+        if !cli.silent {
+            println!("{}", output_file.display());
+        }
+
+        summary.processed();
+        return Ok(summary);
+    }
+
+    let (input_path, files) = list_input_files(
+        &cli.input,
+        BYTECODE_EXTENSIONS,
+        cli.recursive,
+        cli.follow_symlinks,
+    )?;
+    let files = deny.filter(files, &summary);
+    let output_path = cli.output.clone().unwrap_or_else(|| input_path.clone());
+
+    if output_path.is_file() {
+        bail!(report::usage("Output path is a file"))
+    }
+
+    ThreadPoolBuilder::new()
+        .num_threads(cli.num_threads.into())
+        .build_global()
+        .unwrap();
+
+    let mapper = OutputMapper::new(&input_path, &output_path, None)?;
+    let get_output_file = |file: &PathBuf| mapper.map(file, output_extension(&cli, file));
+
+    check_unique_outputs(&files, get_output_file)?;
+
+    let cache_options = format!(
+        "luau_arg={:?},luajit_arg={:?}",
+        cli.luau_arg, cli.luajit_arg
+    );
+    let cache = Cache::load(&output_path, "fs-decompile", &cache_options, cli.force);
+
+    let process_entry = |file: &PathBuf| -> Result<Vec<String>> {
+        let output_file = get_output_file(file)?;
+
+        if !cli.dry_run && cache.is_fresh(file, &output_file)? {
+            summary.skipped();
+            return Ok(Vec::new());
+        }
+
+        let generation = detect_generation(file)?;
+        let line = format!(
+            "{} -> {} ({})",
+            file.display(),
+            output_file.display(),
+            generation.decompiler()
+        );
+
+        if !cli.dry_run {
+            decompile(&cli, generation, file, &output_file)?;
+            cache.update(file, &output_file)?;
+        }
+
+        summary.processed();
+
+        match cli.silent && !cli.dry_run {
+            true => Ok(Vec::new()),
+            false => Ok(vec![line]),
+        }
+    };
+
+    let outputs: Vec<_> = files
+        .into_par_iter()
+        .map(|file| {
+            let output = process_entry(&file);
+            (file.display().to_string(), output)
+        })
+        .collect();
+
+    summary.print_outputs(outputs);
+
+    if !cli.dry_run {
+        cache.save()?;
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
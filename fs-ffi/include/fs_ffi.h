@@ -0,0 +1,50 @@
+/*
+ * C interface of fs_ffi (fs_ffi.dll / libfs_ffi.so)
+ *
+ * Functions return FS_OK or a non-negative count on success and a negative
+ * code on failure. fs_last_error() returns the message of the last failed
+ * call on the calling thread. Buffers returned through out/out_len are
+ * released with fs_free_buffer().
+ */
+#ifndef FS_FFI_H
+#define FS_FFI_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define FS_OK 0
+#define FS_ERROR -1
+#define FS_INVALID_ARGUMENT -2
+
+/* Copies the last error (NUL-terminated, truncated to len), returns its full length */
+size_t fs_last_error(char *buffer, size_t len);
+
+void fs_free_buffer(uint8_t *data, size_t len);
+
+/* Decodes a LuaJIT (FS22) or Luau (FS25) .l64 file into plain bytecode */
+int32_t fs_decode_l64(const uint8_t *data, size_t len, uint8_t **out, size_t *out_len);
+
+/* Unlocks a .i3d.shapes file in place, returns 1 if unlocked, 0 if it wasn't locked */
+int32_t fs_unlock_shapes(uint8_t *data, size_t len);
+
+/* Reads and decrypts one entry of a .gar/.dlc archive, e.g. "dataS/scripts/main.lua" */
+int32_t fs_extract_archive_entry(const char *archive, const char *entry, uint8_t **out,
+                                 size_t *out_len);
+
+/*
+ * Applies (or reverts) the patches of a platform ("steam" or "giants") to
+ * an executable. pattern_file is NULL for the built-in patterns. Returns the number of changed patches.
+ */
+int32_t fs_apply_patches(const char *executable, const char *platform, const char *pattern_file,
+                         bool revert);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
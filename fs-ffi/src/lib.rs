@@ -0,0 +1,295 @@
+//! C ABI for mod managers and other tools that can't call the command line
+//! tools, see `include/fs_ffi.h`.
+//!
+//! Functions return [`FS_OK`] or a non-negative count on success and a
+//! negative code on failure, the message is read with [`fs_last_error`].
+//! Buffers returned by the library are released with [`fs_free_buffer`].
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, c_char},
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    ptr, slice,
+};
+
+use anyhow::{Result, anyhow, bail};
+use fs_lib::{
+    Platform,
+    archive::ArchiveFs,
+    buffer::BufferExtension,
+    codec,
+    hash::{self, Algorithm},
+    pattern::{PatternFile, load_pattern_file, select_patterns},
+    shapes,
+};
+use fs_patch::pe::{code_sections, find_pattern};
+
+pub const FS_OK: i32 = 0;
+pub const FS_ERROR: i32 = -1;
+pub const FS_INVALID_ARGUMENT: i32 = -2;
+
+thread_local! {
+    static LAST_ERROR: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Runs a call, errors and panics are stored as the last error. Panics are
+/// only caught when built with the `release-lib` profile (or a debug build),
+/// the `release` profile aborts.
+fn call<F: FnOnce() -> Result<i32>>(f: F) -> i32 {
+    let result = panic::catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|_| Err(anyhow!("Internal error (panic)")));
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            let code = match e.downcast_ref::<InvalidArgument>() {
+                Some(_) => FS_INVALID_ARGUMENT,
+                None => FS_ERROR,
+            };
+
+            LAST_ERROR.with(|last| *last.borrow_mut() = format!("{:#}", e));
+            code
+        }
+    }
+}
+
+#[derive(Debug)]
+struct InvalidArgument(&'static str);
+
+impl std::fmt::Display for InvalidArgument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid argument: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidArgument {}
+
+/// # Safety
+/// `value` is null or a valid NUL-terminated string
+unsafe fn path_arg(value: *const c_char, name: &'static str) -> Result<PathBuf> {
+    Ok(PathBuf::from(unsafe { str_arg(value, name)? }))
+}
+
+/// # Safety
+/// `value` is null or a valid NUL-terminated string
+unsafe fn str_arg<'a>(value: *const c_char, name: &'static str) -> Result<&'a str> {
+    if value.is_null() {
+        bail!(InvalidArgument(name))
+    }
+
+    unsafe { CStr::from_ptr(value) }
+        .to_str()
+        .map_err(|_| InvalidArgument(name).into())
+}
+
+/// # Safety
+/// `data` is null or points to `len` readable bytes
+unsafe fn bytes_arg<'a>(data: *const u8, len: usize) -> Result<&'a [u8]> {
+    if data.is_null() {
+        bail!(InvalidArgument("data"))
+    }
+
+    Ok(unsafe { slice::from_raw_parts(data, len) })
+}
+
+/// Hands a buffer to the caller, released with [`fs_free_buffer`]
+///
+/// # Safety
+/// `out` and `out_len` are null or valid for writes
+unsafe fn output_buffer(buffer: Vec<u8>, out: *mut *mut u8, out_len: *mut usize) -> Result<i32> {
+    if out.is_null() || out_len.is_null() {
+        bail!(InvalidArgument("out"))
+    }
+
+    let buffer = buffer.into_boxed_slice();
+
+    unsafe {
+        *out_len = buffer.len();
+        *out = Box::into_raw(buffer) as *mut u8;
+    }
+
+    Ok(FS_OK)
+}
+
+/// Copies the message of the last failed call on this thread into `buffer`,
+/// NUL-terminated and truncated to `len`. Returns the full message length.
+///
+/// # Safety
+/// `buffer` is null or valid for `len` bytes of writes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fs_last_error(buffer: *mut c_char, len: usize) -> usize {
+    LAST_ERROR.with(|last| {
+        let last = last.borrow();
+
+        if !buffer.is_null() && len > 0 {
+            let count = last.len().min(len - 1);
+
+            unsafe {
+                ptr::copy_nonoverlapping(last.as_ptr(), buffer as *mut u8, count);
+                *buffer.add(count) = 0;
+            }
+        }
+
+        last.len()
+    })
+}
+
+/// Releases a buffer returned by the library
+///
+/// # Safety
+/// `data` and `len` were returned by this library and not freed before
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fs_free_buffer(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)) });
+    }
+}
+
+/// Decodes a LuaJIT (FS22) or Luau (FS25) `.l64` file into plain bytecode
+///
+/// # Safety
+/// `data` points to `len` readable bytes, `out` and `out_len` are valid for
+/// writes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fs_decode_l64(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut u8,
+    out_len: *mut usize,
+) -> i32 {
+    call(|| {
+        let mut buffer = unsafe { bytes_arg(data, len)? }.to_vec();
+
+        if codec::is_luajit(&buffer) {
+            if codec::is_luajit_encoded(&buffer) {
+                codec::decode_luajit(&mut buffer, &[])?;
+            }
+        } else {
+            let Some(header) = codec::detect(&buffer, &[]) else {
+                bail!("Unsupported/unknown bytecode")
+            };
+
+            codec::decode(&mut buffer, &header)?;
+        }
+
+        unsafe { output_buffer(buffer, out, out_len) }
+    })
+}
+
+/// Unlocks a `.i3d.shapes` file in place. Returns 1 if it was unlocked, 0 if
+/// it wasn't locked.
+///
+/// # Safety
+/// `data` points to `len` readable and writable bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fs_unlock_shapes(data: *mut u8, len: usize) -> i32 {
+    call(|| {
+        if data.is_null() {
+            bail!(InvalidArgument("data"))
+        }
+
+        let buffer = unsafe { slice::from_raw_parts_mut(data, len) };
+
+        if !shapes::is_locked(buffer)? {
+            return Ok(0);
+        }
+
+        shapes::unlock(buffer)?;
+
+        Ok(1)
+    })
+}
+
+/// Reads and decrypts one entry of a `.gar`/`.dlc` archive, e.g.
+/// `dataS/scripts/main.lua`
+///
+/// # Safety
+/// `archive` and `entry` are NUL-terminated UTF-8 strings, `out` and
+/// `out_len` are valid for writes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fs_extract_archive_entry(
+    archive: *const c_char,
+    entry: *const c_char,
+    out: *mut *mut u8,
+    out_len: *mut usize,
+) -> i32 {
+    call(|| {
+        let archive = ArchiveFs::open(unsafe { path_arg(archive, "archive")? })?;
+        let buffer = archive.read(unsafe { str_arg(entry, "entry")? })?;
+
+        unsafe { output_buffer(buffer, out, out_len) }
+    })
+}
+
+/// Applies (or reverts) the executable patches of a platform like fs-patch,
+/// searching the whole file. Patterns keyed by the executable hash are used
+/// if the pattern file has them. Returns the number of changed patches.
+///
+/// # Safety
+/// `executable` and `platform` are NUL-terminated UTF-8 strings,
+/// `pattern_file` is one too or null for the built-in patterns
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fs_apply_patches(
+    executable: *const c_char,
+    platform: *const c_char,
+    pattern_file: *const c_char,
+    revert: bool,
+) -> i32 {
+    call(|| {
+        let executable = unsafe { path_arg(executable, "executable")? };
+        let platform: Platform = unsafe { str_arg(platform, "platform")? }
+            .parse()
+            .map_err(|_| InvalidArgument("platform"))?;
+
+        let pattern_file = match pattern_file.is_null() {
+            true => PatternFile::builtin(),
+            false => load_pattern_file(unsafe { path_arg(pattern_file, "pattern_file")? })?,
+        };
+
+        let mut buffer = Vec::read_from_file(&executable)?;
+        let sha256 = hash::hash_buffer(&buffer, Algorithm::Sha256);
+
+        let items: Vec<_> = select_patterns(&pattern_file.patterns, &sha256)
+            .into_iter()
+            .filter(|item| item.platform == platform)
+            .collect();
+
+        if items.is_empty() {
+            bail!("No patch items found for platform {:?}", platform)
+        }
+
+        // Searched like fs-patch does, in the code sections of the PE image.
+        // Located first, so nothing is written if any pattern is missing.
+        let sections = code_sections(&buffer);
+        let mut changes = Vec::new();
+
+        for item in items {
+            let (from, to) = match revert {
+                true => (&item.replace, &item.find),
+                false => (&item.find, &item.replace),
+            };
+
+            let matches = find_pattern(&buffer, &sections, from);
+
+            match matches.as_slice() {
+                [(offset, _, captures)] => {
+                    changes.push((*offset, to.render(&buffer, *offset, captures)));
+                }
+                [] if !find_pattern(&buffer, &sections, to).is_empty() => {}
+                [] => bail!("No valid offsets found for patch {:?}", item.patch_type),
+                _ => bail!("Pattern for {:?} matches more than once", item.patch_type),
+            }
+        }
+
+        for (offset, bytes) in changes.iter() {
+            buffer.replace_bytes(bytes, *offset);
+        }
+
+        if !changes.is_empty() {
+            buffer.write_to_file(&executable)?;
+        }
+
+        Ok(changes.len() as i32)
+    })
+}
@@ -0,0 +1,196 @@
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::Result;
+use argh::FromArgs;
+use fs_lib::{
+    buffer::BufferExtension,
+    codec, list_files,
+    report::{self, Summary},
+};
+use regex::{Regex, RegexBuilder};
+
+/// Longest printed line, long lines are cut around the match
+const MAX_LINE_LENGTH: usize = 200;
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Search an unpacked tree, strings inside .l64 bytecode are searched too
+pub struct Cmd {
+    /// case insensitive search
+    #[argh(switch, short = 'i')]
+    ignore_case: bool,
+
+    /// pattern is a plain string, not a regular expression
+    #[argh(switch, short = 'F')]
+    fixed_strings: bool,
+
+    /// only print names of matching files
+    #[argh(switch, short = 'l')]
+    files_with_matches: bool,
+
+    /// file extension to search, can be repeated (default xml, lua, l64)
+    #[argh(option, short = 'e')]
+    extension: Vec<String>,
+
+    /// enter directory symlinks and junctions of the tree
+    #[argh(switch)]
+    follow_symlinks: bool,
+
+    /// search pattern
+    #[argh(positional)]
+    pattern: String,
+
+    /// path to unpacked tree or file
+    #[argh(positional)]
+    tree: PathBuf,
+}
+
+/// Location of a match, line for text files, string table entry for bytecode
+enum Location {
+    Line(usize),
+    String { index: usize, offset: usize },
+}
+
+fn shorten(text: &str, start: usize) -> &str {
+    let text = text.trim_end();
+
+    if text.len() <= MAX_LINE_LENGTH {
+        return text.trim_start();
+    }
+
+    let from = (0..=start.saturating_sub(MAX_LINE_LENGTH / 4))
+        .rev()
+        .find(|i| text.is_char_boundary(*i))
+        .unwrap_or(0);
+    let to = ((from + MAX_LINE_LENGTH).min(text.len())..=text.len())
+        .find(|i| text.is_char_boundary(*i))
+        .unwrap_or(text.len());
+
+    &text[from..to]
+}
+
+fn search_text<'a>(regex: &Regex, text: &'a str) -> Vec<(Location, &'a str)> {
+    text.lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let found = regex.find(line)?;
+
+            Some((Location::Line(index + 1), shorten(line, found.start())))
+        })
+        .collect()
+}
+
+/// Searches the string table of a Luau file
+fn search_bytecode(regex: &Regex, buffer: &[u8]) -> Result<Vec<(Location, String)>> {
+    let mut matches = Vec::new();
+
+    for (index, (offset, string)) in codec::decode_string_table(buffer)?.into_iter().enumerate() {
+        let string = String::from_utf8_lossy(&string);
+
+        if let Some(found) = regex.find(&string) {
+            matches.push((
+                Location::String {
+                    index: index + 1,
+                    offset,
+                },
+                format!("{:?}", shorten(&string, found.start())),
+            ));
+        }
+    }
+
+    Ok(matches)
+}
+
+fn search_file(cli: &Cmd, regex: &Regex, file: &Path) -> Result<usize> {
+    let buffer = Vec::read_from_file(file)?;
+
+    let matches: Vec<(Location, String)> = match file.extension().is_some_and(|ext| ext == "l64") {
+        true => search_bytecode(regex, &buffer)?,
+        false => search_text(regex, &String::from_utf8_lossy(&buffer))
+            .into_iter()
+            .map(|(location, line)| (location, line.to_string()))
+            .collect(),
+    };
+
+    if cli.files_with_matches {
+        if !matches.is_empty() {
+            println!("{}", file.display());
+        }
+
+        return Ok(matches.len());
+    }
+
+    for (location, text) in matches.iter() {
+        match location {
+            Location::Line(line) => println!("{}:{}: {}", file.display(), line, text),
+            Location::String { index, offset } => println!(
+                "{}:string {}@0x{:X}: {}",
+                file.display(),
+                index,
+                offset,
+                text
+            ),
+        }
+    }
+
+    Ok(matches.len())
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    let pattern = match cli.fixed_strings {
+        true => regex::escape(&cli.pattern),
+        false => cli.pattern.clone(),
+    };
+
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(cli.ignore_case)
+        .build()
+        .map_err(|e| report::usage(e.to_string()))?;
+
+    let extensions = match cli.extension.is_empty() {
+        true => vec!["xml".to_string(), "lua".to_string(), "l64".to_string()],
+        false => cli.extension.clone(),
+    };
+
+    let mut files = match cli.tree.is_file() {
+        true => vec![cli.tree.clone()],
+        false => list_files(&cli.tree, true, cli.follow_symlinks)?
+            .into_iter()
+            .filter(|file| {
+                file.extension()
+                    .is_some_and(|ext| extensions.iter().any(|e| ext == e.as_str()))
+            })
+            .collect(),
+    };
+    files.sort();
+
+    let mut match_count = 0;
+    let mut file_count = 0;
+
+    for file in files.iter() {
+        match search_file(&cli, &regex, file) {
+            Ok(0) => summary.processed(),
+            Ok(count) => {
+                match_count += count;
+                file_count += 1;
+                summary.processed();
+            }
+            Err(e) => summary.error(file.display(), &e),
+        }
+    }
+
+    if !cli.files_with_matches {
+        println!("\n{} matches in {} files", match_count, file_count);
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
@@ -0,0 +1,164 @@
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    buffer::BufferExtension,
+    hash::{self, Algorithm},
+    list_files,
+    report::{self, Summary},
+};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Hash a folder tree into a manifest, or verify a tree against one
+pub struct Cmd {
+    /// hash algorithm: sha256, md5, crc32 (default sha256)
+    #[argh(option, short = 'a', default = "Algorithm::Sha256")]
+    algorithm: Algorithm,
+
+    /// verify files against this manifest instead of writing one
+    #[argh(option, short = 'c')]
+    check: Option<PathBuf>,
+
+    /// only print failed files when verifying
+    #[argh(switch, short = 'q')]
+    quiet: bool,
+
+    /// enter directory symlinks and junctions of the folder
+    #[argh(switch)]
+    follow_symlinks: bool,
+
+    /// path to folder or file
+    #[argh(positional)]
+    input: PathBuf,
+
+    /// path to manifest file (optional, default stdout)
+    #[argh(positional)]
+    output: Option<PathBuf>,
+}
+
+/// Manifest line: `<hash>  <path>`, same layout as sha256sum
+fn format_line(hash: &str, name: &str) -> String {
+    format!("{}  {}\n", hash, name)
+}
+
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+    let (hash, name) = line.split_once("  ")?;
+
+    Some((hash.trim(), name.trim_end()))
+}
+
+/// Files of the input with names relative to the input folder
+fn input_files(input: &Path, follow_symlinks: bool) -> Result<Vec<(PathBuf, String)>> {
+    if input.is_file() {
+        let name = input.file_name().unwrap_or_default().to_string_lossy();
+
+        return Ok(vec![(input.to_path_buf(), name.into_owned())]);
+    }
+
+    list_files(input, true, follow_symlinks)?
+        .into_iter()
+        .map(|file| {
+            let name = file
+                .strip_prefix(input)?
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            Ok((file, name))
+        })
+        .collect()
+}
+
+fn create_manifest(cli: &Cmd, summary: &Summary) -> Result<()> {
+    let files = input_files(&cli.input, cli.follow_symlinks)?;
+
+    let hashes: Vec<(String, Result<String>)> = files
+        .par_iter()
+        .map(|(file, name)| (name.clone(), hash::hash_file(file, cli.algorithm)))
+        .collect();
+
+    let mut manifest = String::new();
+
+    for (name, hash) in hashes {
+        match hash {
+            Ok(hash) => {
+                manifest.push_str(&format_line(&hash, &name));
+                summary.processed();
+            }
+            Err(e) => summary.error(&name, &e),
+        }
+    }
+
+    let output = cli.output.clone().unwrap_or_else(|| PathBuf::from("-"));
+
+    manifest.into_bytes().write_to_output(output)
+}
+
+fn verify_manifest(cli: &Cmd, manifest: &Path, summary: &Summary) -> Result<()> {
+    let manifest = String::from_utf8(Vec::read_from_file(manifest)?)?;
+    let base = match cli.input.is_file() {
+        true => cli.input.parent().unwrap_or(Path::new("")).to_path_buf(),
+        false => cli.input.clone(),
+    };
+
+    let entries: Vec<(&str, &str)> = manifest.lines().filter_map(parse_line).collect();
+
+    if entries.is_empty() {
+        bail!(report::usage("Manifest has no entries"))
+    }
+
+    let results: Vec<(&str, Result<String>)> = entries
+        .par_iter()
+        .map(|(_, name)| (*name, hash::hash_file(base.join(name), cli.algorithm)))
+        .collect();
+
+    for ((expected, name), (_, hash)) in entries.iter().zip(results) {
+        match hash {
+            Ok(hash) if hash.eq_ignore_ascii_case(expected) => {
+                if !cli.quiet {
+                    println!("{}: OK", name);
+                }
+
+                summary.processed();
+            }
+            Ok(_) => {
+                println!("{}: FAILED", name);
+                summary.failed();
+            }
+            Err(_) if !base.join(name).exists() => {
+                println!("{}: MISSING", name);
+                summary.failed();
+            }
+            Err(e) => summary.error(name, &e),
+        }
+    }
+
+    Ok(())
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    if !cli.input.exists() {
+        bail!(report::usage(format!(
+            "Input not found: {}",
+            cli.input.display()
+        )))
+    }
+
+    match &cli.check {
+        Some(manifest) => verify_manifest(&cli, manifest, &summary)?,
+        None => create_manifest(&cli, &summary)?,
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
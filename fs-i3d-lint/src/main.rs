@@ -0,0 +1,255 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    buffer::BufferExtension,
+    list_input_files,
+    report::{self, Summary},
+    xml::{self, Element},
+};
+
+/// (section, element, id attribute) checked for duplicate ids
+const ID_ATTRIBUTES: [(&str, &str, &str); 4] = [
+    ("Files", "File", "fileId"),
+    ("Materials", "Material", "materialId"),
+    ("Shapes", "", "shapeId"),
+    ("Scene", "", "nodeId"),
+];
+
+/// Material attributes and child elements referencing a file id
+const FILE_ID_REFERENCES: [&str; 2] = ["fileId", "customShaderId"];
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Check .i3d files for missing files, absolute paths and duplicate ids
+pub struct Cmd {
+    /// recursive mode if folder input
+    #[argh(switch, short = 'r')]
+    recursive: bool,
+
+    /// enter directory symlinks and junctions in recursive mode
+    #[argh(switch)]
+    follow_symlinks: bool,
+
+    /// only list files with issues
+    #[argh(switch, short = 'q')]
+    quiet: bool,
+
+    /// path to input file/folder or glob pattern
+    #[argh(positional)]
+    input: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Error,
+    Warning,
+}
+
+#[derive(Debug)]
+struct Issue {
+    level: Level,
+    line: u64,
+    message: String,
+}
+
+#[derive(Default)]
+struct Lint {
+    issues: Vec<Issue>,
+}
+
+impl Lint {
+    fn error(&mut self, line: u64, message: String) {
+        self.issues.push(Issue {
+            level: Level::Error,
+            line,
+            message,
+        });
+    }
+
+    fn warning(&mut self, line: u64, message: String) {
+        self.issues.push(Issue {
+            level: Level::Warning,
+            line,
+            message,
+        });
+    }
+}
+
+fn is_absolute(path: &str) -> bool {
+    path.starts_with('/') || path.starts_with('\\') || path.as_bytes().get(1) == Some(&b':')
+}
+
+/// A referenced .png is loaded as .dds when that exists
+fn file_exists(dir: &Path, path: &str) -> bool {
+    let file = dir.join(path.replace('\\', "/"));
+
+    file.is_file() || file.with_extension("dds").is_file()
+}
+
+fn check_path(lint: &mut Lint, dir: &Path, line: u64, path: &str) {
+    // $data points into the game folder
+    if path.is_empty() || path.starts_with('$') {
+        return;
+    }
+
+    if is_absolute(path) {
+        lint.error(line, format!("absolute path: {}", path));
+    } else if !file_exists(dir, path) {
+        lint.error(line, format!("missing file: {}", path));
+    }
+}
+
+fn check_files(lint: &mut Lint, root: &Element, dir: &Path) {
+    for file in root
+        .children("Files")
+        .flat_map(|files| files.children("File"))
+    {
+        match file.attr("filename") {
+            Some(filename) => check_path(lint, dir, file.line, filename),
+            None => lint.warning(file.line, "<File> without filename".to_string()),
+        }
+    }
+
+    for shapes in root.children("Shapes") {
+        if let Some(filename) = shapes.attr("externalShapesFile") {
+            check_path(lint, dir, shapes.line, filename);
+        }
+    }
+}
+
+fn check_duplicate_ids(lint: &mut Lint, root: &Element) {
+    for (section, name, attribute) in ID_ATTRIBUTES {
+        let mut ids: BTreeMap<&str, u64> = BTreeMap::new();
+
+        for element in root
+            .children(section)
+            .flat_map(|section| section.descendants())
+            .filter(|element| name.is_empty() || element.name == name)
+        {
+            let Some(id) = element.attr(attribute) else {
+                continue;
+            };
+
+            if let Some(first_line) = ids.insert(id, element.line) {
+                lint.error(
+                    element.line,
+                    format!(
+                        "duplicate {} {} (first on line {})",
+                        attribute, id, first_line
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn check_file_ids(lint: &mut Lint, root: &Element) {
+    let file_ids: BTreeSet<&str> = root
+        .children("Files")
+        .flat_map(|files| files.children("File"))
+        .filter_map(|file| file.attr("fileId"))
+        .collect();
+
+    for material in root.children("Materials").flat_map(|m| m.descendants()) {
+        for attribute in FILE_ID_REFERENCES {
+            if let Some(id) = material.attr(attribute)
+                && !file_ids.contains(id)
+            {
+                lint.error(
+                    material.line,
+                    format!("<{}> {} {} not in <Files>", material.name, attribute, id),
+                );
+            }
+        }
+    }
+}
+
+fn lint_file(file: &Path) -> Result<Vec<Issue>> {
+    let mut lint = Lint::default();
+    let root = xml::parse(&Vec::read_from_file(file)?)?;
+    let dir = file.parent().unwrap_or(Path::new(""));
+
+    if root.name != "i3D" {
+        bail!("Root element is <{}>, expected <i3D>", root.name)
+    }
+
+    check_files(&mut lint, &root, dir);
+    check_duplicate_ids(&mut lint, &root);
+    check_file_ids(&mut lint, &root);
+
+    lint.issues.sort_by_key(|issue| (issue.level, issue.line));
+
+    Ok(lint.issues)
+}
+
+fn print_issues(name: &str, issues: &[Issue]) {
+    let errors = issues.iter().filter(|i| i.level == Level::Error).count();
+    let warnings = issues.len() - errors;
+
+    if issues.is_empty() {
+        println!("{}: ok", name);
+        return;
+    }
+
+    println!("{}: {} errors, {} warnings", name, errors, warnings);
+
+    for issue in issues {
+        let level = match issue.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        };
+
+        println!("  {:<8}{:>5}  {}", level, issue.line, issue.message);
+    }
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    let mut files = match cli.input.is_file() {
+        true => vec![cli.input.clone()],
+        false => list_input_files(&cli.input, "i3d", cli.recursive, cli.follow_symlinks)?.1,
+    };
+    files.sort();
+
+    if files.is_empty() {
+        bail!(report::usage(format!(
+            "No .i3d files found in {}",
+            cli.input.display()
+        )))
+    }
+
+    for file in files.iter() {
+        let issues = match lint_file(file) {
+            Ok(issues) => issues,
+            Err(e) => vec![Issue {
+                level: Level::Error,
+                line: 0,
+                message: format!("unable to parse: {}", e),
+            }],
+        };
+
+        let has_errors = issues.iter().any(|i| i.level == Level::Error);
+
+        if !cli.quiet || !issues.is_empty() {
+            print_issues(&file.display().to_string(), &issues);
+        }
+
+        match has_errors {
+            true => summary.failed(),
+            false => summary.processed(),
+        }
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
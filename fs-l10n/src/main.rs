@@ -0,0 +1,412 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    buffer::BufferExtension,
+    l10n::{self, BASE_LANGUAGE, Layout},
+    report::{self, Summary},
+    xml::{self, FormatOptions},
+};
+
+const MOD_DESC_FILE_NAME: &str = "modDesc.xml";
+
+/// Used when the modDesc has no filenamePrefix
+const DEFAULT_PREFIX: &str = "l10n";
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Check, merge and convert mod translation files
+pub struct Cmd {
+    /// language file prefix relative to the mod folder (default from
+    /// modDesc.xml, or l10n)
+    #[argh(option)]
+    prefix: Option<String>,
+
+    #[argh(subcommand)]
+    command: Command,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand)]
+enum Command {
+    Report(ReportCmd),
+    Merge(MergeCmd),
+    Export(ExportCmd),
+    Import(ImportCmd),
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// List keys missing in each language compared to English
+#[argh(subcommand, name = "report")]
+struct ReportCmd {
+    /// path to mod folder
+    #[argh(positional)]
+    mod_dir: PathBuf,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Merge a partial translation file into the mod
+#[argh(subcommand, name = "merge")]
+struct MergeCmd {
+    /// language of the file (default from file name, e.g. l10n_de.xml)
+    #[argh(option, short = 'l')]
+    language: Option<String>,
+
+    /// only add missing keys, keep existing translations
+    #[argh(switch, short = 'k')]
+    keep_existing: bool,
+
+    /// path to mod folder
+    #[argh(positional)]
+    mod_dir: PathBuf,
+
+    /// path to translation file
+    #[argh(positional)]
+    file: PathBuf,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Export all languages to a CSV file (key, en, ...)
+#[argh(subcommand, name = "export")]
+struct ExportCmd {
+    /// path to mod folder
+    #[argh(positional)]
+    mod_dir: PathBuf,
+
+    /// path to CSV file (- for stdout)
+    #[argh(positional)]
+    output: PathBuf,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Import a CSV file (key, en, ...), empty cells are skipped
+#[argh(subcommand, name = "import")]
+struct ImportCmd {
+    /// only add missing keys, keep existing translations
+    #[argh(switch, short = 'k')]
+    keep_existing: bool,
+
+    /// path to mod folder
+    #[argh(positional)]
+    mod_dir: PathBuf,
+
+    /// path to CSV file (- for stdin)
+    #[argh(positional)]
+    input: PathBuf,
+}
+
+struct Language {
+    file: PathBuf,
+    layout: Layout,
+    texts: Vec<(String, String)>,
+}
+
+/// Language files of a mod
+struct Translations {
+    mod_dir: PathBuf,
+    prefix: String,
+    languages: BTreeMap<String, Language>,
+}
+
+impl Translations {
+    fn load(mod_dir: &Path, prefix: Option<&String>) -> Result<Self> {
+        let prefix = match prefix {
+            Some(prefix) => prefix.clone(),
+            None => mod_prefix(mod_dir)?,
+        };
+
+        let mut languages = BTreeMap::new();
+
+        for (language, file) in l10n::language_files(mod_dir, &prefix)? {
+            let root = xml::parse(&Vec::read_from_file(&file)?)?;
+
+            languages.insert(
+                language,
+                Language {
+                    file,
+                    layout: Layout::detect(&root),
+                    texts: l10n::read_texts(&root, None),
+                },
+            );
+        }
+
+        Ok(Translations {
+            mod_dir: mod_dir.to_path_buf(),
+            prefix,
+            languages,
+        })
+    }
+
+    fn base(&self) -> Result<&Language> {
+        match self.languages.get(BASE_LANGUAGE) {
+            Some(base) => Ok(base),
+            None => bail!("No {}_{}.xml found", self.prefix, BASE_LANGUAGE),
+        }
+    }
+
+    /// Adds or updates entries, returns (added, updated)
+    fn merge(
+        &mut self,
+        language: &str,
+        texts: &[(String, String)],
+        keep_existing: bool,
+    ) -> (usize, usize) {
+        if !self.languages.contains_key(language) {
+            let layout = self
+                .languages
+                .get(BASE_LANGUAGE)
+                .map_or(Layout::Elements, |base| base.layout);
+            let file = self
+                .mod_dir
+                .join(format!("{}_{}.xml", self.prefix, language));
+
+            self.languages.insert(
+                language.to_string(),
+                Language {
+                    file,
+                    layout,
+                    texts: Vec::new(),
+                },
+            );
+        }
+
+        let entries = &mut self.languages.get_mut(language).unwrap().texts;
+        let (mut added, mut updated) = (0, 0);
+
+        for (key, value) in texts {
+            match entries.iter_mut().find(|(k, _)| k == key) {
+                Some((_, existing)) => {
+                    if !keep_existing && existing != value {
+                        *existing = value.clone();
+                        updated += 1;
+                    }
+                }
+                None => {
+                    entries.push((key.clone(), value.clone()));
+                    added += 1;
+                }
+            }
+        }
+
+        (added, updated)
+    }
+
+    fn save(&self, language: &str) -> Result<()> {
+        let Some(language) = self.languages.get(language) else {
+            return Ok(());
+        };
+
+        let document = l10n::language_document(&language.texts, language.layout);
+
+        xml::write_xml(&document, &FormatOptions::default())?.write_to_file(&language.file)
+    }
+}
+
+fn mod_prefix(mod_dir: &Path) -> Result<String> {
+    let mod_desc = mod_dir.join(MOD_DESC_FILE_NAME);
+
+    if !mod_desc.is_file() {
+        return Ok(DEFAULT_PREFIX.to_string());
+    }
+
+    let root = xml::parse(&Vec::read_from_file(&mod_desc)?)?;
+
+    Ok(root
+        .child("l10n")
+        .and_then(|l10n| l10n.attr("filenamePrefix"))
+        .unwrap_or(DEFAULT_PREFIX)
+        .to_string())
+}
+
+fn report(translations: &Translations, summary: &Summary) -> Result<()> {
+    let base = translations.base()?;
+    let base_keys: BTreeSet<&String> = base.texts.iter().map(|(key, _)| key).collect();
+
+    println!("{}: {} keys", BASE_LANGUAGE, base_keys.len());
+
+    for (name, language) in translations.languages.iter() {
+        if name == BASE_LANGUAGE {
+            continue;
+        }
+
+        let keys: BTreeSet<&String> = language.texts.iter().map(|(key, _)| key).collect();
+        let missing: Vec<&&String> = base_keys.difference(&keys).collect();
+        let extra: Vec<&&String> = keys.difference(&base_keys).collect();
+        let coverage = match base_keys.len() {
+            0 => 100.0,
+            count => (count - missing.len()) as f64 / count as f64 * 100.0,
+        };
+
+        println!(
+            "{}: {:.1}%, {} missing, {} not in {}",
+            name,
+            coverage,
+            missing.len(),
+            extra.len(),
+            BASE_LANGUAGE
+        );
+
+        for key in missing {
+            println!("  - {}", key);
+        }
+
+        for key in extra {
+            println!("  + {}", key);
+        }
+
+        summary.processed();
+    }
+
+    Ok(())
+}
+
+fn merge(translations: &mut Translations, cmd: &MergeCmd, summary: &Summary) -> Result<()> {
+    let language = match &cmd.language {
+        Some(language) => language.clone(),
+        None => {
+            let stem = cmd.file.file_stem().unwrap_or_default().to_string_lossy();
+
+            match stem.rsplit_once('_') {
+                Some((_, language)) => language.to_string(),
+                None => bail!(report::usage(
+                    "Unable to detect language from file name, use --language"
+                )),
+            }
+        }
+    };
+
+    let texts = l10n::read_language_file(&cmd.file)?;
+    let (added, updated) = translations.merge(&language, &texts, cmd.keep_existing);
+
+    translations.save(&language)?;
+
+    println!("{}: {} added, {} updated", language, added, updated);
+    summary.processed();
+
+    Ok(())
+}
+
+fn export(translations: &Translations, cmd: &ExportCmd, summary: &Summary) -> Result<()> {
+    let base = translations.base()?;
+
+    // English first, then the other languages alphabetically
+    let mut languages: Vec<&String> = translations.languages.keys().collect();
+    languages.sort_by_key(|language| *language != BASE_LANGUAGE);
+
+    // Base order, then keys missing in the base language
+    let mut keys: Vec<&String> = base.texts.iter().map(|(key, _)| key).collect();
+
+    for language in translations.languages.values() {
+        for (key, _) in language.texts.iter() {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer.write_record(std::iter::once("key").chain(languages.iter().map(|l| l.as_str())))?;
+
+    for key in keys {
+        let row = languages.iter().map(|language| {
+            translations.languages[*language]
+                .texts
+                .iter()
+                .find(|(k, _)| k == key)
+                .map_or("", |(_, value)| value.as_str())
+        });
+
+        writer.write_record(std::iter::once(key.as_str()).chain(row))?;
+    }
+
+    writer.into_inner()?.write_to_output(&cmd.output)?;
+
+    summary.processed();
+
+    Ok(())
+}
+
+fn import(translations: &mut Translations, cmd: &ImportCmd, summary: &Summary) -> Result<()> {
+    let buffer = Vec::read_from_input(&cmd.input)?;
+    let mut reader = csv::Reader::from_reader(buffer.as_slice());
+
+    let headers = reader.headers()?.clone();
+
+    if headers.get(0) != Some("key") {
+        bail!("First CSV column must be key")
+    }
+
+    let mut columns: Vec<Vec<(String, String)>> = vec![Vec::new(); headers.len()];
+
+    for record in reader.records() {
+        let record = record?;
+        let key = record.get(0).unwrap_or_default();
+
+        if key.is_empty() {
+            continue;
+        }
+
+        for (index, value) in record.iter().enumerate().skip(1) {
+            if !value.is_empty() {
+                columns[index].push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+
+    for (language, texts) in headers.iter().zip(columns).skip(1) {
+        if texts.is_empty() {
+            summary.skipped();
+            continue;
+        }
+
+        let (added, updated) = translations.merge(language, &texts, cmd.keep_existing);
+
+        if added + updated > 0 {
+            translations.save(language)?;
+        }
+
+        println!("{}: {} added, {} updated", language, added, updated);
+        summary.processed();
+    }
+
+    Ok(())
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    let mod_dir = match &cli.command {
+        Command::Report(cmd) => &cmd.mod_dir,
+        Command::Merge(cmd) => &cmd.mod_dir,
+        Command::Export(cmd) => &cmd.mod_dir,
+        Command::Import(cmd) => &cmd.mod_dir,
+    };
+
+    if !mod_dir.is_dir() {
+        bail!(report::usage(format!(
+            "Mod folder not found: {}",
+            mod_dir.display()
+        )))
+    }
+
+    let mut translations = Translations::load(mod_dir, cli.prefix.as_ref())?;
+
+    match &cli.command {
+        Command::Report(_) => report(&translations, &summary)?,
+        Command::Merge(cmd) => merge(&mut translations, cmd, &summary)?,
+        Command::Export(cmd) => export(&translations, cmd, &summary)?,
+        Command::Import(cmd) => import(&mut translations, cmd, &summary)?,
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
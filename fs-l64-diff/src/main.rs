@@ -0,0 +1,206 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    buffer::BufferExtension,
+    codec::{self, Generation},
+    luau::{self, Bytecode, Constant, Prototype},
+    report::{self, Summary},
+};
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Compare the functions and constants of two Luau .l64 bytecode files
+pub struct Cmd {
+    /// path to old file
+    #[argh(positional)]
+    old: PathBuf,
+
+    /// path to new file
+    #[argh(positional)]
+    new: PathBuf,
+}
+
+fn read_file(file: &Path) -> Result<Bytecode> {
+    let mut buffer = Vec::read_from_file(file)?;
+
+    let Some(info) = codec::identify(&buffer, &[]) else {
+        bail!("{}: Unsupported/unknown bytecode", file.display())
+    };
+
+    if info.generation != Generation::Luau {
+        bail!(
+            "{}: {} bytecode is not supported",
+            file.display(),
+            info.generation.name()
+        )
+    }
+
+    let header = codec::detect(&buffer, &[]).unwrap();
+    codec::decode(&mut buffer, &header).with_context(|| file.display().to_string())?;
+
+    luau::read_bytecode(&buffer).with_context(|| file.display().to_string())
+}
+
+/// Functions by their path from the main function, e.g. `update/anonymous 2`.
+/// Names repeated in the same parent are numbered.
+fn function_paths(bytecode: &Bytecode) -> BTreeMap<String, &Prototype> {
+    let mut paths = BTreeMap::new();
+    let mut pending = vec![(bytecode.main, String::new())];
+
+    while let Some((index, path)) = pending.pop() {
+        let Some(prototype) = bytecode.prototypes.get(index) else {
+            continue;
+        };
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+
+        for child in prototype.children.iter() {
+            let Some(function) = bytecode.prototypes.get(*child) else {
+                continue;
+            };
+
+            let name = function.name.as_deref().unwrap_or("anonymous");
+            let count = counts.entry(name).or_default();
+            *count += 1;
+
+            let name = match (function.name.is_some(), *count) {
+                (true, 1) => name.to_string(),
+                _ => format!("{} {}", name, count),
+            };
+
+            let child_path = match path.is_empty() {
+                true => name,
+                false => format!("{}/{}", path, name),
+            };
+
+            pending.push((*child, child_path));
+        }
+
+        paths.insert(path, prototype);
+    }
+
+    paths
+}
+
+/// Constants except closures, their indices change with any added function
+fn constant_counts(prototype: &Prototype) -> BTreeMap<String, isize> {
+    let mut counts = BTreeMap::new();
+
+    for constant in prototype.constants.iter() {
+        if !matches!(constant, Constant::Closure(_)) {
+            *counts.entry(constant.to_string()).or_default() += 1;
+        }
+    }
+
+    counts
+}
+
+/// Changes of a function, empty if it is unchanged
+fn compare(old: &Prototype, new: &Prototype) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    if (old.params, old.is_vararg) != (new.params, new.is_vararg) {
+        let params = |p: &Prototype| match p.is_vararg {
+            true => format!("{}+...", p.params),
+            false => p.params.to_string(),
+        };
+
+        changes.push(format!("params {} -> {}", params(old), params(new)));
+    }
+
+    if old.upvalues != new.upvalues {
+        changes.push(format!("upvalues {} -> {}", old.upvalues, new.upvalues));
+    }
+
+    if old.code.len() != new.code.len() {
+        changes.push(format!(
+            "code {} -> {} instructions",
+            old.code.len(),
+            new.code.len()
+        ));
+    } else if old.code != new.code {
+        changes.push("code changed".to_string());
+    }
+
+    let mut counts = constant_counts(new);
+
+    for (constant, count) in constant_counts(old) {
+        *counts.entry(constant).or_default() -= count;
+    }
+
+    for (constant, count) in counts {
+        match count {
+            0 => {}
+            count if count > 0 => changes.push(format!("+{}", constant)),
+            _ => changes.push(format!("-{}", constant)),
+        }
+    }
+
+    changes
+}
+
+fn display_path(path: &str) -> &str {
+    match path.is_empty() {
+        true => "<main>",
+        false => path,
+    }
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    let old = read_file(&cli.old)?;
+    summary.processed();
+    let new = read_file(&cli.new)?;
+    summary.processed();
+
+    if old.version != new.version {
+        println!("version {} -> {}", old.version, new.version);
+    }
+
+    let old_functions = function_paths(&old);
+    let new_functions = function_paths(&new);
+    let (mut changed, mut added, mut removed) = (0, 0, 0);
+
+    for (path, function) in old_functions.iter() {
+        let Some(new_function) = new_functions.get(path) else {
+            println!("- {} (line {})", display_path(path), function.line_defined);
+            removed += 1;
+            continue;
+        };
+
+        let changes = compare(function, new_function);
+
+        if !changes.is_empty() {
+            println!("~ {}: {}", display_path(path), changes.join(", "));
+            changed += 1;
+        }
+    }
+
+    for (path, function) in new_functions.iter() {
+        if !old_functions.contains_key(path) {
+            println!("+ {} (line {})", display_path(path), function.line_defined);
+            added += 1;
+        }
+    }
+
+    match changed + added + removed {
+        0 => println!("No structural differences"),
+        _ => println!(
+            "{} changed, {} added, {} removed functions",
+            changed, added, removed
+        ),
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
@@ -0,0 +1,137 @@
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    BYTECODE_EXTENSIONS, LUAJIT_DECODE_TABLES, LUAU_DECODE_TABLES,
+    buffer::BufferExtension,
+    codec::{self, Generation, L64Info},
+    list_input_files,
+    pattern::{BytePattern, PatternFile, load_pattern_file},
+    report::{self, Summary},
+};
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Identify LuaJIT and Luau .l64 bytecode files
+pub struct Cmd {
+    /// recursive mode if folder input
+    #[argh(switch, short = 'r')]
+    recursive: bool,
+
+    /// enter directory symlinks and junctions in recursive mode
+    #[argh(switch)]
+    follow_symlinks: bool,
+
+    /// pattern file with additional Luau headers and LuaJIT byteshift
+    /// tables, see fs-patch --export-patterns
+    #[argh(option)]
+    patterns: Option<PathBuf>,
+
+    /// paths to input files/folders or glob patterns
+    #[argh(positional)]
+    inputs: Vec<PathBuf>,
+}
+
+/// Whether the byteshift table of an encoded file is known
+fn has_table(info: &L64Info, patterns: &PatternFile) -> bool {
+    match info.generation {
+        Generation::LuaJit => {
+            LUAJIT_DECODE_TABLES.contains_key(&info.version)
+                || patterns
+                    .luajit_tables
+                    .iter()
+                    .any(|(index, _)| *index == info.version)
+        }
+        Generation::Luau => {
+            LUAU_DECODE_TABLES.contains_key(&(info.version, info.is_dlc.unwrap_or_default()))
+        }
+    }
+}
+
+/// `main.l64: Luau (FS25), version 6, encoded, DLC`
+fn describe(info: &L64Info, patterns: &PatternFile) -> String {
+    let mut parts = vec![info.generation.name().to_string()];
+
+    match (info.generation, info.is_encoded) {
+        (Generation::LuaJit, true) => parts.push(format!("table index {}", info.version)),
+        _ => parts.push(format!("version {}", info.version)),
+    }
+
+    parts.push(match info.is_encoded {
+        true => "encoded".to_string(),
+        false => "plain".to_string(),
+    });
+
+    match info.is_dlc {
+        Some(true) => parts.push("DLC".to_string()),
+        Some(false) => parts.push("not DLC".to_string()),
+        None => {}
+    }
+
+    if info.is_encoded && !has_table(info, patterns) {
+        parts.push("no known byteshift table".to_string());
+    }
+
+    parts.join(", ")
+}
+
+fn identify_file(file: &Path, patterns: &PatternFile) -> Result<String> {
+    let buffer = Vec::read_from_file(file)?;
+
+    let Some(info) = codec::identify(&buffer, &patterns.headers) else {
+        bail!(
+            "Unknown .l64 header {}",
+            BytePattern::from(&buffer[..buffer.len().min(3)])
+        )
+    };
+
+    Ok(format!("{}: {}", file.display(), describe(&info, patterns)))
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    if cli.inputs.is_empty() {
+        bail!(report::usage("No input files"))
+    }
+
+    let patterns = match &cli.patterns {
+        Some(file) => load_pattern_file(file)?,
+        None => PatternFile::default(),
+    };
+
+    for input in cli.inputs.iter() {
+        let files = match input.is_file() {
+            true => vec![input.clone()],
+            false => {
+                list_input_files(
+                    input,
+                    BYTECODE_EXTENSIONS,
+                    cli.recursive,
+                    cli.follow_symlinks,
+                )?
+                .1
+            }
+        };
+
+        for file in files {
+            let result = identify_file(&file, &patterns);
+
+            summary.add(file.display(), &result);
+
+            if let Ok(line) = result {
+                println!("{}", line);
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
@@ -0,0 +1,107 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use fs_lib::{buffer::BufferExtension, luau};
+use std::hint::black_box;
+
+/// 64 KiB script, 1 MiB shapes file, 16 MiB archive entry
+const SIZES: [usize; 3] = [64 * 1024, 1024 * 1024, 16 * 1024 * 1024];
+
+/// Deterministic pseudo-random bytes, the pattern is placed at the end
+fn buffer(size: usize, pattern: &[u8]) -> Vec<u8> {
+    let mut state: u32 = 0x12345678;
+    let mut buffer: Vec<u8> = (0..size)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+
+    let offset = size - pattern.len();
+    buffer[offset..].copy_from_slice(pattern);
+    buffer
+}
+
+fn write_varint(bytecode: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        match value {
+            0 => return bytecode.push(byte),
+            _ => bytecode.push(byte | 0x80),
+        }
+    }
+}
+
+/// Version 6 bytecode with a string table filling about `size` bytes
+fn bytecode(size: usize) -> Vec<u8> {
+    let string = b"g_currentMission.missionInfo";
+    let count = size / (string.len() + 1);
+    let mut bytecode = vec![6, 3];
+
+    write_varint(&mut bytecode, count);
+
+    for _ in 0..count {
+        write_varint(&mut bytecode, string.len());
+        bytecode.extend_from_slice(string);
+    }
+
+    bytecode
+}
+
+fn find_bytes(c: &mut Criterion) {
+    let pattern = b"\x48\x8b\x05\x00\x00\x00\x00\x48\x85\xc0";
+    let mut group = c.benchmark_group("find_bytes");
+
+    for size in SIZES {
+        let buffer = buffer(size, pattern);
+
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &buffer, |b, buffer| {
+            b.iter(|| black_box(buffer).find_bytes(black_box(pattern)))
+        });
+    }
+
+    group.finish();
+}
+
+fn shift_bytes(c: &mut Criterion) {
+    let table: Vec<u8> = (0..=255).collect();
+    let mut group = c.benchmark_group("shift_bytes");
+
+    for size in SIZES {
+        let buffer = buffer(size, &[]);
+
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &buffer, |b, buffer| {
+            b.iter_batched_ref(
+                || buffer.clone(),
+                |buffer| buffer.shift_bytes(black_box(&table), 0, 0xff),
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn luau_string_table(c: &mut Criterion) {
+    let mut group = c.benchmark_group("luau_string_table");
+
+    for size in SIZES {
+        let bytecode = bytecode(size);
+
+        group.throughput(Throughput::Bytes(bytecode.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &bytecode,
+            |b, bytecode| b.iter(|| luau::read_string_table(black_box(bytecode)).unwrap()),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, find_bytes, shift_bytes, luau_string_table);
+criterion_main!(benches);
@@ -0,0 +1,164 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow, bail};
+use gar_lib::GarArchive;
+
+/// File or folder inside an archive
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DirEntry {
+    /// Path from the archive root, `/` separated
+    pub path: String,
+    pub is_dir: bool,
+}
+
+impl DirEntry {
+    pub fn name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+}
+
+/// Read-only view of a .gar/.dlc archive as a folder tree. Entries are read
+/// and decrypted when opened, nothing is extracted.
+///
+/// Paths are relative to the archive root, `/` and `\` both work as
+/// separators and `""` is the root folder.
+pub struct ArchiveFs {
+    archive: GarArchive,
+    path: PathBuf,
+    /// Normalized entry path -> entry name as stored in the archive
+    entries: BTreeMap<String, String>,
+}
+
+/// `\dataS\scripts\` -> `dataS/scripts`
+fn normalize(path: &str) -> String {
+    path.split(['/', '\\'])
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Prefix of the entries inside a folder
+fn dir_prefix(dir: &str) -> String {
+    match normalize(dir) {
+        dir if dir.is_empty() => dir,
+        dir => format!("{}/", dir),
+    }
+}
+
+impl ArchiveFs {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let archive = GarArchive::open(&path).map_err(|e| anyhow!("{}", e))?;
+
+        let entries = archive
+            .files()
+            .map(|name| (normalize(name), name.to_string()))
+            .collect();
+
+        Ok(Self {
+            archive,
+            path: path.as_ref().to_path_buf(),
+            entries,
+        })
+    }
+
+    /// Path of the archive file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_file(&self, path: &str) -> bool {
+        self.entries.contains_key(&normalize(path))
+    }
+
+    pub fn is_dir(&self, path: &str) -> bool {
+        let prefix = dir_prefix(path);
+
+        self.entries
+            .range(prefix.clone()..)
+            .next()
+            .is_some_and(|(name, _)| name.starts_with(&prefix))
+    }
+
+    pub fn exists(&self, path: &str) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+
+    /// Decrypted content of a file
+    pub fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let Some(name) = self.entries.get(&normalize(path)) else {
+            bail!("{}: {} not found in archive", self.path.display(), path)
+        };
+
+        self.archive.read_file(name).map_err(|e| anyhow!("{}", e))
+    }
+
+    /// Opens a file for `Read + Seek`, e.g. for [`crate::file::BinaryReader`]
+    pub fn open_file(&self, path: &str) -> Result<Cursor<Vec<u8>>> {
+        Ok(Cursor::new(self.read(path)?))
+    }
+
+    /// Files inside a folder sorted by path
+    pub fn list_files(&self, dir: &str, recursive: bool) -> Vec<&str> {
+        let prefix = dir_prefix(dir);
+
+        self.entries
+            .range(prefix.clone()..)
+            .map(|(name, _)| name.as_str())
+            .take_while(|name| name.starts_with(&prefix))
+            .filter(|name| recursive || !name[prefix.len()..].contains('/'))
+            .collect()
+    }
+
+    /// Files with the extension inside a folder sorted by path
+    pub fn list_files_with_extension(
+        &self,
+        dir: &str,
+        extension: &str,
+        recursive: bool,
+    ) -> Vec<&str> {
+        let suffix = format!(".{}", extension);
+
+        self.list_files(dir, recursive)
+            .into_iter()
+            .filter(|name| name.ends_with(&suffix))
+            .collect()
+    }
+
+    /// Files and folders directly inside a folder, folders first
+    pub fn read_dir(&self, dir: &str) -> Result<Vec<DirEntry>> {
+        if !self.is_dir(dir) {
+            bail!(
+                "{}: folder {} not found in archive",
+                self.path.display(),
+                dir
+            )
+        }
+
+        let prefix = dir_prefix(dir);
+        let mut entries = BTreeSet::new();
+
+        for name in self.list_files(dir, true) {
+            let entry = match name[prefix.len()..].split_once('/') {
+                Some((folder, _)) => DirEntry {
+                    path: format!("{}{}", prefix, folder),
+                    is_dir: true,
+                },
+                None => DirEntry {
+                    path: name.to_string(),
+                    is_dir: false,
+                },
+            };
+
+            entries.insert(entry);
+        }
+
+        let (mut dirs, files): (Vec<_>, Vec<_>) = entries.into_iter().partition(|e| e.is_dir);
+        dirs.extend(files);
+
+        Ok(dirs)
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    env,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use anyhow::{Context, Result, bail};
+use argh::TopLevelCommand;
+use rayon::{
+    ThreadPoolBuilder,
+    iter::{ParallelBridge, ParallelIterator},
+};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    config::{self, Config},
+    path::is_stdio,
+    report::{self, Summary},
+};
+
+const JOBS_FROM_FLAG: &str = "--jobs-from";
+const NUM_THREADS_FLAG: &str = "--num-threads";
+
+/// One line of a `--jobs-from` file, options use the long option names:
+///
+/// ```json
+/// {"input": "a.xml", "output": "out/a.xml", "options": {"indent-size": 2}}
+/// ```
+#[derive(Debug, Deserialize)]
+struct Job {
+    input: PathBuf,
+    #[serde(default)]
+    output: Option<PathBuf>,
+    #[serde(default)]
+    options: Map<String, Value>,
+}
+
+impl Job {
+    /// Command line of the job, options first
+    fn args(&self) -> Result<Vec<String>> {
+        if is_stdio(&self.input) || self.output.as_ref().is_some_and(is_stdio) {
+            bail!("Jobs can't read stdin or write stdout")
+        }
+
+        let mut args = Vec::new();
+
+        for (key, value) in self.options.iter() {
+            let flag = format!("--{}", key);
+
+            let values = match value {
+                Value::Array(values) => values.iter().collect(),
+                value => vec![value],
+            };
+
+            for value in values {
+                match value {
+                    Value::Bool(true) => args.push(flag.clone()),
+                    Value::Bool(false) | Value::Null => {}
+                    Value::String(value) => args.extend([flag.clone(), value.clone()]),
+                    Value::Number(value) => args.extend([flag.clone(), value.to_string()]),
+                    _ => bail!("Unsupported value for option {}", key),
+                }
+            }
+        }
+
+        args.push(self.input.to_string_lossy().into_owned());
+
+        if let Some(output) = &self.output {
+            args.push(output.to_string_lossy().into_owned());
+        }
+
+        Ok(args)
+    }
+}
+
+/// Empty lines are skipped
+fn parse_job(line: io::Result<String>) -> Result<Option<Job>> {
+    let line = line?;
+
+    match line.trim().is_empty() {
+        true => Ok(None),
+        false => Ok(Some(serde_json::from_str(&line)?)),
+    }
+}
+
+/// `--jobs-from <file>` mode: newline-delimited JSON jobs read from a file or
+/// stdin (`-`) and processed on one thread pool, so a script can run a tool
+/// on thousands of files without starting it for each one
+pub struct Batch {
+    source: PathBuf,
+    num_threads: usize,
+    cmd: String,
+    config: Config,
+}
+
+impl Batch {
+    /// Checks the command line for `--jobs-from`, only `--num-threads` may be
+    /// given with it
+    pub fn from_env() -> Result<Option<Self>> {
+        let args: Vec<String> = env::args().collect();
+
+        let Some((program, args)) = args.split_first() else {
+            return Ok(None);
+        };
+
+        if !args.iter().any(|arg| arg == JOBS_FROM_FLAG) {
+            return Ok(None);
+        }
+
+        let mut source = None;
+        let mut num_threads = 0;
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            if arg != JOBS_FROM_FLAG && arg != NUM_THREADS_FLAG {
+                bail!(report::usage(format!(
+                    "{} only takes {}, other options are given per job",
+                    JOBS_FROM_FLAG, NUM_THREADS_FLAG
+                )))
+            }
+
+            let Some(value) = args.next() else {
+                bail!(report::usage(format!("Missing value for {}", arg)))
+            };
+
+            match arg.as_str() {
+                JOBS_FROM_FLAG => source = Some(PathBuf::from(value)),
+                _ => {
+                    num_threads = value.parse().map_err(|_| {
+                        report::usage(format!("Invalid value for {}: {}", arg, value))
+                    })?
+                }
+            }
+        }
+
+        let cmd = Path::new(program)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| program.clone());
+
+        Ok(Some(Self {
+            source: source.unwrap(),
+            num_threads,
+            cmd,
+            config: Config::load()?,
+        }))
+    }
+
+    /// Parses each job into the command of the tool and runs `process` on
+    /// it, lines are printed as jobs finish
+    pub fn run<T, F>(self, process: F) -> Result<Summary>
+    where
+        T: TopLevelCommand,
+        F: Fn(T) -> Result<Vec<String>> + Sync,
+    {
+        let reader: Box<dyn BufRead + Send> = match is_stdio(&self.source) {
+            true => Box::new(BufReader::new(io::stdin())),
+            false => Box::new(BufReader::new(
+                File::open(&self.source)
+                    .with_context(|| format!("Failed to open {}", self.source.display()))?,
+            )),
+        };
+
+        ThreadPoolBuilder::new()
+            .num_threads(self.num_threads)
+            .build_global()
+            .unwrap();
+
+        // Jobs are started while later lines are still being written
+        let (sender, receiver) = mpsc::sync_channel(256);
+
+        thread::spawn(move || {
+            for (index, line) in reader.lines().enumerate() {
+                if sender.send((index + 1, line)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let summary = Summary::default();
+
+        receiver
+            .into_iter()
+            .par_bridge()
+            .for_each(|(number, line)| {
+                let job = match parse_job(line) {
+                    Ok(Some(job)) => job,
+                    Ok(None) => return,
+                    Err(e) => return summary.error(format!("Line {}", number), &e),
+                };
+
+                let result = job
+                    .args()
+                    .and_then(|args| config::from_args(&self.config, &self.cmd, &args))
+                    .and_then(&process);
+
+                if result.is_ok() {
+                    summary.processed();
+                }
+
+                summary.print_outputs(vec![(job.input.display(), result)]);
+            });
+
+        Ok(summary)
+    }
+}
@@ -1,8 +1,64 @@
-use std::{fs::create_dir_all, path::Path};
+use std::{
+    fs::create_dir_all,
+    io::{Read, Write},
+    path::Path,
+};
 
 use anyhow::{Result, bail};
 
-use crate::byte_array_hex_string;
+use crate::{
+    byte_array_hex_string,
+    path::{is_stdio, long_path},
+};
+
+/// Finds bytes compared through mask (`buffer & mask == bytes & mask`), mask
+/// bytes of 0x00 match any byte. The longest run of exact (0xFF) bytes is
+/// searched first, the other bytes are only compared at those candidates.
+pub fn find_masked(buffer: &[u8], bytes: &[u8], mask: &[u8], offset: usize) -> Option<usize> {
+    if bytes.is_empty() || bytes.len() != mask.len() || buffer.len() < offset + bytes.len() {
+        return None;
+    }
+
+    let matches_at = |start: usize| {
+        buffer[start..start + bytes.len()]
+            .iter()
+            .zip(bytes.iter().zip(mask))
+            .all(|(value, (byte, mask))| value & mask == byte & mask)
+    };
+
+    let (mut anchor_start, mut anchor_len) = (0, 0);
+    let mut run_start = 0;
+
+    for (i, m) in mask.iter().enumerate() {
+        if *m != 0xFF {
+            run_start = i + 1;
+        } else if i + 1 - run_start > anchor_len {
+            (anchor_start, anchor_len) = (run_start, i + 1 - run_start);
+        }
+    }
+
+    let last = buffer.len() - bytes.len();
+
+    if anchor_len == 0 {
+        return (offset..=last).find(|start| matches_at(*start));
+    }
+
+    let anchor = &bytes[anchor_start..anchor_start + anchor_len];
+    let mut start = offset;
+
+    while start <= last {
+        let window = &buffer[start + anchor_start..last + anchor_start + anchor_len];
+        let candidate = start + window.windows(anchor_len).position(|w| w == anchor)?;
+
+        if matches_at(candidate) {
+            return Some(candidate);
+        }
+
+        start = candidate + 1;
+    }
+
+    None
+}
 
 #[allow(unused)]
 pub trait BufferExtension {
@@ -14,14 +70,22 @@ pub trait BufferExtension {
     fn read_u16(&self, offset: usize) -> u16;
     fn read_u32(&self, offset: usize) -> u32;
     fn read_u64(&self, offset: usize) -> u64;
+    fn read_i32(&self, offset: usize) -> i32;
+    fn read_f32(&self, offset: usize) -> f32;
     fn read_string(&self, offset: usize, length: usize) -> Result<String>;
     fn read_cstring(&self, offset: usize) -> Result<String>;
 
     fn read_from_file<P: AsRef<Path>>(file: P) -> Result<Vec<u8>>;
     fn write_to_file<P: AsRef<Path>>(&self, file: P) -> Result<()>;
+    fn read_from_input<P: AsRef<Path>>(input: P) -> Result<Vec<u8>>;
+    fn write_to_output<P: AsRef<Path>>(&self, output: P) -> Result<()>;
 
     fn find_bytes(&self, bytes: &[u8]) -> Option<usize>;
     fn find_bytes_from(&self, bytes: &[u8], offset: usize) -> Option<usize>;
+    fn find_bytes_all(&self, bytes: &[u8]) -> Vec<usize>;
+    fn find_bytes_all_overlapping(&self, bytes: &[u8]) -> Vec<usize>;
+    fn find_bytes_masked(&self, bytes: &[u8], mask: &[u8]) -> Option<usize>;
+    fn find_bytes_masked_from(&self, bytes: &[u8], mask: &[u8], offset: usize) -> Option<usize>;
     fn replace_bytes(&mut self, bytes: &[u8], offset: usize);
     fn find_and_replace(&mut self, find: &[u8], replace: &[u8], offset: usize);
     fn find_and_replace_string(&mut self, find: &str, replace: &str, offset: usize);
@@ -30,6 +94,52 @@ pub trait BufferExtension {
     fn shift_bytes_reversed(&mut self, bytes: &[u8], offset: usize, mask: usize);
 }
 
+/// Adds (or subtracts) `table[i & mask] + i` to each byte from `offset`.
+///
+/// That value repeats every `max(256, mask + 1)` bytes (rounded up to a power
+/// of two), so it is computed once for a period and applied chunk by chunk,
+/// which the compiler vectorizes.
+fn shift<F: Fn(u8, u8) -> u8>(buffer: &mut [u8], table: &[u8], offset: usize, mask: usize, op: F) {
+    let Some(buffer) = buffer.get_mut(offset..) else {
+        return;
+    };
+
+    let period = mask
+        .saturating_add(1)
+        .checked_next_power_of_two()
+        .unwrap_or(usize::MAX)
+        .max(256);
+
+    // Key bytes are worth computing only if they are used more than once
+    if period >= buffer.len() {
+        for (i, value) in buffer.iter_mut().enumerate() {
+            let i = offset + i;
+
+            *value = op(op(*value, table[i & mask]), i as u8);
+        }
+
+        return;
+    }
+
+    let key: Vec<u8> = (0..period)
+        .map(|i| table[i & mask].wrapping_add(i as u8))
+        .collect();
+
+    // The first chunk ends where the next period starts
+    let start = offset % period;
+    let (head, tail) = buffer.split_at_mut((period - start).min(buffer.len()));
+
+    for (value, key) in head.iter_mut().zip(&key[start..]) {
+        *value = op(*value, *key);
+    }
+
+    for chunk in tail.chunks_mut(period) {
+        for (value, key) in chunk.iter_mut().zip(&key) {
+            *value = op(*value, *key);
+        }
+    }
+}
+
 impl BufferExtension for Vec<u8> {
     fn from_string(str: &str) -> Vec<u8> {
         String::from(str).into_bytes()
@@ -75,6 +185,14 @@ impl BufferExtension for Vec<u8> {
         ])
     }
 
+    fn read_i32(&self, offset: usize) -> i32 {
+        self.read_u32(offset) as i32
+    }
+
+    fn read_f32(&self, offset: usize) -> f32 {
+        f32::from_bits(self.read_u32(offset))
+    }
+
     fn read_string(&self, offset: usize, length: usize) -> Result<String> {
         let bytes = &self[offset..offset + length];
 
@@ -97,7 +215,8 @@ impl BufferExtension for Vec<u8> {
     }
 
     fn write_to_file<P: AsRef<Path>>(&self, file: P) -> Result<()> {
-        let mut path = file.as_ref().to_path_buf();
+        let file = long_path(file);
+        let mut path = file.clone();
 
         path.pop();
 
@@ -108,6 +227,29 @@ impl BufferExtension for Vec<u8> {
         Ok(std::fs::write(file, &self)?)
     }
 
+    fn read_from_input<P: AsRef<Path>>(input: P) -> Result<Vec<u8>> {
+        if !is_stdio(&input) {
+            return Self::read_from_file(input);
+        }
+
+        let mut buffer = Vec::new();
+        std::io::stdin().read_to_end(&mut buffer)?;
+
+        Ok(buffer)
+    }
+
+    fn write_to_output<P: AsRef<Path>>(&self, output: P) -> Result<()> {
+        if !is_stdio(&output) {
+            return self.write_to_file(output);
+        }
+
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(self)?;
+        stdout.flush()?;
+
+        Ok(())
+    }
+
     fn find_bytes(&self, bytes: &[u8]) -> Option<usize> {
         self.windows(bytes.len()).position(|window| window == bytes)
     }
@@ -119,26 +261,54 @@ impl BufferExtension for Vec<u8> {
             .map(|pos| pos + offset)
     }
 
+    /// Offsets of all non-overlapping occurrences
+    fn find_bytes_all(&self, bytes: &[u8]) -> Vec<usize> {
+        let mut offsets = Vec::new();
+        let mut offset = 0;
+
+        if bytes.is_empty() {
+            return offsets;
+        }
+
+        while let Some(found) = self.find_bytes_from(bytes, offset) {
+            offsets.push(found);
+            offset = found + bytes.len();
+        }
+
+        offsets
+    }
+
+    /// Offsets of all occurrences, e.g. `AA AA` is found twice in `AA AA AA`
+    fn find_bytes_all_overlapping(&self, bytes: &[u8]) -> Vec<usize> {
+        if bytes.is_empty() {
+            return Vec::new();
+        }
+
+        self.windows(bytes.len())
+            .enumerate()
+            .filter(|(_, window)| *window == bytes)
+            .map(|(offset, _)| offset)
+            .collect()
+    }
+
+    fn find_bytes_masked(&self, bytes: &[u8], mask: &[u8]) -> Option<usize> {
+        find_masked(self, bytes, mask, 0)
+    }
+
+    fn find_bytes_masked_from(&self, bytes: &[u8], mask: &[u8], offset: usize) -> Option<usize> {
+        find_masked(self, bytes, mask, offset)
+    }
+
     fn replace_bytes(&mut self, bytes: &[u8], offset: usize) {
         self[offset..offset + bytes.len()].copy_from_slice(bytes);
     }
 
     fn shift_bytes(&mut self, table: &[u8], offset: usize, mask: usize) {
-        for i in offset..self.len() {
-            let value = self[i];
-            let bytecode = table[i & mask];
-
-            self[i] = value.wrapping_add(bytecode).wrapping_add(i as u8);
-        }
+        shift(self, table, offset, mask, u8::wrapping_add);
     }
 
     fn shift_bytes_reversed(&mut self, bytes: &[u8], offset: usize, mask: usize) {
-        for i in offset..self.len() {
-            let value = self[i];
-            let bytecode = bytes[i & mask];
-
-            self[i] = value.wrapping_sub(bytecode).wrapping_sub(i as u8);
-        }
+        shift(self, bytes, offset, mask, u8::wrapping_sub);
     }
 
     fn find_and_replace(&mut self, find: &[u8], replace: &[u8], offset: usize) {
@@ -158,3 +328,54 @@ impl BufferExtension for Vec<u8> {
         self.find_and_replace(&Vec::from_string(find), &Vec::from_string(replace), offset);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// xorshift64, deterministic so failures can be reproduced
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 as usize
+        }
+
+        fn bytes(&mut self, len: usize) -> Vec<u8> {
+            (0..len).map(|_| self.next() as u8).collect()
+        }
+    }
+
+    fn shift_reference(buffer: &mut [u8], table: &[u8], offset: usize, mask: usize) {
+        for (i, value) in buffer.iter_mut().enumerate().skip(offset) {
+            *value = value.wrapping_add(table[i & mask]).wrapping_add(i as u8);
+        }
+    }
+
+    #[test]
+    fn shift_matches_reference() {
+        let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+
+        for _ in 0..500 {
+            // Periods of 256 up to 2048 bytes, buffers of several periods
+            let mask = rng.next() % 2048;
+            let table = rng.bytes(mask + 1);
+            let len = rng.next() % 5000;
+            let input = rng.bytes(len);
+            let offset = rng.next() % (input.len() + 2);
+
+            let mut expected = input.clone();
+            shift_reference(&mut expected, &table, offset, mask);
+
+            let mut shifted = input.clone();
+            shifted.shift_bytes(&table, offset, mask);
+            assert_eq!(shifted, expected, "mask {:#x}, offset {}", mask, offset);
+
+            shifted.shift_bytes_reversed(&table, offset, mask);
+            assert_eq!(shifted, input, "mask {:#x}, offset {}", mask, offset);
+        }
+    }
+}
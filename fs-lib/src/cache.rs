@@ -0,0 +1,102 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{buffer::BufferExtension, hash::sha256_file};
+
+pub const CACHE_FILE_NAME: &str = ".fs-utils-cache.json";
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+struct ToolCache {
+    options: String,
+    files: BTreeMap<String, String>,
+}
+
+/// Content hashes of files processed by previous folder runs, stored in the
+/// output folder. Each tool has its own section, invalidated when the tool
+/// options change.
+pub struct Cache {
+    path: PathBuf,
+    output_path: PathBuf,
+    tool: String,
+    options: String,
+    force: bool,
+    tools: BTreeMap<String, ToolCache>,
+    files: Mutex<BTreeMap<String, String>>,
+}
+
+impl Cache {
+    pub fn load<P: AsRef<Path>>(output_path: P, tool: &str, options: &str, force: bool) -> Self {
+        let path = output_path.as_ref().join(CACHE_FILE_NAME);
+
+        let tools: BTreeMap<String, ToolCache> = std::fs::read(&path)
+            .ok()
+            .and_then(|buffer| serde_json::from_slice(&buffer).ok())
+            .unwrap_or_default();
+
+        let files = match tools.get(tool) {
+            Some(entry) if entry.options == options => entry.files.clone(),
+            _ => BTreeMap::new(),
+        };
+
+        Self {
+            path,
+            output_path: output_path.as_ref().to_path_buf(),
+            tool: tool.to_string(),
+            options: options.to_string(),
+            force,
+            tools,
+            files: Mutex::new(files),
+        }
+    }
+
+    fn key(&self, output_file: &Path) -> String {
+        output_file
+            .strip_prefix(&self.output_path)
+            .unwrap_or(output_file)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+
+    /// Returns true if file content is unchanged since it was last processed
+    /// and the output file still exists
+    pub fn is_fresh<P: AsRef<Path>>(&self, file: P, output_file: P) -> Result<bool> {
+        if self.force || !output_file.as_ref().exists() {
+            return Ok(false);
+        }
+
+        let hash = sha256_file(&file)?;
+        let files = self.files.lock().unwrap();
+
+        Ok(files.get(&self.key(output_file.as_ref())) == Some(&hash))
+    }
+
+    /// Records the current file content, call after the file has been processed
+    pub fn update<P: AsRef<Path>>(&self, file: P, output_file: P) -> Result<()> {
+        let hash = sha256_file(&file)?;
+        let key = self.key(output_file.as_ref());
+
+        self.files.lock().unwrap().insert(key, hash);
+
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let mut tools = self.tools.clone();
+
+        tools.insert(
+            self.tool.clone(),
+            ToolCache {
+                options: self.options.clone(),
+                files: self.files.lock().unwrap().clone(),
+            },
+        );
+
+        serde_json::to_vec_pretty(&tools)?.write_to_file(&self.path)
+    }
+}
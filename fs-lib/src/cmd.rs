@@ -1,31 +1,534 @@
-use std::{ffi::OsStr, path::PathBuf, process};
+use std::{
+    ffi::OsStr,
+    io::{self, Read},
+    path::PathBuf,
+    process::{self, Child, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
 
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, anyhow, bail};
 
 use crate::try_get_command_path;
 
-pub fn run_command_return_stdout<I, S>(file: &str, args: I) -> Result<Vec<u8>>
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Interval of exit checks after the output is closed
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Limits of a command run with [run_command_streaming], no limits by default
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CommandLimits {
+    /// The command is killed when it runs longer
+    pub timeout: Option<Duration>,
+    /// Maximum size of stdout and of stderr, the command is killed when
+    /// either is larger
+    pub max_output: Option<usize>,
+}
+
+impl CommandLimits {
+    /// Limits from command line options, 0 for no limit
+    pub fn new(timeout_secs: u64, max_output_mb: usize) -> Self {
+        Self {
+            timeout: (timeout_secs > 0).then(|| Duration::from_secs(timeout_secs)),
+            max_output: (max_output_mb > 0).then(|| max_output_mb * 1024 * 1024),
+        }
+    }
+}
+
+/// Output of a command that ran to the end
+#[derive(Debug, Default)]
+pub struct CommandOutput {
+    /// Exit code, None if the command was terminated by a signal
+    pub code: Option<i32>,
+    /// Empty if stdout was passed to a callback
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl CommandOutput {
+    /// Fails unless the command exited with code 0
+    pub fn check(&self, file: &str) -> Result<()> {
+        let Some(code) = self.code else {
+            bail!("Failed to execute command: {}", file)
+        };
+
+        if code != 0 {
+            bail!("Error (code {}) when executing command: {}", code, file);
+        }
+
+        Ok(())
+    }
+
+    /// Non-empty stderr lines, e.g. warnings of a successful run
+    pub fn stderr_lines(&self) -> Vec<String> {
+        String::from_utf8_lossy(&self.stderr)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(String::from)
+            .collect()
+    }
+}
+
+enum Chunk {
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+}
+
+/// Reads a pipe on its own thread and sends it in chunks until it's closed
+fn spawn_reader<R: Read + Send + 'static>(
+    mut reader: R,
+    sender: Sender<io::Result<Chunk>>,
+    chunk: fn(Vec<u8>) -> Chunk,
+) {
+    thread::spawn(move || {
+        let mut buffer = vec![0; CHUNK_SIZE];
+
+        loop {
+            let result = match reader.read(&mut buffer) {
+                Ok(0) => return,
+                Ok(size) => Ok(chunk(buffer[..size].to_vec())),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => Err(e),
+            };
+
+            let is_error = result.is_err();
+
+            if sender.send(result).is_err() || is_error {
+                return;
+            }
+        }
+    });
+}
+
+/// Job object that kills its processes when the handle is closed
+#[cfg(windows)]
+struct Job(winapi::um::winnt::HANDLE);
+
+#[cfg(windows)]
+impl Job {
+    fn new() -> io::Result<Self> {
+        use winapi::um::{
+            jobapi2::{CreateJobObjectW, SetInformationJobObject},
+            winnt::{
+                JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+                JobObjectExtendedLimitInformation,
+            },
+        };
+
+        let handle = unsafe { CreateJobObjectW(std::ptr::null_mut(), std::ptr::null()) };
+
+        if handle.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+
+        let job = Job(handle);
+        let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { std::mem::zeroed() };
+        info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+
+        let result = unsafe {
+            SetInformationJobObject(
+                job.0,
+                JobObjectExtendedLimitInformation,
+                &mut info as *mut JOBOBJECT_EXTENDED_LIMIT_INFORMATION as *mut _,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            )
+        };
+
+        if result == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(job)
+    }
+
+    fn assign(&self, child: &Child) -> io::Result<()> {
+        use std::os::windows::io::AsRawHandle;
+        use winapi::um::jobapi2::AssignProcessToJobObject;
+
+        if unsafe { AssignProcessToJobObject(self.0, child.as_raw_handle() as _) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+impl Drop for Job {
+    fn drop(&mut self) {
+        unsafe { winapi::um::handleapi::CloseHandle(self.0) };
+    }
+}
+
+/// Resumes the threads of a process started with `CREATE_SUSPENDED`
+#[cfg(windows)]
+fn resume_threads(child: &Child) -> io::Result<()> {
+    use winapi::um::{
+        handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
+        processthreadsapi::{OpenThread, ResumeThread},
+        tlhelp32::{
+            CreateToolhelp32Snapshot, TH32CS_SNAPTHREAD, THREADENTRY32, Thread32First, Thread32Next,
+        },
+        winnt::THREAD_SUSPEND_RESUME,
+    };
+
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) };
+
+    if snapshot == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut entry: THREADENTRY32 = unsafe { std::mem::zeroed() };
+    entry.dwSize = std::mem::size_of::<THREADENTRY32>() as u32;
+
+    let mut resumed = false;
+    let mut found = unsafe { Thread32First(snapshot, &mut entry) } != 0;
+
+    while found {
+        if entry.th32OwnerProcessID == child.id() {
+            let thread = unsafe { OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID) };
+
+            if !thread.is_null() {
+                resumed |= unsafe { ResumeThread(thread) } != u32::MAX;
+                unsafe { CloseHandle(thread) };
+            }
+        }
+
+        found = unsafe { Thread32Next(snapshot, &mut entry) } != 0;
+    }
+
+    unsafe { CloseHandle(snapshot) };
+
+    match resumed {
+        true => Ok(()),
+        false => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Process groups of running commands. They don't get the signals of the
+/// terminal, so they are killed when fs-utils gets SIGINT or SIGTERM.
+#[cfg(unix)]
+mod groups {
+    use std::sync::{
+        Once,
+        atomic::{AtomicI32, Ordering},
+    };
+
+    const MAX_GROUPS: usize = 64;
+
+    /// 0 is a free slot, -1 a slot reserved for a command being started
+    static GROUPS: [AtomicI32; MAX_GROUPS] = [const { AtomicI32::new(0) }; MAX_GROUPS];
+    static HANDLER: Once = Once::new();
+
+    extern "C" fn on_signal(signal: libc::c_int) {
+        for group in &GROUPS {
+            let id = group.load(Ordering::SeqCst);
+
+            if id > 0 {
+                unsafe { libc::kill(-id, libc::SIGKILL) };
+            }
+        }
+
+        // Continue with the default action (exit)
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+    }
+
+    /// Reserves a slot, None when all are taken
+    pub fn reserve() -> Option<usize> {
+        HANDLER.call_once(|| {
+            let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+
+            for signal in [libc::SIGINT, libc::SIGTERM] {
+                unsafe { libc::signal(signal, handler) };
+            }
+        });
+
+        GROUPS.iter().position(|group| {
+            group
+                .compare_exchange(0, -1, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        })
+    }
+
+    pub fn set(slot: usize, id: u32) {
+        GROUPS[slot].store(id as i32, Ordering::SeqCst);
+    }
+
+    pub fn release(slot: usize) {
+        GROUPS[slot].store(0, Ordering::SeqCst);
+    }
+}
+
+/// Command with the processes it starts, so a killed decompiler doesn't
+/// leave helpers behind that keep files locked. On Unix it leads a new
+/// process group (killed on Ctrl+C too, see [groups]), on Windows it's
+/// started suspended and assigned to a job object before it runs, everything
+/// left is killed when the job is dropped.
+struct ProcessTree {
+    child: Child,
+    /// Slot in [groups], None if the command stayed in our process group
+    #[cfg(unix)]
+    group: Option<usize>,
+    #[cfg(windows)]
+    _job: Option<Job>,
+}
+
+impl ProcessTree {
+    #[cfg(unix)]
+    fn spawn(command: &mut process::Command) -> io::Result<Self> {
+        use std::os::unix::process::CommandExt;
+
+        // Without a free slot the command couldn't be killed on Ctrl+C in its
+        // own group, so it stays in ours
+        let group = groups::reserve();
+
+        if group.is_some() {
+            command.process_group(0);
+        }
+
+        match command.spawn() {
+            Ok(child) => {
+                if let Some(slot) = group {
+                    groups::set(slot, child.id());
+                }
+
+                Ok(Self { child, group })
+            }
+            Err(e) => {
+                if let Some(slot) = group {
+                    groups::release(slot);
+                }
+
+                Err(e)
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    fn spawn(command: &mut process::Command) -> io::Result<Self> {
+        use std::os::windows::process::CommandExt;
+        use winapi::um::winbase::CREATE_SUSPENDED;
+
+        let mut child = command.creation_flags(CREATE_SUSPENDED).spawn()?;
+
+        // Without a job (e.g. not allowed by a parent job) only the command
+        // itself can be killed
+        let job = Job::new().and_then(|job| job.assign(&child).map(|_| job));
+
+        if let Err(e) = resume_threads(&child) {
+            let _ = child.kill();
+            let _ = child.wait();
+
+            return Err(e);
+        }
+
+        Ok(Self {
+            child,
+            _job: job.ok(),
+        })
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn spawn(command: &mut process::Command) -> io::Result<Self> {
+        Ok(Self {
+            child: command.spawn()?,
+        })
+    }
+
+    fn kill(mut self) {
+        #[cfg(unix)]
+        if self.group.is_some() {
+            unsafe { libc::kill(-(self.child.id() as libc::pid_t), libc::SIGKILL) };
+        }
+
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(unix)]
+impl Drop for ProcessTree {
+    fn drop(&mut self) {
+        if let Some(slot) = self.group {
+            groups::release(slot);
+        }
+    }
+}
+
+fn check_size(size: usize, limits: &CommandLimits, stream: &str) -> Result<()> {
+    match limits.max_output {
+        Some(max_output) if size > max_output => {
+            bail!("{} is larger than {} bytes", stream, max_output)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Passes stdout to `on_stdout` and collects stderr until both are closed
+fn read_output<F: FnMut(&[u8]) -> Result<()>>(
+    receiver: Receiver<io::Result<Chunk>>,
+    deadline: Option<Instant>,
+    limits: &CommandLimits,
+    mut on_stdout: F,
+    stderr: &mut Vec<u8>,
+) -> Result<()> {
+    let mut stdout_size = 0;
+
+    loop {
+        let chunk = match deadline {
+            Some(deadline) => {
+                match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(chunk) => chunk,
+                    Err(RecvTimeoutError::Timeout) => {
+                        bail!("Timed out after {:?}", limits.timeout.unwrap_or_default())
+                    }
+                    Err(RecvTimeoutError::Disconnected) => return Ok(()),
+                }
+            }
+            None => match receiver.recv() {
+                Ok(chunk) => chunk,
+                Err(_) => return Ok(()),
+            },
+        };
+
+        match chunk? {
+            Chunk::Stdout(data) => {
+                stdout_size += data.len();
+                check_size(stdout_size, limits, "Output")?;
+                on_stdout(&data)?;
+            }
+            Chunk::Stderr(data) => {
+                stderr.extend_from_slice(&data);
+                check_size(stderr.len(), limits, "Error output")?;
+            }
+        }
+    }
+}
+
+/// Waits for the exit after the output is closed, until the deadline
+fn wait_until(child: &mut Child, deadline: Option<Instant>) -> Result<Option<i32>> {
+    let Some(deadline) = deadline else {
+        return Ok(child.wait()?.code());
+    };
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status.code());
+        }
+
+        if Instant::now() >= deadline {
+            bail!("Timed out after closing its output")
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Runs a command and passes stdout to `on_stdout` in chunks as it's
+/// written, stderr is collected. The command is killed when it exceeds the
+/// limits or the callback fails. The exit code isn't checked, see
+/// [CommandOutput::check].
+pub fn run_command_streaming<I, S, F>(
+    file: &str,
+    args: I,
+    limits: &CommandLimits,
+    on_stdout: F,
+) -> Result<CommandOutput>
 where
     I: IntoIterator<Item = S>,
     S: AsRef<OsStr>,
+    F: FnMut(&[u8]) -> Result<()>,
 {
     let file_path: PathBuf = try_get_command_path(file)?.components().collect();
 
-    let mut cmd = process::Command::new(&file_path);
-    let cmd = cmd.args(args);
-    let output = cmd.output()?;
+    let mut tree = ProcessTree::spawn(
+        process::Command::new(&file_path)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )
+    .with_context(|| format!("Failed to execute command: {}", file_path.display()))?;
 
-    let Some(code) = output.status.code() else {
-        bail!("Failed to execute command: {}", file_path.display())
-    };
+    let deadline = limits.timeout.map(|timeout| Instant::now() + timeout);
+    let (sender, receiver) = mpsc::channel();
 
-    if code != 0 {
-        bail!(
-            "Error (code {}) when executing command: {}",
+    spawn_reader(
+        tree.child.stdout.take().unwrap(),
+        sender.clone(),
+        Chunk::Stdout,
+    );
+    spawn_reader(tree.child.stderr.take().unwrap(), sender, Chunk::Stderr);
+
+    let mut stderr = Vec::new();
+    let result = read_output(receiver, deadline, limits, on_stdout, &mut stderr)
+        .and_then(|_| wait_until(&mut tree.child, deadline));
+
+    match result {
+        Ok(code) => Ok(CommandOutput {
             code,
-            file_path.display()
-        );
+            stdout: Vec::new(),
+            stderr,
+        }),
+        Err(e) => {
+            tree.kill();
+            Err(anyhow!("{:#}, killed {}", e, file_path.display()))
+        }
     }
+}
+
+/// Runs a command and returns stdout and stderr, within the limits
+pub fn run_command_output<I, S>(
+    file: &str,
+    args: I,
+    limits: &CommandLimits,
+) -> Result<CommandOutput>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut stdout = Vec::new();
+
+    let mut output = run_command_streaming(file, args, limits, |data| {
+        stdout.extend_from_slice(data);
+        Ok(())
+    })?;
+
+    output.stdout = stdout;
+
+    Ok(output)
+}
+
+pub fn run_command_return_stdout<I, S>(file: &str, args: I) -> Result<Vec<u8>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = run_command_output(file, args, &CommandLimits::default())?;
+
+    output.check(file)?;
 
     Ok(output.stdout)
 }
+
+/// Runs a command with stdout/stderr passed through, returns the exit code
+pub fn run_command<I, S>(file: &str, args: I) -> Result<i32>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let file_path: PathBuf = try_get_command_path(file)?.components().collect();
+
+    let status = process::Command::new(&file_path).args(args).status()?;
+
+    let Some(code) = status.code() else {
+        bail!("Failed to execute command: {}", file_path.display())
+    };
+
+    Ok(code)
+}
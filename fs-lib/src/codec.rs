@@ -0,0 +1,411 @@
+use std::str::FromStr;
+
+use anyhow::{Result, bail};
+
+use crate::{
+    ByteshiftTable, LUAJIT_DECODE_TABLES, LUAU_DECODE_TABLES, LUAU_HEADERS, LuauHeader,
+    buffer::BufferExtension, luau::read_string_table_with_offsets,
+};
+
+/// First byte of Luau files encoded with the regular (non-DLC) table
+const LUAU_ENCODED_MARKER: u8 = 0x02;
+
+const LUAJIT_MAGIC: [u8; 3] = [0x1B, 0x4C, 0x4A];
+const LUAJIT_ENCODED_FLAGS: u8 = 0xFC;
+const LUAJIT_VERSION: u8 = 0x02;
+
+/// Returns the header of a Luau file, headers are checked before the
+/// built-in headers
+pub fn detect(buffer: &[u8], headers: &[LuauHeader]) -> Option<LuauHeader> {
+    headers
+        .iter()
+        .chain(LUAU_HEADERS.iter())
+        .find(|header| buffer.starts_with(&header.magic))
+        .cloned()
+}
+
+/// Decodes a Luau file in place, the result is plain bytecode
+pub fn decode(buffer: &mut Vec<u8>, header: &LuauHeader) -> Result<()> {
+    if !header.is_encoded {
+        return Ok(());
+    }
+
+    // Marker byte and at least the version byte
+    if buffer.len() < 2 {
+        bail!(
+            "Unable to decode, file is too short ({} bytes)",
+            buffer.len()
+        )
+    }
+
+    let Some(table) = LUAU_DECODE_TABLES.get(&(header.version, header.is_dlc)) else {
+        bail!("Unable to decode, no valid byteshift table found")
+    };
+
+    buffer.shift_bytes(&table.bytes, table.offset, table.mask);
+    buffer.remove(0);
+
+    Ok(())
+}
+
+/// Encodes plain Luau bytecode in place with the regular table
+pub fn encode(bytecode: &mut Vec<u8>) -> Result<()> {
+    let Some(&version) = bytecode.first() else {
+        bail!("Empty bytecode")
+    };
+
+    let Some(table) = LUAU_DECODE_TABLES.get(&(version, false)) else {
+        bail!("Missing bytecode shift table for version {}", version)
+    };
+
+    bytecode.insert(0, 0);
+    bytecode.shift_bytes_reversed(&table.bytes, table.offset, table.mask);
+    bytecode[0] = LUAU_ENCODED_MARKER;
+
+    Ok(())
+}
+
+/// Decodes a Luau file and returns its string table as (offset in decoded
+/// bytecode, string)
+pub fn decode_string_table(buffer: &[u8]) -> Result<Vec<(usize, Vec<u8>)>> {
+    let Some(header) = detect(buffer, &[]) else {
+        bail!("Unsupported/unknown bytecode")
+    };
+
+    let mut buffer = buffer.to_vec();
+    decode(&mut buffer, &header)?;
+
+    read_string_table_with_offsets(&buffer)
+}
+
+/// Version used instead of header detection, written as `6` or `6-dlc`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AssumedVersion {
+    pub version: u8,
+    pub is_dlc: bool,
+}
+
+impl FromStr for AssumedVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (version, is_dlc) = match s.to_lowercase().strip_suffix("-dlc") {
+            Some(version) => (version.to_string(), true),
+            None => (s.to_string(), false),
+        };
+
+        match version.parse::<u8>() {
+            Ok(version) => Ok(AssumedVersion { version, is_dlc }),
+            Err(_) => Err(format!("Invalid version: {}, expected e.g. 6 or 6-dlc", s)),
+        }
+    }
+}
+
+impl AssumedVersion {
+    /// Plain bytecode starts with the version byte, anything else is treated
+    /// as encoded
+    pub fn header(&self, buffer: &[u8]) -> LuauHeader {
+        let is_encoded = buffer.first() != Some(&self.version);
+
+        LuauHeader {
+            magic: Vec::new(),
+            version: self.version,
+            is_encoded,
+            is_dlc: self.is_dlc,
+        }
+    }
+}
+
+/// Script bytecode generation of a `.l64` file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generation {
+    /// FS19/FS22
+    LuaJit,
+    /// FS25
+    Luau,
+}
+
+impl Generation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Generation::LuaJit => "LuaJIT (FS19/FS22)",
+            Generation::Luau => "Luau (FS25)",
+        }
+    }
+
+    /// Tool that decompiles files of this generation
+    pub fn decompiler(&self) -> &'static str {
+        match self {
+            Generation::LuaJit => "fs-luajit-decompile",
+            Generation::Luau => "fs-luau-decompile",
+        }
+    }
+}
+
+/// What a `.l64` file header tells about its contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L64Info {
+    pub generation: Generation,
+    /// Bytecode version, the byteshift table index for encoded LuaJIT files
+    pub version: u8,
+    pub is_encoded: bool,
+    /// Encoded with the DLC table, only known for Luau files
+    pub is_dlc: Option<bool>,
+}
+
+/// Identifies a LuaJIT or Luau file, headers are checked before the built-in
+/// Luau headers
+pub fn identify(buffer: &[u8], headers: &[LuauHeader]) -> Option<L64Info> {
+    if is_luajit(buffer) {
+        return Some(L64Info {
+            generation: Generation::LuaJit,
+            version: buffer[3],
+            is_encoded: is_luajit_encoded(buffer),
+            is_dlc: None,
+        });
+    }
+
+    detect(buffer, headers).map(|header| L64Info {
+        generation: Generation::Luau,
+        version: header.version,
+        is_encoded: header.is_encoded,
+        is_dlc: Some(header.is_dlc),
+    })
+}
+
+/// Error for a file of the other generation, e.g. a LuaJIT file given to
+/// the Luau decompiler
+pub fn check_generation(buffer: &[u8], expected: Generation) -> Result<()> {
+    match identify(buffer, &[]) {
+        Some(info) if info.generation != expected => bail!(
+            "{} bytecode, use {} instead",
+            info.generation.name(),
+            info.generation.decompiler()
+        ),
+        _ => Ok(()),
+    }
+}
+
+pub fn is_luajit(buffer: &[u8]) -> bool {
+    buffer.len() > 4 && buffer[0..3] == LUAJIT_MAGIC
+}
+
+pub fn is_luajit_encoded(buffer: &[u8]) -> bool {
+    is_luajit(buffer) && buffer[4] == LUAJIT_ENCODED_FLAGS
+}
+
+/// Decodes a LuaJIT file in place, tables are checked before the built-in
+/// tables
+pub fn decode_luajit(buffer: &mut Vec<u8>, tables: &[(u8, ByteshiftTable)]) -> Result<()> {
+    let index = buffer[3];
+
+    let table = tables
+        .iter()
+        .find(|(table_index, _)| *table_index == index)
+        .map(|(_, table)| table)
+        .or_else(|| LUAJIT_DECODE_TABLES.get(&index));
+
+    let Some(table) = table else {
+        bail!("Unable to decode, no valid byteshift table found")
+    };
+
+    buffer.shift_bytes(&table.bytes, table.offset, table.mask);
+    buffer[3] = LUAJIT_VERSION;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdata::{
+        check_golden, encode_luau, golden_file, luajit_bytecode, luajit_l64_encoded, luau_bytecode,
+    };
+
+    /// Start of plain version 6 bytecode, the codec never parses past the
+    /// version byte
+    const LUAU_PLAIN: [u8; 12] = [
+        0x06, 0x03, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x02,
+    ];
+
+    const LUAJIT_PLAIN: [u8; 12] = [
+        0x1B, 0x4C, 0x4A, 0x02, 0x02, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66,
+    ];
+
+    /// DLC encoding is only done by the game, the inverse of [decode]
+    fn encode_dlc(bytecode: &[u8]) -> Vec<u8> {
+        let table = &LUAU_DECODE_TABLES[&(bytecode[0], true)];
+
+        let mut output = bytecode.to_vec();
+        output.insert(0, 0);
+        output.shift_bytes_reversed(&table.bytes, table.offset, table.mask);
+        output[0] = 0x03;
+
+        output
+    }
+
+    #[test]
+    fn detect_headers() {
+        let header = detect(&LUAU_PLAIN, &[]).unwrap();
+        assert_eq!((header.version, header.is_encoded), (6, false));
+
+        let header = detect(&[0x04, 0x03], &[]).unwrap();
+        assert_eq!((header.version, header.is_encoded), (4, false));
+
+        assert!(detect(&[0x7F, 0x00], &[]).is_none());
+    }
+
+    #[test]
+    fn custom_headers_first() {
+        let custom = LuauHeader {
+            magic: vec![0x06, 0x03],
+            version: 7,
+            is_encoded: false,
+            is_dlc: false,
+        };
+
+        assert_eq!(detect(&LUAU_PLAIN, &[custom]).unwrap().version, 7);
+    }
+
+    #[test]
+    fn luau_round_trip() {
+        let mut encoded = LUAU_PLAIN.to_vec();
+        encode(&mut encoded).unwrap();
+        assert_eq!(encoded[..2], [0x02, 0xF2]);
+
+        let header = detect(&encoded, &[]).unwrap();
+        assert_eq!(header.version, 6);
+        assert!(header.is_encoded && !header.is_dlc);
+
+        decode(&mut encoded, &header).unwrap();
+        assert_eq!(encoded, LUAU_PLAIN);
+    }
+
+    #[test]
+    fn luau_dlc_round_trip() {
+        let mut encoded = encode_dlc(&LUAU_PLAIN);
+        assert_eq!(encoded[..3], [0x03, 0x00, 0xF2]);
+
+        let header = detect(&encoded, &[]).unwrap();
+        assert_eq!(header.version, 6);
+        assert!(header.is_encoded && header.is_dlc);
+
+        decode(&mut encoded, &header).unwrap();
+        assert_eq!(encoded, LUAU_PLAIN);
+    }
+
+    #[test]
+    fn encode_errors() {
+        assert!(encode(&mut Vec::new()).is_err());
+        assert!(encode(&mut vec![0x7F, 0x00]).is_err());
+    }
+
+    #[test]
+    fn decode_short_input() {
+        let assumed = AssumedVersion {
+            version: 6,
+            is_dlc: false,
+        };
+
+        for input in [vec![], vec![LUAU_ENCODED_MARKER]] {
+            let header = assumed.header(&input);
+            assert!(header.is_encoded);
+
+            let mut buffer = input.clone();
+            assert!(decode(&mut buffer, &header).is_err());
+            assert_eq!(buffer, input);
+        }
+    }
+
+    #[test]
+    fn luajit_round_trip() {
+        for (index, table) in LUAJIT_DECODE_TABLES.iter() {
+            let mut encoded = LUAJIT_PLAIN.to_vec();
+            encoded.shift_bytes_reversed(&table.bytes, table.offset, table.mask);
+            encoded[3] = *index;
+            assert!(is_luajit(&encoded));
+
+            decode_luajit(&mut encoded, &[]).unwrap();
+            assert_eq!(encoded, LUAJIT_PLAIN);
+        }
+    }
+
+    #[test]
+    fn luajit_unknown_table() {
+        let mut encoded = LUAJIT_PLAIN.to_vec();
+        encoded[3] = 0x7F;
+
+        assert!(decode_luajit(&mut encoded, &[]).is_err());
+    }
+
+    #[test]
+    fn assumed_version() {
+        let assumed: AssumedVersion = "6-DLC".parse().unwrap();
+        assert_eq!(
+            assumed,
+            AssumedVersion {
+                version: 6,
+                is_dlc: true
+            }
+        );
+        assert!("six".parse::<AssumedVersion>().is_err());
+
+        assert!(!assumed.header(&LUAU_PLAIN).is_encoded);
+        assert!(assumed.header(&encode_dlc(&LUAU_PLAIN)).is_encoded);
+    }
+
+    #[test]
+    fn luau_golden_files() {
+        for is_dlc in [false, true] {
+            let bytecode = luau_bytecode(6);
+            let encoded = encode_luau(&bytecode, is_dlc).unwrap();
+
+            let name = match is_dlc {
+                true => "luau_v6_dlc.l64",
+                false => "luau_v6.l64",
+            };
+            check_golden(&golden_file(name), &encoded).unwrap();
+
+            let header = detect(&encoded, &[]).unwrap();
+            assert_eq!(header.is_dlc, is_dlc);
+
+            let mut decoded = encoded;
+            decode(&mut decoded, &header).unwrap();
+            assert_eq!(decoded, bytecode);
+        }
+
+        let mut encoded = luau_bytecode(6);
+        encode(&mut encoded).unwrap();
+        assert_eq!(encoded, encode_luau(&luau_bytecode(6), false).unwrap());
+    }
+
+    #[test]
+    fn luajit_golden_files() {
+        let body = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77];
+
+        for index in [3, 4] {
+            let encoded = luajit_l64_encoded(index, &body).unwrap();
+            check_golden(&golden_file(&format!("luajit_t{}.l64", index)), &encoded).unwrap();
+
+            let info = identify(&encoded, &[]).unwrap();
+            assert_eq!(info.generation, Generation::LuaJit);
+            assert_eq!(info.version, index);
+            assert!(info.is_encoded);
+
+            let mut decoded = encoded;
+            decode_luajit(&mut decoded, &[]).unwrap();
+            assert_eq!(decoded, luajit_bytecode(decoded[4], &body));
+        }
+    }
+
+    #[test]
+    fn generation_mismatch() {
+        let luajit = luajit_l64_encoded(3, &[0x00]).unwrap();
+        let luau = encode_luau(&luau_bytecode(6), false).unwrap();
+
+        assert!(check_generation(&luajit, Generation::Luau).is_err());
+        assert!(check_generation(&luajit, Generation::LuaJit).is_ok());
+        assert!(check_generation(&luau, Generation::LuaJit).is_err());
+        assert!(check_generation(&luau, Generation::Luau).is_ok());
+    }
+}
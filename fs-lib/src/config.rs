@@ -0,0 +1,278 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow, bail};
+use argh::TopLevelCommand;
+use toml::{Table, Value};
+
+use crate::report::EXIT_USAGE;
+
+pub const CONFIG_FILE_NAME: &str = "fs-utils.toml";
+
+/// Table with paths of external tools (e.g. luajit-decompiler.exe)
+const TOOLS_TABLE: &str = "tools";
+const PROFILES_TABLE: &str = "profiles";
+const PROFILE_FLAG: &str = "--profile";
+
+/// Settings from `fs-utils.toml` in the home folder and the current folder,
+/// the current folder wins.
+///
+/// ```toml
+/// [fs-xml-format]
+/// indent-char = "tab"
+/// indent-size = 1
+///
+/// [tools]
+/// "luajit-decompiler.exe" = "C:/tools/luajit-decompiler.exe"
+///
+/// [profiles.giants.fs-patch]
+/// platform = "giants"
+/// ```
+#[derive(Debug, Default)]
+pub struct Config {
+    table: Table,
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Merges tables recursively, values of other replace values of base
+fn merge(base: &mut Table, other: Table) {
+    for (key, value) in other {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(other)) => merge(base, other),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn read_table(file: &Path) -> Result<Option<Table>> {
+    if !file.is_file() {
+        return Ok(None);
+    }
+
+    match std::fs::read_to_string(file)?.parse::<Table>() {
+        Ok(table) => Ok(Some(table)),
+        Err(e) => bail!("Invalid {}: {}", file.display(), e),
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Self> {
+        let mut table = Table::new();
+        let mut files = Vec::new();
+
+        if let Some(home) = home_dir() {
+            files.push(home.join(CONFIG_FILE_NAME));
+        }
+
+        files.push(env::current_dir()?.join(CONFIG_FILE_NAME));
+        files.dedup();
+
+        for file in files {
+            if let Some(other) = read_table(&file)? {
+                merge(&mut table, other);
+            }
+        }
+
+        Ok(Config { table })
+    }
+
+    /// Options of a tool, profile options replace the tool defaults
+    pub fn tool_options(&self, tool: &str, profile: Option<&str>) -> Result<Table> {
+        let mut options = match self.table.get(tool) {
+            Some(Value::Table(options)) => options.clone(),
+            _ => Table::new(),
+        };
+
+        if let Some(name) = profile {
+            let Some(Value::Table(profile)) = self
+                .table
+                .get(PROFILES_TABLE)
+                .and_then(|profiles| profiles.get(name))
+            else {
+                bail!("Profile not found in {}: {}", CONFIG_FILE_NAME, name)
+            };
+
+            if let Some(Value::Table(profile_options)) = profile.get(tool) {
+                merge(&mut options, profile_options.clone());
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Top-level value, e.g. a list shared by several tools
+    pub fn value(&self, key: &str) -> Option<&Value> {
+        self.table.get(key)
+    }
+
+    /// Configured path of an external tool
+    pub fn tool_path(&self, tool: &str) -> Option<PathBuf> {
+        self.table
+            .get(TOOLS_TABLE)
+            .and_then(|tools| tools.get(tool))
+            .and_then(|path| path.as_str())
+            .map(PathBuf::from)
+    }
+}
+
+/// Maps short flags to long flags using the help text, e.g. `-r` -> `--recursive`
+fn short_flags<T: TopLevelCommand>(cmd: &str) -> Vec<(String, String)> {
+    let Err(early_exit) = T::from_args(&[cmd], &["--help"]) else {
+        return Vec::new();
+    };
+
+    early_exit
+        .output
+        .lines()
+        .filter_map(|line| {
+            let (short, long) = line.trim_start().split_once(", ")?;
+            let long = long.split_whitespace().next()?;
+
+            (short.len() == 2 && short.starts_with('-') && long.starts_with("--"))
+                .then(|| (short.to_string(), long.to_string()))
+        })
+        .collect()
+}
+
+/// Converts options to arguments, options given on the command line are skipped
+fn option_args(options: &Table, passed: &[String]) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+
+    for (key, value) in options {
+        let flag = format!("--{}", key);
+
+        if passed.contains(&flag) {
+            continue;
+        }
+
+        let values = match value {
+            Value::Array(values) => values.clone(),
+            value => vec![value.clone()],
+        };
+
+        for value in values {
+            match value {
+                Value::Boolean(true) => args.push(flag.clone()),
+                Value::Boolean(false) => {}
+                Value::String(value) => args.extend([flag.clone(), value]),
+                Value::Integer(value) => args.extend([flag.clone(), value.to_string()]),
+                Value::Float(value) => args.extend([flag.clone(), value.to_string()]),
+                _ => bail!("Unsupported value for {} in {}", key, CONFIG_FILE_NAME),
+            }
+        }
+    }
+
+    Ok(args)
+}
+
+/// Adds configured options of the tool and the selected `--profile` to the
+/// command line arguments
+fn config_args<T: TopLevelCommand>(
+    config: &Config,
+    cmd: &str,
+    args: &[String],
+) -> Result<Vec<String>> {
+    let mut args = args.to_vec();
+    let mut profile = None;
+
+    if let Some(index) = args.iter().position(|arg| arg == PROFILE_FLAG) {
+        if index + 1 >= args.len() {
+            bail!("Missing value for {}", PROFILE_FLAG)
+        }
+
+        profile = Some(args.remove(index + 1));
+        args.remove(index);
+    }
+
+    let tool = Path::new(cmd)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let options = config.tool_options(&tool, profile.as_deref())?;
+
+    if options.is_empty() {
+        return Ok(args);
+    }
+
+    // Arguments given as short flags count as given
+    let short_flags = short_flags::<T>(cmd);
+    let passed: Vec<String> = args
+        .iter()
+        .map(|arg| {
+            short_flags
+                .iter()
+                .find(|(short, _)| short == arg)
+                .map_or(arg.clone(), |(_, long)| long.clone())
+        })
+        .collect();
+
+    let mut config_args = option_args(&options, &passed)?;
+    config_args.extend(args);
+
+    Ok(config_args)
+}
+
+/// Same as `argh::from_env` with defaults from `fs-utils.toml`
+pub fn from_env<T: TopLevelCommand>() -> T {
+    let strings: Vec<String> = env::args_os()
+        .map(|s| s.into_string())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|arg| {
+            eprintln!("Invalid utf8: {}", arg.to_string_lossy());
+            std::process::exit(EXIT_USAGE.into())
+        });
+
+    let Some((program, args)) = strings.split_first() else {
+        eprintln!("No program name, argv is empty");
+        std::process::exit(EXIT_USAGE.into())
+    };
+
+    let cmd = Path::new(program)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or(program);
+
+    let args = Config::load()
+        .and_then(|config| config_args::<T>(&config, cmd, args))
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(EXIT_USAGE.into())
+        });
+
+    let strs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+
+    T::from_args(&[cmd], &strs).unwrap_or_else(|early_exit| {
+        std::process::exit(match early_exit.status {
+            Ok(()) => {
+                println!("{}", early_exit.output);
+                0
+            }
+            Err(()) => {
+                eprintln!(
+                    "{}\nRun {} --help for more information.",
+                    early_exit.output, cmd
+                );
+                EXIT_USAGE.into()
+            }
+        })
+    })
+}
+
+/// Parses arguments that didn't come from the command line (e.g. a
+/// `--jobs-from` job) like [`from_env`], errors are returned instead of exiting
+pub fn from_args<T: TopLevelCommand>(config: &Config, cmd: &str, args: &[String]) -> Result<T> {
+    let args = config_args::<T>(config, cmd, args)?;
+    let strs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+
+    T::from_args(&[cmd], &strs).map_err(|early_exit| anyhow!(early_exit.output.trim().to_string()))
+}
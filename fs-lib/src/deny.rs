@@ -0,0 +1,104 @@
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use glob::Pattern;
+use toml::Value;
+
+use crate::{
+    config::{CONFIG_FILE_NAME, Config},
+    report::Summary,
+};
+
+const DENY_TABLE: &str = "deny";
+
+/// Files the decompile tools skip, e.g. files known to crash the decompiler:
+///
+/// ```toml
+/// [[deny]]
+/// pattern = "**/XMLSchema.l64"
+/// reason = "crashes the decompiler"
+/// ```
+///
+/// Patterns without `/` are matched against the file name only
+#[derive(Debug, Default)]
+pub struct DenyList {
+    entries: Vec<(Pattern, Option<String>)>,
+}
+
+impl DenyList {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let Some(value) = config.value(DENY_TABLE) else {
+            return Ok(Self::default());
+        };
+
+        let Some(values) = value.as_array() else {
+            bail!("{} must be a list in {}", DENY_TABLE, CONFIG_FILE_NAME)
+        };
+
+        let mut entries = Vec::new();
+
+        for value in values {
+            let (pattern, reason) = match value {
+                Value::String(pattern) => (pattern.as_str(), None),
+                Value::Table(table) => match table.get("pattern").and_then(Value::as_str) {
+                    Some(pattern) => (
+                        pattern,
+                        table
+                            .get("reason")
+                            .and_then(Value::as_str)
+                            .map(String::from),
+                    ),
+                    None => bail!("Missing pattern in {} entry", DENY_TABLE),
+                },
+                _ => bail!("Invalid {} entry in {}", DENY_TABLE, CONFIG_FILE_NAME),
+            };
+
+            match Pattern::new(pattern) {
+                Ok(pattern) => entries.push((pattern, reason)),
+                Err(e) => bail!("Invalid {} pattern {}: {}", DENY_TABLE, pattern, e),
+            }
+        }
+
+        Ok(Self { entries })
+    }
+
+    pub fn load() -> Result<Self> {
+        Self::from_config(&Config::load()?)
+    }
+
+    /// Reason a file is denied, None if it isn't
+    pub fn reason<P: AsRef<Path>>(&self, file: P) -> Option<String> {
+        let path = file.as_ref().to_string_lossy().replace('\\', "/");
+        let name = path.rsplit('/').next().unwrap_or(&path);
+
+        self.entries
+            .iter()
+            .find(|(pattern, _)| match pattern.as_str().contains('/') {
+                true => pattern.matches(&path),
+                false => pattern.matches(name),
+            })
+            .map(|(pattern, reason)| match reason {
+                Some(reason) => reason.clone(),
+                None => format!("denied by {}", pattern),
+            })
+    }
+
+    /// Reports a denied file as skipped, returns false for other files
+    pub fn check<P: AsRef<Path>>(&self, file: P, summary: &Summary) -> bool {
+        match self.reason(&file) {
+            Some(reason) => {
+                summary.denied(file.as_ref().display(), &reason);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Files that are not denied, the others are reported as skipped
+    pub fn filter<P: AsRef<Path>>(&self, files: Vec<P>, summary: &Summary) -> Vec<P> {
+        files
+            .into_iter()
+            .filter(|file| !self.check(file, summary))
+            .collect()
+    }
+}
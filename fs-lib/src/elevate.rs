@@ -0,0 +1,198 @@
+use std::{fs::OpenOptions, io::ErrorKind, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::report;
+
+/// Switch that requests the relaunch, removed from the relaunched arguments
+pub const ELEVATE_FLAG: &str = "--elevate";
+
+/// The executable requires elevation to start
+const ERROR_ELEVATION_REQUIRED: i32 = 740;
+
+/// True if the error chain contains an OS permission error
+pub fn is_access_denied(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.downcast_ref::<std::io::Error>().is_some_and(|e| {
+            e.kind() == ErrorKind::PermissionDenied
+                || e.raw_os_error() == Some(ERROR_ELEVATION_REQUIRED)
+        })
+    })
+}
+
+/// Opens the file for writing without changing it, to find missing
+/// permissions before any work is done
+pub fn check_write_access(file: &Path) -> Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .open(file)
+        .with_context(|| format!("Can't open {} for writing", file.display()))?;
+
+    Ok(())
+}
+
+/// Handles an error of an up-front access check. For a permission error
+/// with `elevate` the tool is started again as administrator and exits with
+/// its exit code, otherwise the error explains how to get access.
+pub fn handle_access_denied(error: anyhow::Error, elevate: bool) -> anyhow::Error {
+    if !is_access_denied(&error) || is_elevated() {
+        return error;
+    }
+
+    if elevate {
+        match relaunch_elevated() {
+            Ok(code) => std::process::exit(code),
+            Err(e) => return e.context("Relaunch as administrator failed"),
+        }
+    }
+
+    let hint = match cfg!(windows) {
+        true => format!("run as administrator or pass {}", ELEVATE_FLAG),
+        false => "check the permissions or run with sudo".to_string(),
+    };
+
+    anyhow!(report::environment(format!("{:#}, {}", error, hint)))
+}
+
+/// True if the process runs with an elevated (administrator) token
+#[cfg(windows)]
+pub fn is_elevated() -> bool {
+    use winapi::{
+        ctypes::c_void,
+        um::{
+            handleapi::CloseHandle,
+            processthreadsapi::{GetCurrentProcess, OpenProcessToken},
+            securitybaseapi::GetTokenInformation,
+            winnt::{HANDLE, TOKEN_ELEVATION, TOKEN_QUERY, TokenElevation},
+        },
+    };
+
+    unsafe {
+        let mut token: HANDLE = std::ptr::null_mut();
+
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+            return false;
+        }
+
+        let mut elevation: TOKEN_ELEVATION = std::mem::zeroed();
+        let mut size = 0;
+
+        let result = GetTokenInformation(
+            token,
+            TokenElevation,
+            &mut elevation as *mut TOKEN_ELEVATION as *mut c_void,
+            std::mem::size_of::<TOKEN_ELEVATION>() as u32,
+            &mut size,
+        );
+
+        CloseHandle(token);
+
+        result != 0 && elevation.TokenIsElevated != 0
+    }
+}
+
+#[cfg(not(windows))]
+pub fn is_elevated() -> bool {
+    false
+}
+
+/// Quotes an argument for the Windows command line
+#[cfg(windows)]
+fn quote_argument(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Backslashes before a quote are escaped, then the quote
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                backslashes = 0;
+            }
+        }
+
+        if c != '\\' {
+            quoted.push(c);
+        }
+    }
+
+    // The closing quote must not be escaped
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+
+    quoted
+}
+
+/// Starts the tool again with the runas verb (UAC prompt) and the same
+/// arguments minus --elevate, waits for it and returns its exit code
+#[cfg(windows)]
+pub fn relaunch_elevated() -> Result<i32> {
+    use std::{ffi::OsStr, os::windows::ffi::OsStrExt};
+
+    use winapi::um::{
+        handleapi::CloseHandle,
+        processthreadsapi::GetExitCodeProcess,
+        shellapi::{SEE_MASK_NOASYNC, SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW, ShellExecuteExW},
+        synchapi::WaitForSingleObject,
+        winbase::INFINITE,
+        winuser::SW_SHOWNORMAL,
+    };
+
+    let wide = |s: &OsStr| s.encode_wide().chain(Some(0)).collect::<Vec<u16>>();
+
+    let parameters = std::env::args()
+        .skip(1)
+        .filter(|arg| arg != ELEVATE_FLAG)
+        .map(|arg| quote_argument(&arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let verb = wide(OsStr::new("runas"));
+    let file = wide(std::env::current_exe()?.as_os_str());
+    let parameters = wide(OsStr::new(&parameters));
+    let directory = wide(std::env::current_dir()?.as_os_str());
+
+    let mut info: SHELLEXECUTEINFOW = unsafe { std::mem::zeroed() };
+    info.cbSize = std::mem::size_of::<SHELLEXECUTEINFOW>() as u32;
+    info.fMask = SEE_MASK_NOCLOSEPROCESS | SEE_MASK_NOASYNC;
+    info.lpVerb = verb.as_ptr();
+    info.lpFile = file.as_ptr();
+    info.lpParameters = parameters.as_ptr();
+    info.lpDirectory = directory.as_ptr();
+    info.nShow = SW_SHOWNORMAL;
+
+    println!("Relaunching as administrator, output continues in a new window");
+
+    // Fails with ERROR_CANCELLED when the UAC prompt is declined
+    if unsafe { ShellExecuteExW(&mut info) } == 0 {
+        return Err(anyhow::Error::new(std::io::Error::last_os_error()))
+            .context("ShellExecuteExW() failed");
+    }
+
+    let mut code = 0;
+
+    unsafe {
+        WaitForSingleObject(info.hProcess, INFINITE);
+        GetExitCodeProcess(info.hProcess, &mut code);
+        CloseHandle(info.hProcess);
+    }
+
+    Ok(code as i32)
+}
+
+#[cfg(not(windows))]
+pub fn relaunch_elevated() -> Result<i32> {
+    Err(anyhow!(report::environment(format!(
+        "{} is only supported on Windows, run with sudo instead",
+        ELEVATE_FLAG
+    ))))
+}
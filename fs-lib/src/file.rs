@@ -1,6 +1,6 @@
 use std::{
     fs::File,
-    io::{Read, Seek, SeekFrom},
+    io::{BufReader, Read, Seek, SeekFrom},
 };
 
 use anyhow::{Result, bail};
@@ -12,6 +12,9 @@ pub trait FileExtension {
     fn read_u8(&mut self, offset: u64) -> Result<u8>;
     fn read_u16(&mut self, offset: u64) -> Result<u16>;
     fn read_u32(&mut self, offset: u64) -> Result<u32>;
+    fn read_u64(&mut self, offset: u64) -> Result<u64>;
+    fn read_i32(&mut self, offset: u64) -> Result<i32>;
+    fn read_f32(&mut self, offset: u64) -> Result<f32>;
     fn read_string(&mut self, offset: u64, length: usize) -> Result<String>;
 }
 
@@ -25,8 +28,8 @@ impl FileExtension for File {
         if n < length {
             bail!(
                 "Failed to read requested {} bytes at offset {}",
-                offset,
-                length
+                length,
+                offset
             )
         }
 
@@ -51,9 +54,112 @@ impl FileExtension for File {
         Ok(buffer.read_u32(0))
     }
 
+    fn read_u64(&mut self, offset: u64) -> Result<u64> {
+        let buffer = self.read_bytes(offset, 8)?;
+
+        Ok(buffer.read_u64(0))
+    }
+
+    fn read_i32(&mut self, offset: u64) -> Result<i32> {
+        let buffer = self.read_bytes(offset, 4)?;
+
+        Ok(buffer.read_i32(0))
+    }
+
+    fn read_f32(&mut self, offset: u64) -> Result<f32> {
+        let buffer = self.read_bytes(offset, 4)?;
+
+        Ok(buffer.read_f32(0))
+    }
+
     fn read_string(&mut self, offset: u64, length: usize) -> Result<String> {
         let buffer = self.read_bytes(offset, length)?;
 
         buffer.read_string(0, length)
     }
 }
+
+/// Sequential little endian reader with an internal cursor, for parsing
+/// headers without passing offsets around
+pub struct BinaryReader<R: Read + Seek> {
+    inner: BufReader<R>,
+    position: u64,
+}
+
+impl BinaryReader<File> {
+    pub fn open<P: AsRef<std::path::Path>>(file: P) -> Result<Self> {
+        Ok(Self::new(File::open(file)?))
+    }
+}
+
+impl<R: Read + Seek> BinaryReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner: BufReader::new(inner),
+            position: 0,
+        }
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn seek(&mut self, position: u64) -> Result<()> {
+        self.inner.seek(SeekFrom::Start(position))?;
+        self.position = position;
+
+        Ok(())
+    }
+
+    pub fn skip(&mut self, length: u64) -> Result<()> {
+        self.seek(self.position + length)
+    }
+
+    pub fn read_bytes(&mut self, length: usize) -> Result<Vec<u8>> {
+        let mut buffer: Vec<u8> = vec![0; length];
+
+        if self.inner.read_exact(&mut buffer).is_err() {
+            bail!(
+                "Failed to read requested {} bytes at offset {}",
+                length,
+                self.position
+            )
+        }
+
+        self.position += length as u64;
+
+        Ok(buffer)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16> {
+        Ok(self.read_bytes(2)?.read_u16(0))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32> {
+        Ok(self.read_bytes(4)?.read_u32(0))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64> {
+        Ok(self.read_bytes(8)?.read_u64(0))
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32> {
+        Ok(self.read_bytes(4)?.read_i32(0))
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32> {
+        Ok(self.read_bytes(4)?.read_f32(0))
+    }
+
+    pub fn read_string(&mut self, length: usize) -> Result<String> {
+        self.read_bytes(length)?.read_string(0, length)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner()
+    }
+}
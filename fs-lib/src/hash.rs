@@ -0,0 +1,96 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::Result;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+/// Chunk size used when streaming into hashers without `io::Write`
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Algorithm {
+    #[default]
+    Sha256,
+    Md5,
+    Crc32,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sha256" => Ok(Algorithm::Sha256),
+            "md5" => Ok(Algorithm::Md5),
+            "crc32" => Ok(Algorithm::Crc32),
+            _ => Err(format!("Unknown hash algorithm: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Algorithm::Sha256 => f.write_str("sha256"),
+            Algorithm::Md5 => f.write_str("md5"),
+            Algorithm::Crc32 => f.write_str("crc32"),
+        }
+    }
+}
+
+fn digest_reader<D: Digest + Write, R: Read>(reader: &mut R) -> Result<String> {
+    let mut hasher = D::new();
+
+    io::copy(reader, &mut hasher)?;
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+fn crc32_reader<R: Read>(reader: &mut R) -> Result<String> {
+    let mut hasher = crc32fast::Hasher::new();
+    let mut chunk = vec![0; CHUNK_SIZE];
+
+    loop {
+        match reader.read(&mut chunk)? {
+            0 => break,
+            n => hasher.update(&chunk[..n]),
+        }
+    }
+
+    Ok(format!("{:08x}", hasher.finalize()))
+}
+
+/// Hashes everything read from reader, returns the lowercase hex digest
+pub fn hash_reader<R: Read>(reader: &mut R, algorithm: Algorithm) -> Result<String> {
+    match algorithm {
+        Algorithm::Sha256 => digest_reader::<Sha256, R>(reader),
+        Algorithm::Md5 => digest_reader::<Md5, R>(reader),
+        Algorithm::Crc32 => crc32_reader(reader),
+    }
+}
+
+/// Hashes a file without loading it into memory
+pub fn hash_file<P: AsRef<Path>>(file: P, algorithm: Algorithm) -> Result<String> {
+    hash_reader(&mut File::open(file)?, algorithm)
+}
+
+pub fn hash_buffer(buffer: &[u8], algorithm: Algorithm) -> String {
+    let mut reader = buffer;
+
+    // Reading from a slice can't fail
+    hash_reader(&mut reader, algorithm).unwrap_or_default()
+}
+
+pub fn sha256_file<P: AsRef<Path>>(file: P) -> Result<String> {
+    hash_file(file, Algorithm::Sha256)
+}
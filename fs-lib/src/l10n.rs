@@ -0,0 +1,136 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::{buffer::BufferExtension, xml::Element};
+
+/// Language used as reference for missing keys
+pub const BASE_LANGUAGE: &str = "en";
+
+/// Translation entries in document order, supports both the `<elements><e k v>`
+/// and the older `<texts><text name text>` layout as well as modDesc inline
+/// `<text name><en>..</en></text>` entries (for the given language)
+pub fn read_texts(l10n: &Element, language: Option<&str>) -> Vec<(String, String)> {
+    let mut texts = Vec::new();
+
+    for e in l10n.children("elements").flat_map(|e| e.children("e")) {
+        if let (Some(key), Some(value)) = (e.attr("k"), e.attr("v")) {
+            texts.push((key.to_string(), value.to_string()));
+        }
+    }
+
+    for text in l10n
+        .children("texts")
+        .flat_map(|texts| texts.children("text"))
+        .chain(l10n.children("text"))
+    {
+        let Some(key) = text.attr("name") else {
+            continue;
+        };
+
+        let value = match (text.attr("text"), language) {
+            (Some(value), _) => Some(value.to_string()),
+            (None, Some(language)) => text
+                .child(language)
+                .map(|value| value.text().unwrap_or_default().to_string()),
+            (None, None) => None,
+        };
+
+        if let Some(value) = value {
+            texts.push((key.to_string(), value));
+        }
+    }
+
+    texts
+}
+
+/// Languages used by inline modDesc entries
+pub fn inline_languages(l10n: &Element) -> Vec<String> {
+    let mut languages: Vec<String> = l10n
+        .children("text")
+        .flat_map(|text| text.children.iter().map(|child| child.name.clone()))
+        .collect();
+
+    languages.sort();
+    languages.dedup();
+
+    languages
+}
+
+/// Finds `<prefix>_<language>.xml` files, prefix is relative to the mod folder
+pub fn language_files(mod_dir: &Path, prefix: &str) -> Result<Vec<(String, PathBuf)>> {
+    let prefix_path = mod_dir.join(prefix);
+    let (Some(dir), Some(name)) = (prefix_path.parent(), prefix_path.file_name()) else {
+        return Ok(Vec::new());
+    };
+
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let name = format!("{}_", name.to_string_lossy());
+    let mut files: Vec<(String, PathBuf)> =
+        crate::list_files_with_extension(dir, "xml", false, false)?
+            .into_iter()
+            .filter_map(|file| {
+                let stem = file.file_stem()?.to_string_lossy().into_owned();
+                let language = stem.strip_prefix(&name)?.to_string();
+
+                Some((language, file))
+            })
+            .collect();
+
+    files.sort();
+
+    Ok(files)
+}
+
+/// Reads the entries of a `<prefix>_<language>.xml` file
+pub fn read_language_file(file: &Path) -> Result<Vec<(String, String)>> {
+    let root = crate::xml::parse(&Vec::read_from_file(file)?)?;
+
+    Ok(read_texts(&root, None))
+}
+
+/// Layout of a language file, new files use `<elements>`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    Elements,
+    Texts,
+}
+
+impl Layout {
+    pub fn detect(root: &Element) -> Layout {
+        match root.child("elements").is_none() && root.child("texts").is_some() {
+            true => Layout::Texts,
+            false => Layout::Elements,
+        }
+    }
+}
+
+/// Builds a `<l10n>` document from entries
+pub fn language_document(texts: &[(String, String)], layout: Layout) -> Element {
+    let (list, entry, key, value) = match layout {
+        Layout::Elements => ("elements", "e", "k", "v"),
+        Layout::Texts => ("texts", "text", "name", "text"),
+    };
+
+    let entries = texts
+        .iter()
+        .map(|(k, v)| Element {
+            name: entry.to_string(),
+            attributes: vec![(key.to_string(), k.clone()), (value.to_string(), v.clone())],
+            ..Default::default()
+        })
+        .collect();
+
+    Element {
+        name: "l10n".to_string(),
+        children: vec![Element {
+            name: list.to_string(),
+            children: entries,
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
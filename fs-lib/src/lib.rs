@@ -1,7 +1,7 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     env,
-    fmt::UpperHex,
+    fmt::{self, UpperHex},
     fs,
     path::{Path, PathBuf},
     str::FromStr,
@@ -9,10 +9,29 @@ use std::{
 
 use anyhow::{Result, bail};
 
+pub mod archive;
+pub mod batch;
 pub mod buffer;
+pub mod cache;
 pub mod cmd;
+pub mod codec;
+pub mod config;
+pub mod deny;
+pub mod elevate;
 pub mod file;
+pub mod hash;
+pub mod l10n;
+pub mod luau;
 pub mod path;
+pub mod pattern;
+pub mod report;
+pub mod shapes;
+pub mod source;
+#[cfg(any(test, feature = "testutil"))]
+pub mod testdata;
+pub mod watch;
+pub mod xml;
+pub mod zip_input;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PatchType {
@@ -22,6 +41,20 @@ pub enum PatchType {
     RenameArchive,
 }
 
+impl FromStr for PatchType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "archivecheck" => Ok(PatchType::ArchiveCheck),
+            "compileerror" => Ok(PatchType::CompileError),
+            "corruptfile" => Ok(PatchType::CorruptFile),
+            "renamearchive" => Ok(PatchType::RenameArchive),
+            _ => Err(format!("Unknown patch type: {}", s)),
+        }
+    }
+}
+
 #[allow(dead_code)]
 pub struct PatternItem {
     pub find: Vec<u8>,
@@ -29,7 +62,7 @@ pub struct PatternItem {
     pub patch_type: PatchType,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Platform {
     Steam,
     Giants,
@@ -47,12 +80,33 @@ impl FromStr for Platform {
     }
 }
 
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Platform::Steam => "steam",
+            Platform::Giants => "giants",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ByteshiftTable {
     pub bytes: Vec<u8>,
     pub offset: usize,
     pub mask: usize,
 }
 
+/// Luau bytecode file header, identified by its leading magic bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LuauHeader {
+    pub magic: Vec<u8>,
+    pub version: u8,
+    pub is_encoded: bool,
+    pub is_dlc: bool,
+}
+
 pub fn byte_array_hex_string<T: UpperHex>(v: &[T]) -> String {
     v.iter()
         .map(|k| format!("0x{:02X}", k))
@@ -67,43 +121,124 @@ pub fn get_optional_path<P: AsRef<Path>>(path: Option<P>, fallback: P) -> PathBu
     }
 }
 
-pub fn list_files<P: AsRef<Path>>(path: P, recursive: bool) -> Result<Vec<PathBuf>> {
-    let mut files = Vec::new();
-    let entries = fs::read_dir(path)?;
-    for entry in entries.flatten() {
+/// Collects files of a folder, directory symlinks and junctions are only
+/// entered with `follow_symlinks` and every folder is entered once, so links
+/// pointing up the tree can't loop
+fn collect_files<F: Fn(&Path) -> bool>(
+    path: &Path,
+    recursive: bool,
+    follow_symlinks: bool,
+    filter: &F,
+    visited: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    if !visited.insert(fs::canonicalize(path)?) {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(path)?.flatten() {
         let path = entry.path();
+
         if path.is_file() {
-            files.push(path);
+            if filter(&path) {
+                files.push(path);
+            }
         } else if recursive && path.is_dir() {
-            files.extend(list_files(path, true)?);
+            let is_link = entry.file_type().is_ok_and(|t| t.is_symlink());
+
+            if !is_link || follow_symlinks {
+                collect_files(&path, true, follow_symlinks, filter, visited, files)?;
+            }
         }
     }
+
+    Ok(())
+}
+
+/// Lists files sorted by path, so batch runs process them in the same order
+/// on every system
+pub fn list_files<P: AsRef<Path>>(
+    path: P,
+    recursive: bool,
+    follow_symlinks: bool,
+) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    collect_files(
+        path.as_ref(),
+        recursive,
+        follow_symlinks,
+        &|_| true,
+        &mut HashSet::new(),
+        &mut files,
+    )?;
+    files.sort();
     Ok(files)
 }
 
+/// Script bytecode extensions, some DLCs use .luac or .lc instead of .l64
+pub const BYTECODE_EXTENSIONS: &str = "l64|luac|lc";
+
+/// Checks the extension of a file, `extension` can list several separated by
+/// `|`, e.g. [BYTECODE_EXTENSIONS]
+pub fn has_extension<P: AsRef<Path>>(file: P, extension: &str) -> bool {
+    file.as_ref()
+        .extension()
+        .is_some_and(|ext| extension.split('|').any(|extension| *ext == *extension))
+}
+
+/// Lists files with the extension sorted by path, see [has_extension]
 pub fn list_files_with_extension<P: AsRef<Path>>(
     path: P,
     extension: &str,
     recursive: bool,
+    follow_symlinks: bool,
 ) -> Result<Vec<PathBuf>> {
     let mut files = Vec::new();
-    let entries = fs::read_dir(path)?;
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if path.is_file() {
-            if let Some(file_ext) = path.extension() {
-                if *file_ext == *extension {
-                    files.push(path.components().collect());
-                }
-            }
-        } else if recursive && path.is_dir() {
-            files.extend(list_files_with_extension(path, extension, true)?);
-        }
-    }
+    collect_files(
+        path.as_ref(),
+        recursive,
+        follow_symlinks,
+        &|file| has_extension(file, extension),
+        &mut HashSet::new(),
+        &mut files,
+    )?;
+    let mut files: Vec<PathBuf> = files
+        .into_iter()
+        .map(|f| f.components().collect())
+        .collect();
+    files.sort();
     Ok(files)
 }
 
+/// Resolves a folder or glob pattern input into (base path, files)
+pub fn list_input_files<P: AsRef<Path>>(
+    input: P,
+    extension: &str,
+    recursive: bool,
+    follow_symlinks: bool,
+) -> Result<(PathBuf, Vec<PathBuf>)> {
+    if path::is_glob_pattern(&input) {
+        return Ok((path::glob_base(&input), path::glob(&input)?));
+    }
+
+    let files = list_files_with_extension(&input, extension, recursive, follow_symlinks)?;
+
+    Ok((input.as_ref().to_path_buf(), files))
+}
+
 pub fn try_get_command_path(file: &str) -> Result<PathBuf> {
+    if let Some(file_path) = config::Config::load()?.tool_path(file) {
+        if !file_path.exists() {
+            bail!(report::environment(format!(
+                "Failed to locate '{}' configured in {}",
+                file,
+                config::CONFIG_FILE_NAME
+            )))
+        }
+
+        return Ok(file_path);
+    }
+
     let mut dir = std::env::current_exe()?;
     dir.pop();
 
@@ -131,7 +266,7 @@ pub fn try_get_command_path(file: &str) -> Result<PathBuf> {
         return Ok(file_path);
     }
 
-    bail!("Failed to locate '{}'", file)
+    bail!(report::environment(format!("Failed to locate '{}'", file)))
 }
 
 lazy_static::lazy_static! {
@@ -267,4 +402,25 @@ lazy_static::lazy_static! {
 
         entries
     };
+
+    /// Known headers, checked in order so longer magic must come first
+    pub static ref LUAU_HEADERS: Vec<LuauHeader> = {
+        let header = |magic: &[u8], version, is_encoded, is_dlc| LuauHeader {
+            magic: magic.to_vec(),
+            version,
+            is_encoded,
+            is_dlc,
+        };
+
+        vec![
+            header(&[0x03, 0x00, 0xF2], 6, true, true),
+            header(&[0x02, 0xEF], 3, true, false),
+            header(&[0x03, 0xFD], 3, true, true),
+            header(&[0x02, 0xF0], 4, true, false),
+            header(&[0x02, 0xF2], 6, true, false),
+            header(&[0x06, 0x03], 6, false, false),
+            header(&[0x03], 3, false, false),
+            header(&[0x04], 4, false, false),
+        ]
+    };
 }
@@ -0,0 +1,577 @@
+use std::fmt;
+
+use anyhow::{Result, bail};
+
+/// Reader for decoded Luau bytecode
+pub struct BytecodeReader<'a> {
+    buffer: &'a [u8],
+    pub offset: usize,
+}
+
+impl<'a> BytecodeReader<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer, offset: 0 }
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        let Some(value) = self.buffer.get(self.offset) else {
+            bail!("Unexpected end of bytecode at offset {}", self.offset)
+        };
+
+        self.offset += 1;
+
+        Ok(*value)
+    }
+
+    pub fn read_bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        let end = self.offset.saturating_add(length);
+
+        let Some(bytes) = self.buffer.get(self.offset..end) else {
+            bail!(
+                "Unexpected end of bytecode reading {} bytes at offset {}",
+                length,
+                self.offset
+            )
+        };
+
+        self.offset += length;
+
+        Ok(bytes)
+    }
+
+    /// LEB128 encoded unsigned integer
+    pub fn read_varint(&mut self) -> Result<usize> {
+        let mut result: usize = 0;
+        let mut shift = 0;
+
+        loop {
+            let byte = self.read_u8()?;
+
+            if shift >= usize::BITS {
+                bail!("Invalid varint at offset {}", self.offset)
+            }
+
+            result |= ((byte & 0x7F) as usize) << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+    }
+}
+
+/// Returns (version, types version), types version is 0 before version 4
+pub fn read_header(reader: &mut BytecodeReader) -> Result<(u8, u8)> {
+    let version = reader.read_u8()?;
+
+    if !(3..=6).contains(&version) {
+        bail!("Unsupported Luau bytecode version {}", version)
+    }
+
+    let types_version = match version >= 4 {
+        true => reader.read_u8()?,
+        false => 0,
+    };
+
+    Ok((version, types_version))
+}
+
+/// Returns the string table of decoded bytecode. Strings are referenced by
+/// 1-based index in the bytecode, 0 meaning no string.
+pub fn read_string_table(bytecode: &[u8]) -> Result<Vec<Vec<u8>>> {
+    Ok(read_string_table_with_offsets(bytecode)?
+        .into_iter()
+        .map(|(_, string)| string)
+        .collect())
+}
+
+/// Same as [read_string_table] with the offset of each string in the bytecode
+pub fn read_string_table_with_offsets(bytecode: &[u8]) -> Result<Vec<(usize, Vec<u8>)>> {
+    let mut reader = BytecodeReader::new(bytecode);
+
+    read_header(&mut reader)?;
+
+    let count = reader.read_varint()?;
+    let mut strings = Vec::new();
+
+    for _ in 0..count {
+        let length = reader.read_varint()?;
+        let offset = reader.offset;
+        strings.push((offset, reader.read_bytes(length)?.to_vec()));
+    }
+
+    Ok(strings)
+}
+
+const TYPE_FUNCTION: u8 = 5;
+const TYPE_ANY: u8 = 15;
+const TYPE_TAGGED_USERDATA_BASE: u8 = 64;
+const TYPE_TAGGED_USERDATA_END: u8 = TYPE_TAGGED_USERDATA_BASE + 32;
+const TYPE_OPTIONAL_BIT: u8 = 1 << 7;
+
+/// Name of an encoded type, tagged userdata types are named by the userdata
+/// type table of types version 3 (1-based index, name)
+fn type_name(code: u8, userdata: &[(u8, String)]) -> String {
+    let name = match code & !TYPE_OPTIONAL_BIT {
+        0 => "nil",
+        1 => "boolean",
+        2 => "number",
+        3 => "string",
+        4 => "table",
+        TYPE_FUNCTION => "function",
+        6 => "thread",
+        7 => "userdata",
+        8 => "vector",
+        9 => "buffer",
+        TYPE_ANY => "any",
+        code @ TYPE_TAGGED_USERDATA_BASE..TYPE_TAGGED_USERDATA_END => userdata
+            .iter()
+            .find(|(index, _)| *index == code - TYPE_TAGGED_USERDATA_BASE + 1)
+            .map_or("userdata", |(_, name)| name.as_str()),
+        _ => "unknown",
+    };
+
+    match code & TYPE_OPTIONAL_BIT != 0 && name != "any" {
+        true => format!("{}?", name),
+        false => name.to_string(),
+    }
+}
+
+/// Parameter types of a function compiled from typed source
+#[derive(Debug)]
+pub struct FunctionTypes {
+    /// Debug name, None for anonymous functions
+    pub name: Option<String>,
+    pub params: Vec<String>,
+}
+
+/// String table reference, 1-based with 0 for none
+fn string_ref(strings: &[Vec<u8>], index: usize) -> Result<Option<String>> {
+    let Some(index) = index.checked_sub(1) else {
+        return Ok(None);
+    };
+
+    match strings.get(index) {
+        Some(string) => Ok(Some(String::from_utf8_lossy(string).into_owned())),
+        None => bail!(
+            "String reference {} is out of range ({} strings)",
+            index + 1,
+            strings.len()
+        ),
+    }
+}
+
+/// Reads the function signature of a type info blob: function type, number
+/// of params and a type per param
+fn read_signature(signature: &[u8], userdata: &[(u8, String)]) -> Result<Vec<String>> {
+    let [TYPE_FUNCTION, count, types @ ..] = signature else {
+        bail!("Invalid function type info")
+    };
+
+    let Some(types) = types.get(..*count as usize) else {
+        bail!("Truncated function type info")
+    };
+
+    Ok(types
+        .iter()
+        .map(|code| type_name(*code, userdata))
+        .collect())
+}
+
+/// Constant of a function, imports are resolved to their path
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    /// Global or global table path, e.g. `math.floor`
+    Import(String),
+    /// Table template with the number of keys
+    Table(usize),
+    /// Index of the function in the function table
+    Closure(usize),
+    Vector([f32; 4]),
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constant::Nil => write!(f, "nil"),
+            Constant::Boolean(value) => write!(f, "{}", value),
+            Constant::Number(value) => write!(f, "{}", value),
+            Constant::String(value) => write!(f, "{:?}", value),
+            Constant::Import(path) => write!(f, "import {}", path),
+            Constant::Table(keys) => write!(f, "table({} keys)", keys),
+            Constant::Closure(index) => write!(f, "closure {}", index),
+            Constant::Vector([x, y, z, w]) => write!(f, "vector({}, {}, {}, {})", x, y, z, w),
+        }
+    }
+}
+
+/// Import ids hold up to 3 10-bit constant indices and their count in the
+/// top 2 bits
+fn import_path(id: u32, constants: &[Constant]) -> String {
+    let count = (id >> 30) as usize;
+
+    (0..count)
+        .map(|i| {
+            let index = (id >> (20 - 10 * i)) & 0x3FF;
+
+            match constants.get(index as usize) {
+                Some(Constant::String(name)) => name.clone(),
+                _ => "?".to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn read_f32(reader: &mut BytecodeReader) -> Result<f32> {
+    Ok(f32::from_le_bytes(reader.read_bytes(4)?.try_into()?))
+}
+
+/// Fails for a constant index past the constant table of the function
+fn check_constant_ref(index: usize, count: usize) -> Result<()> {
+    if index >= count {
+        bail!(
+            "Constant reference {} is out of range ({} constants)",
+            index,
+            count
+        )
+    }
+
+    Ok(())
+}
+
+fn read_constants(reader: &mut BytecodeReader, strings: &[Vec<u8>]) -> Result<Vec<Constant>> {
+    let mut constants = Vec::new();
+    let count = reader.read_varint()?;
+
+    for _ in 0..count {
+        let constant = match reader.read_u8()? {
+            0 => Constant::Nil,
+            1 => Constant::Boolean(reader.read_u8()? != 0),
+            2 => Constant::Number(f64::from_le_bytes(reader.read_bytes(8)?.try_into()?)),
+            3 => Constant::String(string_ref(strings, reader.read_varint()?)?.unwrap_or_default()),
+            4 => {
+                let id = u32::from_le_bytes(reader.read_bytes(4)?.try_into()?);
+
+                for i in 0..(id >> 30) {
+                    check_constant_ref(((id >> (20 - 10 * i)) & 0x3FF) as usize, count)?;
+                }
+
+                Constant::Import(import_path(id, &constants))
+            }
+            5 => {
+                let keys = reader.read_varint()?;
+
+                for _ in 0..keys {
+                    check_constant_ref(reader.read_varint()?, count)?;
+                }
+
+                Constant::Table(keys)
+            }
+            6 => Constant::Closure(reader.read_varint()?),
+            7 => Constant::Vector([
+                read_f32(reader)?,
+                read_f32(reader)?,
+                read_f32(reader)?,
+                read_f32(reader)?,
+            ]),
+            // Table with constant values
+            8 => {
+                let keys = reader.read_varint()?;
+
+                for _ in 0..keys {
+                    check_constant_ref(reader.read_varint()?, count)?;
+                    reader.read_bytes(4)?;
+                }
+
+                Constant::Table(keys)
+            }
+            kind => bail!(
+                "Unknown constant type {} at offset {}",
+                kind,
+                reader.offset - 1
+            ),
+        };
+
+        constants.push(constant);
+    }
+
+    Ok(constants)
+}
+
+/// Function of decoded bytecode
+#[derive(Debug)]
+pub struct Prototype {
+    /// Debug name, None for anonymous functions and the main function
+    pub name: Option<String>,
+    pub line_defined: usize,
+    pub params: u8,
+    pub upvalues: u8,
+    pub is_vararg: bool,
+    /// Instruction words, including auxiliary words
+    pub code: Vec<u32>,
+    pub constants: Vec<Constant>,
+    /// Indices of the functions defined in this one
+    pub children: Vec<usize>,
+    /// Parameter types of bytecode compiled with type info
+    pub param_types: Option<Vec<String>>,
+}
+
+/// Function table of decoded bytecode
+#[derive(Debug)]
+pub struct Bytecode {
+    pub version: u8,
+    pub prototypes: Vec<Prototype>,
+    /// Index of the main function
+    pub main: usize,
+}
+
+/// Parses decoded Luau bytecode into its functions
+pub fn read_bytecode(bytecode: &[u8]) -> Result<Bytecode> {
+    let mut reader = BytecodeReader::new(bytecode);
+    let (version, types_version) = read_header(&mut reader)?;
+
+    if types_version > 3 {
+        bail!("Unsupported Luau types version {}", types_version)
+    }
+
+    let mut strings = Vec::new();
+
+    for _ in 0..reader.read_varint()? {
+        let length = reader.read_varint()?;
+        strings.push(reader.read_bytes(length)?.to_vec());
+    }
+
+    // Names of tagged userdata types, terminated by index 0
+    let mut userdata = Vec::new();
+
+    if types_version == 3 {
+        loop {
+            let index = reader.read_u8()?;
+
+            if index == 0 {
+                break;
+            }
+
+            let name = string_ref(&strings, reader.read_varint()?)?;
+            userdata.push((index, name.unwrap_or_else(|| "userdata".to_string())));
+        }
+    }
+
+    let mut prototypes = Vec::new();
+
+    for _ in 0..reader.read_varint()? {
+        // max stack size, params, upvalues, vararg
+        let [_, params, upvalues, is_vararg] = reader.read_bytes(4)?.try_into()?;
+
+        let mut param_types = None;
+
+        if version >= 4 {
+            // flags
+            reader.read_u8()?;
+
+            let type_info_size = reader.read_varint()?;
+            let type_info = reader.read_bytes(type_info_size)?;
+
+            if types_version > 0 && !type_info.is_empty() {
+                let signature = match types_version {
+                    1 => type_info,
+                    _ => {
+                        // Signature size, upvalue and local counts, then the
+                        // signature followed by upvalue and local types
+                        let mut info = BytecodeReader::new(type_info);
+                        let size = info.read_varint()?;
+                        info.read_varint()?;
+                        info.read_varint()?;
+                        info.read_bytes(size)?
+                    }
+                };
+
+                // Types version 2 can have typed locals without a signature
+                if !signature.is_empty() {
+                    param_types = Some(read_signature(signature, &userdata)?);
+                }
+            }
+        }
+
+        let code_size = reader.read_varint()?;
+        let Some(code_bytes) = code_size.checked_mul(4) else {
+            bail!("Invalid code size {}", code_size)
+        };
+        let code = reader
+            .read_bytes(code_bytes)?
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+
+        let constants = read_constants(&mut reader, &strings)?;
+
+        let mut children = Vec::new();
+
+        for _ in 0..reader.read_varint()? {
+            children.push(reader.read_varint()?);
+        }
+
+        // Line defined, then debug name
+        let line_defined = reader.read_varint()?;
+        let name = string_ref(&strings, reader.read_varint()?)?;
+
+        if reader.read_u8()? != 0 {
+            let line_gap_log2 = reader.read_u8()?;
+            let intervals = code_size
+                .saturating_sub(1)
+                .checked_shr(line_gap_log2.into())
+                .unwrap_or(0)
+                + 1;
+
+            let Some(interval_bytes) = intervals.checked_mul(4) else {
+                bail!("Invalid line info size {}", intervals)
+            };
+
+            reader.read_bytes(code_size)?;
+            reader.read_bytes(interval_bytes)?;
+        }
+
+        // Debug info: locals (name, start and end pc, register), then
+        // upvalue names
+        if reader.read_u8()? != 0 {
+            for _ in 0..reader.read_varint()? {
+                string_ref(&strings, reader.read_varint()?)?;
+                reader.read_varint()?;
+                reader.read_varint()?;
+                reader.read_u8()?;
+            }
+
+            for _ in 0..reader.read_varint()? {
+                string_ref(&strings, reader.read_varint()?)?;
+            }
+        }
+
+        prototypes.push(Prototype {
+            name,
+            line_defined,
+            params,
+            upvalues,
+            is_vararg: is_vararg != 0,
+            code,
+            constants,
+            children,
+            param_types,
+        });
+    }
+
+    let main = reader.read_varint()?;
+
+    Ok(Bytecode {
+        version,
+        prototypes,
+        main,
+    })
+}
+
+/// Checks decoded bytecode before it's passed to the decompiler, which
+/// panics on out of range string, constant and function references. The
+/// string and constant references are checked by [read_bytecode].
+pub fn validate_bytecode(bytecode: &[u8]) -> Result<()> {
+    let bytecode = read_bytecode(bytecode)?;
+    let count = bytecode.prototypes.len();
+
+    let check_function_ref = |index: usize| -> Result<()> {
+        if index >= count {
+            bail!(
+                "Function reference {} is out of range ({} functions)",
+                index,
+                count
+            )
+        }
+
+        Ok(())
+    };
+
+    check_function_ref(bytecode.main)?;
+
+    for prototype in bytecode.prototypes.iter() {
+        for child in prototype.children.iter() {
+            check_function_ref(*child)?;
+        }
+
+        for constant in prototype.constants.iter() {
+            if let Constant::Closure(index) = constant {
+                check_function_ref(*index)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the parameter types of functions in bytecode compiled with type
+/// info (types version 1 and later), in the order of the function table.
+/// Functions without type info are left out. Return types aren't stored in
+/// bytecode.
+pub fn read_function_types(bytecode: &[u8]) -> Result<Vec<FunctionTypes>> {
+    Ok(read_bytecode(bytecode)?
+        .prototypes
+        .into_iter()
+        .filter_map(|prototype| {
+            let params = prototype.param_types?;
+
+            Some(FunctionTypes {
+                name: prototype.name,
+                params,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdata::luau_bytecode;
+
+    #[test]
+    fn read_empty_chunk() {
+        for version in [4, 6] {
+            let bytecode = read_bytecode(&luau_bytecode(version)).unwrap();
+
+            assert_eq!(bytecode.version, version);
+            assert_eq!(bytecode.prototypes.len(), 1);
+            assert!(validate_bytecode(&luau_bytecode(version)).is_ok());
+        }
+    }
+
+    #[test]
+    fn truncated_input() {
+        for version in [4, 6] {
+            let bytecode = luau_bytecode(version);
+
+            for length in 0..bytecode.len() {
+                let truncated = &bytecode[..length];
+
+                assert!(read_bytecode(truncated).is_err(), "length {}", length);
+                assert!(validate_bytecode(truncated).is_err(), "length {}", length);
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_string_table() {
+        // One string of 4 bytes, only 2 present
+        let bytecode = [6, 3, 1, 4, b'a', b'b'];
+
+        assert!(read_string_table(&bytecode).is_err());
+        assert!(read_string_table(&bytecode[..1]).is_err());
+    }
+
+    #[test]
+    fn invalid_varint() {
+        let mut reader = BytecodeReader::new(&[0xFF; 16]);
+
+        assert!(reader.read_varint().is_err());
+    }
+}
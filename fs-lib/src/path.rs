@@ -1,6 +1,11 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
 
-use anyhow::Result;
+use anyhow::{Result, bail};
+
+use crate::report;
 
 pub trait PathExtension {
     fn convert_relative_path(&self, source: &PathBuf, destination: &PathBuf) -> Result<PathBuf>;
@@ -16,8 +21,321 @@ impl PathExtension for PathBuf {
     }
 
     fn get_relative_path(&self, target: &PathBuf) -> Result<PathBuf> {
-        let path = target.strip_prefix(self)?;
+        let target = without_cur_dir(target);
+        let path = target.strip_prefix(without_cur_dir(self))?;
 
         Ok(PathBuf::from(path))
     }
 }
+
+/// `./mods` and `mods` are the same folder, glob inputs are listed without `./`
+fn without_cur_dir(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| *c != Component::CurDir)
+        .collect()
+}
+
+/// Placeholders available in output patterns
+const OUTPUT_PLACEHOLDERS: [&str; 4] = ["dir", "name", "stem", "ext"];
+
+/// Maps input files of a batch run to output files. Without a pattern the input
+/// tree is mirrored into the output folder, a pattern like
+/// `{dir}/{stem}_formatted.{ext}` is resolved relative to the output folder:
+/// `{dir}` is the folder of the file relative to the input folder, `{name}` the
+/// file name, `{stem}` the name without extension and `{ext}` the extension.
+#[derive(Debug, Clone)]
+pub struct OutputMapper {
+    input: PathBuf,
+    output: PathBuf,
+    pattern: Option<String>,
+    guard: Option<bool>,
+}
+
+impl OutputMapper {
+    pub fn new<P: AsRef<Path>>(input: P, output: P, pattern: Option<String>) -> Result<Self> {
+        if let Some(pattern) = &pattern {
+            validate_output_pattern(pattern)?;
+        }
+
+        Ok(Self {
+            input: input.as_ref().to_path_buf(),
+            output: output.as_ref().to_path_buf(),
+            pattern,
+            guard: None,
+        })
+    }
+
+    /// Marks the transformation as lossy, mapping a file onto itself then
+    /// fails unless `in_place` is set, see [`check_in_place`]
+    pub fn lossy(mut self, in_place: bool) -> Self {
+        self.guard = Some(in_place);
+        self
+    }
+
+    /// Mapper for a single input file, outputs go next to it
+    pub fn beside<P: AsRef<Path>>(file: P, pattern: Option<String>) -> Result<Self> {
+        let dir = file.as_ref().parent().unwrap_or(Path::new(""));
+
+        Self::new(dir, dir, pattern)
+    }
+
+    /// Output file for an input file, `extension` replaces the input extension
+    pub fn map<P: AsRef<Path>>(&self, file: P, extension: Option<&str>) -> Result<PathBuf> {
+        let output_file = self.resolve(file.as_ref(), extension)?;
+
+        if let Some(in_place) = self.guard {
+            check_in_place(file.as_ref(), &output_file, in_place)?;
+        }
+
+        Ok(output_file)
+    }
+
+    fn resolve(&self, file: &Path, extension: Option<&str>) -> Result<PathBuf> {
+        let relative = without_cur_dir(file);
+        let relative = relative.strip_prefix(without_cur_dir(&self.input))?;
+
+        let Some(pattern) = &self.pattern else {
+            let mut output_file: PathBuf = self.output.join(relative).components().collect();
+
+            if let Some(extension) = extension {
+                output_file.set_extension(extension);
+            }
+
+            return Ok(output_file);
+        };
+
+        let dir = relative.parent().unwrap_or(Path::new(""));
+        let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = match extension {
+            Some(extension) => extension.into(),
+            None => file.extension().unwrap_or_default().to_string_lossy(),
+        };
+        let name = match ext.is_empty() {
+            true => stem.to_string(),
+            false => format!("{}.{}", stem, ext),
+        };
+
+        let mut resolved = pattern
+            .replace("{dir}", &dir.to_string_lossy())
+            .replace("{name}", &name)
+            .replace("{stem}", &stem);
+
+        // No trailing dot for files without extension
+        resolved = match ext.is_empty() {
+            true => resolved.replace(".{ext}", "").replace("{ext}", ""),
+            false => resolved.replace("{ext}", &ext),
+        };
+
+        let mut output_file = without_cur_dir(&self.output);
+
+        for part in resolved.split(['/', '\\']) {
+            if !part.is_empty() && part != "." {
+                output_file.push(part);
+            }
+        }
+
+        Ok(output_file)
+    }
+}
+
+/// Fails when a lossy transformation (e.g. decompiling) would overwrite its
+/// input, unless the user passed `--in-place`
+pub fn check_in_place<P: AsRef<Path>, Q: AsRef<Path>>(
+    file: P,
+    output_file: Q,
+    in_place: bool,
+) -> Result<()> {
+    let (file, output_file) = (file.as_ref(), output_file.as_ref());
+
+    if in_place || is_stdio(file) || is_stdio(output_file) {
+        return Ok(());
+    }
+
+    let same_file = without_cur_dir(file) == without_cur_dir(output_file)
+        || match (file.canonicalize(), output_file.canonicalize()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        };
+
+    if same_file {
+        bail!(report::usage(format!(
+            "{} would be overwritten, use --in-place to replace input files",
+            file.display()
+        )))
+    }
+
+    Ok(())
+}
+
+/// Fails when two input files map to the same output file, e.g. an output
+/// pattern without `{dir}` on a recursive input
+pub fn check_unique_outputs<F>(files: &[PathBuf], get_output_file: F) -> Result<()>
+where
+    F: Fn(&PathBuf) -> Result<PathBuf>,
+{
+    let mut outputs: HashMap<PathBuf, &PathBuf> = HashMap::new();
+
+    for file in files {
+        if let Some(previous) = outputs.insert(get_output_file(file)?, file) {
+            bail!(report::usage(format!(
+                "{} and {} map to the same output file",
+                previous.display(),
+                file.display()
+            )))
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_output_pattern(pattern: &str) -> Result<()> {
+    let mut rest = pattern;
+
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            bail!(report::usage(format!(
+                "Unclosed placeholder in output pattern: {}",
+                pattern
+            )))
+        };
+
+        let placeholder = &rest[start + 1..start + end];
+
+        if !OUTPUT_PLACEHOLDERS.contains(&placeholder) {
+            bail!(report::usage(format!(
+                "Unknown placeholder {{{}}} in output pattern, expected one of: {}",
+                placeholder,
+                OUTPUT_PLACEHOLDERS.map(|p| format!("{{{}}}", p)).join(", ")
+            )))
+        }
+
+        rest = &rest[start + end + 1..];
+    }
+
+    if pattern.split(['/', '\\']).any(|part| part == "..") {
+        bail!(report::usage(format!(
+            "Parent directory in output pattern not allowed: {}",
+            pattern
+        )))
+    }
+
+    if !pattern.contains("{name}") && !pattern.contains("{stem}") {
+        bail!(report::usage(format!(
+            "Output pattern must contain {{name}} or {{stem}}: {}",
+            pattern
+        )))
+    }
+
+    Ok(())
+}
+
+/// Converts an archive entry name into a relative path that can be safely joined
+/// onto an output folder. Absolute paths, drive prefixes and `..` components are
+/// rejected, both `/` and `\` are treated as separators.
+pub fn sanitize_entry_path(name: &str) -> Result<PathBuf> {
+    if name.starts_with(['/', '\\']) {
+        bail!("Absolute entry path not allowed: {}", name)
+    }
+
+    let mut path = PathBuf::new();
+
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => continue,
+            ".." => bail!("Parent directory in entry path not allowed: {}", name),
+            _ if part.contains(':') => bail!("Drive or stream in entry path not allowed: {}", name),
+            _ => path.push(part),
+        }
+    }
+
+    if path.as_os_str().is_empty() {
+        bail!("Empty entry path")
+    }
+
+    Ok(path)
+}
+
+/// Extended-length form (`\\?\C:\...`) of a path, so deeply nested outputs
+/// aren't limited by MAX_PATH
+#[cfg(windows)]
+pub fn long_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    const VERBATIM_PREFIX: &str = r"\\?\";
+    const UNC_PREFIX: &str = r"\\";
+
+    let path = path.as_ref();
+
+    // Resolves `.`/`..` and separators, verbatim paths are used as they are
+    let Ok(absolute) = std::path::absolute(path) else {
+        return path.to_path_buf();
+    };
+
+    let absolute = absolute.to_string_lossy();
+
+    if absolute.starts_with(VERBATIM_PREFIX) {
+        return PathBuf::from(absolute.as_ref());
+    }
+
+    match absolute.strip_prefix(UNC_PREFIX) {
+        Some(share) => PathBuf::from(format!(r"{}UNC\{}", VERBATIM_PREFIX, share)),
+        None => PathBuf::from(format!("{}{}", VERBATIM_PREFIX, absolute)),
+    }
+}
+
+/// Paths aren't length limited outside of Windows
+#[cfg(not(windows))]
+pub fn long_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    path.as_ref().to_path_buf()
+}
+
+/// Path argument that stands for stdin/stdout
+pub const STDIO_PATH: &str = "-";
+
+pub fn is_stdio<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref() == Path::new(STDIO_PATH)
+}
+
+fn has_glob_chars(str: &str) -> bool {
+    str.contains(['*', '?', '['])
+}
+
+pub fn is_glob_pattern<P: AsRef<Path>>(path: P) -> bool {
+    has_glob_chars(&path.as_ref().to_string_lossy())
+}
+
+/// Returns the leading components of a glob pattern that contain no wildcards,
+/// e.g. `mods/FS25_map/**/*.i3d.shapes` -> `mods/FS25_map`
+pub fn glob_base<P: AsRef<Path>>(pattern: P) -> PathBuf {
+    let base: PathBuf = pattern
+        .as_ref()
+        .components()
+        .take_while(|c| match c {
+            Component::Normal(s) => !has_glob_chars(&s.to_string_lossy()),
+            _ => true,
+        })
+        .collect();
+
+    if base.as_os_str().is_empty() {
+        return PathBuf::from(".");
+    }
+
+    base
+}
+
+/// Returns all files matching the glob pattern, e.g. `**/*.i3d.shapes`
+pub fn glob<P: AsRef<Path>>(pattern: P) -> Result<Vec<PathBuf>> {
+    let Some(pattern) = pattern.as_ref().to_str() else {
+        bail!("Invalid glob pattern: {}", pattern.as_ref().display())
+    };
+
+    let mut files = Vec::new();
+
+    for entry in glob::glob(pattern)? {
+        let path = entry?;
+
+        if path.is_file() {
+            files.push(path.components().collect());
+        }
+    }
+
+    Ok(files)
+}
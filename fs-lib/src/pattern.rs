@@ -0,0 +1,1087 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    ByteshiftTable, EXECUTABLE_PATTERNS, LUAJIT_DECODE_TABLES, LUAU_HEADERS, LuauHeader, PatchType,
+    Platform, buffer::find_masked,
+};
+
+/// Single byte of a pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternByte {
+    /// Exact byte value
+    Byte(u8),
+    /// `??`, matches any byte and keeps the existing byte when written
+    Any,
+    /// `<name>`, matches any byte and makes it available to the other pattern
+    Capture(String),
+}
+
+/// Byte pattern written as hex, e.g. `74 16 48 8B ?? 18 <reg>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BytePattern(pub Vec<PatternByte>);
+
+pub type Captures = HashMap<String, u8>;
+
+impl BytePattern {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn capture_names(&self) -> Vec<&str> {
+        self.0
+            .iter()
+            .filter_map(|b| match b {
+                PatternByte::Capture(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns captured bytes if the pattern matches the buffer at offset
+    pub fn match_at(&self, buffer: &[u8], offset: usize) -> Option<Captures> {
+        let window = buffer.get(offset..offset + self.len())?;
+        let mut captures = Captures::new();
+
+        for (pattern_byte, &value) in self.0.iter().zip(window) {
+            match pattern_byte {
+                PatternByte::Byte(b) if *b != value => return None,
+                PatternByte::Capture(name) => {
+                    captures.insert(name.clone(), value);
+                }
+                _ => {}
+            }
+        }
+
+        Some(captures)
+    }
+
+    /// Pattern as bytes and mask for [`find_masked`], wildcards have mask 0x00
+    fn bytes_and_mask(&self) -> (Vec<u8>, Vec<u8>) {
+        self.0
+            .iter()
+            .map(|b| match b {
+                PatternByte::Byte(b) => (*b, 0xFF),
+                _ => (0, 0),
+            })
+            .unzip()
+    }
+
+    /// Returns offset and captured bytes of the first match at or after offset
+    fn find_from(
+        &self,
+        buffer: &[u8],
+        bytes: &[u8],
+        mask: &[u8],
+        offset: usize,
+    ) -> Option<(usize, Captures)> {
+        let offset = find_masked(buffer, bytes, mask, offset)?;
+
+        self.match_at(buffer, offset).map(|c| (offset, c))
+    }
+
+    /// Returns offset and captured bytes of the first match
+    pub fn find(&self, buffer: &[u8]) -> Option<(usize, Captures)> {
+        let (bytes, mask) = self.bytes_and_mask();
+
+        self.find_from(buffer, &bytes, &mask, 0)
+    }
+
+    /// Returns offsets and captured bytes of every match, matches may overlap
+    pub fn find_all(&self, buffer: &[u8]) -> Vec<(usize, Captures)> {
+        let (bytes, mask) = self.bytes_and_mask();
+        let mut matches = Vec::new();
+        let mut offset = 0;
+
+        while let Some((found, captures)) = self.find_from(buffer, &bytes, &mask, offset) {
+            matches.push((found, captures));
+            offset = found + 1;
+        }
+
+        matches
+    }
+
+    /// Resolves the pattern into concrete bytes, wildcards keep the bytes
+    /// currently in the buffer at offset
+    pub fn render(&self, buffer: &[u8], offset: usize, captures: &Captures) -> Vec<u8> {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(i, pattern_byte)| match pattern_byte {
+                PatternByte::Byte(b) => *b,
+                PatternByte::Any => buffer[offset + i],
+                PatternByte::Capture(name) => captures[name],
+            })
+            .collect()
+    }
+}
+
+impl From<&[u8]> for BytePattern {
+    fn from(bytes: &[u8]) -> Self {
+        Self(bytes.iter().map(|b| PatternByte::Byte(*b)).collect())
+    }
+}
+
+impl fmt::Display for BytePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self
+            .0
+            .iter()
+            .map(|b| match b {
+                PatternByte::Byte(b) => format!("{:02X}", b),
+                PatternByte::Any => "??".to_string(),
+                PatternByte::Capture(name) => format!("<{}>", name),
+            })
+            .collect();
+
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+pub struct PatternDefinition {
+    pub platform: Platform,
+    pub patch_type: PatchType,
+    pub find: BytePattern,
+    pub replace: BytePattern,
+    /// SHA-256 of the only executable the pattern is for, e.g. an Epic build.
+    /// Patterns without one apply to every executable without its own set.
+    pub sha256: Option<String>,
+}
+
+/// Bytes to replace at a fixed file offset, for one-off fixes without a
+/// pattern. The original bytes are verified before writing.
+#[derive(Clone)]
+pub struct OffsetPatch {
+    pub name: Option<String>,
+    pub offset: usize,
+    pub original: Vec<u8>,
+    pub replace: Vec<u8>,
+    /// SHA-256 of the only executable the patch is for
+    pub sha256: Option<String>,
+}
+
+impl OffsetPatch {
+    /// Name for output, "Offset patch" if the patch has no name
+    pub fn display_name(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| "Offset patch".to_string())
+    }
+}
+
+/// Patterns for an executable: its own set if the pattern file has patterns
+/// keyed by its SHA-256, otherwise the patterns without a hash
+pub fn select_patterns<'a>(
+    patterns: &'a [PatternDefinition],
+    sha256: &str,
+) -> Vec<&'a PatternDefinition> {
+    let keyed: Vec<&PatternDefinition> = patterns
+        .iter()
+        .filter(|item| {
+            item.sha256
+                .as_ref()
+                .is_some_and(|hash| hash.eq_ignore_ascii_case(sha256))
+        })
+        .collect();
+
+    match keyed.is_empty() {
+        true => patterns
+            .iter()
+            .filter(|item| item.sha256.is_none())
+            .collect(),
+        false => keyed,
+    }
+}
+
+/// Returns the built-in pattern table
+pub fn builtin_patterns() -> Vec<PatternDefinition> {
+    let mut platforms: Vec<&Platform> = EXECUTABLE_PATTERNS.keys().collect();
+    platforms.sort();
+
+    let mut patterns = Vec::new();
+
+    for platform in platforms {
+        for item in EXECUTABLE_PATTERNS[platform].iter() {
+            patterns.push(PatternDefinition {
+                platform: *platform,
+                patch_type: item.patch_type,
+                find: BytePattern::from(item.find.as_slice()),
+                replace: BytePattern::from(item.replace.as_slice()),
+                sha256: None,
+            });
+        }
+    }
+
+    patterns
+}
+
+/// Formats a LuaJIT byteshift table as a pattern file section
+pub fn format_luajit_table(index: u8, table: &ByteshiftTable) -> String {
+    format!(
+        "[luajit-table]\nindex = {}\nbytes = \"{}\"\noffset = {}\n",
+        index,
+        BytePattern::from(table.bytes.as_slice()),
+        table.offset
+    )
+}
+
+/// Escapes `"` and `\` of a quoted value
+fn escape_string(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Splits a quoted value after its opening quote into the unescaped string
+/// and the rest after the closing quote, with the length of the raw string.
+/// `\"` and `\\` are escapes, other backslashes are kept.
+fn split_quoted(quoted: &str) -> Option<(String, usize, &str)> {
+    let mut string = String::new();
+    let mut chars = quoted.char_indices();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Some((string, index, &quoted[index + 1..])),
+            '\\' => match chars.clone().next() {
+                Some((_, escaped @ ('"' | '\\'))) => {
+                    chars.next();
+                    string.push(escaped);
+                }
+                _ => string.push(c),
+            },
+            c => string.push(c),
+        }
+    }
+
+    None
+}
+
+/// Formats patterns, Luau headers and LuaJIT tables as a pattern file
+pub fn export_patterns(file: &PatternFile) -> String {
+    let mut output = String::from(
+        "# fs-patch pattern file\n\
+         #\n\
+         # [platform.PatchType]\n\
+         # find = \"hex bytes, ?? matches any byte, <name> captures a byte\"\n\
+         # replace = \"same length as find, <name> writes the captured byte\"\n\
+         # sha256 = \"only for the executable with this hash (optional)\"\n\
+         #\n\
+         # [luau-header]\n\
+         # magic = \"leading bytes of .l64 files, checked before the built-in headers\"\n\
+         # version = bytecode version\n\
+         # encoded = true/false (default false)\n\
+         # dlc = true/false, DLC byteshift table (default false)\n\
+         #\n\
+         # [luajit-table]\n\
+         # index = table index, byte 3 of encoded LuaJIT .l64 files\n\
+         # bytes = \"8 or 16 hex bytes\"\n\
+         # offset = first shifted byte (default 4)\n\
+         #\n\
+         # [offset-patch]\n\
+         # name = \"shown in the output (optional)\"\n\
+         # offset = file offset, decimal or 0x hex\n\
+         # original = \"bytes expected at offset\"\n\
+         # replace = \"same length as original\"\n\
+         # sha256 = \"only for the executable with this hash (optional)\"\n",
+    );
+
+    for pattern in file.patterns.iter() {
+        output.push_str(&format!(
+            "\n[{}.{:?}]\nfind = \"{}\"\nreplace = \"{}\"\n",
+            pattern.platform, pattern.patch_type, pattern.find, pattern.replace
+        ));
+
+        if let Some(sha256) = &pattern.sha256 {
+            output.push_str(&format!("sha256 = \"{}\"\n", sha256));
+        }
+    }
+
+    for header in file.headers.iter() {
+        output.push_str(&format!(
+            "\n[luau-header]\nmagic = \"{}\"\nversion = {}\nencoded = {}\ndlc = {}\n",
+            BytePattern::from(header.magic.as_slice()),
+            header.version,
+            header.is_encoded,
+            header.is_dlc
+        ));
+    }
+
+    for (index, table) in file.luajit_tables.iter() {
+        output.push('\n');
+        output.push_str(&format_luajit_table(*index, table));
+    }
+
+    for patch in file.offset_patches.iter() {
+        output.push_str("\n[offset-patch]\n");
+
+        if let Some(name) = &patch.name {
+            output.push_str(&format!("name = \"{}\"\n", escape_string(name)));
+        }
+
+        output.push_str(&format!(
+            "offset = 0x{:X}\noriginal = \"{}\"\nreplace = \"{}\"\n",
+            patch.offset,
+            BytePattern::from(patch.original.as_slice()),
+            BytePattern::from(patch.replace.as_slice())
+        ));
+
+        if let Some(sha256) = &patch.sha256 {
+            output.push_str(&format!("sha256 = \"{}\"\n", sha256));
+        }
+    }
+
+    output
+}
+
+/// Contents of a pattern file
+#[derive(Default)]
+pub struct PatternFile {
+    pub patterns: Vec<PatternDefinition>,
+    pub headers: Vec<LuauHeader>,
+    pub luajit_tables: Vec<(u8, ByteshiftTable)>,
+    pub offset_patches: Vec<OffsetPatch>,
+}
+
+impl PatternFile {
+    /// Returns the built-in patterns and tables
+    pub fn builtin() -> Self {
+        let mut luajit_tables: Vec<(u8, ByteshiftTable)> = LUAJIT_DECODE_TABLES
+            .iter()
+            .map(|(index, table)| (*index, table.clone()))
+            .collect();
+        luajit_tables.sort_by_key(|(index, _)| *index);
+
+        Self {
+            patterns: builtin_patterns(),
+            headers: LUAU_HEADERS.clone(),
+            luajit_tables,
+            offset_patches: Vec::new(),
+        }
+    }
+}
+
+pub fn load_pattern_file<P: AsRef<Path>>(file: P) -> Result<PatternFile> {
+    let source = std::fs::read_to_string(&file)?;
+
+    parse_pattern_file(&source).with_context(|| format!("{}", file.as_ref().display()))
+}
+
+pub fn load_patterns<P: AsRef<Path>>(file: P) -> Result<Vec<PatternDefinition>> {
+    Ok(load_pattern_file(file)?.patterns)
+}
+
+struct PatternSection {
+    line: usize,
+    column: usize,
+    platform: Platform,
+    patch_type: PatchType,
+    find: Option<BytePattern>,
+    replace: Option<BytePattern>,
+    sha256: Option<String>,
+}
+
+struct HeaderSection {
+    line: usize,
+    column: usize,
+    magic: Option<Vec<u8>>,
+    version: Option<u8>,
+    is_encoded: Option<bool>,
+    is_dlc: Option<bool>,
+}
+
+struct LuajitTableSection {
+    line: usize,
+    column: usize,
+    index: Option<u8>,
+    bytes: Option<Vec<u8>>,
+    offset: Option<usize>,
+}
+
+struct OffsetPatchSection {
+    line: usize,
+    column: usize,
+    name: Option<String>,
+    offset: Option<usize>,
+    original: Option<Vec<u8>>,
+    replace: Option<Vec<u8>>,
+    sha256: Option<String>,
+}
+
+enum Section {
+    Pattern(PatternSection),
+    Header(HeaderSection),
+    LuajitTable(LuajitTableSection),
+    OffsetPatch(OffsetPatchSection),
+}
+
+fn finish_pattern_section(section: PatternSection) -> Result<PatternDefinition> {
+    let (line, column) = (section.line, section.column);
+
+    let Some(find) = section.find else {
+        bail!("{}:{}: section is missing 'find'", line, column)
+    };
+
+    let Some(replace) = section.replace else {
+        bail!("{}:{}: section is missing 'replace'", line, column)
+    };
+
+    if find.len() != replace.len() {
+        bail!(
+            "{}:{}: 'find' is {} bytes but 'replace' is {} bytes",
+            line,
+            column,
+            find.len(),
+            replace.len()
+        )
+    }
+
+    if !find.0.iter().any(|b| matches!(b, PatternByte::Byte(_))) {
+        bail!(
+            "{}:{}: 'find' must contain at least one exact byte",
+            line,
+            column
+        )
+    }
+
+    let mut find_captures = find.capture_names();
+    let mut replace_captures = replace.capture_names();
+    find_captures.sort();
+    replace_captures.sort();
+
+    if find_captures != replace_captures {
+        bail!(
+            "{}:{}: 'find' and 'replace' must use the same captures",
+            line,
+            column
+        )
+    }
+
+    Ok(PatternDefinition {
+        platform: section.platform,
+        patch_type: section.patch_type,
+        find,
+        replace,
+        sha256: section.sha256,
+    })
+}
+
+fn parse_byte_pattern(value: &str, line: usize, column: usize) -> Result<BytePattern> {
+    let mut bytes = Vec::new();
+    let mut names: Vec<String> = Vec::new();
+    let mut position = 0;
+
+    for token in value.split(' ') {
+        let token_column = column + position;
+        position += token.len() + 1;
+
+        if token.is_empty() {
+            continue;
+        }
+
+        if token == "??" {
+            bytes.push(PatternByte::Any);
+        } else if let Some(name) = token.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                bail!(
+                    "{}:{}: invalid capture name '{}'",
+                    line,
+                    token_column,
+                    token
+                )
+            }
+
+            if names.iter().any(|n| n == name) {
+                bail!("{}:{}: duplicate capture '{}'", line, token_column, name)
+            }
+
+            names.push(name.to_string());
+            bytes.push(PatternByte::Capture(name.to_string()));
+        } else if token.len() == 2 {
+            let Ok(b) = u8::from_str_radix(token, 16) else {
+                bail!("{}:{}: invalid hex byte '{}'", line, token_column, token)
+            };
+
+            bytes.push(PatternByte::Byte(b));
+        } else {
+            bail!("{}:{}: invalid token '{}'", line, token_column, token)
+        }
+    }
+
+    if bytes.is_empty() {
+        bail!("{}:{}: empty pattern", line, column)
+    }
+
+    Ok(BytePattern(bytes))
+}
+
+fn finish_header_section(section: HeaderSection) -> Result<LuauHeader> {
+    let (line, column) = (section.line, section.column);
+
+    let Some(magic) = section.magic else {
+        bail!("{}:{}: section is missing 'magic'", line, column)
+    };
+
+    let Some(version) = section.version else {
+        bail!("{}:{}: section is missing 'version'", line, column)
+    };
+
+    Ok(LuauHeader {
+        magic,
+        version,
+        is_encoded: section.is_encoded.unwrap_or(false),
+        is_dlc: section.is_dlc.unwrap_or(false),
+    })
+}
+
+fn finish_luajit_table_section(section: LuajitTableSection) -> Result<(u8, ByteshiftTable)> {
+    let (line, column) = (section.line, section.column);
+
+    let Some(index) = section.index else {
+        bail!("{}:{}: section is missing 'index'", line, column)
+    };
+
+    let Some(bytes) = section.bytes else {
+        bail!("{}:{}: section is missing 'bytes'", line, column)
+    };
+
+    // Bytes are selected by position & mask
+    if !bytes.len().is_power_of_two() {
+        bail!(
+            "{}:{}: 'bytes' must be a power of two long, got {}",
+            line,
+            column,
+            bytes.len()
+        )
+    }
+
+    let mask = bytes.len() - 1;
+
+    Ok((
+        index,
+        ByteshiftTable {
+            bytes,
+            offset: section.offset.unwrap_or(4),
+            mask,
+        },
+    ))
+}
+
+fn finish_offset_patch_section(section: OffsetPatchSection) -> Result<OffsetPatch> {
+    let (line, column) = (section.line, section.column);
+
+    let Some(offset) = section.offset else {
+        bail!("{}:{}: section is missing 'offset'", line, column)
+    };
+
+    let Some(original) = section.original else {
+        bail!("{}:{}: section is missing 'original'", line, column)
+    };
+
+    let Some(replace) = section.replace else {
+        bail!("{}:{}: section is missing 'replace'", line, column)
+    };
+
+    if original.len() != replace.len() {
+        bail!(
+            "{}:{}: 'original' is {} bytes but 'replace' is {} bytes",
+            line,
+            column,
+            original.len(),
+            replace.len()
+        )
+    }
+
+    Ok(OffsetPatch {
+        name: section.name,
+        offset,
+        original,
+        replace,
+        sha256: section.sha256,
+    })
+}
+
+fn finish_section(section: Section, file: &mut PatternFile) -> Result<()> {
+    match section {
+        Section::Pattern(section) => file.patterns.push(finish_pattern_section(section)?),
+        Section::Header(section) => file.headers.push(finish_header_section(section)?),
+        Section::LuajitTable(section) => file
+            .luajit_tables
+            .push(finish_luajit_table_section(section)?),
+        Section::OffsetPatch(section) => file
+            .offset_patches
+            .push(finish_offset_patch_section(section)?),
+    }
+
+    Ok(())
+}
+
+/// Parses a decimal or 0x prefixed hex integer
+fn parse_int<T: TryFrom<u64>>(value: &str, line: usize, column: usize) -> Result<T> {
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse::<u64>(),
+    };
+
+    match parsed.ok().and_then(|v| T::try_from(v).ok()) {
+        Some(value) => Ok(value),
+        None => bail!("{}:{}: invalid number '{}'", line, column, value),
+    }
+}
+
+/// Parses a quoted hex string without wildcards or captures
+fn parse_exact_bytes(value: &str, line: usize, column: usize) -> Result<Vec<u8>> {
+    let pattern = parse_byte_pattern(value, line, column)?;
+
+    let Some(bytes) = pattern
+        .0
+        .iter()
+        .map(|b| match b {
+            PatternByte::Byte(b) => Some(*b),
+            _ => None,
+        })
+        .collect::<Option<Vec<u8>>>()
+    else {
+        bail!("{}:{}: value must only contain exact bytes", line, column)
+    };
+
+    Ok(bytes)
+}
+
+fn parse_bool(value: &str, line: usize, column: usize) -> Result<bool> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => bail!(
+            "{}:{}: expected true or false, got '{}'",
+            line,
+            column,
+            value
+        ),
+    }
+}
+
+/// 64 hex digits, stored lower case like the output of fs-hash
+fn parse_sha256(value: &str, line: usize, column: usize) -> Result<String> {
+    if value.len() != 64 || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!(
+            "{}:{}: expected SHA-256 as 64 hex digits, got '{}'",
+            line,
+            column,
+            value
+        )
+    }
+
+    Ok(value.to_lowercase())
+}
+
+/// Sets a section value once, reports duplicate keys
+fn set_once<T>(
+    slot: &mut Option<T>,
+    value: T,
+    key: &str,
+    line: usize,
+    column: usize,
+) -> Result<()> {
+    if slot.is_some() {
+        bail!("{}:{}: duplicate key '{}'", line, column, key)
+    }
+
+    *slot = Some(value);
+
+    Ok(())
+}
+
+/// Parses a pattern file, errors are reported as `line:column: message`
+pub fn parse_patterns(source: &str) -> Result<Vec<PatternDefinition>> {
+    Ok(parse_pattern_file(source)?.patterns)
+}
+
+/// Parses a pattern file including Luau header and LuaJIT table sections,
+/// errors are reported as `line:column: message`
+pub fn parse_pattern_file(source: &str) -> Result<PatternFile> {
+    let mut file = PatternFile::default();
+    let mut section: Option<Section> = None;
+
+    for (index, raw_line) in source.lines().enumerate() {
+        let line = index + 1;
+        let indent = raw_line.len() - raw_line.trim_start().len();
+        let text = raw_line.trim();
+
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = text.strip_prefix('[') {
+            let Some(header) = header.strip_suffix(']') else {
+                bail!("{}:{}: expected ']'", line, indent + text.len() + 1)
+            };
+
+            if let Some(section) = section.take() {
+                finish_section(section, &mut file)?;
+            }
+
+            match header.trim() {
+                "luau-header" => {
+                    section = Some(Section::Header(HeaderSection {
+                        line,
+                        column: indent + 1,
+                        magic: None,
+                        version: None,
+                        is_encoded: None,
+                        is_dlc: None,
+                    }));
+
+                    continue;
+                }
+                "luajit-table" => {
+                    section = Some(Section::LuajitTable(LuajitTableSection {
+                        line,
+                        column: indent + 1,
+                        index: None,
+                        bytes: None,
+                        offset: None,
+                    }));
+
+                    continue;
+                }
+                "offset-patch" => {
+                    section = Some(Section::OffsetPatch(OffsetPatchSection {
+                        line,
+                        column: indent + 1,
+                        name: None,
+                        offset: None,
+                        original: None,
+                        replace: None,
+                        sha256: None,
+                    }));
+
+                    continue;
+                }
+                _ => {}
+            }
+
+            let Some((platform, patch_type)) = header.split_once('.') else {
+                bail!(
+                    "{}:{}: expected [platform.PatchType], [luau-header], [luajit-table] or [offset-patch]",
+                    line,
+                    indent + 1
+                )
+            };
+
+            let platform = platform
+                .trim()
+                .parse::<Platform>()
+                .map_err(|e| anyhow::anyhow!("{}:{}: {}", line, indent + 2, e))?;
+
+            let patch_type = patch_type.trim().parse::<PatchType>().map_err(|e| {
+                anyhow::anyhow!("{}:{}: {}", line, indent + header.find('.').unwrap() + 3, e)
+            })?;
+
+            section = Some(Section::Pattern(PatternSection {
+                line,
+                column: indent + 1,
+                platform,
+                patch_type,
+                find: None,
+                replace: None,
+                sha256: None,
+            }));
+
+            continue;
+        }
+
+        let Some((key, value)) = text.split_once('=') else {
+            bail!("{}:{}: expected 'key = value'", line, indent + 1)
+        };
+
+        let Some(current) = section.as_mut() else {
+            bail!("{}:{}: key outside of a section", line, indent + 1)
+        };
+
+        let key_column = indent + 1;
+        let value_start = indent + key.len() + 1 + (value.len() - value.trim_start().len());
+        let value = value.trim_start();
+        let key = key.trim();
+
+        // Strings are quoted, numbers and booleans are bare values
+        let (value, value_column, is_quoted) = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let Some((string, raw_length, rest)) = split_quoted(quoted) else {
+                    bail!("{}:{}: unterminated string", line, value_start + 1)
+                };
+
+                let rest = rest.trim();
+
+                if !rest.is_empty() && !rest.starts_with('#') {
+                    bail!(
+                        "{}:{}: unexpected '{}' after value",
+                        line,
+                        value_start + raw_length + 3,
+                        rest
+                    )
+                }
+
+                (string, value_start + 2, true)
+            }
+            None => {
+                let bare = value.split('#').next().unwrap().trim();
+
+                if bare.is_empty() {
+                    bail!("{}:{}: expected value", line, value_start + 1)
+                }
+
+                (bare.to_string(), value_start + 1, false)
+            }
+        };
+        let value = value.as_str();
+
+        match current {
+            Section::Pattern(current) => {
+                if !is_quoted {
+                    bail!("{}:{}: expected '\"'", line, value_start + 1)
+                }
+
+                if key == "sha256" {
+                    let sha256 = parse_sha256(value, line, value_column)?;
+                    set_once(&mut current.sha256, sha256, key, line, key_column)?;
+                    continue;
+                }
+
+                let pattern = parse_byte_pattern(value, line, value_column)?;
+
+                match key {
+                    "find" => set_once(&mut current.find, pattern, key, line, key_column)?,
+                    "replace" => set_once(&mut current.replace, pattern, key, line, key_column)?,
+                    key => bail!("{}:{}: unknown key '{}'", line, key_column, key),
+                }
+            }
+            Section::Header(current) => match key {
+                "magic" => {
+                    if !is_quoted {
+                        bail!("{}:{}: expected '\"'", line, value_start + 1)
+                    }
+
+                    let magic = parse_exact_bytes(value, line, value_column)?;
+                    set_once(&mut current.magic, magic, key, line, key_column)?
+                }
+                "version" => {
+                    let version = parse_int(value, line, value_column)?;
+                    set_once(&mut current.version, version, key, line, key_column)?
+                }
+                "encoded" => {
+                    let is_encoded = parse_bool(value, line, value_column)?;
+                    set_once(&mut current.is_encoded, is_encoded, key, line, key_column)?
+                }
+                "dlc" => {
+                    let is_dlc = parse_bool(value, line, value_column)?;
+                    set_once(&mut current.is_dlc, is_dlc, key, line, key_column)?
+                }
+                key => bail!("{}:{}: unknown key '{}'", line, key_column, key),
+            },
+            Section::LuajitTable(current) => match key {
+                "index" => {
+                    let index = parse_int(value, line, value_column)?;
+                    set_once(&mut current.index, index, key, line, key_column)?
+                }
+                "bytes" => {
+                    if !is_quoted {
+                        bail!("{}:{}: expected '\"'", line, value_start + 1)
+                    }
+
+                    let bytes = parse_exact_bytes(value, line, value_column)?;
+                    set_once(&mut current.bytes, bytes, key, line, key_column)?
+                }
+                "offset" => {
+                    let offset = parse_int(value, line, value_column)?;
+                    set_once(&mut current.offset, offset, key, line, key_column)?
+                }
+                key => bail!("{}:{}: unknown key '{}'", line, key_column, key),
+            },
+            Section::OffsetPatch(current) => {
+                if key == "offset" {
+                    let offset = parse_int(value, line, value_column)?;
+                    set_once(&mut current.offset, offset, key, line, key_column)?;
+                    continue;
+                }
+
+                if !is_quoted {
+                    bail!("{}:{}: expected '\"'", line, value_start + 1)
+                }
+
+                match key {
+                    "name" => {
+                        set_once(&mut current.name, value.to_string(), key, line, key_column)?
+                    }
+                    "sha256" => {
+                        let sha256 = parse_sha256(value, line, value_column)?;
+                        set_once(&mut current.sha256, sha256, key, line, key_column)?
+                    }
+                    "original" => {
+                        let original = parse_exact_bytes(value, line, value_column)?;
+                        set_once(&mut current.original, original, key, line, key_column)?
+                    }
+                    "replace" => {
+                        let replace = parse_exact_bytes(value, line, value_column)?;
+                        set_once(&mut current.replace, replace, key, line, key_column)?
+                    }
+                    key => bail!("{}:{}: unknown key '{}'", line, key_column, key),
+                }
+            }
+        }
+    }
+
+    if let Some(section) = section.take() {
+        finish_section(section, &mut file)?;
+    }
+
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(source: &str) -> String {
+        match parse_pattern_file(source) {
+            Ok(_) => panic!("parsed: {}", source),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let cases = [
+            ("[steam.CorruptFile", "1:19: expected ']'"),
+            ("find = \"00\"", "1:1: key outside of a section"),
+            ("[steam]", "1:1: expected [platform.PatchType]"),
+            (
+                "[steam.CorruptFile]\nfind \"00\"",
+                "2:1: expected 'key = value'",
+            ),
+            (
+                "[steam.CorruptFile]\nfind = \"00",
+                "2:8: unterminated string",
+            ),
+            ("[steam.CorruptFile]\nfind = 00", "2:8: expected '\"'"),
+            (
+                "[steam.CorruptFile]\nfind = \"0G\"",
+                "2:9: invalid hex byte '0G'",
+            ),
+            (
+                "[steam.CorruptFile]\nfind = \"00\"\nfind = \"01\"",
+                "3:1: duplicate key 'find'",
+            ),
+            (
+                "[steam.CorruptFile]\nfind = \"00\"",
+                "1:1: section is missing 'replace'",
+            ),
+            (
+                "  [luau-header]\n  version = 6",
+                "1:3: section is missing 'magic'",
+            ),
+            ("[luau-header]\nversion = x", "2:11: invalid number 'x'"),
+            (
+                "[luau-header]\nmagic = \"02\"\nsize = 1",
+                "3:1: unknown key 'size'",
+            ),
+        ];
+
+        for (source, expected) in cases {
+            let error = parse_error(source);
+            assert!(error.starts_with(expected), "{:?}: {}", source, error);
+        }
+    }
+
+    #[test]
+    fn parse_sections() {
+        let file = parse_pattern_file(
+            "# comment\n\
+             [steam.CorruptFile]\n\
+             find = \"0F 84 <a> ?? 00\" # trailing comment\n\
+             replace = \"90 E9 <a> ?? 00\"\n\
+             \n\
+             [luau-header]\n\
+             magic = \"02 F2\"\n\
+             version = 6\n\
+             encoded = true\n\
+             \n\
+             [luajit-table]\n\
+             index = 0x05\n\
+             bytes = \"01 02 03 04 05 06 07 08\"\n\
+             \n\
+             [offset-patch]\n\
+             offset = 0x10\n\
+             original = \"74\"\n\
+             replace = \"EB\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(file.patterns.len(), 1);
+        assert_eq!(file.patterns[0].find.to_string(), "0F 84 <a> ?? 00");
+        assert_eq!(file.headers[0].magic, [0x02, 0xF2]);
+        assert!(file.headers[0].is_encoded && !file.headers[0].is_dlc);
+        assert_eq!(file.luajit_tables[0].0, 5);
+        assert_eq!(file.luajit_tables[0].1.offset, 4);
+        assert_eq!(file.offset_patches[0].offset, 0x10);
+    }
+
+    #[test]
+    fn parse_escaped_name() {
+        let file = parse_pattern_file(
+            "[offset-patch]\n\
+             name = \"Skip \\\"intro\\\" in C:\\\\Games\\ok\" # comment\n\
+             offset = 16\n\
+             original = \"74\"\n\
+             replace = \"EB\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            file.offset_patches[0].name.as_deref(),
+            Some("Skip \"intro\" in C:\\Games\\ok")
+        );
+
+        let error = parse_error("[offset-patch]\nname = \"a\\\"");
+        assert!(error.starts_with("2:8: unterminated string"), "{}", error);
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let mut file = PatternFile::builtin();
+        file.offset_patches.push(OffsetPatch {
+            name: Some("Fix \"quoted\" C:\\path\\".to_string()),
+            offset: 0x1234,
+            original: vec![0x74, 0x05],
+            replace: vec![0xEB, 0x05],
+            sha256: Some("ab".repeat(32)),
+        });
+
+        let parsed = parse_pattern_file(&export_patterns(&file)).unwrap();
+
+        assert_eq!(parsed.patterns.len(), file.patterns.len());
+
+        for (parsed, pattern) in parsed.patterns.iter().zip(file.patterns.iter()) {
+            assert_eq!(parsed.platform, pattern.platform);
+            assert_eq!(parsed.patch_type, pattern.patch_type);
+            assert_eq!(parsed.find.to_string(), pattern.find.to_string());
+            assert_eq!(parsed.replace.to_string(), pattern.replace.to_string());
+            assert_eq!(parsed.sha256, pattern.sha256);
+        }
+
+        assert_eq!(parsed.headers, file.headers);
+        assert_eq!(parsed.luajit_tables, file.luajit_tables);
+
+        let (parsed, patch) = (&parsed.offset_patches[0], &file.offset_patches[0]);
+        assert_eq!(parsed.name, patch.name);
+        assert_eq!(parsed.offset, patch.offset);
+        assert_eq!(parsed.original, patch.original);
+        assert_eq!(parsed.replace, patch.replace);
+        assert_eq!(parsed.sha256, patch.sha256);
+    }
+}
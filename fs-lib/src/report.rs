@@ -0,0 +1,201 @@
+use std::{
+    fmt::{self, Display},
+    process::ExitCode,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+/// Everything processed
+pub const EXIT_OK: u8 = 0;
+
+/// Some inputs failed, or a fatal error without a more specific code
+pub const EXIT_FAILURE: u8 = 1;
+
+/// Invalid arguments or options
+pub const EXIT_USAGE: u8 = 2;
+
+/// Missing external tool or unsupported system
+pub const EXIT_ENVIRONMENT: u8 = 3;
+
+/// Errors with a dedicated exit code, created with [`usage`] and [`environment`]
+#[derive(Debug)]
+pub enum Failure {
+    Usage(String),
+    Environment(String),
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Usage(message) | Failure::Environment(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Failure {}
+
+pub fn usage<S: Into<String>>(message: S) -> Failure {
+    Failure::Usage(message.into())
+}
+
+pub fn environment<S: Into<String>>(message: S) -> Failure {
+    Failure::Environment(message.into())
+}
+
+/// Counts of processed inputs, can be shared between threads
+#[derive(Debug, Default)]
+pub struct Summary {
+    processed: AtomicUsize,
+    failed: AtomicUsize,
+    skipped: AtomicUsize,
+    denied: AtomicUsize,
+}
+
+impl Summary {
+    pub fn processed(&self) {
+        self.processed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn skipped(&self) {
+        self.skipped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Input skipped because of the deny-list in the config file
+    pub fn denied<D: Display>(&self, name: D, reason: &str) {
+        eprintln!("{}: skipped, {}", name, reason);
+        self.skipped();
+        self.denied.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Input processed with problems that were already printed
+    pub fn failed(&self) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Prints the error of an input and counts it as failed
+    pub fn error<D: Display>(&self, name: D, error: &anyhow::Error) {
+        eprintln!("{}: {:#}", name, error);
+        self.failed();
+    }
+
+    /// Counts the result of an input
+    pub fn add<D: Display, T>(&self, name: D, result: &Result<T>) {
+        match result {
+            Ok(_) => self.processed(),
+            Err(e) => self.error(name, e),
+        }
+    }
+
+    /// Prints buffered output of a parallel run in input order, errors are
+    /// counted as failed
+    pub fn print_outputs<D: Display>(&self, outputs: Vec<(D, Result<Vec<String>>)>) {
+        for (name, output) in outputs {
+            match output {
+                Ok(lines) => {
+                    for line in lines {
+                        println!("{}", line);
+                    }
+                }
+                Err(e) => self.error(name, &e),
+            }
+        }
+    }
+
+    pub fn failed_count(&self) -> usize {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self.failed_count() {
+            0 => EXIT_OK,
+            _ => EXIT_FAILURE,
+        }
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Summary: {} processed, {} failed, {} skipped",
+            self.processed.load(Ordering::Relaxed),
+            self.failed_count(),
+            self.skipped.load(Ordering::Relaxed)
+        )?;
+
+        match self.denied.load(Ordering::Relaxed) {
+            0 => Ok(()),
+            denied => write!(f, " ({} denied)", denied),
+        }
+    }
+}
+
+/// Durations of the stages of a run, printed with `--timings`
+#[derive(Debug)]
+pub struct Timings {
+    enabled: bool,
+    start: Instant,
+    last: Instant,
+    stages: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    pub fn new(enabled: bool) -> Self {
+        let now = Instant::now();
+
+        Self {
+            enabled,
+            start: now,
+            last: now,
+            stages: Vec::new(),
+        }
+    }
+
+    /// Ends a stage, it lasted since the previous one ended
+    pub fn stage(&mut self, name: &'static str) {
+        let now = Instant::now();
+
+        self.stages.push((name, now - self.last));
+        self.last = now;
+    }
+
+    /// Prints the stages and the total to stderr, if enabled
+    pub fn print(&self) {
+        if !self.enabled {
+            return;
+        }
+
+        eprintln!("Timings:");
+
+        for (name, duration) in self.stages.iter() {
+            eprintln!("  {:<16}{:>10.3?}", name, duration);
+        }
+
+        eprintln!("  {:<16}{:>10.3?}", "total", self.start.elapsed());
+    }
+}
+
+pub fn error_exit_code(error: &anyhow::Error) -> u8 {
+    match error.downcast_ref::<Failure>() {
+        Some(Failure::Usage(_)) => EXIT_USAGE,
+        Some(Failure::Environment(_)) => EXIT_ENVIRONMENT,
+        None => EXIT_FAILURE,
+    }
+}
+
+/// Prints the summary line (stderr, so stdout output stays usable) or the
+/// error and returns the exit code, used as `fn main() -> ExitCode`
+pub fn finish(result: Result<Summary>) -> ExitCode {
+    match result {
+        Ok(summary) => {
+            eprintln!("{}", summary);
+            ExitCode::from(summary.exit_code())
+        }
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            ExitCode::from(error_exit_code(&e))
+        }
+    }
+}
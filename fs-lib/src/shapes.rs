@@ -0,0 +1,76 @@
+use anyhow::{Result, bail};
+
+/// Bytes needed to tell if a file is locked
+pub const HEADER_SIZE: usize = 4;
+
+pub fn is_locked(buffer: &[u8]) -> Result<bool> {
+    if buffer.len() < HEADER_SIZE {
+        bail!("Truncated header")
+    }
+
+    match buffer[0] {
+        0x05 | 0x06 | 0x07 | 0x09 | 0x0A => Ok(buffer[1] != 0 || buffer[3] != 0),
+        0x00 | 0x01 => Ok(buffer[2] != 0),
+        _ => bail!("Unknown format"),
+    }
+}
+
+/// Unlocks a .i3d.shapes file in place, only the header changes
+pub fn unlock(buffer: &mut [u8]) -> Result<()> {
+    if buffer.len() < HEADER_SIZE {
+        bail!("Truncated header")
+    }
+
+    match buffer[0] {
+        0x05 | 0x06 | 0x07 | 0x09 | 0x0A => {
+            // FS22, FS25 (0x0A, 0x06, 0x09)
+            buffer[1] = 0;
+            buffer[2] = buffer[2].wrapping_sub(0x0D);
+            buffer[3] = 0;
+        }
+        0x00 | 0x01 => {
+            // Legacy
+            buffer[0] = 0;
+            buffer[1] = buffer[1].wrapping_sub(0x0D);
+            buffer[2] = 0;
+        }
+        _ => bail!("Unknown format"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdata::{check_golden, golden_file, lock_shapes, shapes_file};
+
+    const BODY: [u8; 4] = [0xDE, 0xAD, 0xBE, 0xEF];
+
+    #[test]
+    fn lock_unlock_round_trip() {
+        for format in [0x00, 0x01, 0x05, 0x06, 0x07, 0x09, 0x0A] {
+            let unlocked = shapes_file(format, &BODY);
+            assert!(!is_locked(&unlocked).unwrap());
+
+            let mut buffer = unlocked.clone();
+            lock_shapes(&mut buffer, 0x42).unwrap();
+            assert!(is_locked(&buffer).unwrap());
+
+            if format == 0x0A {
+                check_golden(&golden_file("locked_fs25.i3d.shapes"), &buffer).unwrap();
+            }
+
+            unlock(&mut buffer).unwrap();
+            assert_eq!(buffer, unlocked);
+        }
+    }
+
+    #[test]
+    fn invalid_header() {
+        assert!(is_locked(&[0x0A, 0x00]).is_err());
+        assert!(unlock(&mut [0x0A, 0x00]).is_err());
+        assert!(is_locked(&[0xFF, 0x00, 0x00, 0x00]).is_err());
+        assert!(unlock(&mut [0xFF, 0x00, 0x00, 0x00]).is_err());
+    }
+}
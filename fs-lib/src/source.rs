@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Name used for functions defined without a table
+pub const GLOBAL_CLASS: &str = "_G";
+
+lazy_static! {
+    // function Class.name( / function Class:name( / function name(
+    static ref FUNCTION_STATEMENT: Regex =
+        Regex::new(r"^\s*function\s+(?:([\w.]+)([.:]))?(\w+)\s*\(").unwrap();
+    // Class.name = function(
+    static ref FUNCTION_ASSIGNMENT: Regex =
+        Regex::new(r"^\s*([\w.]+)\.(\w+)\s*=\s*function\s*\(").unwrap();
+}
+
+/// Tracks multi-line long strings and comments (`[[ ]]`, `[==[ ]==]`) while
+/// processing Lua source line by line, their content must not be modified
+#[derive(Default)]
+pub struct LongStringState {
+    close: Option<String>,
+}
+
+/// Returns the closing bracket if a long bracket opens at the start of text
+fn long_bracket_close(text: &str) -> Option<String> {
+    let rest = text.strip_prefix('[')?;
+    let level = rest.len() - rest.trim_start_matches('=').len();
+
+    rest[level..]
+        .starts_with('[')
+        .then(|| format!("]{}]", "=".repeat(level)))
+}
+
+impl LongStringState {
+    /// Returns true if the line starts inside a long string
+    pub fn update(&mut self, line: &str) -> bool {
+        let is_inside = self.close.is_some();
+        let mut rest = line;
+
+        loop {
+            match &self.close {
+                Some(close) => match rest.find(close.as_str()) {
+                    Some(position) => {
+                        rest = &rest[position + close.len()..];
+                        self.close = None;
+                    }
+                    None => break,
+                },
+                None => match rest.find('[') {
+                    Some(position) => {
+                        rest = &rest[position..];
+
+                        match long_bracket_close(rest) {
+                            Some(close) => {
+                                rest = &rest[close.len()..];
+                                self.close = Some(close);
+                            }
+                            None => rest = &rest[1..],
+                        }
+                    }
+                    None => break,
+                },
+            }
+        }
+
+        is_inside
+    }
+
+    /// Returns true if the last line ended inside a long string
+    pub fn is_open(&self) -> bool {
+        self.close.is_some()
+    }
+}
+
+/// Prefixes of numbered temporaries emitted by the decompiler
+const TEMP_PREFIXES: [&str; 4] = ["var", "temp", "tmp", "v"];
+
+/// Returns the prefix (including an optional `_`) of a numbered temporary
+fn temp_prefix(identifier: &str) -> Option<&str> {
+    TEMP_PREFIXES.iter().find_map(|prefix| {
+        let rest = identifier.strip_prefix(prefix)?;
+        let digits = rest.strip_prefix('_').unwrap_or(rest);
+
+        (!digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+            .then(|| &identifier[..identifier.len() - digits.len()])
+    })
+}
+
+/// New names of numbered temporaries and the number of names per prefix
+#[derive(Default)]
+struct Renames {
+    names: HashMap<String, String>,
+    counts: HashMap<String, usize>,
+}
+
+impl Renames {
+    fn get(&mut self, identifier: &str, prefix: &str) -> &str {
+        if !self.names.contains_key(identifier) {
+            let count = self.counts.entry(prefix.to_string()).or_default();
+            *count += 1;
+
+            self.names
+                .insert(identifier.to_string(), format!("{}{}", prefix, count));
+        }
+
+        &self.names[identifier]
+    }
+
+    fn clear(&mut self) {
+        self.names.clear();
+        self.counts.clear();
+    }
+}
+
+/// Returns true for numbered temporaries of the decompiler, e.g. `v12`
+pub fn is_temporary(identifier: &str) -> bool {
+    temp_prefix(identifier).is_some()
+}
+
+/// Replaces identifiers in the code part of a line, strings and comments are
+/// copied as is. Fields and methods (`self.tmp1`, `obj:tmp2()`) are kept.
+/// `rename` returns the new name or None to keep it.
+pub fn rename_identifiers<F: FnMut(&str) -> Option<String>>(line: &str, mut rename: F) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut chars = line.char_indices().peekable();
+    // The previous token is `.` or `:`, but not `..`, `...` or `::`
+    let mut is_member = false;
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '.' | ':' => {
+                let is_repeated = line[..index].ends_with(c) || line[index + 1..].starts_with(c);
+
+                is_member = !is_repeated;
+                output.push(c);
+            }
+            c if c.is_whitespace() => output.push(c),
+            '"' | '\'' => {
+                is_member = false;
+                output.push(c);
+
+                while let Some((_, next)) = chars.next() {
+                    output.push(next);
+
+                    if next == '\\' {
+                        if let Some((_, escaped)) = chars.next() {
+                            output.push(escaped);
+                        }
+                    } else if next == c {
+                        break;
+                    }
+                }
+            }
+            '-' if line[index..].starts_with("--") => {
+                output.push_str(&line[index..]);
+                break;
+            }
+            '[' if long_bracket_close(&line[index..]).is_some() => {
+                output.push_str(&line[index..]);
+                break;
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = index + c.len_utf8();
+
+                while let Some((next_index, next)) = chars.peek() {
+                    if !next.is_ascii_alphanumeric() && *next != '_' {
+                        break;
+                    }
+
+                    end = next_index + next.len_utf8();
+                    chars.next();
+                }
+
+                let identifier = &line[index..end];
+
+                match is_member {
+                    true => output.push_str(identifier),
+                    false => match rename(identifier) {
+                        Some(name) => output.push_str(&name),
+                        None => output.push_str(identifier),
+                    },
+                }
+
+                is_member = false;
+            }
+            _ => {
+                is_member = false;
+                output.push(c);
+            }
+        }
+    }
+
+    output
+}
+
+/// Renames numbered temporaries in the code part of a line
+fn renumber_line(line: &str, renames: &mut Renames) -> String {
+    rename_identifiers(line, |identifier| {
+        temp_prefix(identifier).map(|prefix| renames.get(identifier, prefix).to_string())
+    })
+}
+
+/// Removes differences between decompiler runs that don't reflect code
+/// changes: numbered temporaries are renumbered in order of appearance
+/// within each top-level function, trailing whitespace and CRLF line
+/// endings are removed
+pub fn stabilize(source: &str) -> String {
+    let mut state = LongStringState::default();
+    let mut renames = Renames::default();
+    let mut lines = Vec::new();
+
+    for line in source.lines() {
+        if state.update(line) {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        if line.starts_with("function ") || line.starts_with("local function ") {
+            renames.clear();
+        }
+
+        let line = renumber_line(line, &mut renames);
+
+        lines.push(match state.is_open() {
+            true => line,
+            false => line.trim_end().to_string(),
+        });
+    }
+
+    let mut output = lines.join("\n");
+
+    if source.ends_with('\n') {
+        output.push('\n');
+    }
+
+    output
+}
+
+pub struct FunctionDefinition {
+    pub class: String,
+    pub function: String,
+    /// 1-based
+    pub line: usize,
+    pub method: bool,
+}
+
+/// Finds global and table function definitions, local functions are skipped
+pub fn function_definitions(source: &str) -> Vec<FunctionDefinition> {
+    let mut definitions = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        if let Some(captures) = FUNCTION_STATEMENT.captures(line) {
+            definitions.push(FunctionDefinition {
+                class: captures
+                    .get(1)
+                    .map_or(GLOBAL_CLASS, |m| m.as_str())
+                    .to_string(),
+                function: captures[3].to_string(),
+                line: index + 1,
+                method: captures.get(2).is_some_and(|m| m.as_str() == ":"),
+            });
+        } else if let Some(captures) = FUNCTION_ASSIGNMENT.captures(line) {
+            definitions.push(FunctionDefinition {
+                class: captures[1].to_string(),
+                function: captures[2].to_string(),
+                line: index + 1,
+                method: false,
+            });
+        }
+    }
+
+    definitions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename_temporaries(line: &str) -> String {
+        rename_identifiers(line, |identifier| {
+            is_temporary(identifier).then(|| "x".to_string())
+        })
+    }
+
+    #[test]
+    fn rename_skips_members() {
+        assert_eq!(
+            rename_temporaries("local v1 = self.tmp1 + obj:tmp2(v2)"),
+            "local x = self.tmp1 + obj:tmp2(x)"
+        );
+        assert_eq!(rename_temporaries("v1 = t . v2"), "x = t . v2");
+        assert_eq!(
+            rename_temporaries("print(v1 .. v2, ...)"),
+            "print(x .. x, ...)"
+        );
+        assert_eq!(
+            rename_temporaries("local v3 = v1 :: v2 -- v4"),
+            "local x = x :: x -- v4"
+        );
+        assert_eq!(
+            rename_temporaries("t[\"v1\"] = f(v1).v2"),
+            "t[\"v1\"] = f(x).v2"
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Minimal valid inputs for regression tests of the parsers, codecs and
+//! patchers, and golden file comparison. Enabled with the `testutil` feature.
+//!
+//! .gar archives are read by gar-lib, which has no writer, so there is no
+//! archive generator.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    LUAJIT_DECODE_TABLES, LUAU_DECODE_TABLES, buffer::BufferExtension, pattern::BytePattern,
+};
+
+/// Set to rewrite golden files with the actual output instead of comparing
+pub const UPDATE_GOLDEN_VAR: &str = "UPDATE_GOLDEN";
+
+/// First byte of encoded Luau files, regular and DLC table
+const LUAU_REGULAR_MARKER: u8 = 0x02;
+const LUAU_DLC_MARKER: u8 = 0x03;
+
+/// Luau types version written by the FS25 compiler
+const LUAU_TYPES_VERSION: u8 = 3;
+
+const LUAU_OP_RETURN: u32 = 22;
+const LUAU_OP_PREPVARARGS: u32 = 65;
+
+const LUAJIT_HEADER: [u8; 4] = [0x1B, 0x4C, 0x4A, 0x02];
+const LUAJIT_ENCODED_FLAGS: u8 = 0xFC;
+
+/// Added to the header byte that locking shifts in .i3d.shapes files
+const SHAPES_KEY: u8 = 0x0D;
+
+fn write_varint(output: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            output.push(byte);
+            return;
+        }
+
+        output.push(byte | 0x80);
+    }
+}
+
+/// Plain Luau bytecode of an empty chunk without debug info: `PREPVARARGS`,
+/// then `RETURN` without values
+pub fn luau_bytecode(version: u8) -> Vec<u8> {
+    let mut output = vec![version];
+
+    if version >= 4 {
+        output.push(LUAU_TYPES_VERSION);
+    }
+
+    // No strings, userdata type list terminator
+    write_varint(&mut output, 0);
+
+    if version >= 4 {
+        output.push(0);
+    }
+
+    // One function: max stack size, params, upvalues, vararg
+    write_varint(&mut output, 1);
+    output.extend_from_slice(&[0, 0, 0, 1]);
+
+    if version >= 4 {
+        // Flags, no type info
+        output.push(0);
+        write_varint(&mut output, 0);
+    }
+
+    let code = [LUAU_OP_PREPVARARGS, LUAU_OP_RETURN | (1 << 16)];
+    write_varint(&mut output, code.len());
+
+    for word in code {
+        output.extend_from_slice(&word.to_le_bytes());
+    }
+
+    // Constants, children, line defined, debug name, no line or debug info
+    output.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+
+    // Main function
+    write_varint(&mut output, 0);
+
+    output
+}
+
+/// Encodes plain Luau bytecode like a `.l64` file, with the DLC table if
+/// `is_dlc`
+pub fn encode_luau(bytecode: &[u8], is_dlc: bool) -> Result<Vec<u8>> {
+    let Some(&version) = bytecode.first() else {
+        bail!("Empty bytecode")
+    };
+
+    let Some(table) = LUAU_DECODE_TABLES.get(&(version, is_dlc)) else {
+        bail!("Missing bytecode shift table for version {}", version)
+    };
+
+    let mut output = bytecode.to_vec();
+    output.insert(0, 0);
+    output.shift_bytes_reversed(&table.bytes, table.offset, table.mask);
+    output[0] = match is_dlc {
+        true => LUAU_DLC_MARKER,
+        false => LUAU_REGULAR_MARKER,
+    };
+
+    Ok(output)
+}
+
+/// Luau `.l64` file of an empty chunk, plain or encoded
+pub fn luau_l64(version: u8, is_encoded: bool, is_dlc: bool) -> Result<Vec<u8>> {
+    let bytecode = luau_bytecode(version);
+
+    match is_encoded {
+        true => encode_luau(&bytecode, is_dlc),
+        false => Ok(bytecode),
+    }
+}
+
+/// Plain LuaJIT file with the given flags, `body` follows the header as is
+pub fn luajit_bytecode(flags: u8, body: &[u8]) -> Vec<u8> {
+    let mut output = LUAJIT_HEADER.to_vec();
+    output.push(flags);
+    output.extend_from_slice(body);
+
+    output
+}
+
+/// Encoded LuaJIT `.l64` file for a built-in table. The plain flags are
+/// chosen so the encoded flags byte is the one FS writes.
+pub fn luajit_l64_encoded(index: u8, body: &[u8]) -> Result<Vec<u8>> {
+    let Some(table) = LUAJIT_DECODE_TABLES.get(&index) else {
+        bail!("No LuaJIT table {}", index)
+    };
+
+    let mut flags = vec![0; 4];
+    flags.push(LUAJIT_ENCODED_FLAGS);
+    flags.shift_bytes(&table.bytes, table.offset, table.mask);
+
+    let mut output = luajit_bytecode(flags[4], body);
+    output.shift_bytes_reversed(&table.bytes, table.offset, table.mask);
+    output[3] = index;
+
+    Ok(output)
+}
+
+/// Unlocked `.i3d.shapes` file, `format` is the first byte (0x05 and later
+/// for FS22/FS25). Legacy files (0x00/0x01) start with 0x00 when unlocked.
+pub fn shapes_file(format: u8, body: &[u8]) -> Vec<u8> {
+    let mut output = match format {
+        0x00 | 0x01 => vec![0x00, 0x01, 0x00, 0x00],
+        _ => vec![format, 0x00, 0x01, 0x00],
+    };
+
+    output.extend_from_slice(body);
+
+    output
+}
+
+/// Locks a `.i3d.shapes` file in place with `key` (not 0), the inverse of
+/// `shapes::unlock`
+pub fn lock_shapes(buffer: &mut [u8], key: u8) -> Result<()> {
+    if buffer.len() < 4 || key == 0 {
+        bail!("Truncated header or zero key")
+    }
+
+    match buffer[0] {
+        0x00 | 0x01 => {
+            buffer[0] = 0x01;
+            buffer[1] = buffer[1].wrapping_add(SHAPES_KEY);
+            buffer[2] = key;
+        }
+        _ => {
+            buffer[1] = key;
+            buffer[2] = buffer[2].wrapping_add(SHAPES_KEY);
+            buffer[3] = key;
+        }
+    }
+
+    Ok(())
+}
+
+/// Golden file of the fs-lib tests, in `fs-lib/tests/golden`
+pub fn golden_file(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name)
+}
+
+/// Compares output with a golden file. With `UPDATE_GOLDEN` set the file is
+/// written instead, e.g. after an intended output change.
+pub fn check_golden(path: &Path, actual: &[u8]) -> Result<()> {
+    if env::var_os(UPDATE_GOLDEN_VAR).is_some() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        return actual.to_vec().write_to_file(path);
+    }
+
+    let expected = fs::read(path).with_context(|| {
+        format!(
+            "{}, run with {}=1 to create it",
+            path.display(),
+            UPDATE_GOLDEN_VAR
+        )
+    })?;
+
+    if expected == actual {
+        return Ok(());
+    }
+
+    let offset = expected
+        .iter()
+        .zip(actual)
+        .position(|(a, b)| a != b)
+        .unwrap_or(expected.len().min(actual.len()));
+
+    let context = |buffer: &[u8]| {
+        let end = buffer.len().min(offset + 16);
+        BytePattern::from(&buffer[offset.min(end)..end]).to_string()
+    };
+
+    bail!(
+        "{} differs at byte {} ({} vs {} bytes)\n  expected: {}\n  actual:   {}",
+        path.display(),
+        offset,
+        expected.len(),
+        actual.len(),
+        context(&expected),
+        context(actual)
+    )
+}
@@ -0,0 +1,153 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use crate::has_extension;
+
+// Editors usually emit several events per save, collect them before processing
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Absolute path without `.` components, for comparing event paths
+fn absolute_path(path: &Path) -> Option<PathBuf> {
+    std::path::absolute(path)
+        .ok()
+        .map(|path| path.components().collect())
+}
+
+/// Watched folder as given and as absolute path, with the excluded folder
+struct Scope<'a> {
+    root: &'a Path,
+    absolute_root: Option<PathBuf>,
+    exclude: Option<PathBuf>,
+}
+
+impl Scope<'_> {
+    /// Event paths are absolute, handlers get them inside the folder as given
+    /// (e.g. `mod/file.xml`). None for files inside the excluded folder.
+    fn input_path(&self, path: &Path) -> Option<PathBuf> {
+        let absolute = absolute_path(path);
+
+        if let (Some(exclude), Some(absolute)) = (&self.exclude, &absolute)
+            && absolute.starts_with(exclude)
+        {
+            return None;
+        }
+
+        let relative = absolute
+            .as_deref()
+            .zip(self.absolute_root.as_deref())
+            .and_then(|(path, root)| path.strip_prefix(root).ok());
+
+        let path = match relative {
+            Some(relative) => self.root.join(relative),
+            None => path.to_path_buf(),
+        };
+
+        Some(path.components().collect())
+    }
+}
+
+fn collect_paths(
+    event: notify::Result<notify::Event>,
+    extension: &str,
+    scope: &Scope,
+    changed: &mut BTreeSet<PathBuf>,
+) -> Result<()> {
+    let event = event?;
+
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+        return Ok(());
+    }
+
+    for path in event.paths {
+        if !path.is_file() || !has_extension(&path, extension) {
+            continue;
+        }
+
+        if let Some(path) = scope.input_path(&path) {
+            changed.insert(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Blocks and calls `handler` for every file with the given extension (see
+/// [has_extension]) that is created or modified inside `path`. Files inside
+/// `exclude`, the output folder, are skipped so the tool's own writes aren't
+/// handled as inputs, unless the output folder contains `path`. Directory
+/// symlinks are only watched with `follow_symlinks`.
+pub fn watch_files<P, F>(
+    path: P,
+    extension: &str,
+    recursive: bool,
+    follow_symlinks: bool,
+    exclude: Option<&Path>,
+    handler: F,
+) -> Result<()>
+where
+    P: AsRef<Path>,
+    F: Fn(&PathBuf) -> Result<()>,
+{
+    let absolute_root = absolute_path(path.as_ref());
+    let scope = Scope {
+        root: path.as_ref(),
+        exclude: exclude.and_then(absolute_path).filter(|exclude| {
+            absolute_root
+                .as_ref()
+                .is_some_and(|root| !root.starts_with(exclude))
+        }),
+        absolute_root,
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let config = notify::Config::default().with_follow_symlinks(follow_symlinks);
+    let mut watcher = notify::RecommendedWatcher::new(tx, config)?;
+
+    let mode = match recursive {
+        true => RecursiveMode::Recursive,
+        false => RecursiveMode::NonRecursive,
+    };
+
+    watcher.watch(path.as_ref(), mode)?;
+
+    // Modification time of each file after it was last handled, used to skip
+    // events triggered by our own writes (e.g. when output == input)
+    let mut handled: HashMap<PathBuf, SystemTime> = HashMap::new();
+
+    loop {
+        let mut changed = BTreeSet::new();
+
+        collect_paths(rx.recv()?, extension, &scope, &mut changed)?;
+
+        while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+            collect_paths(event, extension, &scope, &mut changed)?;
+        }
+
+        for file in changed {
+            let modified = modified_time(&file);
+
+            if modified.is_some() && handled.get(&file) == modified.as_ref() {
+                continue;
+            }
+
+            if let Err(e) = handler(&file) {
+                eprintln!("{}: {}", file.display(), e);
+            }
+
+            if let Some(modified) = modified_time(&file) {
+                handled.insert(file, modified);
+            }
+        }
+    }
+}
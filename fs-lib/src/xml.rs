@@ -0,0 +1,805 @@
+use std::{collections::BTreeMap, fmt, io::Write, str::FromStr};
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use xml::{
+    EmitterConfig, EventWriter, ParserConfig,
+    attribute::OwnedAttribute,
+    common::{Position, XmlVersion},
+    namespace::{NS_EMPTY_URI, NS_NO_PREFIX, NS_XML_PREFIX, NS_XMLNS_PREFIX, Namespace},
+    reader::XmlEvent as ReaderEvent,
+    writer::XmlEvent as WriterEvent,
+};
+
+/// Minimal element tree for tools that inspect game XML (modDesc, i3d, l10n)
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Element {
+    pub name: String,
+    /// Attributes in document order
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    pub text: String,
+    /// Line of the start tag, 1-based
+    pub line: u64,
+    /// Written as a comment before the start tag, not read by [`parse`]
+    pub comment: Option<String>,
+}
+
+impl Element {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// Follows a `/` separated path of child names, e.g. `l10n/text`
+    pub fn find(&self, path: &str) -> Option<&Element> {
+        path.split('/')
+            .try_fold(self, |element, name| element.child(name))
+    }
+
+    /// Trimmed text content, None if empty
+    pub fn text(&self) -> Option<&str> {
+        Some(self.text.trim()).filter(|text| !text.is_empty())
+    }
+
+    /// All elements below this one, depth first
+    pub fn descendants(&self) -> Vec<&Element> {
+        let mut elements = Vec::new();
+
+        for child in self.children.iter() {
+            elements.push(child);
+            elements.extend(child.descendants());
+        }
+
+        elements
+    }
+}
+
+/// Parses a document and returns the root element, comments are dropped
+pub fn parse(buffer: &[u8]) -> Result<Element> {
+    let mut reader = ParserConfig::default()
+        .trim_whitespace(false)
+        .cdata_to_characters(true)
+        .create_reader(buffer);
+
+    let mut stack: Vec<Element> = Vec::new();
+
+    loop {
+        let event = reader.next()?;
+        // Position of the event just read
+        let line = reader.position().row + 1;
+
+        match event {
+            ReaderEvent::StartElement {
+                name, attributes, ..
+            } => {
+                stack.push(Element {
+                    name: name.local_name,
+                    attributes: attributes
+                        .into_iter()
+                        .map(|attr| (attr.name.local_name, attr.value))
+                        .collect(),
+                    line,
+                    ..Default::default()
+                });
+            }
+            ReaderEvent::EndElement { .. } => {
+                let Some(element) = stack.pop() else {
+                    bail!("Unexpected end element on line {}", line)
+                };
+
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            ReaderEvent::Characters(text) | ReaderEvent::Whitespace(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text);
+                }
+            }
+            ReaderEvent::EndDocument => bail!("Missing root element"),
+            _ => {}
+        }
+    }
+}
+
+/// Writer settings shared by the tools re-emitting XML
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    pub indent: String,
+    pub escape: bool,
+    pub eol: Eol,
+    pub wrap_attributes: Option<WrapAttributes>,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: " ".repeat(4),
+            escape: true,
+            eol: Eol::Lf,
+            wrap_attributes: None,
+        }
+    }
+}
+
+/// When start tags are broken into one attribute per line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapAttributes {
+    /// Tags longer than the column
+    Column(usize),
+    /// Tags with more attributes than the count
+    Count(usize),
+}
+
+impl FromStr for WrapAttributes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = s.split_once(':').unwrap_or(("column", s));
+        let Ok(value) = value.parse::<usize>() else {
+            return Err(format!("Invalid attribute wrap value: {}", s));
+        };
+
+        match kind {
+            "column" => Ok(WrapAttributes::Column(value)),
+            "count" => Ok(WrapAttributes::Count(value)),
+            _ => Err(format!(
+                "Unknown attribute wrap mode: {}, expected column:<n> or count:<n>",
+                kind
+            )),
+        }
+    }
+}
+
+/// Splits the attributes of a start tag line (`<name a="1" b="2">`) into
+/// name, attributes and the tag end, None for other lines
+fn split_start_tag(line: &str) -> Option<(&str, Vec<&str>, &str)> {
+    let tag = line.strip_prefix('<')?;
+
+    if tag.starts_with(['?', '!', '/']) {
+        return None;
+    }
+
+    let name_end = tag.find([' ', '>', '/'])?;
+    let name = &tag[..name_end];
+    let mut rest = &tag[name_end..];
+    let mut attributes = Vec::new();
+
+    loop {
+        rest = rest.trim_start();
+
+        if rest.starts_with('>') || rest.starts_with("/>") {
+            return Some((name, attributes, rest));
+        }
+
+        // Values are always written with double quotes
+        let value_start = rest.find("=\"")? + 2;
+        let value_end = value_start + rest[value_start..].find('"')? + 1;
+
+        attributes.push(&rest[..value_end]);
+        rest = &rest[value_end..];
+    }
+}
+
+/// Breaks wide start tags into aligned attribute lines:
+///
+/// ```xml
+/// <wheel filename="..."
+///        isLeft="true"
+///        hasParticles="false"/>
+/// ```
+fn wrap_attributes(output: &[u8], wrap: WrapAttributes) -> Vec<u8> {
+    let output = String::from_utf8_lossy(output);
+    let mut wrapped = String::with_capacity(output.len());
+    // Multi line comments and CDATA are left alone
+    let mut inside: Option<&str> = None;
+
+    for line in output.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\r', '\n']);
+        let eol = &line[content.len()..];
+        let text = content.trim_start();
+        let indent = &content[..content.len() - text.len()];
+
+        if let Some(end) = inside {
+            if text.contains(end) {
+                inside = None;
+            }
+
+            wrapped.push_str(line);
+            continue;
+        }
+
+        if text.starts_with("<!--") && !text.contains("-->") {
+            inside = Some("-->");
+        } else if text.starts_with("<![CDATA[") && !text.contains("]]>") {
+            inside = Some("]]>");
+        }
+
+        let Some((name, attributes, end)) = split_start_tag(text) else {
+            wrapped.push_str(line);
+            continue;
+        };
+
+        // Only tags that end on this line, `<a x="1">text</a>` stays as is
+        let is_wide = match wrap {
+            WrapAttributes::Column(column) => content.chars().count() > column,
+            WrapAttributes::Count(count) => attributes.len() > count,
+        };
+
+        if attributes.len() < 2 || !is_wide {
+            wrapped.push_str(line);
+            continue;
+        }
+
+        let align = format!("{}{}", indent, " ".repeat(name.len() + 2));
+        let last_line = match eol.is_empty() {
+            true => "\n",
+            false => eol,
+        };
+
+        for (index, attribute) in attributes.iter().enumerate() {
+            match index {
+                0 => wrapped.push_str(&format!("{}<{} {}", indent, name, attribute)),
+                _ => wrapped.push_str(&format!("{}{}{}", last_line, align, attribute)),
+            }
+        }
+
+        wrapped.push_str(end);
+        wrapped.push_str(eol);
+    }
+
+    wrapped.into_bytes()
+}
+
+/// Line endings of re-emitted documents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Eol {
+    #[default]
+    Lf,
+    CrLf,
+    /// Same as the input document, LF if it has no line breaks
+    Auto,
+}
+
+impl FromStr for Eol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "lf" => Ok(Eol::Lf),
+            "crlf" => Ok(Eol::CrLf),
+            "auto" => Ok(Eol::Auto),
+            _ => Err(format!(
+                "Unknown line ending: {}, expected lf, crlf or auto",
+                s
+            )),
+        }
+    }
+}
+
+impl Eol {
+    fn separator(&self, input: &[u8]) -> &'static str {
+        let crlf = match self {
+            Eol::Lf => false,
+            Eol::CrLf => true,
+            Eol::Auto => input
+                .iter()
+                .position(|&b| b == b'\n')
+                .is_some_and(|index| index > 0 && input[index - 1] == b'\r'),
+        };
+
+        match crlf {
+            true => "\r\n",
+            false => "\n",
+        }
+    }
+}
+
+/// Element path relative to the root element with an optional attribute,
+/// e.g. `statistics/money` or `farm[farmId=1]@money`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    steps: Vec<(String, Option<(String, String)>)>,
+    attribute: Option<String>,
+}
+
+impl FromStr for Selector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, attribute) = match s.rsplit_once('@') {
+            Some((path, attribute)) => (path, Some(attribute.to_string())),
+            None => (s, None),
+        };
+
+        let steps = path
+            .split('/')
+            .map(|step| match step.split_once('[') {
+                Some((name, predicate)) => {
+                    let Some((key, value)) = predicate
+                        .strip_suffix(']')
+                        .and_then(|predicate| predicate.split_once('='))
+                    else {
+                        return Err(format!("Invalid selector step: {}", step));
+                    };
+
+                    Ok((name.to_string(), Some((key.to_string(), value.to_string()))))
+                }
+                None => Ok((step.to_string(), None)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if steps.iter().any(|(name, _)| name.is_empty()) {
+            return Err(format!("Invalid selector: {}", s));
+        }
+
+        Ok(Selector { steps, attribute })
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (name, predicate)) in self.steps.iter().enumerate() {
+            if index > 0 {
+                write!(f, "/")?;
+            }
+            write!(f, "{}", name)?;
+
+            if let Some((key, value)) = predicate {
+                write!(f, "[{}={}]", key, value)?;
+            }
+        }
+
+        if let Some(attribute) = &self.attribute {
+            write!(f, "@{}", attribute)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Selector {
+    /// Stack holds (name, attributes) of the open elements, root first
+    fn matches(&self, stack: &[(String, Vec<OwnedAttribute>)]) -> bool {
+        stack.len() == self.steps.len() + 1
+            && stack[1..].iter().zip(self.steps.iter()).all(
+                |((name, attributes), (step, predicate))| {
+                    name == step
+                        && predicate.as_ref().is_none_or(|(key, value)| {
+                            attributes
+                                .iter()
+                                .any(|attr| attr.name.local_name == *key && attr.value == *value)
+                        })
+                },
+            )
+    }
+}
+
+/// Input is the document being re-emitted, used for [`Eol::Auto`]
+fn emitter_config(options: &FormatOptions, input: &[u8]) -> EmitterConfig {
+    let mut config = EmitterConfig::new()
+        .perform_indent(true)
+        .indent_string(options.indent.clone())
+        .line_separator(options.eol.separator(input))
+        .write_document_declaration(true);
+
+    config.perform_escaping = options.escape;
+
+    config
+}
+
+fn write_element<W: Write>(emitter: &mut EventWriter<W>, element: &Element) -> Result<()> {
+    if let Some(comment) = &element.comment {
+        emitter.write(WriterEvent::comment(comment))?;
+    }
+
+    let mut start = WriterEvent::start_element(element.name.as_str());
+
+    for (key, value) in element.attributes.iter() {
+        start = start.attr(key.as_str(), value);
+    }
+
+    emitter.write(start)?;
+
+    match element.children.is_empty() {
+        true => {
+            if let Some(text) = element.text() {
+                emitter.write(WriterEvent::characters(text))?;
+            }
+        }
+        false => {
+            for child in element.children.iter() {
+                write_element(emitter, child)?;
+            }
+        }
+    }
+
+    emitter.write(WriterEvent::end_element())?;
+
+    Ok(())
+}
+
+/// Writes an element tree as a document
+pub fn write_xml(root: &Element, options: &FormatOptions) -> Result<Vec<u8>> {
+    let mut output: Vec<u8> = Vec::new();
+    let mut emitter = emitter_config(options, &[]).create_writer(&mut output);
+
+    // Written up front so a root comment comes after the declaration
+    emitter.write(WriterEvent::StartDocument {
+        version: XmlVersion::Version10,
+        encoding: Some("UTF-8"),
+        standalone: None,
+    })?;
+    write_element(&mut emitter, root)?;
+
+    if let Some(wrap) = options.wrap_attributes {
+        output = wrap_attributes(&output, wrap);
+    }
+
+    Ok(output)
+}
+
+/// Parses and re-emits a document with consistent formatting
+pub fn format_xml(input: &[u8], options: &FormatOptions) -> Result<Vec<u8>> {
+    Ok(rewrite_xml(input, options, &[])?.0)
+}
+
+/// Formats a document and replaces the values of all elements/attributes
+/// matching each selector, returns the number of changes per selector
+pub fn set_values(
+    input: &[u8],
+    options: &FormatOptions,
+    values: &[(Selector, String)],
+) -> Result<(Vec<u8>, Vec<usize>)> {
+    rewrite_xml(input, options, values)
+}
+
+fn rewrite_xml(
+    input: &[u8],
+    options: &FormatOptions,
+    values: &[(Selector, String)],
+) -> Result<(Vec<u8>, Vec<usize>)> {
+    let mut reader = ParserConfig::default()
+        .ignore_root_level_whitespace(true)
+        .ignore_comments(false)
+        .cdata_to_characters(false)
+        .coalesce_characters(false)
+        .create_reader(input);
+
+    let mut output: Vec<u8> = Vec::new();
+    let mut emitter = emitter_config(options, input).create_writer(&mut output);
+    let mut counts = vec![0; values.len()];
+    let mut stack: Vec<(String, Vec<OwnedAttribute>)> = Vec::new();
+    // Replacement text of the current element and whether it was written
+    let mut text: Vec<Option<(&str, bool)>> = Vec::new();
+
+    loop {
+        let reader_event = reader.next()?;
+
+        match reader_event {
+            ReaderEvent::EndDocument => break,
+            ReaderEvent::StartElement {
+                name,
+                mut attributes,
+                namespace,
+            } => {
+                stack.push((name.local_name.clone(), attributes.clone()));
+                let mut replacement = None;
+
+                for (index, (selector, value)) in values.iter().enumerate() {
+                    if !selector.matches(&stack) {
+                        continue;
+                    }
+
+                    match &selector.attribute {
+                        Some(key) => {
+                            for attr in attributes.iter_mut() {
+                                if attr.name.local_name == *key {
+                                    attr.value = value.clone();
+                                    counts[index] += 1;
+                                }
+                            }
+                        }
+                        None => {
+                            replacement = Some((value.as_str(), false));
+                            counts[index] += 1;
+                        }
+                    }
+                }
+
+                text.push(replacement);
+
+                let event = WriterEvent::StartElement {
+                    name: name.borrow(),
+                    namespace: namespace.borrow(),
+                    attributes: attributes.iter().map(|attr| attr.borrow()).collect(),
+                };
+                emitter.write(event)?;
+            }
+            ReaderEvent::EndElement { name } => {
+                // Empty element, nothing was replaced yet
+                if let Some(Some((value, false))) = text.pop() {
+                    emitter.write(WriterEvent::Characters(value))?;
+                }
+                stack.pop();
+
+                emitter.write(WriterEvent::EndElement {
+                    name: Some(name.borrow()),
+                })?;
+            }
+            ReaderEvent::Characters(characters) => match text.last_mut() {
+                Some(Some((value, written))) => {
+                    if !*written {
+                        emitter.write(WriterEvent::Characters(value))?;
+                        *written = true;
+                    }
+                }
+                _ => emitter.write(WriterEvent::Characters(&characters))?,
+            },
+            ReaderEvent::Comment(comment) => {
+                emitter.write(WriterEvent::Comment(&comment))?;
+            }
+            other => {
+                if let Some(writer_event) = other.as_writer_event() {
+                    emitter.write(writer_event)?;
+                }
+            }
+        }
+    }
+
+    if let Some(wrap) = options.wrap_attributes {
+        output = wrap_attributes(&output, wrap);
+    }
+
+    Ok((output, counts))
+}
+
+/// XML declaration of a [`Document`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Declaration {
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub standalone: Option<bool>,
+}
+
+/// Element of a [`Document`], namespace declarations are kept as `xmlns`
+/// attributes before the other attributes and prefixed names as
+/// `prefix:name`
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
+    /// Attribute names in document order, attributes missing here are
+    /// written after these, sorted by name
+    #[serde(
+        default,
+        rename = "attributeOrder",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub attribute_order: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Node {
+    Element(Tag),
+    Comment { comment: String },
+    CData { cdata: String },
+    Text { text: String },
+}
+
+/// Document tree keeping everything but formatting whitespace, for converting
+/// game XML to other formats and back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub declaration: Option<Declaration>,
+    /// Comments outside the root element, written before it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub comments: Vec<String>,
+    pub root: Tag,
+}
+
+impl Tag {
+    /// Attributes in document order
+    pub fn ordered_attributes(&self) -> Vec<(&str, &str)> {
+        let ordered = self
+            .attribute_order
+            .iter()
+            .filter_map(|key| self.attributes.get_key_value(key));
+        let rest = self
+            .attributes
+            .iter()
+            .filter(|(key, _)| !self.attribute_order.contains(key));
+
+        ordered
+            .chain(rest)
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect()
+    }
+}
+
+/// Parses a document keeping comments, CDATA and attribute order
+pub fn parse_document(buffer: &[u8]) -> Result<Document> {
+    let mut reader = ParserConfig::default()
+        .ignore_root_level_whitespace(true)
+        .ignore_comments(false)
+        .cdata_to_characters(false)
+        .whitespace_to_characters(false)
+        .create_reader(buffer);
+
+    let mut declaration = None;
+    let mut comments = Vec::new();
+    let mut stack: Vec<Tag> = Vec::new();
+    // Namespaces in scope of the parent, to find the declarations of an element
+    let mut scopes: Vec<Namespace> = vec![Namespace::empty()];
+    scopes[0].put(NS_NO_PREFIX, NS_EMPTY_URI);
+    let mut root = None;
+
+    loop {
+        match reader.next()? {
+            ReaderEvent::StartDocument {
+                version,
+                encoding,
+                standalone,
+            } => {
+                declaration = Some(Declaration {
+                    version: version.to_string(),
+                    encoding: Some(encoding),
+                    standalone,
+                });
+            }
+            ReaderEvent::StartElement {
+                name,
+                attributes,
+                namespace,
+            } => {
+                let parent_scope = scopes.last().cloned().unwrap_or_else(Namespace::empty);
+                let mut tag = Tag {
+                    name: name.borrow().to_repr(),
+                    ..Default::default()
+                };
+
+                for (prefix, uri) in namespace.iter() {
+                    if matches!(prefix, NS_XML_PREFIX | NS_XMLNS_PREFIX)
+                        || parent_scope.get(prefix) == Some(uri)
+                    {
+                        continue;
+                    }
+
+                    let key = match prefix {
+                        NS_NO_PREFIX => NS_XMLNS_PREFIX.to_string(),
+                        prefix => format!("{}:{}", NS_XMLNS_PREFIX, prefix),
+                    };
+
+                    tag.attribute_order.push(key.clone());
+                    tag.attributes.insert(key, uri.to_string());
+                }
+
+                for attr in attributes {
+                    let key = attr.name.borrow().to_repr();
+
+                    tag.attribute_order.push(key.clone());
+                    tag.attributes.insert(key, attr.value);
+                }
+
+                scopes.push(namespace);
+                stack.push(tag);
+            }
+            ReaderEvent::EndElement { .. } => {
+                scopes.pop();
+
+                let Some(tag) = stack.pop() else {
+                    bail!("Unexpected end element")
+                };
+
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(Node::Element(tag)),
+                    None => root = Some(tag),
+                }
+            }
+            ReaderEvent::Characters(text) => {
+                if let Some(tag) = stack.last_mut() {
+                    tag.children.push(Node::Text { text });
+                }
+            }
+            ReaderEvent::CData(cdata) => {
+                if let Some(tag) = stack.last_mut() {
+                    tag.children.push(Node::CData { cdata });
+                }
+            }
+            ReaderEvent::Comment(comment) => match stack.last_mut() {
+                Some(tag) => tag.children.push(Node::Comment { comment }),
+                None => comments.push(comment),
+            },
+            ReaderEvent::EndDocument => break,
+            _ => {}
+        }
+    }
+
+    let Some(root) = root else {
+        bail!("Missing root element")
+    };
+
+    Ok(Document {
+        declaration,
+        comments,
+        root,
+    })
+}
+
+fn write_tag<W: Write>(emitter: &mut EventWriter<W>, tag: &Tag) -> Result<()> {
+    let mut start = WriterEvent::start_element(tag.name.as_str());
+
+    for (key, value) in tag.ordered_attributes() {
+        start = start.attr(key, value);
+    }
+
+    emitter.write(start)?;
+
+    for child in tag.children.iter() {
+        match child {
+            Node::Element(tag) => write_tag(emitter, tag)?,
+            Node::Comment { comment } => emitter.write(WriterEvent::comment(comment))?,
+            Node::CData { cdata } => emitter.write(WriterEvent::cdata(cdata))?,
+            Node::Text { text } => emitter.write(WriterEvent::characters(text))?,
+        }
+    }
+
+    emitter.write(WriterEvent::end_element())?;
+
+    Ok(())
+}
+
+/// Writes a document from [`parse_document`], formatting whitespace is
+/// created from the options
+pub fn write_document(document: &Document, options: &FormatOptions) -> Result<Vec<u8>> {
+    let mut output: Vec<u8> = Vec::new();
+    let mut emitter = emitter_config(options, &[]).create_writer(&mut output);
+
+    let version = match document.declaration.as_ref().map(|d| d.version.as_str()) {
+        Some("1.1") => XmlVersion::Version11,
+        _ => XmlVersion::Version10,
+    };
+
+    emitter.write(WriterEvent::StartDocument {
+        version,
+        encoding: Some(
+            document
+                .declaration
+                .as_ref()
+                .and_then(|d| d.encoding.as_deref())
+                .unwrap_or("UTF-8"),
+        ),
+        standalone: document.declaration.as_ref().and_then(|d| d.standalone),
+    })?;
+
+    for comment in document.comments.iter() {
+        emitter.write(WriterEvent::comment(comment))?;
+    }
+
+    write_tag(&mut emitter, &document.root)?;
+
+    if let Some(wrap) = options.wrap_attributes {
+        output = wrap_attributes(&output, wrap);
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,124 @@
+use std::{
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, bail};
+use zip::{ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use crate::{buffer::BufferExtension, cache::CACHE_FILE_NAME, list_files, path, report};
+
+pub fn is_zip<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().is_file()
+        && path
+            .as_ref()
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// Extracts all files of a zip into a folder, returns the number of files
+pub fn extract<P: AsRef<Path>, Q: AsRef<Path>>(file: P, output: Q) -> Result<usize> {
+    let mut archive = ZipArchive::new(File::open(file)?)?;
+    let mut count = 0;
+
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+
+        if entry.is_dir() {
+            continue;
+        }
+
+        let mut buffer = Vec::new();
+        entry.read_to_end(&mut buffer)?;
+        buffer.write_to_file(
+            output
+                .as_ref()
+                .join(path::sanitize_entry_path(entry.name())?),
+        )?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// Packs all files of a folder into a zip, cache files of the tools are left
+/// out
+pub fn pack<P: AsRef<Path>, Q: AsRef<Path>>(dir: P, file: Q) -> Result<()> {
+    let dir = dir.as_ref();
+    let mut writer = ZipWriter::new(File::create(path::long_path(file))?);
+    let options = SimpleFileOptions::default();
+
+    for entry in list_files(dir, true, false)? {
+        let name = entry
+            .strip_prefix(dir)?
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        if name == CACHE_FILE_NAME {
+            continue;
+        }
+
+        writer.start_file(name, options)?;
+        writer.write_all(&Vec::read_from_file(&entry)?)?;
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Zip input of a batch tool, extracted to a temporary folder that the tool
+/// processes like a folder input. Removed when dropped.
+pub struct ZipInput {
+    dir: PathBuf,
+    /// Zip written from the processed folder by [`ZipInput::finish`]
+    repack: Option<PathBuf>,
+}
+
+impl ZipInput {
+    /// Extracts a zip input and points `input` at the extracted folder. A
+    /// `.zip` output is processed in place and repacked, other outputs are
+    /// left as folders. Returns None if the input isn't a zip.
+    pub fn open(input: &mut PathBuf, output: &mut Option<PathBuf>) -> Result<Option<Self>> {
+        if !is_zip(&input) {
+            return Ok(None);
+        }
+
+        let repack = match output {
+            Some(file) if file.extension().is_some_and(|ext| ext == "zip") => Some(file.clone()),
+            Some(_) => None,
+            None => bail!(report::usage(
+                "Output path is required for zip input (folder or .zip)"
+            )),
+        };
+
+        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+        let dir = std::env::temp_dir().join(format!("fs-utils-{}-{}", std::process::id(), stem));
+
+        let zip_input = Self { dir, repack };
+        extract(&input, &zip_input.dir)?;
+
+        *input = zip_input.dir.clone();
+
+        if zip_input.repack.is_some() {
+            *output = Some(zip_input.dir.clone());
+        }
+
+        Ok(Some(zip_input))
+    }
+
+    /// Writes the repacked zip, if the output is one
+    pub fn finish(self) -> Result<()> {
+        match &self.repack {
+            Some(file) => pack(&self.dir, file),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ZipInput {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,328 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    buffer::BufferExtension,
+    report::{self, Summary},
+    source::function_definitions,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    // 2024-11-15 14:22:03.123 at the start of each line (FS25)
+    static ref TIMESTAMP: Regex =
+        Regex::new(r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2}(:\d{2}(\.\d+)?)? ").unwrap();
+    // dataS/scripts/vehicles/Vehicle.lua (482) : update
+    static ref STACK_FRAME: Regex =
+        Regex::new(r"^\s*(.+?\.lua)\s*\((\d+)\)\s*:?\s*(.*)$").unwrap();
+    // -- line 482 / --[[ line 482 ]] written by decompilers keeping line numbers
+    static ref LINE_COMMENT: Regex = Regex::new(r"--(?:\[\[)?\s*line (\d+)").unwrap();
+}
+
+/// Lower case message parts of missing file errors
+const MISSING_FILE_MESSAGES: [&str; 5] = [
+    "can't load resource",
+    "failed to open",
+    "file not found",
+    "does not exist",
+    "could not find file",
+];
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Summarize errors and warnings of the game log.txt, with Lua stack frames
+/// mapped to decompiled scripts
+pub struct Cmd {
+    /// decompiled script folder, stack frames are mapped to files and lines
+    /// in it
+    #[argh(option, short = 's')]
+    scripts: Option<PathBuf>,
+
+    /// only report errors
+    #[argh(switch, short = 'e')]
+    errors_only: bool,
+
+    /// path to log.txt
+    #[argh(positional)]
+    input: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Category {
+    Lua,
+    MissingFile,
+    Xml,
+    Other,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Category::Lua => f.write_str("lua"),
+            Category::MissingFile => f.write_str("missing file"),
+            Category::Xml => f.write_str("xml"),
+            Category::Other => f.write_str("other"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Frame {
+    file: String,
+    line: usize,
+    function: String,
+}
+
+#[derive(Debug)]
+struct Entry {
+    level: Level,
+    /// Line in the log, 1-based
+    line: usize,
+    message: String,
+    frames: Vec<Frame>,
+}
+
+impl Entry {
+    fn category(&self) -> Category {
+        let message = self.message.to_lowercase();
+
+        if !self.frames.is_empty() || message.contains("lua") {
+            Category::Lua
+        } else if MISSING_FILE_MESSAGES.iter().any(|m| message.contains(m)) {
+            Category::MissingFile
+        } else if message.contains("xml") {
+            Category::Xml
+        } else {
+            Category::Other
+        }
+    }
+}
+
+fn parse_frame(line: &str) -> Option<Frame> {
+    let captures = STACK_FRAME.captures(line)?;
+
+    Some(Frame {
+        file: captures[1].replace('\\', "/"),
+        line: captures[2].parse().ok()?,
+        function: captures[3].trim().to_string(),
+    })
+}
+
+/// Error and warning entries with the stack frames logged after them
+fn parse_log(log: &str) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = Vec::new();
+    // Following lines belong to the last entry until another message starts
+    let mut open = false;
+
+    for (index, line) in log.lines().enumerate() {
+        let line = TIMESTAMP.replace(line, "");
+        let text = line.trim();
+
+        let level = if text.starts_with("Error") {
+            Some(Level::Error)
+        } else if text.starts_with("Warning") {
+            Some(Level::Warning)
+        } else {
+            None
+        };
+
+        if let Some(level) = level {
+            entries.push(Entry {
+                level,
+                line: index + 1,
+                message: text.to_string(),
+                frames: Vec::new(),
+            });
+            open = true;
+            continue;
+        }
+
+        let Some(entry) = entries.last_mut().filter(|_| open) else {
+            continue;
+        };
+
+        if text == "LUA call stack:" {
+            continue;
+        }
+
+        match parse_frame(text) {
+            Some(frame) => entry.frames.push(frame),
+            None => open = false,
+        }
+    }
+
+    entries
+}
+
+/// Maps stack frames of the game or mods onto a decompiled script folder
+struct Scripts {
+    dir: PathBuf,
+    sources: HashMap<PathBuf, Option<String>>,
+}
+
+impl Scripts {
+    /// Decompiled file of a frame, the frame path is shortened from the front
+    /// until it exists below the folder (`dataS/scripts/x.lua` -> `x.lua`)
+    fn find_file(&self, file: &str) -> Option<PathBuf> {
+        let parts: Vec<&str> = file.split('/').filter(|p| !p.is_empty()).collect();
+
+        (0..parts.len())
+            .map(|start| self.dir.join(parts[start..].join("/")))
+            .find(|path| path.is_file())
+    }
+
+    fn source(&mut self, file: &Path) -> Option<&str> {
+        self.sources
+            .entry(file.to_path_buf())
+            .or_insert_with(|| {
+                let buffer = Vec::read_from_file(file).ok()?;
+
+                Some(String::from_utf8_lossy(&buffer).into_owned())
+            })
+            .as_deref()
+    }
+
+    /// `file:line` in the decompiled tree. Uses line comments when the
+    /// decompiler wrote them, otherwise the definition of the function.
+    fn map(&mut self, frame: &Frame) -> Option<String> {
+        let file = self.find_file(&frame.file)?;
+        let name = file
+            .strip_prefix(&self.dir)
+            .unwrap_or(&file)
+            .display()
+            .to_string();
+        let source = self.source(&file)?;
+
+        let commented = source
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                let original: usize = LINE_COMMENT.captures(line)?[1].parse().ok()?;
+
+                Some((index + 1, original))
+            })
+            .filter(|(_, original)| *original <= frame.line)
+            .max_by_key(|(_, original)| *original);
+
+        if let Some((line, _)) = commented {
+            return Some(format!("{}:{}", name, line));
+        }
+
+        let function = frame.function.rsplit(['.', ':']).next().unwrap_or_default();
+
+        match function_definitions(source)
+            .into_iter()
+            .find(|definition| definition.function == function)
+        {
+            Some(definition) => Some(format!("{}:{} ({})", name, definition.line, function)),
+            None => Some(name),
+        }
+    }
+}
+
+fn print_entries(entries: &[Entry], scripts: &mut Option<Scripts>) {
+    // Repeated messages with the same stack are listed once
+    let mut groups: BTreeMap<(Level, Category, &str, Option<&Frame>), Vec<&Entry>> =
+        BTreeMap::new();
+
+    for entry in entries {
+        groups
+            .entry((
+                entry.level,
+                entry.category(),
+                &entry.message,
+                entry.frames.first(),
+            ))
+            .or_default()
+            .push(entry);
+    }
+
+    for ((level, category, message, _), group) in groups {
+        let level = match level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        };
+
+        let count = match group.len() {
+            1 => String::new(),
+            count => format!(" (x{})", count),
+        };
+
+        println!(
+            "  {:<8}{:>6}  [{}] {}{}",
+            level, group[0].line, category, message, count
+        );
+
+        for frame in group[0].frames.iter() {
+            let mapped = scripts
+                .as_mut()
+                .and_then(|scripts| scripts.map(frame))
+                .map(|mapped| format!(" -> {}", mapped))
+                .unwrap_or_default();
+
+            println!(
+                "                  {} ({}) : {}{}",
+                frame.file, frame.line, frame.function, mapped
+            );
+        }
+    }
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    if !cli.input.is_file() {
+        bail!(report::usage(format!(
+            "Log file not found: {}",
+            cli.input.display()
+        )))
+    }
+
+    let log = String::from_utf8_lossy(&Vec::read_from_file(&cli.input)?).into_owned();
+
+    let entries: Vec<Entry> = parse_log(&log)
+        .into_iter()
+        .filter(|entry| !cli.errors_only || entry.level == Level::Error)
+        .collect();
+
+    let errors = entries.iter().filter(|e| e.level == Level::Error).count();
+    let warnings = entries.len() - errors;
+
+    println!(
+        "{}: {} errors, {} warnings",
+        cli.input.display(),
+        errors,
+        warnings
+    );
+
+    let mut scripts = cli.scripts.clone().map(|dir| Scripts {
+        dir,
+        sources: HashMap::new(),
+    });
+
+    print_entries(&entries, &mut scripts);
+
+    match errors {
+        0 => summary.processed(),
+        _ => summary.failed(),
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
@@ -0,0 +1,148 @@
+use std::{
+    fmt,
+    path::Path,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::{Result, bail};
+use fs_lib::cmd::{CommandLimits, run_command_output};
+
+/// Decompiler by marsinator358, used when no backend is given
+pub const DEFAULT_BACKEND: &str = "luajit-decompiler.exe";
+
+const INPUT_PLACEHOLDER: &str = "{input}";
+
+/// External decompiler that prints the source of a file to stdout, written as
+/// the command and its arguments, e.g. `ljd.exe {input}`. The input path is
+/// appended when `{input}` is not given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backend {
+    command: String,
+    args: Vec<String>,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace().map(String::from);
+
+        let Some(command) = parts.next() else {
+            return Err("Empty backend".to_string());
+        };
+
+        let mut args: Vec<String> = parts.collect();
+
+        if !args.iter().any(|arg| arg.contains(INPUT_PLACEHOLDER)) {
+            args.push(INPUT_PLACEHOLDER.to_string());
+        }
+
+        Ok(Backend { command, args })
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.command)
+    }
+}
+
+impl Backend {
+    /// Returns the source, warnings printed by the decompiler are passed on
+    /// to stderr
+    fn run(&self, file: &Path, limits: &CommandLimits) -> Result<Vec<u8>> {
+        let input = file.to_string_lossy();
+        let args = self
+            .args
+            .iter()
+            .map(|arg| arg.replace(INPUT_PLACEHOLDER, &input));
+
+        let output = run_command_output(&self.command, args, limits)?;
+
+        output.check(&self.command)?;
+
+        if output.stdout.iter().all(u8::is_ascii_whitespace) {
+            bail!("No output")
+        }
+
+        for line in output.stderr_lines() {
+            eprintln!("{}: {}: {}", file.display(), self, line);
+        }
+
+        Ok(output.stdout)
+    }
+}
+
+/// Backends tried in order for each file, with the number of files each one
+/// decompiled
+pub struct Backends {
+    backends: Vec<(Backend, AtomicUsize)>,
+    limits: CommandLimits,
+}
+
+impl Backends {
+    pub fn new(backends: &[Backend], limits: CommandLimits) -> Self {
+        let mut backends: Vec<Backend> = backends.to_vec();
+
+        if backends.is_empty() {
+            backends.push(DEFAULT_BACKEND.parse().unwrap());
+        }
+
+        Self {
+            backends: backends
+                .into_iter()
+                .map(|backend| (backend, AtomicUsize::new(0)))
+                .collect(),
+            limits,
+        }
+    }
+
+    /// Only one backend, output lines don't name it
+    pub fn is_single(&self) -> bool {
+        self.backends.len() == 1
+    }
+
+    /// Returns the source of the first backend that succeeds and its name,
+    /// the errors of all backends if none does
+    pub fn decompile(&self, file: &Path) -> Result<(Vec<u8>, &Backend)> {
+        let mut errors = Vec::new();
+
+        for (backend, count) in self.backends.iter() {
+            match backend.run(file, &self.limits) {
+                Ok(source) => {
+                    count.fetch_add(1, Ordering::Relaxed);
+                    return Ok((source, backend));
+                }
+                Err(e) => errors.push((backend, e)),
+            }
+        }
+
+        // Keeps the exit code of a missing decompiler
+        if self.is_single() {
+            return Err(errors.pop().unwrap().1);
+        }
+
+        let errors: Vec<String> = errors
+            .iter()
+            .map(|(backend, e)| format!("{}: {:#}", backend, e))
+            .collect();
+
+        bail!("All decompilers failed ({})", errors.join("; "))
+    }
+
+    /// Number of files per backend on stderr, with more than one backend
+    pub fn print_counts(&self) {
+        if self.is_single() {
+            return;
+        }
+
+        let counts: Vec<String> = self
+            .backends
+            .iter()
+            .map(|(backend, count)| format!("{} {}", backend, count.load(Ordering::Relaxed)))
+            .collect();
+
+        eprintln!("Decompiled with: {}", counts.join(", "));
+    }
+}
@@ -0,0 +1,112 @@
+use fs_lib::{ByteshiftTable, buffer::BufferExtension, luau::BytecodeReader};
+
+/// First shifted byte, the LuaJIT magic and table index are stored as is
+const OFFSET: usize = 4;
+
+/// Table sizes tried, smallest first: 8 and 16 bytes are used by the game so
+/// far, 32 bytes covers a longer period
+const MASKS: [usize; 3] = [0x07, 0x0F, 0x1F];
+
+/// LuaJIT dump flags (BE, STRIP, FFI, FR2)
+const KNOWN_FLAGS: usize = 0x0F;
+const FLAG_STRIP: usize = 0x02;
+
+pub struct DerivedTable {
+    pub table: ByteshiftTable,
+    /// Number of files that decode to a valid dump with the table
+    pub valid_files: usize,
+}
+
+/// Walks the dump structure: header, optional chunk name and the list of
+/// length prefixed prototypes terminated by a zero byte at the end of file
+fn is_valid_dump(buffer: &[u8]) -> bool {
+    let mut reader = BytecodeReader::new(buffer);
+    reader.offset = OFFSET;
+
+    let Ok(flags) = reader.read_varint() else {
+        return false;
+    };
+
+    if flags & !KNOWN_FLAGS != 0 {
+        return false;
+    }
+
+    if flags & FLAG_STRIP == 0 {
+        let Ok(length) = reader.read_varint() else {
+            return false;
+        };
+
+        if reader.read_bytes(length).is_err() {
+            return false;
+        }
+    }
+
+    loop {
+        match reader.read_varint() {
+            Ok(0) => return reader.offset == buffer.len(),
+            Ok(length) => {
+                if reader.read_bytes(length).is_err() {
+                    return false;
+                }
+            }
+            Err(_) => return false,
+        }
+    }
+}
+
+fn decode_with(buffer: &[u8], table: &ByteshiftTable) -> Vec<u8> {
+    let mut decoded = buffer.to_vec();
+    decoded.shift_bytes(&table.bytes, table.offset, table.mask);
+
+    decoded
+}
+
+/// Solves for the byteshift table of encoded files sharing the same table
+/// index. Decoding adds table[i & mask] + i to each byte, so every table byte
+/// is chosen to turn the most bytes at its positions into 0x00, by far the
+/// most common byte in LuaJIT bytecode. Candidates are verified by parsing
+/// the decoded dump structure, the smallest table decoding most files wins.
+pub fn derive_table(files: &[Vec<u8>]) -> Option<DerivedTable> {
+    let mut best: Option<DerivedTable> = None;
+
+    for mask in MASKS {
+        let mut counts = vec![[0_usize; 256]; mask + 1];
+
+        for buffer in files {
+            for (i, value) in buffer.iter().enumerate().skip(OFFSET) {
+                let shift = 0_u8.wrapping_sub(*value).wrapping_sub(i as u8);
+                counts[i & mask][shift as usize] += 1;
+            }
+        }
+
+        let bytes = counts
+            .iter()
+            .map(|count| {
+                (0..=255_u8)
+                    .max_by_key(|shift| count[*shift as usize])
+                    .unwrap()
+            })
+            .collect();
+
+        let table = ByteshiftTable {
+            bytes,
+            offset: OFFSET,
+            mask,
+        };
+
+        let valid_files = files
+            .iter()
+            .filter(|buffer| is_valid_dump(&decode_with(buffer, &table)))
+            .count();
+
+        if best.as_ref().is_none_or(|b| valid_files > b.valid_files) {
+            best = Some(DerivedTable { table, valid_files });
+        }
+
+        if valid_files == files.len() {
+            break;
+        }
+    }
+
+    best
+}
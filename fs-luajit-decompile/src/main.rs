@@ -1,13 +1,28 @@
+mod backend;
+mod derive;
+
 use anyhow::{Result, bail};
 use argh::FromArgs;
-use fs_lib::path::PathExtension;
+use backend::{Backend, Backends};
+use fs_lib::path::{OutputMapper, check_in_place, check_unique_outputs, is_glob_pattern, is_stdio};
 use fs_lib::{
-    LUAJIT_DECODE_TABLES, buffer::BufferExtension, cmd::run_command_return_stdout,
-    list_files_with_extension,
+    BYTECODE_EXTENSIONS, ByteshiftTable, LUAJIT_DECODE_TABLES,
+    buffer::BufferExtension,
+    cache::Cache,
+    cmd::CommandLimits,
+    codec::{Generation, check_generation, decode_luajit, is_luajit, is_luajit_encoded},
+    deny::DenyList,
+    has_extension, list_input_files,
+    pattern::{format_luajit_table, load_pattern_file},
+    report::{self, Summary, Timings},
+    watch::watch_files,
+    zip_input::ZipInput,
 };
 use rayon::ThreadPoolBuilder;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// Decode and decompile LuaJIT .l64 bytecode files
@@ -16,6 +31,10 @@ pub struct Cmd {
     #[argh(switch, short = 'r')]
     recursive: bool,
 
+    /// enter directory symlinks and junctions in recursive mode
+    #[argh(switch)]
+    follow_symlinks: bool,
+
     /// suppress output
     #[argh(switch, short = 's')]
     silent: bool,
@@ -24,66 +43,272 @@ pub struct Cmd {
     #[argh(option, default = "0")]
     num_threads: u8,
 
-    /// path to input file/folder
+    /// print the duration of each stage
+    #[argh(switch)]
+    timings: bool,
+
+    /// keep running and re-process files in input folder as they change
+    #[argh(switch, short = 'w')]
+    watch: bool,
+
+    /// process all files, even if unchanged since previous run
+    #[argh(switch, short = 'f')]
+    force: bool,
+
+    /// print planned input -> output mapping without processing files
+    #[argh(switch)]
+    dry_run: bool,
+
+    /// pattern file with additional byteshift tables, see fs-patch
+    /// --export-patterns
+    #[argh(option)]
+    patterns: Option<PathBuf>,
+
+    /// derive byteshift tables from encoded input files and print them as
+    /// pattern file sections, files are not decompiled
+    #[argh(switch)]
+    derive_table: bool,
+
+    /// output file pattern, e.g. "{dir}/{stem}_decompiled.{ext}"
+    #[argh(option)]
+    output_pattern: Option<String>,
+
+    /// external decompiler, e.g. "ljd.exe {input}", can be repeated to try
+    /// the next one when a decompiler fails (default luajit-decompiler.exe)
+    #[argh(option)]
+    backend: Vec<Backend>,
+
+    /// kill a decompiler that runs longer than this many seconds and fail
+    /// the file (default 0 = no limit)
+    #[argh(option, default = "0")]
+    timeout: u64,
+
+    /// kill a decompiler that prints more than this many MiB and fail the
+    /// file (default 0 = no limit)
+    #[argh(option, default = "0")]
+    max_output: usize,
+
+    /// extension of decompiled files (default lua)
+    #[argh(option, default = "String::from(\"lua\")")]
+    output_ext: String,
+
+    /// allow replacing input files with the output
+    #[argh(switch)]
+    in_place: bool,
+
+    /// path to input file/folder or glob pattern (- for stdin)
     #[argh(positional)]
     input: PathBuf,
 
-    /// path to output file/folder (optional)
+    /// path to output file/folder (optional, - for stdout)
     #[argh(positional)]
     output: Option<PathBuf>,
 }
 
-fn is_valid(buffer: &Vec<u8>) -> bool {
-    buffer[0..3] == [0x1B, 0x4C, 0x4A]
+/// Decompiled bytecode files are written with --output-ext, files without
+/// an extension too
+fn output_extension<P: AsRef<Path>>(cli: &Cmd, file: P) -> Option<&str> {
+    let file = file.as_ref();
+    let is_bytecode =
+        has_extension(file, BYTECODE_EXTENSIONS) || (file.extension().is_none() && !is_stdio(file));
+
+    is_bytecode.then_some(&cli.output_ext)
 }
 
-fn is_encoded(buffer: &Vec<u8>) -> bool {
-    buffer[4] == 0xFC
+fn lua_output_file<P: AsRef<Path>>(cli: &Cmd, output_file: P) -> PathBuf {
+    let mut output_file: PathBuf = output_file.as_ref().to_path_buf();
+
+    if let Some(extension) = output_extension(cli, &output_file) {
+        output_file.set_extension(extension);
+    }
+
+    output_file
 }
 
-fn decode(buffer: &mut Vec<u8>) -> Result<()> {
-    let Some(table) = LUAJIT_DECODE_TABLES.get(&buffer[3]) else {
-        bail!("Unable to decode, no valid byteshift table found")
-    };
+// The external decompilers only read files, stdin input goes through a temporary file
+fn decompile_stdin<'a>(
+    mut file_buffer: Vec<u8>,
+    tables: &[(u8, ByteshiftTable)],
+    backends: &'a Backends,
+) -> Result<(Vec<u8>, &'a Backend)> {
+    if is_luajit_encoded(&file_buffer) {
+        decode_luajit(&mut file_buffer, tables)?;
+    }
+
+    let temp_file =
+        std::env::temp_dir().join(format!("fs-luajit-decompile-{}.l64", std::process::id()));
+
+    file_buffer.write_to_file(&temp_file)?;
 
-    buffer.shift_bytes(&table.bytes, table.offset, table.mask);
-    buffer[3] = 0x02;
+    let result = backends.decompile(&temp_file);
+    let _ = std::fs::remove_file(&temp_file);
 
-    Ok(())
+    result
 }
 
-fn decompile<P: AsRef<Path>>(file: P, output_file: P) -> Result<PathBuf> {
-    let mut file_buffer = std::fs::read(&file)?;
+/// Output lines name the backend when more than one is configured
+fn backend_suffix(backends: &Backends, backend: &Backend) -> String {
+    match backends.is_single() {
+        true => String::new(),
+        false => format!(" ({})", backend),
+    }
+}
+
+/// Returns the backend that decompiled the file
+fn decompile<'a, P: AsRef<Path>>(
+    file: P,
+    output_file: P,
+    tables: &[(u8, ByteshiftTable)],
+    backends: &'a Backends,
+) -> Result<&'a Backend> {
+    let mut file_buffer = Vec::read_from_input(&file)?;
 
-    if !is_valid(&file_buffer) {
+    check_generation(&file_buffer, Generation::LuaJit)?;
+
+    if !is_luajit(&file_buffer) {
         bail!("Unsupported bytecode file")
     }
 
-    if is_encoded(&file_buffer) {
-        decode(&mut file_buffer)?;
+    if is_stdio(&file) {
+        let (source, backend) = decompile_stdin(file_buffer, tables, backends)?;
+        source.write_to_output(&output_file)?;
+
+        return Ok(backend);
+    }
+
+    if is_luajit_encoded(&file_buffer) {
+        decode_luajit(&mut file_buffer, tables)?;
         file_buffer.write_to_file(&file)?;
     }
 
-    let result = run_command_return_stdout("luajit-decompiler.exe", [&file.as_ref()])?;
-    let mut output_file: PathBuf = output_file.as_ref().to_path_buf();
+    let (source, backend) = backends.decompile(file.as_ref())?;
+    source.write_to_output(&output_file)?;
+
+    Ok(backend)
+}
+
+/// Prints derived tables for encoded input files, grouped by table index
+fn derive_tables(cli: &Cmd) -> Result<Summary> {
+    let files = match cli.input.is_dir() || is_glob_pattern(&cli.input) {
+        true => {
+            list_input_files(
+                &cli.input,
+                BYTECODE_EXTENSIONS,
+                cli.recursive,
+                cli.follow_symlinks,
+            )?
+            .1
+        }
+        false => vec![cli.input.clone()],
+    };
+
+    let summary = Summary::default();
+    let mut groups: BTreeMap<u8, Vec<Vec<u8>>> = BTreeMap::new();
+
+    for file in files {
+        let buffer = Vec::read_from_file(&file)?;
+
+        match is_luajit_encoded(&buffer) {
+            true => {
+                groups.entry(buffer[3]).or_default().push(buffer);
+                summary.processed();
+            }
+            false => summary.skipped(),
+        }
+    }
 
-    if output_file.extension().unwrap() == "l64" {
-        output_file.set_extension("lua");
+    if groups.is_empty() {
+        bail!("No encoded LuaJIT files found")
     }
 
-    result.write_to_file(&output_file)?;
+    for (index, files) in groups {
+        let Some(derived) = derive::derive_table(&files) else {
+            continue;
+        };
+
+        println!(
+            "# Table index {}: {} of {} files decode to a valid dump",
+            index,
+            derived.valid_files,
+            files.len()
+        );
+
+        if LUAJIT_DECODE_TABLES.get(&index) == Some(&derived.table) {
+            println!("# Matches the built-in table");
+        }
+
+        if derived.valid_files == 0 {
+            println!("# No valid table found\n");
+            continue;
+        }
 
-    Ok(output_file)
+        println!("{}", format_luajit_table(index, &derived.table));
+    }
+
+    Ok(summary)
+}
+
+fn run() -> Result<Summary> {
+    let mut cli: Cmd = fs_lib::config::from_env();
+    let mut timings = Timings::new(cli.timings);
+    let zip_input = ZipInput::open(&mut cli.input, &mut cli.output)?;
+
+    if zip_input.is_some() {
+        timings.stage("extract zip");
+    }
+
+    let summary = process(cli, &mut timings)?;
+    timings.stage("process");
+
+    if let Some(zip_input) = zip_input {
+        zip_input.finish()?;
+        timings.stage("repack zip");
+    }
+
+    timings.print();
+
+    Ok(summary)
 }
 
-fn main() -> Result<()> {
-    let cli: Cmd = argh::from_env();
+/// Processes a file, folder or glob input, zip inputs are extracted folders
+fn process(cli: Cmd, timings: &mut Timings) -> Result<Summary> {
+    if cli.derive_table {
+        return derive_tables(&cli);
+    }
+
+    let summary = Summary::default();
+    let deny = DenyList::load()?;
+    let tables = match &cli.patterns {
+        Some(file) => load_pattern_file(file)?.luajit_tables,
+        None => Vec::new(),
+    };
+    let backends = Backends::new(
+        &cli.backend,
+        CommandLimits::new(cli.timeout, cli.max_output),
+    );
+
+    if cli.watch && !cli.input.is_dir() {
+        bail!(report::usage("Watch mode requires a folder input"))
+    }
+
+    if cli.watch && cli.dry_run {
+        bail!(report::usage("Watch mode can't be combined with dry run"))
+    }
+
+    if cli.input.is_dir() || is_glob_pattern(&cli.input) {
+        let (input_path, files) = list_input_files(
+            &cli.input,
+            BYTECODE_EXTENSIONS,
+            cli.recursive,
+            cli.follow_symlinks,
+        )?;
+        let files = deny.filter(files, &summary);
+        timings.stage("list files");
 
-    if cli.input.is_dir() {
-        let output_path = cli.output.unwrap_or_else(|| cli.input.clone());
+        let output_path = cli.output.clone().unwrap_or_else(|| input_path.clone());
 
         if output_path.is_file() {
-            bail!("Output path is a file")
+            bail!(report::usage("Output path is a file"))
         }
 
         ThreadPoolBuilder::new()
@@ -91,41 +316,137 @@ fn main() -> Result<()> {
             .build_global()
             .unwrap();
 
-        let files = list_files_with_extension(&cli.input, r"l64", cli.recursive)?;
+        let mapper = OutputMapper::new(&input_path, &output_path, cli.output_pattern.clone())?
+            .lossy(cli.in_place);
+        let get_output_file = |file: &PathBuf| mapper.map(file, output_extension(&cli, file));
+
+        check_unique_outputs(&files, get_output_file)?;
+
+        // Returns the lines to print, parallel runs print them in input order
+        let process_file = |file: &PathBuf, output_file: &PathBuf| -> Result<Vec<String>> {
+            let backend = decompile(file, output_file, &tables, &backends)?;
+
+            if cli.silent {
+                return Ok(Vec::new());
+            }
+
+            let line = match output_file != file {
+                true => format!("{} -> {}", file.display(), output_file.display()),
+                false => file.display().to_string(),
+            };
 
-        let iter_result = files.into_par_iter().try_for_each(|file| -> Result<()> {
-            let output_file: PathBuf = file
-                .convert_relative_path(&cli.input, &output_path)?
-                .components()
-                .collect();
+            Ok(vec![line + &backend_suffix(&backends, backend)])
+        };
 
-            let output_file = decompile(&file, &output_file)?;
+        let cache_options = format!("tables={:?},backend={:?}", tables, cli.backend);
+        let cache = Cache::load(
+            &output_path,
+            "fs-luajit-decompile",
+            &cache_options,
+            cli.force,
+        );
 
+        let process_entry = |file: &PathBuf| -> Result<Vec<String>> {
+            let output_file = get_output_file(file)?;
+
+            if cli.dry_run {
+                summary.processed();
+                return Ok(vec![format!(
+                    "{} -> {}",
+                    file.display(),
+                    output_file.display()
+                )]);
+            }
+
+            if cache.is_fresh(file, &output_file)? {
+                summary.skipped();
+                return Ok(Vec::new());
+            }
+
+            let output = process_file(file, &output_file)?;
+            cache.update(file, &output_file)?;
+            summary.processed();
+
+            Ok(output)
+        };
+
+        let outputs: Vec<_> = files
+            .into_par_iter()
+            .map(|file| {
+                let output = process_entry(&file);
+                (file.display().to_string(), output)
+            })
+            .collect();
+
+        summary.print_outputs(outputs);
+        backends.print_counts();
+
+        if !cli.dry_run {
+            cache.save()?;
+        }
+
+        if cli.watch {
             if !cli.silent {
-                if output_file != *file {
-                    println!("{} -> {}", file.display(), output_file.display());
-                } else {
-                    println!("{}", file.display());
-                }
+                println!("Watching {} for changes", input_path.display());
             }
 
-            Ok(())
-        });
+            watch_files(
+                &input_path,
+                BYTECODE_EXTENSIONS,
+                cli.recursive,
+                cli.follow_symlinks,
+                Some(&output_path),
+                |file| {
+                    if deny.check(file, &summary) {
+                        return Ok(());
+                    }
 
-        return iter_result;
+                    for line in process_file(file, &get_output_file(file)?)? {
+                        println!("{}", line);
+                    }
+
+                    Ok(())
+                },
+            )?;
+        }
     } else {
-        let output_file: PathBuf = cli
-            .output
-            .unwrap_or(cli.input.clone())
-            .components()
-            .collect();
+        let output_file = match (&cli.output, &cli.output_pattern) {
+            (Some(output), _) => lua_output_file(&cli, output.components().collect::<PathBuf>()),
+            (None, Some(_)) if !is_stdio(&cli.input) => {
+                OutputMapper::beside(&cli.input, cli.output_pattern.clone())?
+                    .map(&cli.input, output_extension(&cli, &cli.input))?
+            }
+            (None, _) => lua_output_file(&cli, cli.input.components().collect::<PathBuf>()),
+        };
+
+        check_in_place(&cli.input, &output_file, cli.in_place)?;
+
+        if deny.check(&cli.input, &summary) {
+            return Ok(summary);
+        }
+
+        if cli.dry_run {
+            println!("{} -> {}", cli.input.display(), output_file.display());
+            summary.processed();
+            return Ok(summary);
+        }
 
-        let output_file = decompile(&cli.input, &output_file)?;
+        let backend = decompile(&cli.input, &output_file, &tables, &backends)?;
 
-        if !cli.silent {
-            println!("{}", output_file.display());
+        if !cli.silent && !is_stdio(&output_file) {
+            println!(
+                "{}{}",
+                output_file.display(),
+                backend_suffix(&backends, backend)
+            );
         }
+
+        summary.processed();
     }
 
-    Ok(())
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
 }
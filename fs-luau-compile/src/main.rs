@@ -1,20 +1,27 @@
-use std::fs;
-use std::path::PathBuf;
+use std::{path::PathBuf, process::ExitCode};
 
 use anyhow::{Result, bail};
 use argh::FromArgs;
-use fs_lib::{LUAU_DECODE_TABLES, buffer::BufferExtension};
+use fs_lib::{
+    buffer::BufferExtension,
+    codec,
+    path::{
+        OutputMapper, check_in_place, check_unique_outputs, glob, glob_base, is_glob_pattern,
+        is_stdio,
+    },
+    report::{self, Summary},
+};
 use mlua::Compiler;
 use walkdir::WalkDir;
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// Compile and encode Lua(u) file to valid bytecode file
 pub struct Cmd {
-    /// path to input file (.lua) or directory
+    /// path to input file (.lua), directory or glob pattern (- for stdin)
     #[argh(positional)]
     input: PathBuf,
 
-    /// path to output file (optional, defaults to input with .l64 extension)
+    /// path to output file or folder (optional, defaults to input with .l64 extension, - for stdout)
     #[argh(positional)]
     output: Option<PathBuf>,
 
@@ -22,67 +29,182 @@ pub struct Cmd {
     #[argh(switch, short = 'r')]
     recursive: bool,
 
+    /// enter directory symlinks and junctions in recursive mode
+    #[argh(switch)]
+    follow_symlinks: bool,
+
     /// skip encoding
     #[argh(switch, short = 'e')]
     skip_encoding: bool,
+
+    /// output folder is an existing unpacked tree (e.g. dataS), report which
+    /// files are replaced and which are added
+    #[argh(switch, short = 'm')]
+    merge: bool,
+
+    /// debug info level: 0 = none, 1 = line info and function names, 2 =
+    /// also local and upvalue names (default 1)
+    #[argh(option, default = "1", from_str_fn(parse_debug_level))]
+    debug_level: u8,
+
+    /// strip all debug info, same as --debug-level 0
+    #[argh(switch)]
+    strip_debug: bool,
+
+    /// output file pattern, e.g. "{stem}.{ext}" to flatten folders
+    #[argh(option)]
+    output_pattern: Option<String>,
+
+    /// allow replacing input files with the output
+    #[argh(switch)]
+    in_place: bool,
 }
 
-fn compile_file(input: &PathBuf, output: &PathBuf, skip_encoding: bool) -> Result<()> {
-    println!("Compiling {:?}", input);
-    let source = fs::read_to_string(input)?;
-    let compiler = Compiler::new();
+fn parse_debug_level(value: &str) -> Result<u8, String> {
+    match value.parse::<u8>() {
+        Ok(level) if level <= 2 => Ok(level),
+        _ => Err(format!(
+            "Invalid debug level: {}, expected 0, 1 or 2",
+            value
+        )),
+    }
+}
+
+fn compile_file(input: &PathBuf, output: &PathBuf, cli: &Cmd) -> Result<()> {
+    // Bytecode goes to stdout, keep it clean
+    let verbose = !is_stdio(output);
+
+    if verbose {
+        println!("Compiling {:?}", input);
+    }
+    let source = String::from_utf8(Vec::read_from_input(input)?)?;
+    let debug_level = match cli.strip_debug {
+        true => 0,
+        false => cli.debug_level,
+    };
+
+    let compiler = Compiler::new().set_debug_level(debug_level);
     let mut bytecode = compiler
         .compile(&source)
         .map_err(|e| anyhow::anyhow!("Lua compile error: {}", e))?;
 
     let version = bytecode[0];
-    println!("Bytecode version: {}", version);
-
-    if !skip_encoding {
-        let Some(table) = LUAU_DECODE_TABLES.get(&(version, false)) else {
-            bail!("Missing bytecode shift table for version {}", version)
-        };
+    if verbose {
+        println!("Bytecode version: {}", version);
+    }
 
-        bytecode.insert(0, 0);
-        bytecode.shift_bytes_reversed(&table.bytes, table.offset, table.mask);
-        bytecode[0] = 0x02;
+    if !cli.skip_encoding {
+        codec::encode(&mut bytecode)?;
 
-        println!("Writing encoded bytecode to {:?}", output);
-    } else {
+        if verbose {
+            println!("Writing encoded bytecode to {:?}", output);
+        }
+    } else if verbose {
         println!("Writing bytecode to {:?}", output);
     }
-    bytecode.write_to_file(output)
+    bytecode.write_to_output(output)
 }
 
-fn main() -> Result<()> {
-    let cli: Cmd = argh::from_env();
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
 
-    if cli.input.is_dir() || cli.recursive {
-        let walker = if cli.recursive {
-            WalkDir::new(&cli.input)
+    if cli.input.is_dir() || cli.recursive || is_glob_pattern(&cli.input) {
+        let (input_path, files): (PathBuf, Vec<PathBuf>) = if is_glob_pattern(&cli.input) {
+            (glob_base(&cli.input), glob(&cli.input)?)
         } else {
-            WalkDir::new(&cli.input).max_depth(1)
+            // walkdir skips symlink loops itself
+            let walker = WalkDir::new(&cli.input)
+                .sort_by_file_name()
+                .follow_links(cli.follow_symlinks);
+            let walker = match cli.recursive {
+                true => walker,
+                false => walker.max_depth(1),
+            };
+
+            let files = walker
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .map(|e| e.into_path())
+                .filter(|p| p.is_file() && p.extension().map_or(false, |ext| ext == "lua"))
+                .collect();
+
+            let input_path = match cli.input.is_file() {
+                true => cli.input.parent().map(PathBuf::from).unwrap_or_default(),
+                false => cli.input.clone(),
+            };
+
+            (input_path, files)
         };
 
+        if let Some(output) = &cli.output
+            && output.is_file()
+        {
+            bail!(report::usage("Output path is a file"))
+        }
+
+        if cli.merge && !cli.output.as_ref().is_some_and(|output| output.is_dir()) {
+            bail!(report::usage(
+                "Merge mode requires an existing output folder"
+            ))
+        }
+
+        // Mirror the input tree into the output folder, next to input otherwise
+        let mapper = OutputMapper::new(
+            &input_path,
+            cli.output.as_ref().unwrap_or(&input_path),
+            cli.output_pattern.clone(),
+        )?
+        .lossy(cli.in_place);
+
+        check_unique_outputs(&files, |file| mapper.map(file, Some("l64")))?;
+
         let mut count = 0;
-        for entry in walker.into_iter().filter_map(|e| e.ok()) {
-            let path = entry.path();
-            if path.is_file() && path.extension().map_or(false, |ext| ext == "lua") {
-                let output_path = path.with_extension("l64");
-                if let Err(e) = compile_file(&path.to_path_buf(), &output_path, cli.skip_encoding) {
-                    eprintln!("Error compiling {:?}: {}", path, e);
-                } else {
-                    count += 1;
+        let mut replaced = 0;
+
+        for path in files.iter() {
+            let output_path = mapper.map(path, Some("l64"))?;
+
+            let exists = output_path.is_file();
+
+            if let Err(e) = compile_file(path, &output_path, &cli) {
+                summary.error(path.display(), &e);
+                continue;
+            }
+
+            summary.processed();
+            count += 1;
+
+            if cli.merge {
+                match exists {
+                    true => {
+                        replaced += 1;
+                        println!("Replaced {:?}", output_path);
+                    }
+                    false => println!("Added {:?}", output_path),
                 }
             }
         }
-        println!("\nCompiled {} files", count);
+
+        if cli.merge {
+            println!("\n{} replaced, {} added", replaced, count - replaced);
+        }
     } else {
-        let output = cli
-            .output
-            .unwrap_or_else(|| cli.input.with_extension("l64"));
-        compile_file(&cli.input, &output, cli.skip_encoding)?;
+        let output = match &cli.output {
+            Some(output) => output.clone(),
+            None if is_stdio(&cli.input) => cli.input.clone(),
+            None => OutputMapper::beside(&cli.input, cli.output_pattern.clone())?
+                .map(&cli.input, Some("l64"))?,
+        };
+
+        check_in_place(&cli.input, &output, cli.in_place)?;
+        compile_file(&cli.input, &output, &cli)?;
+        summary.processed();
     }
 
-    Ok(())
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
 }
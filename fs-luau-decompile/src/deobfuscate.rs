@@ -0,0 +1,220 @@
+use std::fmt;
+
+use crate::format::{CommentStyle, block_comment, leading_indent};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Encoding {
+    Xor(u8),
+    Shift(u8),
+}
+
+impl Encoding {
+    fn decode(&self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Encoding::Xor(key) => bytes.iter().map(|b| b ^ key).collect(),
+            Encoding::Shift(key) => bytes.iter().map(|b| b.wrapping_sub(*key)).collect(),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encoding::Xor(key) => write!(f, "xor 0x{:02X}", key),
+            Encoding::Shift(key) => write!(f, "shift {}", key),
+        }
+    }
+}
+
+pub struct DecodedString {
+    pub raw: Vec<u8>,
+    pub text: String,
+    pub encoding: Encoding,
+}
+
+// Shorter strings decode to plausible text with too many keys
+const MIN_LENGTH: usize = 6;
+
+fn is_plain_text(bytes: &[u8]) -> bool {
+    match std::str::from_utf8(bytes) {
+        Ok(str) => !str
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r')),
+        Err(_) => false,
+    }
+}
+
+/// Number of bytes that commonly appear in identifiers, paths and messages
+fn text_score(bytes: &[u8]) -> Option<usize> {
+    if !bytes.iter().all(|b| (0x20..0x7F).contains(b)) {
+        return None;
+    }
+
+    Some(
+        bytes
+            .iter()
+            .filter(|b| b.is_ascii_alphanumeric() || matches!(b, b' ' | b'_' | b'.' | b'/'))
+            .count(),
+    )
+}
+
+/// Tries single byte XOR and shift keys on strings that are not valid text
+/// and returns the candidates that decode to printable text
+pub fn find_encoded_strings(strings: &[Vec<u8>]) -> Vec<DecodedString> {
+    let mut result = Vec::new();
+
+    for raw in strings {
+        if raw.len() < MIN_LENGTH || is_plain_text(raw) {
+            continue;
+        }
+
+        let candidates = (1..=255_u8)
+            .map(Encoding::Xor)
+            .chain((1..=255_u8).map(Encoding::Shift));
+
+        let mut best: Option<(usize, Encoding, Vec<u8>)> = None;
+
+        for encoding in candidates {
+            let decoded = encoding.decode(raw);
+
+            let Some(score) = text_score(&decoded) else {
+                continue;
+            };
+
+            if best
+                .as_ref()
+                .is_none_or(|(best_score, ..)| score > *best_score)
+            {
+                best = Some((score, encoding, decoded));
+            }
+        }
+
+        // Require mostly word characters to avoid accepting random punctuation
+        // or digit runs
+        if let Some((score, encoding, decoded)) = best
+            && score * 10 >= decoded.len() * 8
+            && decoded.iter().filter(|b| b.is_ascii_alphabetic()).count() * 2 >= decoded.len()
+        {
+            result.push(DecodedString {
+                raw: raw.clone(),
+                text: String::from_utf8_lossy(&decoded).into_owned(),
+                encoding,
+            });
+        }
+    }
+
+    result
+}
+
+/// Possible renderings of a string constant inside the decompiled source
+fn literal_forms(bytes: &[u8]) -> Vec<String> {
+    let escape = |hex: bool, upper: bool| -> String {
+        bytes
+            .iter()
+            .map(|&b| match b {
+                b'"' => "\\\"".to_string(),
+                b'\\' => "\\\\".to_string(),
+                b'\n' => "\\n".to_string(),
+                0x20..0x7F => (b as char).to_string(),
+                _ if hex && upper => format!("\\x{:02X}", b),
+                _ if hex => format!("\\x{:02x}", b),
+                _ => format!("\\{}", b),
+            })
+            .collect()
+    };
+
+    vec![
+        format!("\"{}\"", escape(false, false)),
+        format!("\"{}\"", escape(true, false)),
+        format!("\"{}\"", escape(true, true)),
+    ]
+}
+
+/// Adds decoded strings as comments to the lines using them, returns the
+/// strings that can't be located
+pub fn annotate<'a>(
+    source: &str,
+    decoded: &'a [DecodedString],
+    style: CommentStyle,
+) -> (String, Vec<&'a DecodedString>) {
+    let mut lines: Vec<String> = source.lines().map(String::from).collect();
+    let mut unmatched = Vec::new();
+
+    for item in decoded {
+        let forms = literal_forms(&item.raw);
+        let comment = format!("{}: {:?}", item.encoding, item.text);
+        let mut output_lines = Vec::with_capacity(lines.len());
+        let mut is_found = false;
+
+        for mut line in lines {
+            if forms.iter().any(|form| line.contains(form.as_str())) {
+                match style {
+                    CommentStyle::Inline => line.push_str(&format!(" -- {}", comment)),
+                    CommentStyle::Block => output_lines.push(format!(
+                        "{}{}",
+                        leading_indent(&line),
+                        block_comment(&comment)
+                    )),
+                }
+
+                is_found = true;
+            }
+
+            output_lines.push(line);
+        }
+
+        lines = output_lines;
+
+        if !is_found {
+            unmatched.push(item);
+        }
+    }
+
+    let mut output = lines.join("\n");
+
+    if source.ends_with('\n') {
+        output.push('\n');
+    }
+
+    (output, unmatched)
+}
+
+/// Lists decoded strings, one per line
+pub fn symbol_table(decoded: &[&DecodedString]) -> String {
+    decoded
+        .iter()
+        .map(|item| format!("{}: {:?}\n", item.encoding, item.text))
+        .collect()
+}
+
+/// Formats the symbol table as a header comment, entries after `limit` are
+/// only counted
+pub fn symbol_header(
+    decoded: &[&DecodedString],
+    style: CommentStyle,
+    limit: Option<usize>,
+) -> String {
+    let shown = limit.map_or(decoded.len(), |limit| limit.min(decoded.len()));
+    let mut table = symbol_table(&decoded[..shown]);
+
+    if shown < decoded.len() {
+        table.push_str(&format!("... {} more\n", decoded.len() - shown));
+    }
+
+    match style {
+        CommentStyle::Inline => {
+            let mut output = String::from("-- Deobfuscated string constants:\n");
+
+            for line in table.lines() {
+                output.push_str(&format!("--   {}\n", line));
+            }
+
+            output + "\n"
+        }
+        CommentStyle::Block => {
+            let text = format!("Deobfuscated string constants:\n{}", table);
+
+            block_comment(&text) + "\n\n"
+        }
+    }
+}
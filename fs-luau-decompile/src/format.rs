@@ -0,0 +1,212 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+};
+
+use fs_lib::{luau::FunctionTypes, source::LongStringState};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndentStyle {
+    Tabs,
+    Spaces,
+}
+
+impl FromStr for IndentStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tabs" | "tab" => Ok(IndentStyle::Tabs),
+            "spaces" | "space" => Ok(IndentStyle::Spaces),
+            _ => Err(format!("Unknown indent style: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommentStyle {
+    /// `-- comment` at the end of the line
+    Inline,
+    /// `--[[ comment ]]` on its own line above
+    Block,
+}
+
+impl FromStr for CommentStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "inline" => Ok(CommentStyle::Inline),
+            "block" => Ok(CommentStyle::Block),
+            _ => Err(format!("Unknown comment style: {}", s)),
+        }
+    }
+}
+
+/// Placement of the symbol table comment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolTablePosition {
+    /// Header comment before the code
+    Top,
+    /// Comment after the code
+    Bottom,
+}
+
+impl FromStr for SymbolTablePosition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "top" => Ok(SymbolTablePosition::Top),
+            "bottom" => Ok(SymbolTablePosition::Bottom),
+            _ => Err(format!("Unknown symbol table position: {}", s)),
+        }
+    }
+}
+
+lazy_static! {
+    static ref FUNCTION_DEFINITION: Regex =
+        Regex::new(r"^(\s*)(?:local\s+)?function\s+([\w.:]+)\s*\(([^)]*)\)").unwrap();
+}
+
+/// Returns the leading whitespace of a line
+pub fn leading_indent(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Wraps text in a block comment using a bracket level not present in text
+pub fn block_comment(text: &str) -> String {
+    let mut level = 0;
+
+    while text.contains(&format!("]{}]", "=".repeat(level))) {
+        level += 1;
+    }
+
+    let equals = "=".repeat(level);
+
+    format!("--[{0}[ {1} ]{0}]", equals, text)
+}
+
+/// Converts leading indentation to the given style and size. The existing
+/// indent unit is detected from the source (tab or smallest space indent).
+pub fn reindent(source: &str, style: IndentStyle, size: usize) -> String {
+    let unit = match source.lines().any(|line| line.starts_with('\t')) {
+        true => "\t".to_string(),
+        false => {
+            let Some(width) = source
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| leading_indent(line).len())
+                .filter(|width| *width > 0)
+                .min()
+            else {
+                return source.to_string();
+            };
+
+            " ".repeat(width)
+        }
+    };
+
+    let indent = match style {
+        IndentStyle::Tabs => "\t".to_string(),
+        IndentStyle::Spaces => " ".repeat(size),
+    };
+
+    let mut state = LongStringState::default();
+    let mut lines = Vec::new();
+
+    for line in source.lines() {
+        if state.update(line) {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        let mut rest = line;
+        let mut level = 0;
+
+        while let Some(stripped) = rest.strip_prefix(unit.as_str()) {
+            rest = stripped;
+            level += 1;
+        }
+
+        lines.push(format!("{}{}", indent.repeat(level), rest));
+    }
+
+    let mut output = lines.join("\n");
+
+    if source.ends_with('\n') {
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Parameter types by function name, in bytecode order
+fn types_by_name(types: &[FunctionTypes]) -> HashMap<&str, VecDeque<&[String]>> {
+    let mut by_name: HashMap<&str, VecDeque<&[String]>> = HashMap::new();
+
+    for function in types {
+        if let Some(name) = &function.name {
+            by_name
+                .entry(name.as_str())
+                .or_default()
+                .push_back(&function.params);
+        }
+    }
+
+    by_name
+}
+
+/// Adds EmmyLua `---@param` annotations above named function definitions.
+/// Parameter types come from the type info of typed bytecode, matched by
+/// function name in order of definition. Other parameters are `any`.
+pub fn annotate_params(source: &str, types: &[FunctionTypes]) -> String {
+    let mut state = LongStringState::default();
+    let mut by_name = types_by_name(types);
+    let mut lines = Vec::new();
+
+    for line in source.lines() {
+        if !state.update(line)
+            && let Some(captures) = FUNCTION_DEFINITION.captures(line)
+        {
+            let indent = &captures[1];
+            let path = &captures[2];
+            let name = path.rsplit(['.', ':']).next().unwrap_or(path);
+            let params: Vec<&str> = captures[3]
+                .split(',')
+                .map(str::trim)
+                .filter(|param| !param.is_empty())
+                .collect();
+
+            // Methods have self as first parameter in bytecode
+            let typed = by_name
+                .get_mut(name)
+                .and_then(|queue| queue.pop_front())
+                .map(|types| match path.contains(':') {
+                    true => types.get(1..).unwrap_or_default(),
+                    false => types,
+                })
+                .filter(|types| types.len() == params.iter().filter(|p| **p != "...").count());
+
+            for (index, param) in params.iter().enumerate() {
+                let param_type = typed
+                    .and_then(|types| types.get(index))
+                    .map_or("any", String::as_str);
+
+                lines.push(format!("{}---@param {} {}", indent, param, param_type));
+            }
+        }
+
+        lines.push(line.to_string());
+    }
+
+    let mut output = lines.join("\n");
+
+    if source.ends_with('\n') {
+        output.push('\n');
+    }
+
+    output
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Result;
+use fs_lib::{
+    buffer::BufferExtension,
+    source::{FunctionDefinition, GLOBAL_CLASS, function_definitions},
+};
+use serde::Serialize;
+
+pub const INDEX_JSON_FILE_NAME: &str = "index.json";
+pub const INDEX_MD_FILE_NAME: &str = "index.md";
+
+#[derive(Serialize)]
+struct Location {
+    file: String,
+    line: usize,
+    method: bool,
+}
+
+/// class -> function -> definitions
+type Classes = BTreeMap<String, BTreeMap<String, Vec<Location>>>;
+
+/// Collects function definitions of decompiled files
+#[derive(Default)]
+pub struct Index {
+    files: Mutex<Vec<(PathBuf, Vec<FunctionDefinition>)>>,
+}
+
+impl Index {
+    pub fn add(&self, output_file: &Path, source: &[u8]) {
+        let definitions = function_definitions(&String::from_utf8_lossy(source));
+
+        self.files
+            .lock()
+            .unwrap()
+            .push((output_file.to_path_buf(), definitions));
+    }
+
+    fn classes(&self, output_path: &Path) -> Classes {
+        let mut classes = Classes::new();
+
+        for (file, definitions) in self.files.lock().unwrap().iter() {
+            let file = file
+                .strip_prefix(output_path)
+                .unwrap_or(file)
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            for definition in definitions {
+                classes
+                    .entry(definition.class.clone())
+                    .or_default()
+                    .entry(definition.function.clone())
+                    .or_default()
+                    .push(Location {
+                        file: file.clone(),
+                        line: definition.line,
+                        method: definition.method,
+                    });
+            }
+        }
+
+        for functions in classes.values_mut() {
+            for locations in functions.values_mut() {
+                locations.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+            }
+        }
+
+        classes
+    }
+
+    /// Writes index.json and index.md to the output folder
+    pub fn save(&self, output_path: &Path) -> Result<()> {
+        let classes = self.classes(output_path);
+
+        serde_json::to_vec_pretty(&classes)?
+            .write_to_file(output_path.join(INDEX_JSON_FILE_NAME))?;
+
+        let mut markdown = String::from("# Function index\n");
+
+        for (class, functions) in classes.iter() {
+            writeln!(markdown, "\n## {}\n", class)?;
+
+            for (function, locations) in functions.iter() {
+                for location in locations {
+                    let name = match (class.as_str(), location.method) {
+                        (GLOBAL_CLASS, _) => function.clone(),
+                        (_, true) => format!("{}:{}", class, function),
+                        (_, false) => format!("{}.{}", class, function),
+                    };
+
+                    writeln!(
+                        markdown,
+                        "- `{}` [{}:{}]({}#L{})",
+                        name, location.file, location.line, location.file, location.line
+                    )?;
+                }
+            }
+        }
+
+        markdown
+            .into_bytes()
+            .write_to_file(output_path.join(INDEX_MD_FILE_NAME))?;
+
+        Ok(())
+    }
+}
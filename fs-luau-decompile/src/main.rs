@@ -1,14 +1,41 @@
+mod deobfuscate;
+mod format;
+mod index;
+mod metadata;
+mod names;
+mod split;
+mod syntax;
+
 use anyhow::{Result, bail};
 use argh::FromArgs;
+use format::{CommentStyle, IndentStyle, SymbolTablePosition};
 use fs_lib::{
-    LUAU_DECODE_TABLES, buffer::BufferExtension, list_files_with_extension, path::PathExtension,
+    BYTECODE_EXTENSIONS, LuauHeader,
+    buffer::BufferExtension,
+    cache::Cache,
+    codec::{self, AssumedVersion, Generation},
+    deny::DenyList,
+    has_extension, list_input_files,
+    luau::{read_function_types, read_string_table, validate_bytecode},
+    path::{
+        OutputMapper, check_in_place, check_unique_outputs, is_glob_pattern, is_stdio,
+        sanitize_entry_path,
+    },
+    pattern::{BytePattern, load_pattern_file},
+    report::{self, Summary, Timings},
+    source::stabilize,
+    watch::watch_files,
+    zip_input::ZipInput,
 };
 use gar_lib::{GarArchive, GarPath};
+use index::Index;
 use rayon::{
     ThreadPoolBuilder,
     iter::{IntoParallelIterator, ParallelIterator},
 };
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use syntax::SyntaxReport;
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// Decode and decompile Luau .l64 bytecode files
@@ -17,6 +44,10 @@ pub struct Cmd {
     #[argh(switch, short = 'r')]
     recursive: bool,
 
+    /// enter directory symlinks and junctions in recursive mode
+    #[argh(switch)]
+    follow_symlinks: bool,
+
     /// suppress output
     #[argh(switch, short = 's')]
     silent: bool,
@@ -29,145 +60,556 @@ pub struct Cmd {
     #[argh(option, default = "0")]
     num_threads: u8,
 
-    /// path to input file/folder
+    /// print the duration of each stage
+    #[argh(switch)]
+    timings: bool,
+
+    /// keep running and re-process files in input folder as they change
+    #[argh(switch, short = 'w')]
+    watch: bool,
+
+    /// process all files, even if unchanged since previous run
+    #[argh(switch, short = 'f')]
+    force: bool,
+
+    /// print planned input -> output mapping without processing files
+    #[argh(switch)]
+    dry_run: bool,
+
+    /// annotate XOR/shift obfuscated string constants with their decoded value
+    #[argh(switch)]
+    deobfuscate: bool,
+
+    /// name anonymous locals of bytecode without debug info after the classes
+    /// registered with them, listed in a header comment
+    #[argh(switch)]
+    infer_names: bool,
+
+    /// re-indent output with tabs or spaces
+    #[argh(option)]
+    indent: Option<IndentStyle>,
+
+    /// number of spaces per indent level with --indent spaces (default 4)
+    #[argh(option, default = "4")]
+    indent_size: usize,
+
+    /// style of injected comments: inline or block (default inline)
+    #[argh(option, default = "CommentStyle::Inline")]
+    comment_style: CommentStyle,
+
+    /// write the symbol table to a .symbols.txt file next to the output
+    /// instead of a header comment
+    #[argh(switch)]
+    symbols_file: bool,
+
+    /// maximum number of entries in the symbol table comment, the others are
+    /// only counted (default all)
+    #[argh(option)]
+    symbol_table_limit: Option<usize>,
+
+    /// place the symbol table comment at the top or bottom of the file
+    /// (default top)
+    #[argh(option, default = "SymbolTablePosition::Top")]
+    symbol_table_position: SymbolTablePosition,
+
+    /// add EmmyLua ---@param annotations to named functions
+    #[argh(switch)]
+    emmylua: bool,
+
+    /// parse each decompiled file and report files with syntax errors
+    #[argh(switch)]
+    check_syntax: bool,
+
+    /// renumber temporaries and strip trailing whitespace so output of
+    /// different game versions can be compared with diff
+    #[argh(switch)]
+    stable_output: bool,
+
+    /// start each file with a comment naming its source file, bytecode
+    /// version, decode table, tool version and date (no date with
+    /// --stable-output)
+    #[argh(switch)]
+    metadata_header: bool,
+
+    /// write index.json and index.md mapping classes and functions to files
+    /// and lines (folder input)
+    #[argh(switch)]
+    index: bool,
+
+    /// also write each class of the decompiled files to its own file in this
+    /// folder, with an index.md per decompiled file
+    #[argh(option)]
+    split_functions: Option<PathBuf>,
+
+    /// pattern file with additional bytecode headers, see fs-patch
+    /// --export-patterns
+    #[argh(option)]
+    patterns: Option<PathBuf>,
+
+    /// bytecode version to use instead of header detection, e.g. 6 or 6-dlc
+    #[argh(option)]
+    assume_version: Option<AssumedVersion>,
+
+    /// output file pattern, e.g. "{dir}/{stem}_decompiled.{ext}"
+    #[argh(option)]
+    output_pattern: Option<String>,
+
+    /// extension of decompiled files (default lua)
+    #[argh(option, default = "String::from(\"lua\")")]
+    output_ext: String,
+
+    /// allow replacing input files with the output, e.g. --decode-only
+    /// without an output folder
+    #[argh(switch)]
+    in_place: bool,
+
+    /// path to input file/folder or glob pattern (- for stdin)
     #[argh(positional)]
     input: PathBuf,
 
-    /// path to output file/folder (optional)
+    /// path to output file/folder (optional, - for stdout)
     #[argh(positional)]
     output: Option<PathBuf>,
 }
 
-// (version, is_encoded, is_dlc)
-fn get_bytecode_info(buffer: &Vec<u8>) -> (u8, bool, bool) {
-    match &buffer[0..3] {
-        [0x03, 0x00, 0xF2] => (6, true, true),
-        [0x02, 0xEF, ..] => (3, true, false),
-        [0x03, 0xFD, ..] => (3, true, true),
-        [0x02, 0xF0, ..] => (4, true, false),
-        [0x02, 0xF2, ..] => (6, true, false),
-        [0x06, 0x03, ..] => (6, false, false),
-        [0x03, ..] => (3, false, false),
-        [0x04, ..] => (4, false, false),
-        _ => (0, false, false),
-    }
+/// Options applied when decompiling each file
+pub struct DecompileOptions {
+    deobfuscate: bool,
+    infer_names: bool,
+    indent: Option<IndentStyle>,
+    indent_size: usize,
+    comment_style: CommentStyle,
+    symbols_file: bool,
+    symbol_table_limit: Option<usize>,
+    symbol_table_position: SymbolTablePosition,
+    emmylua: bool,
+    stable_output: bool,
+    metadata_header: bool,
+    /// Headers from the pattern file, checked before the built-in headers
+    headers: Vec<LuauHeader>,
+    assume_version: Option<AssumedVersion>,
 }
 
-fn decode_bytecode(buffer: &mut Vec<u8>, version: u8, is_dlc: bool) -> Result<()> {
-    let Some(table) = LUAU_DECODE_TABLES.get(&(version, is_dlc)) else {
-        bail!("Unable to decode, no valid byteshift table found")
-    };
+impl DecompileOptions {
+    fn from_cmd(cli: &Cmd) -> Result<Self> {
+        let headers = match &cli.patterns {
+            Some(file) => load_pattern_file(file)?.headers,
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            deobfuscate: cli.deobfuscate,
+            infer_names: cli.infer_names,
+            indent: cli.indent,
+            indent_size: cli.indent_size,
+            comment_style: cli.comment_style,
+            symbols_file: cli.symbols_file,
+            symbol_table_limit: cli.symbol_table_limit,
+            symbol_table_position: cli.symbol_table_position,
+            emmylua: cli.emmylua,
+            stable_output: cli.stable_output,
+            metadata_header: cli.metadata_header,
+            headers,
+            assume_version: cli.assume_version,
+        })
+    }
 
-    buffer.shift_bytes(&table.bytes, table.offset, table.mask);
-    buffer.remove(0);
+    fn detect_header(&self, buffer: &[u8]) -> Result<LuauHeader> {
+        codec::check_generation(buffer, Generation::Luau)?;
 
-    Ok(())
+        if let Some(assumed) = self.assume_version {
+            return Ok(assumed.header(buffer));
+        }
+
+        match codec::detect(buffer, &self.headers) {
+            Some(header) => Ok(header),
+            None => bail!(
+                "Unsupported/unknown bytecode header {}, use --assume-version or add it to a pattern file",
+                BytePattern::from(&buffer[..buffer.len().min(3)])
+            ),
+        }
+    }
+
+    /// Key used to invalidate cached output when options change
+    fn cache_key(&self, decode_only: bool) -> String {
+        format!(
+            "decode_only={},deobfuscate={},infer_names={},indent={:?},indent_size={},comment_style={:?},symbols_file={},symbol_table_limit={:?},symbol_table_position={:?},emmylua={},stable_output={},metadata_header={},headers={:?},assume_version={:?}",
+            decode_only,
+            self.deobfuscate,
+            self.infer_names,
+            self.indent,
+            self.indent_size,
+            self.comment_style,
+            self.symbols_file,
+            self.symbol_table_limit,
+            self.symbol_table_position,
+            self.emmylua,
+            self.stable_output,
+            self.metadata_header,
+            self.headers,
+            self.assume_version
+        )
+    }
 }
 
-fn decompile_bytecode(bytecode: &mut Vec<u8>) -> Result<Vec<u8>> {
-    let (version, is_encoded, is_dlc) = get_bytecode_info(&bytecode);
+pub struct Decompiled {
+    source: Vec<u8>,
+    /// Symbol table, when written to a separate file
+    symbols: Option<String>,
+}
 
-    if version == 0 {
-        bail!("Unsupported/unknown bytecode");
+impl Decompiled {
+    fn write_to_output(&self, output_file: &Path) -> Result<()> {
+        self.source.write_to_output(output_file)?;
+        self.write_symbols(output_file)
     }
 
-    if is_encoded {
-        decode_bytecode(bytecode, version, is_dlc)?;
+    fn write_to_file(&self, output_file: &Path) -> Result<()> {
+        self.source.write_to_file(output_file)?;
+        self.write_symbols(output_file)
     }
 
-    Ok(lantern::decompile_bytecode(&bytecode, 1)
-        .as_bytes()
-        .to_vec())
-}
+    fn write_symbols(&self, output_file: &Path) -> Result<()> {
+        if let Some(symbols) = &self.symbols {
+            symbols
+                .as_bytes()
+                .to_vec()
+                .write_to_file(output_file.with_extension("symbols.txt"))?;
+        }
 
-fn decompile_file<P: AsRef<Path>>(file: P) -> Result<Vec<u8>> {
-    let mut bytecode = Vec::read_from_file(&file)?;
+        Ok(())
+    }
+}
 
-    match decompile_bytecode(&mut bytecode) {
-        Ok(result) => Ok(result),
-        Err(e) => bail!("{}: {}", file.as_ref().display(), e),
+impl From<Vec<u8>> for Decompiled {
+    fn from(source: Vec<u8>) -> Self {
+        Self {
+            source,
+            symbols: None,
+        }
     }
 }
 
-fn decode_file<P: AsRef<Path>>(file: P) -> Result<Vec<u8>> {
-    let mut bytecode = Vec::read_from_file(&file)?;
+/// Returns the detected header
+fn decode_bytecode(buffer: &mut Vec<u8>, options: &DecompileOptions) -> Result<LuauHeader> {
+    let header = options.detect_header(buffer)?;
+
+    codec::decode(buffer, &header)?;
+
+    Ok(header)
+}
+
+/// `source_name` is the input file or archive entry, for the metadata header
+fn decompile_bytecode(
+    bytecode: &mut Vec<u8>,
+    source_name: &str,
+    options: &DecompileOptions,
+) -> Result<Decompiled> {
+    let bytecode_header = decode_bytecode(bytecode, options)?;
 
-    let (version, is_encoded, is_dlc) = get_bytecode_info(&bytecode);
+    // The decompiler panics on malformed bytecode, which aborts the process
+    validate_bytecode(bytecode)?;
 
-    if version == 0 {
-        bail!("Unsupported/unknown bytecode");
+    let mut source = lantern::decompile_bytecode(bytecode, 1);
+
+    if options.infer_names {
+        source = names::infer_names(&source, options.comment_style);
+    }
+
+    if options.stable_output {
+        source = stabilize(&source);
+    }
+
+    let mut header = String::new();
+    let mut symbols = None;
+
+    if options.deobfuscate {
+        let decoded = deobfuscate::find_encoded_strings(&read_string_table(bytecode)?);
+        let unmatched;
+
+        (source, unmatched) = deobfuscate::annotate(&source, &decoded, options.comment_style);
+
+        match options.symbols_file {
+            true if !decoded.is_empty() => {
+                symbols = Some(deobfuscate::symbol_table(
+                    &decoded.iter().collect::<Vec<_>>(),
+                ));
+            }
+            false if !unmatched.is_empty() => {
+                header = deobfuscate::symbol_header(
+                    &unmatched,
+                    options.comment_style,
+                    options.symbol_table_limit,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    if options.emmylua {
+        // Untyped bytecode or a layout this reader doesn't know: all any
+        let types = read_function_types(bytecode).unwrap_or_default();
+
+        source = format::annotate_params(&source, &types);
     }
 
-    if is_encoded {
-        decode_bytecode(&mut bytecode, version, is_dlc)?;
+    if let Some(style) = options.indent {
+        source = format::reindent(&source, style, options.indent_size);
     }
 
-    Ok(bytecode)
+    let source = match options.symbol_table_position {
+        SymbolTablePosition::Bottom if !header.is_empty() => {
+            let separator = match source.ends_with('\n') {
+                true => "\n",
+                false => "\n\n",
+            };
+
+            source + separator + header.trim_end() + "\n"
+        }
+        SymbolTablePosition::Bottom => source,
+        SymbolTablePosition::Top => header + &source,
+    };
+
+    let source = match options.metadata_header {
+        true => {
+            metadata::metadata_header(
+                source_name,
+                &bytecode_header,
+                !options.stable_output,
+                options.comment_style,
+            ) + &source
+        }
+        false => source,
+    };
+
+    Ok(Decompiled {
+        source: source.into_bytes(),
+        symbols,
+    })
 }
 
-fn decompile_from_archive(archive: &GarArchive, path: &str) -> Result<Vec<u8>> {
+fn decompile_file<P: AsRef<Path>>(file: P, options: &DecompileOptions) -> Result<Decompiled> {
+    let mut bytecode = Vec::read_from_input(&file)?;
+    let source_name = file.as_ref().display().to_string();
+
+    match decompile_bytecode(&mut bytecode, &source_name, options) {
+        Ok(result) => Ok(result),
+        Err(e) => bail!("{}: {}", file.as_ref().display(), e),
+    }
+}
+
+fn decode_file<P: AsRef<Path>>(file: P, options: &DecompileOptions) -> Result<Vec<u8>> {
+    let mut bytecode = Vec::read_from_input(&file)?;
+
+    match decode_bytecode(&mut bytecode, options) {
+        Ok(_) => Ok(bytecode),
+        Err(e) => bail!("{}: {}", file.as_ref().display(), e),
+    }
+}
+
+fn decompile_from_archive(
+    archive: &GarArchive,
+    archive_path: &Path,
+    path: &str,
+    options: &DecompileOptions,
+) -> Result<Decompiled> {
     let mut bytecode = archive
         .read_file(path)
         .map_err(|e| anyhow::anyhow!("{}", e))?;
+    let source_name = format!("{}/{}", archive_path.display(), path);
 
-    match decompile_bytecode(&mut bytecode) {
+    match decompile_bytecode(&mut bytecode, &source_name, options) {
         Ok(result) => Ok(result),
         Err(e) => bail!("{}: {}", path, e),
     }
 }
 
-fn decode_from_archive(archive: &GarArchive, path: &str) -> Result<Vec<u8>> {
+fn decode_from_archive(
+    archive: &GarArchive,
+    path: &str,
+    options: &DecompileOptions,
+) -> Result<Vec<u8>> {
     let mut bytecode = archive
         .read_file(path)
         .map_err(|e| anyhow::anyhow!("{}", e))?;
 
-    let (version, is_encoded, is_dlc) = get_bytecode_info(&bytecode);
+    match decode_bytecode(&mut bytecode, options) {
+        Ok(_) => Ok(bytecode),
+        Err(e) => bail!("{}: {}", path, e),
+    }
+}
 
-    if version == 0 {
-        bail!("Unsupported/unknown bytecode");
+fn print_syntax_report(report: &SyntaxReport, output_path: &Path) -> Result<()> {
+    if let Some(file) = report.save(output_path)? {
+        println!(
+            "{} files failed syntax check, see {}",
+            report.len(),
+            file.display()
+        );
     }
 
-    if is_encoded {
-        decode_bytecode(&mut bytecode, version, is_dlc)?;
+    Ok(())
+}
+
+/// Decompiled bytecode files are written with --output-ext, files without
+/// an extension too
+fn output_extension<P: AsRef<Path>>(cli: &Cmd, file: P) -> Option<&str> {
+    let file = file.as_ref();
+    let is_bytecode =
+        has_extension(file, BYTECODE_EXTENSIONS) || (file.extension().is_none() && !is_stdio(file));
+
+    (!cli.decode_only && is_bytecode).then_some(&cli.output_ext)
+}
+
+/// Writes the classes of a decompiled file to `<split folder>/<relative
+/// path without extension>/`
+fn split_output(cli: &Cmd, relative: &Path, source: &[u8]) -> Result<()> {
+    let Some(split_path) = &cli.split_functions else {
+        return Ok(());
+    };
+
+    let relative: PathBuf = relative.with_extension("").components().collect();
+    let name = relative
+        .with_extension("lua")
+        .to_string_lossy()
+        .replace('\\', "/");
+
+    split::write_split(source, &split_path.join(&relative), &name)
+}
+
+fn run() -> Result<Summary> {
+    let mut cli: Cmd = fs_lib::config::from_env();
+    let mut timings = Timings::new(cli.timings);
+    let zip_input = ZipInput::open(&mut cli.input, &mut cli.output)?;
+
+    if zip_input.is_some() {
+        timings.stage("extract zip");
     }
 
-    Ok(bytecode)
+    let summary = process(cli, &mut timings)?;
+    timings.stage("process");
+
+    if let Some(zip_input) = zip_input {
+        zip_input.finish()?;
+        timings.stage("repack zip");
+    }
+
+    timings.print();
+
+    Ok(summary)
 }
 
-fn main() -> Result<()> {
-    let cli: Cmd = argh::from_env();
+/// Processes a file, folder or glob input, zip inputs are extracted folders
+fn process(cli: Cmd, timings: &mut Timings) -> Result<Summary> {
+    let summary = Summary::default();
+    let options = DecompileOptions::from_cmd(&cli)?;
+    let deny = DenyList::load()?;
+    let check_syntax = cli.check_syntax && !cli.decode_only;
+    let build_index = cli.index && !cli.decode_only && !cli.dry_run;
+    let split = cli.split_functions.is_some() && !cli.dry_run;
+
+    if cli.split_functions.is_some() && cli.decode_only {
+        bail!(report::usage(
+            "Split functions can't be combined with decode only"
+        ))
+    }
 
     match GarPath::parse(&cli.input) {
         GarPath::Filesystem(path) => {
-            if path.is_file() {
-                let mut output_file: PathBuf =
-                    cli.output.unwrap_or(path.clone()).components().collect();
+            if cli.watch && !path.is_dir() {
+                bail!(report::usage("Watch mode requires a folder input"))
+            }
 
-                let result = match cli.decode_only {
-                    false => {
-                        if output_file.extension().unwrap() == "l64" {
-                            output_file.set_extension("lua");
+            if cli.watch && cli.dry_run {
+                bail!(report::usage("Watch mode can't be combined with dry run"))
+            }
+
+            if path.is_file() || is_stdio(&path) {
+                let output_file = match (&cli.output, &cli.output_pattern) {
+                    (None, Some(_)) if !is_stdio(&path) => {
+                        OutputMapper::beside(&path, cli.output_pattern.clone())?
+                            .map(&path, output_extension(&cli, &path))?
+                    }
+                    _ => {
+                        let mut output_file: PathBuf = cli
+                            .output
+                            .clone()
+                            .unwrap_or(path.clone())
+                            .components()
+                            .collect();
+
+                        if let Some(extension) = output_extension(&cli, &output_file) {
+                            output_file.set_extension(extension);
                         }
 
-                        decompile_file(&path)?
+                        output_file
                     }
-                    true => decode_file(&path)?,
                 };
 
-                result.write_to_file(&output_file)?;
+                check_in_place(&path, &output_file, cli.in_place)?;
 
-                if !cli.silent {
+                if deny.check(&path, &summary) {
+                    return Ok(summary);
+                }
+
+                if cli.index {
+                    bail!(report::usage("Index requires a folder input"))
+                }
+
+                if cli.symbols_file && is_stdio(&output_file) {
+                    bail!(report::usage(
+                        "Symbols file can't be used with stdout output"
+                    ))
+                }
+
+                if cli.dry_run {
+                    println!("{} -> {}", path.display(), output_file.display());
+                    summary.processed();
+                    return Ok(summary);
+                }
+
+                let result = match cli.decode_only {
+                    false => decompile_file(&path, &options)?,
+                    true => decode_file(&path, &options)?.into(),
+                };
+
+                result.write_to_output(&output_file)?;
+
+                if split {
+                    let name = path.file_name().map_or("stdin".into(), PathBuf::from);
+                    split_output(&cli, &name, &result.source)?;
+                }
+
+                if check_syntax
+                    && let Some(warning) =
+                        SyntaxReport::default().check(&path.display().to_string(), &result.source)
+                {
+                    println!("{}", warning);
+                }
+
+                if !cli.silent && !is_stdio(&output_file) {
                     if output_file != path {
                         println!("{} -> {}", path.display(), output_file.display());
                     } else {
                         println!("{}", path.display());
                     }
                 }
-            } else if path.is_dir() {
-                let output_path = cli.output.unwrap_or_else(|| path.clone());
+
+                summary.processed();
+            } else if path.is_dir() || is_glob_pattern(&path) {
+                let (input_path, files) = list_input_files(
+                    &path,
+                    BYTECODE_EXTENSIONS,
+                    cli.recursive,
+                    cli.follow_symlinks,
+                )?;
+                let files = deny.filter(files, &summary);
+                timings.stage("list files");
+
+                let output_path = cli.output.clone().unwrap_or_else(|| input_path.clone());
 
                 if output_path.is_file() {
-                    bail!("Output path is a file")
+                    bail!(report::usage("Output path is a file"))
                 }
 
                 ThreadPoolBuilder::new()
@@ -176,48 +618,167 @@ fn main() -> Result<()> {
                     .build_global()
                     .unwrap();
 
-                let files: Vec<_> = list_files_with_extension(&path, r"l64", cli.recursive)?;
+                let mapper =
+                    OutputMapper::new(&input_path, &output_path, cli.output_pattern.clone())?
+                        .lossy(cli.in_place);
+                let get_output_file =
+                    |file: &PathBuf| mapper.map(file, output_extension(&cli, file));
 
-                let iter_result = files.into_par_iter().try_for_each(|file| -> Result<()> {
-                    let mut output_file: PathBuf = file
-                        .convert_relative_path(&path, &output_path)?
-                        .components()
-                        .collect();
+                check_unique_outputs(&files, get_output_file)?;
 
-                    let result = match cli.decode_only {
-                        false => {
-                            if output_file.extension().unwrap() == "l64" {
-                                output_file.set_extension("lua");
-                            }
+                let report = SyntaxReport::default();
+                let index = Index::default();
 
-                            decompile_file(&file)?
-                        }
-                        true => decode_file(&file)?,
+                // Returns the lines to print, parallel runs print them in input order
+                let process_file = |file: &PathBuf, output_file: &PathBuf| -> Result<Vec<String>> {
+                    let mut output = Vec::new();
+                    let result = match cli.decode_only {
+                        false => decompile_file(file, &options)?,
+                        true => decode_file(file, &options)?.into(),
                     };
 
-                    result.write_to_file(&output_file)?;
+                    result.write_to_file(output_file)?;
+
+                    if check_syntax {
+                        output.extend(
+                            report.check(&output_file.display().to_string(), &result.source),
+                        );
+                    }
+
+                    if build_index {
+                        index.add(output_file, &result.source);
+                    }
+
+                    if split {
+                        split_output(&cli, file.strip_prefix(&input_path)?, &result.source)?;
+                    }
 
                     if !cli.silent {
-                        if output_file != *file {
-                            println!("{} -> {}", file.display(), output_file.display());
+                        if output_file != file {
+                            output.push(format!("{} -> {}", file.display(), output_file.display()));
                         } else {
-                            println!("{}", file.display());
+                            output.push(file.display().to_string());
+                        }
+                    }
+
+                    Ok(output)
+                };
+
+                let cache_options = options.cache_key(cli.decode_only);
+                let cache =
+                    Cache::load(&output_path, "fs-luau-decompile", &cache_options, cli.force);
+
+                let process_entry = |file: &PathBuf| -> Result<Vec<String>> {
+                    let output_file = get_output_file(file)?;
+
+                    if cli.dry_run {
+                        summary.processed();
+                        return Ok(vec![format!(
+                            "{} -> {}",
+                            file.display(),
+                            output_file.display()
+                        )]);
+                    }
+
+                    if cache.is_fresh(file, &output_file)? {
+                        let mut output = Vec::new();
+
+                        // Unchanged outputs still need to be part of the report, index
+                        // and split files
+                        if check_syntax || build_index || split {
+                            let source = Vec::read_from_file(&output_file)?;
+
+                            if check_syntax {
+                                output.extend(
+                                    report.check(&output_file.display().to_string(), &source),
+                                );
+                            }
+
+                            if build_index {
+                                index.add(&output_file, &source);
+                            }
+
+                            if split {
+                                split_output(&cli, file.strip_prefix(&input_path)?, &source)?;
+                            }
                         }
+
+                        summary.skipped();
+                        return Ok(output);
+                    }
+
+                    let output = process_file(file, &output_file)?;
+                    cache.update(file, &output_file)?;
+                    summary.processed();
+
+                    Ok(output)
+                };
+
+                let outputs: Vec<_> = files
+                    .into_par_iter()
+                    .map(|file| {
+                        let output = process_entry(&file);
+                        (file.display().to_string(), output)
+                    })
+                    .collect();
+
+                summary.print_outputs(outputs);
+
+                if !cli.dry_run {
+                    cache.save()?;
+                }
+
+                if !cli.dry_run {
+                    print_syntax_report(&report, &output_path)?;
+                }
+
+                if build_index {
+                    index.save(&output_path)?;
+                }
+
+                if cli.watch {
+                    if !cli.silent {
+                        println!("Watching {} for changes", input_path.display());
                     }
 
-                    Ok(())
-                });
+                    watch_files(
+                        &input_path,
+                        BYTECODE_EXTENSIONS,
+                        cli.recursive,
+                        cli.follow_symlinks,
+                        Some(&output_path),
+                        |file| {
+                            if deny.check(file, &summary) {
+                                return Ok(());
+                            }
 
-                return iter_result;
+                            for line in process_file(file, &get_output_file(file)?)? {
+                                println!("{}", line);
+                            }
+
+                            Ok(())
+                        },
+                    )?;
+                }
             }
         }
         GarPath::Archive {
             archive_path,
             internal_path,
         } => {
+            if cli.watch {
+                bail!(report::usage(
+                    "Watch mode is not supported for archive input"
+                ))
+            }
+
             let archive = GarArchive::open(&archive_path).map_err(|e| anyhow::anyhow!("{}", e))?;
+            timings.stage("open archive");
+
             let base = internal_path.as_deref().unwrap_or("");
-            let output_path = cli.output.unwrap_or_else(|| PathBuf::from("."));
+            let output_path = cli.output.clone().unwrap_or_else(|| PathBuf::from("."));
+            let mapper =
+                OutputMapper::new(Path::new(""), &output_path, cli.output_pattern.clone())?;
 
             ThreadPoolBuilder::new()
                 .num_threads(cli.num_threads.into())
@@ -226,64 +787,134 @@ fn main() -> Result<()> {
                 .unwrap();
 
             // Check if internal path is a single file
-            if base.ends_with(".l64") {
+            if has_extension(base, BYTECODE_EXTENSIONS) {
+                if cli.index {
+                    bail!(report::usage("Index requires a folder input"))
+                }
+
+                if deny.check(base, &summary) {
+                    return Ok(summary);
+                }
+
+                let filename = Path::new(base).file_name().unwrap();
+                let out_file = mapper.map(filename, output_extension(&cli, filename))?;
+
+                if cli.dry_run {
+                    println!("{} -> {}", base, out_file.display());
+                    summary.processed();
+                    return Ok(summary);
+                }
+
                 let result = if cli.decode_only {
-                    decode_from_archive(&archive, base)?
+                    decode_from_archive(&archive, base, &options)?.into()
                 } else {
-                    decompile_from_archive(&archive, base)?
+                    decompile_from_archive(&archive, &archive_path, base, &options)?
                 };
 
-                let filename = Path::new(base).file_name().unwrap();
-                let mut out_file = output_path.join(filename);
-                if !cli.decode_only {
-                    out_file.set_extension("lua");
+                result.write_to_file(&out_file)?;
+
+                if split {
+                    split_output(&cli, Path::new(filename), &result.source)?;
                 }
 
-                result.write_to_file(&out_file)?;
+                if check_syntax
+                    && let Some(warning) = SyntaxReport::default().check(base, &result.source)
+                {
+                    println!("{}", warning);
+                }
 
                 if !cli.silent {
                     println!("{} -> {}", base, out_file.display());
                 }
+
+                summary.processed();
             } else {
                 // Directory - process multiple files
-                let files: Vec<&str> = archive
-                    .files_with_extension(base, "l64", cli.recursive)
-                    .into_iter()
+                let mut files: Vec<&str> = BYTECODE_EXTENSIONS
+                    .split('|')
+                    .flat_map(|extension| {
+                        archive.files_with_extension(base, extension, cli.recursive)
+                    })
                     .collect();
+                files.sort();
 
                 if files.is_empty() {
-                    bail!("No .l64 files found in archive path: {}", base);
+                    bail!(report::usage(format!(
+                        "No bytecode files ({}) found in archive path: {}",
+                        BYTECODE_EXTENSIONS.replace('|', ", "),
+                        base
+                    )));
                 }
 
-                let iter_result = files.into_par_iter().try_for_each(|file| -> Result<()> {
-                    let result = if cli.decode_only {
-                        decode_from_archive(&archive, file)?
-                    } else {
-                        decompile_from_archive(&archive, file)?
-                    };
+                let files = deny.filter(files, &summary);
 
+                let report = SyntaxReport::default();
+                let index = Index::default();
+
+                let process_entry = |file: &str| -> Result<Vec<String>> {
+                    let mut output = Vec::new();
                     let rel_path = file
                         .strip_prefix(base)
                         .unwrap_or(file)
                         .trim_start_matches('/');
-                    let mut out_file = output_path.join(rel_path);
-                    if !cli.decode_only {
-                        out_file.set_extension("lua");
+                    let entry_path = sanitize_entry_path(rel_path)?;
+                    let out_file = mapper.map(&entry_path, output_extension(&cli, &entry_path))?;
+
+                    if cli.dry_run {
+                        summary.processed();
+                        return Ok(vec![format!("{} -> {}", file, out_file.display())]);
                     }
 
+                    let result = if cli.decode_only {
+                        decode_from_archive(&archive, file, &options)?.into()
+                    } else {
+                        decompile_from_archive(&archive, &archive_path, file, &options)?
+                    };
+
                     result.write_to_file(&out_file)?;
 
+                    if check_syntax {
+                        output.extend(report.check(file, &result.source));
+                    }
+
+                    if build_index {
+                        index.add(&out_file, &result.source);
+                    }
+
+                    if split {
+                        split_output(&cli, &entry_path, &result.source)?;
+                    }
+
                     if !cli.silent {
-                        println!("{} -> {}", file, out_file.display());
+                        output.push(format!("{} -> {}", file, out_file.display()));
                     }
 
-                    Ok(())
-                });
+                    summary.processed();
 
-                return iter_result;
+                    Ok(output)
+                };
+
+                let outputs: Vec<_> = files
+                    .into_par_iter()
+                    .map(|file| (archive_path.display(), process_entry(file)))
+                    .collect();
+
+                summary.print_outputs(outputs);
+
+                if !cli.dry_run {
+                    print_syntax_report(&report, &output_path)?;
+                }
+
+                if build_index {
+                    index.save(&output_path)?;
+                }
             }
         }
     }
 
-    Ok(())
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
 }
@@ -0,0 +1,83 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fs_lib::LuauHeader;
+
+use crate::format::{CommentStyle, block_comment};
+
+/// `2026-01-31T12:00:00Z`, days to date conversion from
+/// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn utc_timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+
+    let days = (seconds / 86400) as i64 + 719468;
+    let time = seconds % 86400;
+
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+fn encoding(header: &LuauHeader) -> &'static str {
+    match (header.is_encoded, header.is_dlc) {
+        (false, _) => "plain",
+        (true, false) => "encoded, regular table",
+        (true, true) => "encoded, DLC table",
+    }
+}
+
+/// Comment recording where a decompiled file comes from, the date is left
+/// out for reproducible output
+pub fn metadata_header(
+    source: &str,
+    header: &LuauHeader,
+    with_date: bool,
+    style: CommentStyle,
+) -> String {
+    let mut lines = vec![
+        format!(
+            "Decompiled by fs-luau-decompile {} (Lantern)",
+            env!("CARGO_PKG_VERSION")
+        ),
+        format!("Source: {}", source.replace('\\', "/")),
+        format!(
+            "Bytecode: Luau version {}, {}",
+            header.version,
+            encoding(header)
+        ),
+    ];
+
+    if with_date {
+        lines.push(format!("Date: {}", utc_timestamp()));
+    }
+
+    match style {
+        CommentStyle::Inline => {
+            let mut output: String = lines.iter().map(|line| format!("-- {}\n", line)).collect();
+            output.push('\n');
+            output
+        }
+        CommentStyle::Block => block_comment(&lines.join("\n")) + "\n\n",
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+
+use fs_lib::source::{LongStringState, is_temporary, rename_identifiers};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::format::{CommentStyle, block_comment};
+
+lazy_static! {
+    // InitObjectClass(v1, "MyClass")
+    static ref CLASS_REGISTRATION: Regex = Regex::new(
+        r#"\b(?:InitObjectClass|InitStaticObjectClass|registerObjectClassName)\(\s*(\w+)\s*,\s*"(\w+)""#
+    )
+    .unwrap();
+    // setmetatable(v1, { __name = "MyClass" })
+    static ref METATABLE_NAME: Regex =
+        Regex::new(r#"\bsetmetatable\(\s*(\w+)\s*,.*\b__name\s*=\s*"(\w+)""#).unwrap();
+    // _G.MyClass = v1 / _G["MyClass"] = v1
+    static ref GLOBAL_ASSIGNMENT: Regex =
+        Regex::new(r#"\b_G\s*(?:\.(\w+)|\[\s*"(\w+)"\s*\])\s*=\s*(\w+)\s*$"#).unwrap();
+    // local v2 = Class(v1, Object)
+    static ref CLASS_METATABLE: Regex =
+        Regex::new(r"^\s*local\s+(\w+)\s*=\s*Class\(\s*(\w+)\s*[,)]").unwrap();
+    // local function v3( / local v3 = function(
+    static ref LOCAL_FUNCTION: Regex =
+        Regex::new(r"^\s*local\s+(?:function\s+(\w+)\s*\(|(\w+)\s*=\s*function\s*\()").unwrap();
+    // MyClass.onLoad = v3 -> MyClass_onLoad
+    static ref FIELD_ASSIGNMENT: Regex =
+        Regex::new(r"^\s*([\w.]+)\.(\w+)\s*=\s*(\w+)\s*$").unwrap();
+}
+
+/// Temporaries and their inferred names, a temporary or name is only used
+/// once and names already in the source are skipped
+#[derive(Default)]
+struct InferredNames {
+    names: Vec<(String, String)>,
+    identifiers: HashSet<String>,
+}
+
+impl InferredNames {
+    fn add(&mut self, temporary: &str, name: String) {
+        if !is_temporary(temporary)
+            || self.identifiers.contains(&name)
+            || self.names.iter().any(|(t, n)| t == temporary || *n == name)
+        {
+            return;
+        }
+
+        self.names.push((temporary.to_string(), name));
+    }
+
+    fn get(&self, temporary: &str) -> Option<&str> {
+        self.names
+            .iter()
+            .find(|(t, _)| t == temporary)
+            .map(|(_, name)| name.as_str())
+    }
+}
+
+fn inferred_header(names: &[(String, String)], style: CommentStyle) -> String {
+    let title = "Names inferred from class registrations (no debug info):";
+    let table: String = names
+        .iter()
+        .map(|(temporary, name)| format!("{} -> {}\n", temporary, name))
+        .collect();
+
+    match style {
+        CommentStyle::Inline => {
+            let mut output = format!("-- {}\n", title);
+
+            for line in table.lines() {
+                output.push_str(&format!("--   {}\n", line));
+            }
+
+            output + "\n"
+        }
+        CommentStyle::Block => block_comment(&format!("{}\n{}", title, table)) + "\n\n",
+    }
+}
+
+/// Renames numbered temporaries of bytecode without debug info using the
+/// class names registered with them (`InitObjectClass`, `setmetatable` with
+/// `__name`, `_G` assignments). Metatables from `Class()` become
+/// `<class>_mt` and local functions stored in a table field are named
+/// `<table>_<field>`. The renames are listed in a header comment.
+pub fn infer_names(source: &str, style: CommentStyle) -> String {
+    let mut state = LongStringState::default();
+    let mut code_lines = Vec::new();
+    let mut inferred = InferredNames::default();
+
+    for line in source.lines() {
+        if !state.update(line) {
+            code_lines.push(line);
+        }
+    }
+
+    for line in code_lines.iter() {
+        rename_identifiers(line, |identifier| {
+            inferred.identifiers.insert(identifier.to_string());
+            None
+        });
+    }
+
+    for line in code_lines.iter() {
+        if let Some(captures) = CLASS_REGISTRATION
+            .captures(line)
+            .or_else(|| METATABLE_NAME.captures(line))
+        {
+            inferred.add(&captures[1], captures[2].to_string());
+        } else if let Some(captures) = GLOBAL_ASSIGNMENT.captures(line) {
+            let name = captures.get(1).or_else(|| captures.get(2)).unwrap();
+
+            inferred.add(&captures[3], name.as_str().to_string());
+        }
+    }
+
+    for line in code_lines.iter() {
+        if let Some(captures) = CLASS_METATABLE.captures(line)
+            && let Some(class) = inferred.get(&captures[2])
+        {
+            let name = format!("{}_mt", class);
+
+            inferred.add(&captures[1], name);
+        }
+    }
+
+    let functions: HashSet<&str> = code_lines
+        .iter()
+        .filter_map(|line| LOCAL_FUNCTION.captures(line))
+        .filter_map(|captures| captures.get(1).or_else(|| captures.get(2)))
+        .map(|m| m.as_str())
+        .collect();
+
+    for line in code_lines.iter() {
+        if let Some(captures) = FIELD_ASSIGNMENT.captures(line)
+            && functions.contains(&captures[3])
+        {
+            let table = captures[1]
+                .split('.')
+                .map(|part| inferred.get(part).unwrap_or(part))
+                .collect::<Vec<_>>()
+                .join("_");
+            let name = format!("{}_{}", table, &captures[2]);
+
+            inferred.add(&captures[3], name);
+        }
+    }
+
+    if inferred.names.is_empty() {
+        return source.to_string();
+    }
+
+    let mut state = LongStringState::default();
+    let mut output = inferred_header(&inferred.names, style);
+
+    for line in source.split_inclusive('\n') {
+        match state.update(line) {
+            true => output.push_str(line),
+            false => output.push_str(&rename_identifiers(line, |identifier| {
+                inferred.get(identifier).map(String::from)
+            })),
+        }
+    }
+
+    output
+}
@@ -0,0 +1,134 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use anyhow::Result;
+use fs_lib::{
+    buffer::BufferExtension,
+    source::{GLOBAL_CLASS, LongStringState, function_definitions},
+};
+
+/// File of the code outside top-level functions
+const MAIN_FILE_NAME: &str = "_main.lua";
+const INDEX_FILE_NAME: &str = "index.md";
+
+/// Top-level function of a class
+struct Function {
+    name: String,
+    /// 1-based line in the decompiled file
+    line: usize,
+    lines: Vec<String>,
+}
+
+#[derive(Default)]
+struct Split {
+    classes: BTreeMap<String, Vec<Function>>,
+    main: Vec<String>,
+}
+
+/// Groups top-level function definitions by class. A function ends at the
+/// first `end` without indentation, the decompiler doesn't indent top-level
+/// functions.
+fn split_source(source: &str) -> Split {
+    let mut split = Split::default();
+    let mut state = LongStringState::default();
+    let mut current: Option<(String, Function)> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let inside_string = state.update(line);
+
+        if let Some((_, function)) = current.as_mut() {
+            function.lines.push(line.to_string());
+
+            if !inside_string && line.trim_end() == "end" {
+                let (class, function) = current.take().unwrap();
+                split.classes.entry(class).or_default().push(function);
+            }
+
+            continue;
+        }
+
+        let definition = match inside_string || line.starts_with(char::is_whitespace) {
+            true => None,
+            false => function_definitions(line).pop(),
+        };
+
+        let Some(definition) = definition else {
+            split.main.push(line.to_string());
+            continue;
+        };
+
+        let name = match (definition.class.as_str(), definition.method) {
+            (GLOBAL_CLASS, _) => definition.function,
+            (class, true) => format!("{}:{}", class, definition.function),
+            (class, false) => format!("{}.{}", class, definition.function),
+        };
+
+        let function = Function {
+            name,
+            line: index + 1,
+            lines: vec![line.to_string()],
+        };
+
+        // One-line definitions, e.g. `function Class.name() end`
+        match line.trim_end().ends_with(" end") {
+            true => split
+                .classes
+                .entry(definition.class)
+                .or_default()
+                .push(function),
+            false => current = Some((definition.class, function)),
+        }
+    }
+
+    // Unterminated function, keep it with the rest of the code
+    if let Some((_, function)) = current {
+        split.main.extend(function.lines);
+    }
+
+    split
+}
+
+/// Writes each class of a decompiled file to `<class>.lua` in `dir`, code
+/// outside functions to `_main.lua` and an `index.md` listing the functions
+pub fn write_split(source: &[u8], dir: &Path, name: &str) -> Result<()> {
+    let split = split_source(&String::from_utf8_lossy(source));
+    let mut index = format!("# {}\n", name);
+
+    for (class, functions) in split.classes.iter() {
+        let file_name = format!("{}.lua", class);
+        let mut output = String::new();
+        let mut line = 1;
+
+        writeln!(index, "\n## [{}]({})\n", class, file_name)?;
+
+        for function in functions {
+            if !output.is_empty() {
+                output.push('\n');
+                line += 1;
+            }
+
+            writeln!(
+                index,
+                "- `{}` [line {}]({}#L{}), line {} in {}",
+                function.name, line, file_name, line, function.line, name
+            )?;
+
+            for function_line in function.lines.iter() {
+                writeln!(output, "{}", function_line)?;
+            }
+
+            line += function.lines.len();
+        }
+
+        output.into_bytes().write_to_file(dir.join(&file_name))?;
+    }
+
+    if split.main.iter().any(|line| !line.trim().is_empty()) {
+        writeln!(index, "\n## [Other code]({})", MAIN_FILE_NAME)?;
+
+        (split.main.join("\n") + "\n")
+            .into_bytes()
+            .write_to_file(dir.join(MAIN_FILE_NAME))?;
+    }
+
+    index.into_bytes().write_to_file(dir.join(INDEX_FILE_NAME))
+}
@@ -0,0 +1,72 @@
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Result;
+use fs_lib::buffer::BufferExtension;
+use mlua::Compiler;
+
+pub const REPORT_FILE_NAME: &str = "syntax-errors.txt";
+
+/// Returns the parser error if the source fails to compile
+pub fn check_syntax(source: &[u8]) -> Option<String> {
+    Compiler::new()
+        .compile(source)
+        .err()
+        .map(|e| e.to_string().trim().to_string())
+}
+
+/// Collects files that fail the syntax check
+#[derive(Default)]
+pub struct SyntaxReport {
+    failures: Mutex<Vec<(String, String)>>,
+}
+
+impl SyntaxReport {
+    /// Checks the source and records it under name if it fails to parse,
+    /// returns the warning to print
+    pub fn check(&self, name: &str, source: &[u8]) -> Option<String> {
+        let error = check_syntax(source)?;
+        let warning = format!("Warning: {} failed syntax check: {}", name, error);
+
+        self.failures
+            .lock()
+            .unwrap()
+            .push((name.to_string(), error));
+
+        Some(warning)
+    }
+
+    /// Writes the report to the output folder if any files failed, returns
+    /// the report path. A report left from a previous run is removed.
+    pub fn save(&self, output_path: &Path) -> Result<Option<PathBuf>> {
+        let mut failures = self.failures.lock().unwrap();
+        let file = output_path.join(REPORT_FILE_NAME);
+
+        if failures.is_empty() {
+            if file.is_file() {
+                std::fs::remove_file(&file)?;
+            }
+
+            return Ok(None);
+        }
+
+        failures.sort();
+
+        let mut report = String::new();
+
+        for (name, error) in failures.iter() {
+            writeln!(report, "{}\n    {}\n", name, error)?;
+        }
+
+        report.into_bytes().write_to_file(&file)?;
+
+        Ok(Some(file))
+    }
+
+    pub fn len(&self) -> usize {
+        self.failures.lock().unwrap().len()
+    }
+}
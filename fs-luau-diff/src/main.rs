@@ -0,0 +1,90 @@
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    buffer::BufferExtension,
+    codec, luau,
+    report::{self, Summary},
+    source::stabilize,
+};
+use similar::TextDiff;
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Decompile two Luau .l64 bytecode files and print a unified diff
+pub struct Cmd {
+    /// number of context lines (default 3)
+    #[argh(option, short = 'U', default = "3")]
+    context: usize,
+
+    /// compare decompiler output as is, without renumbering temporaries
+    #[argh(switch)]
+    raw: bool,
+
+    /// path to old file (.l64 or already decompiled .lua)
+    #[argh(positional)]
+    old: PathBuf,
+
+    /// path to new file (.l64 or already decompiled .lua)
+    #[argh(positional)]
+    new: PathBuf,
+}
+
+fn decompile_file(file: &Path) -> Result<String> {
+    let mut buffer = Vec::read_from_file(file)?;
+
+    if file.extension().is_some_and(|ext| ext == "lua") {
+        return Ok(String::from_utf8_lossy(&buffer).into_owned());
+    }
+
+    let Some(header) = codec::detect(&buffer, &[]) else {
+        bail!("{}: Unsupported/unknown bytecode", file.display());
+    };
+
+    if let Err(e) = codec::decode(&mut buffer, &header) {
+        bail!("{}: {}", file.display(), e)
+    }
+
+    if let Err(e) = luau::validate_bytecode(&buffer) {
+        bail!("{}: {}", file.display(), e)
+    }
+
+    Ok(lantern::decompile_bytecode(&buffer, 1))
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    let mut old_source = decompile_file(&cli.old)?;
+    summary.processed();
+    let mut new_source = decompile_file(&cli.new)?;
+    summary.processed();
+
+    if !cli.raw {
+        old_source = stabilize(&old_source);
+        new_source = stabilize(&new_source);
+    }
+
+    if old_source == new_source {
+        return Ok(summary);
+    }
+
+    let diff = TextDiff::from_lines(&old_source, &new_source);
+
+    print!(
+        "{}",
+        diff.unified_diff()
+            .context_radius(cli.context)
+            .header(&cli.old.to_string_lossy(), &cli.new.to_string_lossy())
+    );
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
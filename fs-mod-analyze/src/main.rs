@@ -0,0 +1,446 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    buffer::BufferExtension,
+    codec, list_files, luau,
+    report::{self, Summary},
+    source::{GLOBAL_CLASS, function_definitions},
+    xml::{self, Element},
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+const MOD_DESC_FILE_NAME: &str = "modDesc.xml";
+
+lazy_static! {
+    // Class.name = Utils.overwrittenFunction( / appendedFunction( / prependedFunction(
+    static ref FUNCTION_HOOK: Regex = Regex::new(
+        r"^\s*([\w.]+)\.(\w+)\s*=\s*Utils\.(overwritten|appended|prepended)Function\s*\("
+    )
+    .unwrap();
+    // Name = ... at the top level of a script, defines a global in the mod environment
+    static ref GLOBAL_ASSIGNMENT: Regex = Regex::new(r"^(\w+)\s*=").unwrap();
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Analyze a mods folder for missing dependencies and scripts changing the same
+/// game functions
+pub struct Cmd {
+    /// also list game functions hooked by multiple mods with Utils.*Function,
+    /// hooks are chained and usually compatible
+    #[argh(switch)]
+    hooks: bool,
+
+    /// enter directory symlinks and junctions of the mod folders
+    #[argh(switch)]
+    follow_symlinks: bool,
+
+    /// path to mods folder (unzipped folders and .zip files)
+    #[argh(positional)]
+    input: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Change {
+    /// Function is replaced
+    Define,
+    /// Function is wrapped with Utils.overwrittenFunction etc.
+    Hook,
+}
+
+struct Touch {
+    mod_name: String,
+    file: String,
+    line: usize,
+    change: Change,
+}
+
+struct Mod {
+    name: String,
+    path: PathBuf,
+    dependencies: Vec<String>,
+    /// Scripts as (path inside mod, source), compiled scripts are decompiled
+    scripts: Vec<(String, String)>,
+}
+
+/// modDesc.xml and (path inside mod, content) of every script
+type ModFiles = (Vec<u8>, Vec<(String, Vec<u8>)>);
+
+fn relative_name(path: &str) -> String {
+    path.replace('\\', "/")
+}
+
+fn is_script(path: &str) -> bool {
+    path.ends_with(".lua") || path.ends_with(".l64")
+}
+
+fn decompile_script(path: &str, mut buffer: Vec<u8>) -> Result<String> {
+    if path.ends_with(".lua") {
+        return Ok(String::from_utf8_lossy(&buffer).into_owned());
+    }
+
+    let Some(header) = codec::detect(&buffer, &[]) else {
+        bail!("Unsupported/unknown bytecode")
+    };
+
+    codec::decode(&mut buffer, &header)?;
+
+    luau::validate_bytecode(&buffer)?;
+
+    Ok(lantern::decompile_bytecode(&buffer, 1))
+}
+
+/// Returns modDesc.xml and all scripts of an unzipped mod
+fn read_folder(dir: &Path, follow_symlinks: bool) -> Result<ModFiles> {
+    let mod_desc = Vec::read_from_file(dir.join(MOD_DESC_FILE_NAME))?;
+    let mut scripts = Vec::new();
+
+    for file in list_files(dir, true, follow_symlinks)? {
+        let name = relative_name(&file.strip_prefix(dir)?.to_string_lossy());
+
+        if is_script(&name) {
+            scripts.push((name, Vec::read_from_file(&file)?));
+        }
+    }
+
+    Ok((mod_desc, scripts))
+}
+
+/// Returns modDesc.xml and all scripts of a zipped mod
+fn read_zip(file: &Path) -> Result<ModFiles> {
+    let mut archive = zip::ZipArchive::new(File::open(file)?)?;
+    let mut mod_desc = None;
+    let mut scripts = Vec::new();
+
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        let name = relative_name(entry.name());
+
+        if name != MOD_DESC_FILE_NAME && !is_script(&name) {
+            continue;
+        }
+
+        let mut buffer = Vec::new();
+        entry.read_to_end(&mut buffer)?;
+
+        match name == MOD_DESC_FILE_NAME {
+            true => mod_desc = Some(buffer),
+            false => scripts.push((name, buffer)),
+        }
+    }
+
+    let Some(mod_desc) = mod_desc else {
+        bail!("No {} in archive root", MOD_DESC_FILE_NAME)
+    };
+
+    Ok((mod_desc, scripts))
+}
+
+fn read_dependencies(root: &Element) -> Vec<String> {
+    root.children("dependencies")
+        .flat_map(|dependencies| dependencies.children("dependency"))
+        .filter_map(|dependency| dependency.text().map(str::to_string))
+        .collect()
+}
+
+fn load_mod(path: &Path, follow_symlinks: bool) -> Result<Mod> {
+    let (mod_desc, files) = match path.is_dir() {
+        true => read_folder(path, follow_symlinks)?,
+        false => read_zip(path)?,
+    };
+
+    let root = xml::parse(&mod_desc)?;
+    let mut scripts = Vec::new();
+
+    for (name, buffer) in files {
+        match decompile_script(&name, buffer) {
+            Ok(source) => scripts.push((name, source)),
+            Err(e) => eprintln!("{}: skipped {}: {}", path.display(), name, e),
+        }
+    }
+
+    Ok(Mod {
+        name: path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+        path: path.to_path_buf(),
+        dependencies: read_dependencies(&root),
+        scripts,
+    })
+}
+
+fn find_mods(input: &Path) -> Result<Vec<PathBuf>> {
+    let mut mods: Vec<PathBuf> = std::fs::read_dir(input)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| match path.is_dir() {
+            true => path.join(MOD_DESC_FILE_NAME).is_file(),
+            false => path.extension().is_some_and(|ext| ext == "zip"),
+        })
+        .collect();
+
+    mods.sort();
+
+    Ok(mods)
+}
+
+/// Globals assigned by the mod itself, scripts run in a per-mod environment
+/// so these can't clash with other mods
+fn mod_globals(m: &Mod) -> BTreeSet<String> {
+    m.scripts
+        .iter()
+        .flat_map(|(_, source)| source.lines())
+        .filter_map(|line| GLOBAL_ASSIGNMENT.captures(line))
+        .map(|captures| captures[1].to_string())
+        .collect()
+}
+
+/// Collects changes to shared (game) functions, "Class.function" -> touches
+fn collect_touches(mods: &[Mod]) -> BTreeMap<String, Vec<Touch>> {
+    let mut touches: BTreeMap<String, Vec<Touch>> = BTreeMap::new();
+
+    for m in mods {
+        let globals = mod_globals(m);
+
+        for (file, source) in m.scripts.iter() {
+            let mut changes: Vec<(String, String, usize, Change)> = function_definitions(source)
+                .into_iter()
+                .map(|d| (d.class, d.function, d.line, Change::Define))
+                .collect();
+
+            for (index, line) in source.lines().enumerate() {
+                if let Some(captures) = FUNCTION_HOOK.captures(line) {
+                    changes.push((
+                        captures[1].to_string(),
+                        captures[2].to_string(),
+                        index + 1,
+                        Change::Hook,
+                    ));
+                }
+            }
+
+            for (class, function, line, change) in changes {
+                let root_name = class.split('.').next().unwrap_or_default();
+
+                if class == GLOBAL_CLASS || globals.contains(root_name) {
+                    continue;
+                }
+
+                touches
+                    .entry(format!("{}.{}", class, function))
+                    .or_default()
+                    .push(Touch {
+                        mod_name: m.name.clone(),
+                        file: file.clone(),
+                        line,
+                        change,
+                    });
+            }
+        }
+    }
+
+    touches
+}
+
+/// Depth-first search state of a mod, unvisited mods aren't in the map
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Visit {
+    /// On the current path
+    InProgress,
+    /// All dependencies searched
+    Done,
+}
+
+fn find_cycles(mods: &[Mod]) -> Vec<Vec<String>> {
+    let graph: BTreeMap<&str, &Vec<String>> = mods
+        .iter()
+        .map(|m| (m.name.as_str(), &m.dependencies))
+        .collect();
+
+    let mut cycles: Vec<Vec<String>> = Vec::new();
+    let mut visits: BTreeMap<String, Visit> = BTreeMap::new();
+
+    fn visit(
+        name: &str,
+        graph: &BTreeMap<&str, &Vec<String>>,
+        visits: &mut BTreeMap<String, Visit>,
+        path: &mut Vec<String>,
+        cycles: &mut Vec<Vec<String>>,
+    ) {
+        match visits.get(name) {
+            Some(Visit::Done) => return,
+            Some(Visit::InProgress) => {
+                let position = path.iter().position(|n| n == name).unwrap_or(0);
+                let mut cycle = path[position..].to_vec();
+
+                // Keep the rotation starting with the smallest name
+                let start = (0..cycle.len()).min_by_key(|i| &cycle[*i]).unwrap_or(0);
+                cycle.rotate_left(start);
+
+                if !cycles.contains(&cycle) {
+                    cycles.push(cycle);
+                }
+                return;
+            }
+            None => {}
+        }
+
+        let Some(dependencies) = graph.get(name) else {
+            return;
+        };
+
+        visits.insert(name.to_string(), Visit::InProgress);
+        path.push(name.to_string());
+
+        for dependency in dependencies.iter() {
+            visit(dependency, graph, visits, path, cycles);
+        }
+
+        path.pop();
+        visits.insert(name.to_string(), Visit::Done);
+    }
+
+    for m in mods {
+        visit(&m.name, &graph, &mut visits, &mut Vec::new(), &mut cycles);
+    }
+
+    cycles
+}
+
+fn print_report(cli: &Cmd, mods: &[Mod]) {
+    let names: BTreeSet<&str> = mods.iter().map(|m| m.name.as_str()).collect();
+
+    println!("Mods: {}", mods.len());
+
+    let mut seen = BTreeSet::new();
+
+    for m in mods {
+        if !seen.insert(&m.name) {
+            println!("\nDuplicate mod {} ({})", m.name, m.path.display());
+        }
+    }
+
+    let missing: Vec<(&str, &str)> = mods
+        .iter()
+        .flat_map(|m| {
+            m.dependencies
+                .iter()
+                .filter(|dependency| !names.contains(dependency.as_str()))
+                .map(|dependency| (m.name.as_str(), dependency.as_str()))
+        })
+        .collect();
+
+    if !missing.is_empty() {
+        println!("\nMissing dependencies:");
+
+        for (name, dependency) in missing {
+            println!("  {} requires {}", name, dependency);
+        }
+    }
+
+    let cycles = find_cycles(mods);
+
+    if !cycles.is_empty() {
+        println!("\nDependency cycles:");
+
+        for cycle in cycles {
+            println!("  {} -> {}", cycle.join(" -> "), cycle[0]);
+        }
+    }
+
+    let touches = collect_touches(mods);
+    let mut conflicts = 0;
+    let mut listed = 0;
+
+    for (function, touches) in touches.iter() {
+        let mod_count = touches
+            .iter()
+            .map(|touch| &touch.mod_name)
+            .collect::<BTreeSet<_>>()
+            .len();
+        let replaced = touches.iter().any(|touch| touch.change == Change::Define);
+
+        if mod_count < 2 || (!replaced && !cli.hooks) {
+            continue;
+        }
+
+        if listed == 0 {
+            println!("\nFunctions changed by multiple mods:");
+        }
+        listed += 1;
+
+        if replaced {
+            conflicts += 1;
+        }
+
+        println!(
+            "  {}{}",
+            function,
+            if replaced { "" } else { " (hooks only)" }
+        );
+
+        for touch in touches {
+            let change = match touch.change {
+                Change::Define => "replaces",
+                Change::Hook => "hooks",
+            };
+
+            println!(
+                "    {:<8} {} {}:{}",
+                change, touch.mod_name, touch.file, touch.line
+            );
+        }
+    }
+
+    println!(
+        "\n{} conflicts, {} changed game functions",
+        conflicts,
+        touches.len()
+    );
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    if !cli.input.is_dir() {
+        bail!(report::usage(format!(
+            "Mods folder not found: {}",
+            cli.input.display()
+        )))
+    }
+
+    let mut mods = Vec::new();
+
+    for path in find_mods(&cli.input)? {
+        match load_mod(&path, cli.follow_symlinks) {
+            Ok(m) => {
+                mods.push(m);
+                summary.processed();
+            }
+            Err(e) => summary.error(path.display(), &e),
+        }
+    }
+
+    if mods.is_empty() {
+        bail!("No mods found in {}", cli.input.display())
+    }
+
+    print_report(&cli, &mods);
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
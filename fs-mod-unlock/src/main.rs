@@ -0,0 +1,160 @@
+use std::{
+    env::consts::EXE_SUFFIX,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    buffer::BufferExtension,
+    cache::CACHE_FILE_NAME,
+    cmd::run_command,
+    list_files, list_files_with_extension,
+    report::{self, Summary},
+    zip_input,
+};
+
+/// LuaJIT bytecode (FS22), other .l64 files are Luau (FS25)
+const LUAJIT_MAGIC: &[u8] = b"\x1bLJ";
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Unzip a mod, unlock .i3d.shapes, decompile .l64 scripts and format XML into
+/// an output folder
+pub struct Cmd {
+    /// suppress output of the tools
+    #[argh(switch, short = 's')]
+    silent: bool,
+
+    /// write into an existing output folder
+    #[argh(switch, short = 'f')]
+    force: bool,
+
+    /// enter directory symlinks and junctions of the mod folder
+    #[argh(switch)]
+    follow_symlinks: bool,
+
+    /// path to mod folder or .zip
+    #[argh(positional)]
+    input: PathBuf,
+
+    /// path to output folder (optional, default <input>_unlocked)
+    #[argh(positional)]
+    output: Option<PathBuf>,
+}
+
+fn default_output(input: &Path) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+
+    input.with_file_name(format!("{}_unlocked", stem))
+}
+
+fn copy_folder(input: &Path, output: &Path, follow_symlinks: bool) -> Result<usize> {
+    let files = list_files(input, true, follow_symlinks)?;
+
+    for file in files.iter() {
+        let output_file = output.join(file.strip_prefix(input)?);
+
+        if let Some(dir) = output_file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::copy(file, output_file)?;
+    }
+
+    Ok(files.len())
+}
+
+/// Decompiler for the .l64 files of a mod, None without scripts
+fn decompiler(output: &Path) -> Result<Option<&'static str>> {
+    let Some(file) = list_files_with_extension(output, "l64", true, false)?
+        .into_iter()
+        .next()
+    else {
+        return Ok(None);
+    };
+
+    match Vec::read_from_file(file)?.starts_with(LUAJIT_MAGIC) {
+        true => Ok(Some("fs-luajit-decompile")),
+        false => Ok(Some("fs-luau-decompile")),
+    }
+}
+
+/// Runs a tool of this collection on the whole output folder
+fn run_tool(cli: &Cmd, tool: &str, output: &Path, summary: &Summary) -> Result<()> {
+    if !cli.silent {
+        println!("Running {}", tool);
+    }
+
+    let output = output.as_os_str();
+    let args = match cli.silent {
+        true => vec!["-r".as_ref(), "-s".as_ref(), output],
+        false => vec!["-r".as_ref(), output],
+    };
+
+    match run_command(&format!("{}{}", tool, EXE_SUFFIX), args)? {
+        0 => summary.processed(),
+        code => {
+            eprintln!("{}: exited with code {}", tool, code);
+            summary.failed();
+        }
+    }
+
+    Ok(())
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    let output = cli
+        .output
+        .clone()
+        .unwrap_or_else(|| default_output(&cli.input));
+
+    if output.exists() && !cli.force {
+        bail!(report::usage(format!(
+            "Output folder already exists: {} (use --force to write into it)",
+            output.display()
+        )))
+    }
+
+    let count = match cli.input.is_dir() {
+        true => copy_folder(&cli.input, &output, cli.follow_symlinks)?,
+        false if zip_input::is_zip(&cli.input) => zip_input::extract(&cli.input, &output)?,
+        false => bail!(report::usage(format!(
+            "Input is not a mod folder or .zip: {}",
+            cli.input.display()
+        ))),
+    };
+
+    if !cli.silent {
+        println!("Copied {} files to {}", count, output.display());
+    }
+
+    if !list_files_with_extension(&output, "shapes", true, false)?.is_empty() {
+        run_tool(&cli, "fs-shapes-unlock", &output, &summary)?;
+    }
+
+    if let Some(tool) = decompiler(&output)? {
+        run_tool(&cli, tool, &output, &summary)?;
+    }
+
+    if !list_files_with_extension(&output, "xml", true, false)?.is_empty() {
+        run_tool(&cli, "fs-xml-format", &output, &summary)?;
+    }
+
+    // Written by the tools, not part of the mod
+    let cache_file = output.join(CACHE_FILE_NAME);
+
+    if cache_file.is_file() {
+        fs::remove_file(cache_file)?;
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
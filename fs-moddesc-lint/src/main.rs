@@ -0,0 +1,380 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    buffer::BufferExtension,
+    l10n::{self, BASE_LANGUAGE},
+    report::{self, Summary},
+    xml::{self, Element},
+};
+
+const MOD_DESC_FILE_NAME: &str = "modDesc.xml";
+
+/// Elements the game requires to load a mod
+const REQUIRED_ELEMENTS: [&str; 4] = ["title", "author", "version", "iconFilename"];
+
+/// Attributes holding a path relative to the mod folder
+const FILE_ATTRIBUTES: [&str; 5] = [
+    "filename",
+    "xmlFilename",
+    "configFilename",
+    "imageFilename",
+    "image",
+];
+
+/// Number of missing l10n keys listed per language
+const MAX_LISTED_KEYS: usize = 5;
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Check modDesc.xml for missing elements, missing files and incomplete
+/// translations
+pub struct Cmd {
+    /// only report errors
+    #[argh(switch, short = 'e')]
+    errors_only: bool,
+
+    /// path to mod folder, modDesc.xml or mods folder with unzipped mods
+    #[argh(positional)]
+    input: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Error,
+    Warning,
+}
+
+#[derive(Debug)]
+struct Issue {
+    level: Level,
+    line: Option<u64>,
+    message: String,
+}
+
+#[derive(Default)]
+struct Lint {
+    issues: Vec<Issue>,
+}
+
+impl Lint {
+    fn error(&mut self, line: Option<u64>, message: String) {
+        self.issues.push(Issue {
+            level: Level::Error,
+            line,
+            message,
+        });
+    }
+
+    fn warning(&mut self, line: Option<u64>, message: String) {
+        self.issues.push(Issue {
+            level: Level::Warning,
+            line,
+            message,
+        });
+    }
+}
+
+/// A referenced .png is loaded as .dds when that exists
+fn file_exists(mod_dir: &Path, value: &str) -> bool {
+    let file = mod_dir.join(value.replace('\\', "/"));
+
+    file.is_file() || file.with_extension("dds").is_file()
+}
+
+fn check_required(lint: &mut Lint, root: &Element) {
+    match root.attr("descVersion") {
+        None => lint.error(Some(root.line), "missing descVersion attribute".to_string()),
+        Some(value) if value.parse::<u32>().is_err() => {
+            lint.error(Some(root.line), format!("invalid descVersion: {}", value))
+        }
+        _ => {}
+    }
+
+    for name in REQUIRED_ELEMENTS {
+        let Some(element) = root.child(name) else {
+            lint.error(None, format!("missing <{}>", name));
+            continue;
+        };
+
+        // Title and description are either plain text or per language
+        let has_text =
+            element.text().is_some() || element.children.iter().any(|child| child.text().is_some());
+
+        if !has_text {
+            lint.error(Some(element.line), format!("empty <{}>", name));
+        }
+    }
+
+    if let Some(title) = root.child("title")
+        && !title.children.is_empty()
+        && title.child(BASE_LANGUAGE).is_none()
+    {
+        lint.warning(
+            Some(title.line),
+            format!("<title> has no <{}> entry", BASE_LANGUAGE),
+        );
+    }
+
+    if root.child("description").is_none() {
+        lint.warning(None, "missing <description>".to_string());
+    }
+}
+
+fn check_files(lint: &mut Lint, root: &Element, mod_dir: &Path) {
+    for element in root.descendants() {
+        let mut references: Vec<&str> = element
+            .attributes
+            .iter()
+            .filter(|(key, _)| FILE_ATTRIBUTES.contains(&key.as_str()))
+            .map(|(_, value)| value.as_str())
+            .collect();
+
+        if element.name == "iconFilename"
+            && let Some(text) = element.text()
+        {
+            references.push(text);
+        }
+
+        for value in references {
+            // $data, $moddir$ etc. point outside the mod folder
+            if value.is_empty() || value.starts_with('$') {
+                continue;
+            }
+
+            if !file_exists(mod_dir, value) {
+                lint.error(Some(element.line), format!("missing file: {}", value));
+            }
+        }
+    }
+}
+
+/// Returns the keys of every language, inline entries and language files
+fn load_l10n(
+    lint: &mut Lint,
+    l10n_element: &Element,
+    mod_dir: &Path,
+) -> Result<BTreeMap<String, BTreeSet<String>>> {
+    let mut languages: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+    for language in l10n::inline_languages(l10n_element) {
+        let keys = l10n::read_texts(l10n_element, Some(&language))
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+
+        languages.insert(language, keys);
+    }
+
+    if let Some(prefix) = l10n_element.attr("filenamePrefix") {
+        let files = l10n::language_files(mod_dir, prefix)?;
+
+        if files.is_empty() {
+            lint.error(
+                Some(l10n_element.line),
+                format!("no l10n files found for prefix: {}", prefix),
+            );
+        }
+
+        for (language, file) in files {
+            match l10n::read_language_file(&file) {
+                Ok(texts) => languages
+                    .entry(language)
+                    .or_default()
+                    .extend(texts.into_iter().map(|(key, _)| key)),
+                Err(e) => lint.error(None, format!("invalid l10n file {}: {}", file.display(), e)),
+            };
+        }
+    }
+
+    Ok(languages)
+}
+
+fn check_l10n(lint: &mut Lint, root: &Element, mod_dir: &Path) -> Result<()> {
+    let languages = match root.child("l10n") {
+        Some(l10n_element) => load_l10n(lint, l10n_element, mod_dir)?,
+        None => BTreeMap::new(),
+    };
+
+    let all_keys: BTreeSet<&String> = languages.values().flatten().collect();
+
+    if !languages.is_empty() && !languages.contains_key(BASE_LANGUAGE) {
+        lint.warning(None, format!("no l10n entries for {}", BASE_LANGUAGE));
+    }
+
+    for (language, keys) in languages.iter() {
+        let missing: Vec<&str> = all_keys
+            .iter()
+            .filter(|key| !keys.contains(**key))
+            .map(|key| key.as_str())
+            .collect();
+
+        if missing.is_empty() {
+            continue;
+        }
+
+        let mut listed = missing[..missing.len().min(MAX_LISTED_KEYS)].join(", ");
+
+        if missing.len() > MAX_LISTED_KEYS {
+            listed.push_str(", ...");
+        }
+
+        lint.warning(
+            None,
+            format!(
+                "l10n {}: {} missing keys: {}",
+                language,
+                missing.len(),
+                listed
+            ),
+        );
+    }
+
+    // Keys referenced by the modDesc itself, other references may be game texts
+    for element in [root.child("title"), root.child("description")]
+        .into_iter()
+        .flatten()
+    {
+        let texts = std::iter::once(element)
+            .chain(element.children.iter())
+            .filter_map(|element| Some((element.line, element.text()?)));
+
+        for (line, text) in texts {
+            let Some(key) = text.strip_prefix("$l10n_") else {
+                continue;
+            };
+
+            if !all_keys.contains(&key.to_string()) {
+                lint.error(Some(line), format!("undefined l10n key: {}", key));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn lint_mod(mod_dir: &Path) -> Result<Vec<Issue>> {
+    let mut lint = Lint::default();
+    let root = xml::parse(&Vec::read_from_file(mod_dir.join(MOD_DESC_FILE_NAME))?)?;
+
+    if root.name != "modDesc" {
+        bail!("Root element is <{}>, expected <modDesc>", root.name)
+    }
+
+    check_required(&mut lint, &root);
+    check_files(&mut lint, &root, mod_dir);
+    check_l10n(&mut lint, &root, mod_dir)?;
+
+    lint.issues.sort_by_key(|issue| (issue.level, issue.line));
+
+    Ok(lint.issues)
+}
+
+fn find_mods(input: &Path) -> Result<Vec<PathBuf>> {
+    if input.is_file() {
+        let mod_dir = match input.parent() {
+            Some(parent) if parent != Path::new("") => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        return Ok(vec![mod_dir]);
+    }
+
+    if input.join(MOD_DESC_FILE_NAME).is_file() {
+        return Ok(vec![input.to_path_buf()]);
+    }
+
+    let mut mods: Vec<PathBuf> = std::fs::read_dir(input)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join(MOD_DESC_FILE_NAME).is_file())
+        .collect();
+
+    mods.sort();
+
+    Ok(mods)
+}
+
+fn print_issues(name: &str, issues: &[Issue]) {
+    let errors = issues.iter().filter(|i| i.level == Level::Error).count();
+    let warnings = issues.len() - errors;
+
+    if issues.is_empty() {
+        println!("{}: ok", name);
+        return;
+    }
+
+    println!("{}: {} errors, {} warnings", name, errors, warnings);
+
+    for issue in issues {
+        let level = match issue.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        };
+        let line = issue
+            .line
+            .map(|line| line.to_string())
+            .unwrap_or_else(|| "-".to_string());
+
+        println!("  {:<8}{:>5}  {}", level, line, issue.message);
+    }
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    if !cli.input.exists() {
+        bail!(report::usage(format!(
+            "Input not found: {}",
+            cli.input.display()
+        )))
+    }
+
+    let mods = find_mods(&cli.input)?;
+
+    if mods.is_empty() {
+        bail!(report::usage(format!(
+            "No {} found in {}",
+            MOD_DESC_FILE_NAME,
+            cli.input.display()
+        )))
+    }
+
+    for mod_dir in mods.iter() {
+        let name = mod_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| mod_dir.display().to_string());
+
+        let mut issues = match lint_mod(mod_dir) {
+            Ok(issues) => issues,
+            Err(e) => vec![Issue {
+                level: Level::Error,
+                line: None,
+                message: format!("unable to parse {}: {}", MOD_DESC_FILE_NAME, e),
+            }],
+        };
+
+        if cli.errors_only {
+            issues.retain(|issue| issue.level == Level::Error);
+        }
+
+        print_issues(&name, &issues);
+
+        match issues.iter().any(|i| i.level == Level::Error) {
+            true => summary.failed(),
+            false => summary.processed(),
+        }
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
@@ -0,0 +1,82 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use fs_lib::buffer::BufferExtension;
+
+#[cfg(windows)]
+const EXTENSION: &str = "bat";
+#[cfg(target_os = "linux")]
+const EXTENSION: &str = "sh";
+
+/// `%` would expand variables in batch files
+#[cfg(windows)]
+fn quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('%', "%%"))
+}
+
+#[cfg(target_os = "linux")]
+fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+#[cfg(windows)]
+fn script(dir: &str, command: &str) -> String {
+    format!(
+        "@echo off\r\nrem Generated by fs-patch-process --install-launcher\r\ncd /d {}\r\n{}\r\n",
+        dir, command
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn script(dir: &str, command: &str) -> String {
+    format!(
+        "#!/bin/sh\n# Generated by fs-patch-process --install-launcher\ncd {} && exec {}\n",
+        dir, command
+    )
+}
+
+/// Launcher file, a folder gets `<executable stem> (patched).bat/.sh`
+fn launcher_path(path: &Path, executable: &Path) -> PathBuf {
+    match path.is_dir() {
+        true => {
+            let stem = executable.file_stem().unwrap_or_default().to_string_lossy();
+
+            path.join(format!("{} (patched).{}", stem, EXTENSION))
+        }
+        false => path.to_path_buf(),
+    }
+}
+
+/// Writes a script that starts the executable through this patcher with the
+/// given options, to be used instead of the game shortcut. Returns its path.
+pub fn install_launcher(path: &Path, executable: &Path, args: &[String]) -> Result<PathBuf> {
+    let patcher = std::env::current_exe()?;
+    let executable = std::path::absolute(executable)?;
+    let dir = executable
+        .parent()
+        .context("Executable has no parent folder")?;
+
+    let command: Vec<String> = [
+        patcher.display().to_string(),
+        executable.display().to_string(),
+    ]
+    .into_iter()
+    .chain(args.iter().cloned())
+    .map(|arg| quote(&arg))
+    .collect();
+
+    let launcher = launcher_path(path, &executable);
+
+    script(&quote(&dir.display().to_string()), &command.join(" "))
+        .into_bytes()
+        .write_to_file(&launcher)?;
+
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::set_permissions(&launcher, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(launcher)
+}
@@ -1,22 +1,41 @@
-#[cfg(windows)]
+#[cfg(any(windows, target_os = "linux"))]
+mod launcher;
+#[cfg(any(windows, target_os = "linux"))]
 mod process;
+#[cfg(any(windows, target_os = "linux"))]
+mod session;
 
-#[cfg(windows)]
-mod windows {
-    use std::{path::PathBuf, process::Command};
+#[cfg(any(windows, target_os = "linux"))]
+mod patcher {
+    use std::{
+        path::{Path, PathBuf},
+        process::Command,
+    };
 
-    use anyhow::{Result, bail};
+    use anyhow::{Context, Result, bail};
     use argh::FromArgs;
-    use fs_lib::{EXECUTABLE_PATTERNS, Platform, buffer::BufferExtension};
-
-    use crate::process::{get_process_modules, open_process, resume_process, suspend_process};
+    use fs_lib::{
+        Platform,
+        elevate::handle_access_denied,
+        pattern::{BytePattern, Captures, PatternDefinition, builtin_patterns, load_patterns},
+        report::{self, Summary},
+    };
+
+    use crate::{
+        launcher::install_launcher,
+        process::{
+            Access, Handle, MemoryRegion, Module, get_process_modules, open_process,
+            resume_process, suspend_process,
+        },
+        session::{Session, SessionPatch},
+    };
 
     #[derive(FromArgs, PartialEq, Debug)]
     /// Launch executable as child process and patch memory
     pub struct Cmd {
         /// path to executable
         #[argh(positional)]
-        input: PathBuf,
+        input: Option<PathBuf>,
 
         /// platform: steam, giants (default: steam)
         #[argh(option, default = "Platform::Steam")]
@@ -33,72 +52,339 @@ mod windows {
         /// don't wait for child process to exit
         #[argh(switch)]
         no_wait: bool,
+
+        /// additional module to patch, e.g. a game DLL (repeatable)
+        #[argh(option)]
+        module: Vec<String>,
+
+        /// patch all loaded modules
+        #[argh(switch)]
+        all_modules: bool,
+
+        /// load patterns from file instead of the built-in table
+        #[argh(option)]
+        patterns: Option<PathBuf>,
+
+        /// check running process (--pid) for active/inactive patches without
+        /// modifying it
+        #[argh(switch, short = 'c')]
+        check: bool,
+
+        /// relaunch as administrator (UAC prompt) when the process can't be
+        /// opened or started, e.g. one running as administrator (Windows)
+        #[argh(switch)]
+        elevate: bool,
+
+        /// write a launcher script (.bat/.sh) to this file or folder that
+        /// starts the executable patched with the same options, use it
+        /// instead of the game shortcut
+        #[argh(option)]
+        install_launcher: Option<PathBuf>,
+
+        #[argh(subcommand)]
+        command: Option<Subcommand>,
     }
 
-    pub fn run() -> Result<()> {
-        let cli: Cmd = argh::from_env();
+    #[derive(FromArgs, PartialEq, Debug)]
+    #[argh(subcommand)]
+    enum Subcommand {
+        Verify(VerifyCmd),
+    }
 
-        let Some(items) = EXECUTABLE_PATTERNS.get(&cli.platform) else {
-            bail!("No patch items found")
-        };
+    #[derive(FromArgs, PartialEq, Debug)]
+    /// Check that the patches applied to a process are still in memory,
+    /// using the session file written when it was patched
+    #[argh(subcommand, name = "verify")]
+    struct VerifyCmd {
+        /// PID of the patched process
+        #[argh(option)]
+        pid: u32,
+    }
 
-        let (pid, child_process) = match cli.pid {
-            Some(id) => (id, None),
-            _ => {
-                let child_process = Command::new(&cli.input).spawn()?;
+    /// Options repeated by the launcher, paths made absolute so it works
+    /// from any folder
+    fn launcher_args(cli: &Cmd) -> Result<Vec<String>> {
+        let mut args = vec!["--platform".to_string(), cli.platform.to_string()];
 
-                println!("Started new child ProcessID: {}", child_process.id());
+        for module in cli.module.iter() {
+            args.extend(["--module".to_string(), module.clone()]);
+        }
 
-                (child_process.id(), Some(child_process))
-            }
-        };
+        if cli.all_modules {
+            args.push("--all-modules".to_string());
+        }
 
-        if !suspend_process(pid) {
-            bail!("Failed to suspend child process")
+        if let Some(patterns) = &cli.patterns {
+            let patterns = std::path::absolute(patterns)?;
+            args.extend(["--patterns".to_string(), patterns.display().to_string()]);
         }
 
-        println!("Suspended process");
+        // The game keeps running after the launcher exits
+        args.push("--no-wait".to_string());
 
-        let process_handle = open_process(pid)?;
-        let modules = get_process_modules(&process_handle, pid)?;
+        Ok(args)
+    }
 
-        println!("Found {} process modules", modules.len());
+    fn find_pattern<'a>(
+        regions: &'a [MemoryRegion],
+        pattern: &BytePattern,
+    ) -> Option<(&'a MemoryRegion, usize, Captures)> {
+        regions.iter().find_map(|region| {
+            pattern
+                .find(&region.data)
+                .map(|(offset, captures)| (region, offset, captures))
+        })
+    }
+
+    fn check_module(
+        module: &Module,
+        handle: &Handle,
+        patterns: &[&PatternDefinition],
+    ) -> Result<()> {
+        println!("Module name: {}", module.name);
+
+        let regions = module.read_regions(handle)?;
+
+        for item in patterns {
+            if let Some((region, offset, _)) = find_pattern(&regions, &item.find) {
+                println!(
+                    "[-] Patch is not active {:?} (offset {})",
+                    item.patch_type,
+                    region.offset + offset
+                );
+            } else if let Some((region, offset, _)) = find_pattern(&regions, &item.replace) {
+                println!(
+                    "[+] Patch is active {:?} (offset {})",
+                    item.patch_type,
+                    region.offset + offset
+                );
+            } else {
+                println!("[?] Could not locate pattern {:?}", item.patch_type);
+            }
+        }
 
-        let file_name = cli
-            .input
+        Ok(())
+    }
+
+    /// Returns the main module followed by the modules selected with
+    /// --module/--all-modules
+    fn select_modules<'a>(
+        cli: &Cmd,
+        input: &Path,
+        modules: &'a [Module],
+    ) -> Result<Vec<&'a Module>> {
+        let file_name = input
             .file_name()
-            .unwrap()
+            .with_context(|| format!("{} has no file name", input.display()))?
             .to_string_lossy()
             .into_owned();
 
-        let Some(module) = modules.iter().find(|m| m.name == file_name) else {
+        let Some(main_module) = modules.iter().find(|m| m.name == file_name) else {
             bail!("Unable to locate main process module")
         };
 
+        let mut targets: Vec<&Module> = vec![main_module];
+
+        for module in modules.iter() {
+            let is_selected = cli.all_modules
+                || cli
+                    .module
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(&module.name));
+
+            if is_selected && module.name != main_module.name {
+                targets.push(module);
+            }
+        }
+
+        for name in cli.module.iter() {
+            if !modules.iter().any(|m| name.eq_ignore_ascii_case(&m.name)) {
+                println!("Warning: module {} is not loaded", name);
+            }
+        }
+
+        Ok(targets)
+    }
+
+    fn patch_module(
+        module: &Module,
+        handle: &Handle,
+        patterns: &[&PatternDefinition],
+    ) -> Result<Vec<SessionPatch>> {
         println!("Module name: {}", module.name);
         println!("Module base_addr: {}", module.base_addr);
         println!("Module base_size: {}", module.base_size);
 
-        let (module_buffer, bytes_read) = module.to_buffer(&process_handle)?;
+        let regions = module.read_regions(handle)?;
+        let bytes_read: usize = regions.iter().map(|r| r.data.len()).sum();
+
+        println!(
+            "{} bytes read from {} readable module regions",
+            bytes_read,
+            regions.len()
+        );
+
+        let mut applied = Vec::new();
+
+        for item in patterns {
+            if let Some((region, offset, captures)) = find_pattern(&regions, &item.find) {
+                let module_offset = region.offset + offset;
+
+                println!(
+                    "Applying {:?} at offset {} (address {:#X})",
+                    item.patch_type,
+                    module_offset,
+                    module.base_addr + module_offset
+                );
+
+                let replace = item.replace.render(&region.data, offset, &captures);
+                let bytes_written = module.replace_bytes(&replace, module_offset, handle)?;
+
+                println!("Bytes written: {}", bytes_written);
+                applied.push(SessionPatch::new(
+                    format!("{:?}", item.patch_type),
+                    &module.name,
+                    module.base_addr + module_offset,
+                    &region.data[offset..offset + replace.len()],
+                    &replace,
+                ));
+            }
+        }
 
-        println!("{} bytes read from module memory into buffer", bytes_read);
+        Ok(applied)
+    }
 
-        for item in items {
-            if let Some(offset) = module_buffer.find_bytes(&item.find) {
-                println!("Applying {:?} at offset {}", item.patch_type, offset);
+    pub fn run() -> Result<Summary> {
+        let cli: Cmd = fs_lib::config::from_env();
+        let summary = Summary::default();
 
-                let bytes_written = module.replace_bytes(&item.replace, offset, &process_handle)?;
+        if let Some(Subcommand::Verify(command)) = &cli.command {
+            let session = Session::load(command.pid)?;
+            let process_handle = open_process(command.pid, Access::Read)
+                .map_err(|e| handle_access_denied(e, cli.elevate))?;
+
+            session.verify(&process_handle, &summary)?;
+
+            return Ok(summary);
+        }
+
+        let Some(input) = cli.input.clone() else {
+            bail!(report::usage("No executable given"))
+        };
+
+        let patterns = match &cli.patterns {
+            Some(file) => load_patterns(file)?,
+            None => builtin_patterns(),
+        };
+
+        // Sets keyed by executable hash are for fs-patch, modules in memory
+        // can't be hashed like the file on disk
+        let items: Vec<&PatternDefinition> = patterns
+            .iter()
+            .filter(|item| item.platform == cli.platform && item.sha256.is_none())
+            .collect();
+
+        if items.is_empty() {
+            bail!(report::usage(format!(
+                "No patch items found for platform {:?}",
+                cli.platform
+            )))
+        }
 
-                println!("Bytes written: {}", bytes_written)
+        if let Some(path) = &cli.install_launcher {
+            if cli.pid.is_some() || cli.test || cli.check {
+                bail!(report::usage(
+                    "--install-launcher can't be combined with --pid, --test or --check"
+                ))
             }
+
+            if !input.is_file() {
+                bail!(report::usage(format!(
+                    "Executable not found: {}",
+                    input.display()
+                )))
+            }
+
+            let launcher = install_launcher(path, &input, &launcher_args(&cli)?)?;
+
+            println!("Launcher written to {}", launcher.display());
+            println!("Start the game with it instead of the usual shortcut");
+            summary.processed();
+
+            return Ok(summary);
         }
 
-        println!("Resuming process");
+        if cli.check {
+            let Some(pid) = cli.pid else {
+                bail!(report::usage(
+                    "Check mode requires --pid of a running process"
+                ))
+            };
+
+            let process_handle = open_process(pid, Access::Read)
+                .map_err(|e| handle_access_denied(e, cli.elevate))?;
+            let modules = get_process_modules(&process_handle, pid)?;
+
+            for module in select_modules(&cli, &input, &modules)? {
+                check_module(module, &process_handle, &items)?;
+                summary.processed();
+            }
+
+            return Ok(summary);
+        }
+
+        let (pid, child_process) = match cli.pid {
+            Some(id) => (id, None),
+            _ => {
+                let child_process = Command::new(&input)
+                    .spawn()
+                    .with_context(|| format!("Failed to start {}", input.display()))
+                    .map_err(|e| handle_access_denied(e, cli.elevate))?;
+
+                println!("Started new child ProcessID: {}", child_process.id());
+
+                (child_process.id(), Some(child_process))
+            }
+        };
+
+        // Checked before suspending, a process that can't be opened would
+        // stay suspended
+        let process_handle =
+            open_process(pid, Access::Write).map_err(|e| handle_access_denied(e, cli.elevate))?;
+
+        suspend_process(pid).context("Failed to suspend process")?;
+
+        println!("Suspended process");
 
-        if !resume_process(pid) {
-            bail!("Failed to resume process!")
+        let modules = get_process_modules(&process_handle, pid)?;
+
+        println!("Found {} process modules", modules.len());
+
+        let targets = select_modules(&cli, &input, &modules)?;
+        let mut session_patches = Vec::new();
+
+        for module in targets {
+            match patch_module(module, &process_handle, &items) {
+                Ok(applied) => {
+                    println!("{} patches applied to {}\n", applied.len(), module.name);
+                    session_patches.extend(applied);
+                    summary.processed();
+                }
+                Err(e) => summary.error(&module.name, &e),
+            }
+        }
+
+        if !session_patches.is_empty() {
+            match Session::new(pid, session_patches).save() {
+                Ok(path) => println!("Session written to {}", path.display()),
+                Err(e) => println!("Warning: session file not written: {:#}", e),
+            }
         }
 
+        println!("Resuming process");
+
+        resume_process(pid).context("Failed to resume process")?;
+
         if let Some(mut child_process) = child_process {
             if cli.test {
                 println!("Klling child process");
@@ -110,17 +396,17 @@ mod windows {
             }
         }
 
-        Ok(())
+        Ok(summary)
     }
 }
 
-#[cfg(windows)]
-fn main() -> anyhow::Result<()> {
-    windows::run()
+#[cfg(any(windows, target_os = "linux"))]
+fn main() -> std::process::ExitCode {
+    fs_lib::report::finish(patcher::run())
 }
 
-#[cfg(not(windows))]
+#[cfg(not(any(windows, target_os = "linux")))]
 fn main() {
-    eprintln!("fs-patch-process is only supported on Windows");
-    std::process::exit(1);
+    eprintln!("fs-patch-process is only supported on Windows and Linux");
+    std::process::exit(fs_lib::report::EXIT_ENVIRONMENT.into());
 }
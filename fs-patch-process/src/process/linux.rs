@@ -0,0 +1,269 @@
+use std::{fs::OpenOptions, os::unix::fs::FileExt, path::Path};
+
+use anyhow::{Result, bail};
+use libc::{c_void, iovec, pid_t};
+
+use super::Access;
+
+pub struct Module {
+    pub name: String,
+    pub base_addr: usize,
+    pub base_size: u32,
+}
+
+/// Readable memory of a module, offset is relative to the module base address
+pub struct MemoryRegion {
+    pub offset: usize,
+    pub data: Vec<u8>,
+}
+
+pub struct Handle {
+    pid: pid_t,
+}
+
+/// Mapping from /proc/<pid>/maps
+struct Mapping {
+    start: usize,
+    end: usize,
+    readable: bool,
+    path: String,
+}
+
+fn read_mappings(pid: pid_t) -> Result<Vec<Mapping>> {
+    let maps = std::fs::read_to_string(format!("/proc/{}/maps", pid))?;
+    let mut mappings = Vec::new();
+
+    // start-end perms offset dev inode [path]
+    for line in maps.lines() {
+        let mut fields = line.splitn(6, ' ');
+
+        let (Some(range), Some(perms)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+
+        let Some((start, end)) = range.split_once('-') else {
+            continue;
+        };
+
+        let path = fields.nth(3).unwrap_or("").trim().to_string();
+
+        mappings.push(Mapping {
+            start: usize::from_str_radix(start, 16)?,
+            end: usize::from_str_radix(end, 16)?,
+            readable: perms.starts_with('r'),
+            path,
+        });
+    }
+
+    Ok(mappings)
+}
+
+impl Module {
+    /// Reads the readable mappings of the module, adjacent mappings are merged
+    /// into a single region
+    pub fn read_regions(&self, handle: &Handle) -> Result<Vec<MemoryRegion>> {
+        let end = self.base_addr + self.base_size as usize;
+        let mut regions: Vec<MemoryRegion> = Vec::new();
+
+        for mapping in read_mappings(handle.pid)? {
+            if !mapping.readable || mapping.end <= self.base_addr || mapping.start >= end {
+                continue;
+            }
+
+            let start = mapping.start.max(self.base_addr);
+            let size = mapping.end.min(end) - start;
+
+            match read_process_memory(handle, start, size) {
+                Ok((data, bytes_read)) => {
+                    let offset = start - self.base_addr;
+
+                    match regions.last_mut() {
+                        Some(last) if last.offset + last.data.len() == offset => {
+                            last.data.extend_from_slice(&data[..bytes_read]);
+                        }
+                        _ => regions.push(MemoryRegion {
+                            offset,
+                            data: data[..bytes_read].to_vec(),
+                        }),
+                    }
+                }
+                Err(e) => println!("Warning: skipping region at {:#X}: {}", start, e),
+            }
+        }
+
+        Ok(regions)
+    }
+
+    pub fn replace_bytes(&self, bytes: &[u8], offset: usize, handle: &Handle) -> Result<usize> {
+        write_process_memory(handle, self.base_addr + offset, bytes)
+    }
+}
+
+/// Keeps the process attached with ptrace while alive
+struct PtraceGuard(pid_t);
+
+impl PtraceGuard {
+    fn attach(pid: pid_t) -> Result<Self> {
+        let result = unsafe {
+            libc::ptrace(
+                libc::PTRACE_ATTACH,
+                pid,
+                std::ptr::null_mut::<c_void>(),
+                std::ptr::null_mut::<c_void>(),
+            )
+        };
+
+        if result == -1 {
+            bail!(
+                "ptrace(PTRACE_ATTACH) failed: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+
+        let mut status = 0;
+
+        if unsafe { libc::waitpid(pid, &mut status, libc::__WALL) } == -1 {
+            bail!("waitpid() failed: {}", std::io::Error::last_os_error());
+        }
+
+        Ok(Self(pid))
+    }
+}
+
+impl Drop for PtraceGuard {
+    fn drop(&mut self) {
+        let result = unsafe {
+            libc::ptrace(
+                libc::PTRACE_DETACH,
+                self.0,
+                std::ptr::null_mut::<c_void>(),
+                std::ptr::null_mut::<c_void>(),
+            )
+        };
+
+        if result == -1 {
+            println!("Warning: ptrace(PTRACE_DETACH) failed");
+        }
+    }
+}
+
+fn send_signal(id: u32, signal: i32, name: &str) -> Result<()> {
+    if unsafe { libc::kill(id as pid_t, signal) } == -1 {
+        bail!(
+            "kill({}) for process {} failed: {}",
+            name,
+            id,
+            std::io::Error::last_os_error()
+        );
+    }
+
+    Ok(())
+}
+
+pub fn suspend_process(id: u32) -> Result<()> {
+    send_signal(id, libc::SIGSTOP, "SIGSTOP")
+}
+
+pub fn resume_process(id: u32) -> Result<()> {
+    send_signal(id, libc::SIGCONT, "SIGCONT")
+}
+
+/// Memory is accessed through process_vm_readv/writev and ptrace, which
+/// check permissions per call, so the access only documents intent
+pub fn open_process(id: u32, _access: Access) -> Result<Handle> {
+    if !Path::new(&format!("/proc/{}", id)).exists() {
+        bail!("Process {} not found", id);
+    }
+
+    Ok(Handle { pid: id as pid_t })
+}
+
+/// Modules are the mapped files of the process, e.g. the executable and
+/// DLLs mapped by Wine/Proton
+pub fn get_process_modules(handle: &Handle, _pid: u32) -> Result<Vec<Module>> {
+    let mut modules: Vec<Module> = Vec::new();
+    let mut paths: Vec<String> = Vec::new();
+
+    let mappings = read_mappings(handle.pid)?;
+
+    for mapping in mappings.iter() {
+        if !mapping.path.starts_with('/') || paths.contains(&mapping.path) {
+            continue;
+        }
+
+        let parts = mappings.iter().filter(|m| m.path == mapping.path);
+        let start = parts.clone().map(|m| m.start).min().unwrap();
+        let end = parts.map(|m| m.end).max().unwrap();
+
+        let name = Path::new(&mapping.path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        paths.push(mapping.path.clone());
+        modules.push(Module {
+            name,
+            base_addr: start,
+            base_size: u32::try_from(end - start).unwrap_or(u32::MAX),
+        });
+    }
+
+    Ok(modules)
+}
+
+pub fn read_process_memory(
+    handle: &Handle,
+    position: usize,
+    num_bytes: usize,
+) -> Result<(Vec<u8>, usize)> {
+    let mut buffer = vec![0_u8; num_bytes];
+
+    let local = iovec {
+        iov_base: buffer.as_mut_ptr().cast(),
+        iov_len: num_bytes,
+    };
+    let remote = iovec {
+        iov_base: position as *mut c_void,
+        iov_len: num_bytes,
+    };
+
+    let result = unsafe { libc::process_vm_readv(handle.pid, &local, 1, &remote, 1, 0) };
+
+    if result == -1 {
+        bail!(
+            "process_vm_readv() failed: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+
+    Ok((buffer, result as usize))
+}
+
+pub fn write_process_memory(handle: &Handle, position: usize, bytes: &[u8]) -> Result<usize> {
+    let local = iovec {
+        iov_base: bytes.as_ptr() as *mut c_void,
+        iov_len: bytes.len(),
+    };
+    let remote = iovec {
+        iov_base: position as *mut c_void,
+        iov_len: bytes.len(),
+    };
+
+    let result = unsafe { libc::process_vm_writev(handle.pid, &local, 1, &remote, 1, 0) };
+
+    if result == bytes.len() as isize {
+        return Ok(bytes.len());
+    }
+
+    // Code pages are mapped read-only, writes through /proc/<pid>/mem ignore
+    // page protection but require the process to be attached
+    let _guard = PtraceGuard::attach(handle.pid)?;
+
+    let mem = OpenOptions::new()
+        .write(true)
+        .open(format!("/proc/{}/mem", handle.pid))?;
+
+    mem.write_all_at(bytes, position as u64)?;
+
+    Ok(bytes.len())
+}
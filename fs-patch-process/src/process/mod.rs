@@ -0,0 +1,18 @@
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(target_os = "linux")]
+pub use linux::*;
+#[cfg(windows)]
+pub use windows::*;
+
+/// Rights a process is opened with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    /// Query and read memory, for --check
+    Read,
+    /// Read and write memory, for patching
+    Write,
+}
@@ -0,0 +1,355 @@
+use anyhow::Result;
+use winapi::{
+    ctypes::c_void,
+    shared::{
+        minwindef::{DWORD, FALSE, HMODULE},
+        ntdef::{HANDLE, NULL},
+        winerror::ERROR_NO_MORE_FILES,
+    },
+    um::{
+        handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
+        memoryapi::{ReadProcessMemory, VirtualQueryEx, WriteProcessMemory},
+        processthreadsapi::{OpenProcess, OpenThread, ResumeThread, SuspendThread},
+        psapi::{EnumProcessModulesEx, LIST_MODULES_ALL},
+        tlhelp32::{
+            CreateToolhelp32Snapshot, MODULEENTRY32, Module32First, Module32Next,
+            TH32CS_SNAPMODULE, TH32CS_SNAPMODULE32, TH32CS_SNAPTHREAD, THREADENTRY32,
+            Thread32First, Thread32Next,
+        },
+        winnt::{
+            MEM_COMMIT, MEMORY_BASIC_INFORMATION, PAGE_GUARD, PAGE_NOACCESS,
+            PROCESS_QUERY_INFORMATION, PROCESS_VM_OPERATION, PROCESS_VM_READ, PROCESS_VM_WRITE,
+            THREAD_SUSPEND_RESUME,
+        },
+    },
+};
+
+use super::Access;
+
+pub trait FromCString {
+    fn from_cstring(arr: &[i8]) -> String;
+}
+
+impl FromCString for String {
+    fn from_cstring(arr: &[i8]) -> String {
+        convert_c_string_lossy(arr)
+    }
+}
+
+pub struct Module {
+    pub name: String,
+    pub base_addr: usize,
+    pub base_size: u32,
+}
+
+impl Module {
+    pub fn from_entry(entry: &MODULEENTRY32) -> Self {
+        let name = String::from_cstring(&entry.szModule);
+
+        Self {
+            name,
+            base_addr: entry.modBaseAddr as usize,
+            base_size: entry.modBaseSize,
+        }
+    }
+
+    /// Reads the readable parts of the module image, adjacent readable pages
+    /// are merged into a single region
+    pub fn read_regions(&self, handle: &Handle) -> Result<Vec<MemoryRegion>> {
+        let end = self.base_addr + self.base_size as usize;
+        let mut regions: Vec<MemoryRegion> = Vec::new();
+        let mut address = self.base_addr;
+
+        while address < end {
+            let info = query_memory(handle, address)?;
+            let region_end = (info.BaseAddress as usize + info.RegionSize).min(end);
+            let is_readable =
+                info.State == MEM_COMMIT && info.Protect & (PAGE_NOACCESS | PAGE_GUARD) == 0;
+
+            if is_readable {
+                match read_process_memory(handle, address, region_end - address) {
+                    Ok((data, bytes_read)) => {
+                        let offset = address - self.base_addr;
+
+                        match regions.last_mut() {
+                            Some(last) if last.offset + last.data.len() == offset => {
+                                last.data.extend_from_slice(&data[..bytes_read]);
+                            }
+                            _ => regions.push(MemoryRegion {
+                                offset,
+                                data: data[..bytes_read].to_vec(),
+                            }),
+                        }
+                    }
+                    Err(e) => println!("Warning: skipping region at {:#X}: {:#}", address, e),
+                }
+            }
+
+            address = region_end;
+        }
+
+        Ok(regions)
+    }
+
+    pub fn replace_bytes(&self, bytes: &Vec<u8>, offset: usize, handle: &Handle) -> Result<usize> {
+        write_process_memory(handle, self.base_addr + offset, &bytes)
+    }
+}
+
+/// Readable memory of a module, offset is relative to the module base address
+pub struct MemoryRegion {
+    pub offset: usize,
+    pub data: Vec<u8>,
+}
+
+pub struct Handle(HANDLE);
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        close_handle(&self);
+    }
+}
+
+pub fn convert_c_string_lossy(arr: &[i8]) -> String {
+    // Find the null terminator (0)
+    let len = arr.iter().position(|&c| c == 0).unwrap_or(arr.len());
+
+    // Convert to &[u8] using a raw pointer cast
+    let u8_slice = unsafe { std::slice::from_raw_parts(arr.as_ptr() as *const u8, len) };
+
+    // Create a String, lossy conversion handles invalid UTF-8
+    String::from_utf8_lossy(u8_slice).into_owned()
+}
+
+/// Error of a failed Windows API call with the last OS error
+fn os_error(function: &str) -> anyhow::Error {
+    anyhow::Error::new(std::io::Error::last_os_error()).context(format!("{} failed", function))
+}
+
+/// True if the last failed call ran out of snapshot entries
+fn is_no_more_files() -> bool {
+    std::io::Error::last_os_error().raw_os_error() == Some(ERROR_NO_MORE_FILES as i32)
+}
+
+fn create_snapshot(flags: DWORD, pid: u32) -> Result<Handle> {
+    let snapshot = unsafe { CreateToolhelp32Snapshot(flags, pid) };
+
+    if snapshot == INVALID_HANDLE_VALUE {
+        return Err(os_error("CreateToolhelp32Snapshot()"));
+    }
+
+    Ok(Handle(snapshot))
+}
+
+/// Calls `action` (SuspendThread or ResumeThread) on every thread of the
+/// process, stops at the first failure
+fn for_each_thread(
+    id: u32,
+    name: &str,
+    action: unsafe extern "system" fn(HANDLE) -> DWORD,
+) -> Result<()> {
+    let snapshot = create_snapshot(TH32CS_SNAPTHREAD, 0)?;
+
+    let mut entry: THREADENTRY32 = unsafe { std::mem::zeroed() };
+    entry.dwSize = std::mem::size_of::<THREADENTRY32>() as u32;
+
+    if unsafe { Thread32First(snapshot.0, &mut entry) } == FALSE {
+        return Err(os_error("Thread32First()"));
+    }
+
+    loop {
+        if entry.th32OwnerProcessID == id {
+            let tid = entry.th32ThreadID;
+            let thread: HANDLE = unsafe { OpenThread(THREAD_SUSPEND_RESUME, FALSE, tid) };
+
+            if thread == NULL {
+                return Err(os_error(&format!("OpenThread() for thread {}", tid)));
+            }
+
+            let thread = Handle(thread);
+
+            if unsafe { action(thread.0) } == DWORD::MAX {
+                return Err(os_error(&format!("{}() for thread {}", name, tid)));
+            }
+        }
+
+        if unsafe { Thread32Next(snapshot.0, &mut entry) } == FALSE {
+            if is_no_more_files() {
+                return Ok(());
+            }
+
+            return Err(os_error("Thread32Next()"));
+        }
+    }
+}
+
+pub fn suspend_process(id: u32) -> Result<()> {
+    for_each_thread(id, "SuspendThread", SuspendThread)
+}
+
+/// Opens the process with only the rights needed: querying and reading
+/// memory, plus writing for Access::Write
+pub fn open_process(id: u32, access: Access) -> Result<Handle> {
+    let rights = match access {
+        Access::Read => PROCESS_QUERY_INFORMATION | PROCESS_VM_READ,
+        Access::Write => {
+            PROCESS_QUERY_INFORMATION | PROCESS_VM_READ | PROCESS_VM_WRITE | PROCESS_VM_OPERATION
+        }
+    };
+
+    let handle: HANDLE = unsafe { OpenProcess(rights, FALSE, id) };
+
+    if handle == NULL {
+        return Err(os_error(&format!("OpenProcess() for process {}", id)));
+    }
+
+    Ok(Handle(handle))
+}
+
+pub fn close_handle(handle: &Handle) -> bool {
+    let result = unsafe { CloseHandle(handle.0) };
+
+    if result == FALSE {
+        println!("Warning: {:#}", os_error("CloseHandle()"));
+        return false;
+    }
+
+    true
+}
+
+/// Number of modules, 32-bit targets included. The list holds one HMODULE
+/// per module, pointer sized.
+pub fn get_process_num_modules(handle: &Handle) -> Result<usize> {
+    let mut module_list_size: DWORD = 0;
+    let result = unsafe {
+        EnumProcessModulesEx(
+            handle.0,
+            std::ptr::null_mut(),
+            0,
+            &mut module_list_size,
+            LIST_MODULES_ALL,
+        )
+    };
+
+    if result == FALSE {
+        return Err(os_error("EnumProcessModulesEx()"));
+    }
+
+    Ok(module_list_size as usize / std::mem::size_of::<HMODULE>())
+}
+
+pub fn get_process_modules(handle: &Handle, pid: u32) -> Result<Vec<Module>> {
+    let mut modules: Vec<Module> = Vec::new();
+
+    let num_modules = get_process_num_modules(handle)?;
+
+    if num_modules > 0 {
+        let snapshot = create_snapshot(TH32CS_SNAPMODULE | TH32CS_SNAPMODULE32, pid)?;
+
+        for i in 0..num_modules {
+            match get_next_module(&snapshot, i)? {
+                Some(module) => {
+                    modules.push(module);
+                }
+                None => break,
+            }
+        }
+    }
+
+    Ok(modules)
+}
+
+/// Next snapshot entry, None when the snapshot has no more modules
+fn get_next_module(snapshot: &Handle, index: usize) -> Result<Option<Module>> {
+    let mut entry: MODULEENTRY32 = unsafe { std::mem::zeroed() };
+    entry.dwSize = std::mem::size_of::<MODULEENTRY32>() as u32;
+
+    let (result, function) = match index {
+        0 => (
+            unsafe { Module32First(snapshot.0, &mut entry) },
+            "Module32First()",
+        ),
+        _ => (
+            unsafe { Module32Next(snapshot.0, &mut entry) },
+            "Module32Next()",
+        ),
+    };
+
+    if result != FALSE {
+        return Ok(Some(Module::from_entry(&entry)));
+    }
+
+    if is_no_more_files() {
+        return Ok(None);
+    }
+
+    Err(os_error(function))
+}
+
+pub fn resume_process(id: u32) -> Result<()> {
+    for_each_thread(id, "ResumeThread", ResumeThread)
+}
+
+pub fn query_memory(handle: &Handle, position: usize) -> Result<MEMORY_BASIC_INFORMATION> {
+    let mut info: MEMORY_BASIC_INFORMATION = unsafe { std::mem::zeroed() };
+
+    let result = unsafe {
+        VirtualQueryEx(
+            handle.0,
+            position as *const c_void,
+            &mut info,
+            std::mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+        )
+    };
+
+    if result == 0 {
+        return Err(os_error("VirtualQueryEx()"));
+    }
+
+    Ok(info)
+}
+
+pub fn read_process_memory(
+    handle: &Handle,
+    position: usize,
+    num_bytes: usize,
+) -> Result<(Vec<u8>, usize)> {
+    let mut bytes_read = 0;
+    let mut buffer = vec![0_u8; num_bytes];
+
+    let result = unsafe {
+        ReadProcessMemory(
+            handle.0,
+            position as *const c_void,
+            buffer.as_mut_ptr().cast(),
+            num_bytes,
+            &mut bytes_read,
+        )
+    };
+
+    if result == FALSE {
+        return Err(os_error("ReadProcessMemory()"));
+    }
+
+    Ok((buffer, bytes_read))
+}
+
+pub fn write_process_memory(handle: &Handle, position: usize, bytes: &[u8]) -> Result<usize> {
+    let mut bytes_written = 0;
+
+    let result: i32 = unsafe {
+        WriteProcessMemory(
+            handle.0,
+            position as *mut _,
+            bytes.as_ptr() as *const _,
+            bytes.len(),
+            &mut bytes_written,
+        )
+    };
+
+    if result == FALSE {
+        return Err(os_error("WriteProcessMemory()"));
+    }
+
+    Ok(bytes_written)
+}
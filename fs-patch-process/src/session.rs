@@ -0,0 +1,176 @@
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, bail};
+use fs_lib::{buffer::BufferExtension, pattern::BytePattern, report::Summary};
+use serde::{Deserialize, Serialize};
+
+use crate::process::{Handle, get_process_modules, read_process_memory};
+
+/// Patch written to process memory
+#[derive(Serialize, Deserialize)]
+pub struct SessionPatch {
+    pub name: String,
+    pub module: String,
+    pub address: usize,
+    pub original: String,
+    pub replace: String,
+}
+
+impl SessionPatch {
+    pub fn new(
+        name: String,
+        module: &str,
+        address: usize,
+        original: &[u8],
+        replace: &[u8],
+    ) -> Self {
+        Self {
+            name,
+            module: module.to_string(),
+            address,
+            original: BytePattern::from(original).to_string(),
+            replace: BytePattern::from(replace).to_string(),
+        }
+    }
+}
+
+/// Patches applied to a process, stored as `<pid>.json` so they can be
+/// verified later with `fs-patch-process verify --pid <pid>`
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub pid: u32,
+    /// Seconds since the Unix epoch
+    pub patched_at: u64,
+    pub patches: Vec<SessionPatch>,
+}
+
+/// %LOCALAPPDATA%/fs-utils/sessions
+#[cfg(windows)]
+fn sessions_dir() -> Result<PathBuf> {
+    let local = std::env::var_os("LOCALAPPDATA").context("LOCALAPPDATA is not set")?;
+
+    Ok(PathBuf::from(local).join("fs-utils").join("sessions"))
+}
+
+/// $XDG_STATE_HOME/fs-utils/sessions, ~/.local/state by default
+#[cfg(target_os = "linux")]
+fn sessions_dir() -> Result<PathBuf> {
+    let state = match std::env::var_os("XDG_STATE_HOME") {
+        Some(state) => PathBuf::from(state),
+        None => PathBuf::from(std::env::var_os("HOME").context("HOME is not set")?)
+            .join(".local")
+            .join("state"),
+    };
+
+    Ok(state.join("fs-utils").join("sessions"))
+}
+
+fn parse_hex(value: &str) -> Result<Vec<u8>> {
+    value
+        .split_whitespace()
+        .map(|b| u8::from_str_radix(b, 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .with_context(|| format!("Invalid hex bytes '{}'", value))
+}
+
+impl Session {
+    pub fn new(pid: u32, patches: Vec<SessionPatch>) -> Self {
+        let patched_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            pid,
+            patched_at,
+            patches,
+        }
+    }
+
+    fn path(pid: u32) -> Result<PathBuf> {
+        Ok(sessions_dir()?.join(format!("{}.json", pid)))
+    }
+
+    pub fn load(pid: u32) -> Result<Self> {
+        let path = Self::path(pid)?;
+
+        if !path.is_file() {
+            bail!(
+                "No session for process {}, sessions are written when fs-patch-process patches a process",
+                pid
+            )
+        }
+
+        serde_json::from_slice(&Vec::read_from_file(&path)?)
+            .with_context(|| format!("{}", path.display()))
+    }
+
+    /// Writes the session file and returns its path
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = Self::path(self.pid)?;
+
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        serde_json::to_vec_pretty(self)?.write_to_file(&path)?;
+
+        Ok(path)
+    }
+
+    /// Reads the patched addresses again, patches no longer in memory are
+    /// counted as failed
+    pub fn verify(&self, handle: &Handle, summary: &Summary) -> Result<()> {
+        let modules = get_process_modules(handle, self.pid)?;
+
+        // A reused PID belongs to a process without the patched modules
+        for patch in self.patches.iter() {
+            let is_loaded = modules.iter().any(|module| {
+                module.name == patch.module
+                    && (module.base_addr..module.base_addr + module.base_size as usize)
+                        .contains(&patch.address)
+            });
+
+            if !is_loaded {
+                bail!(
+                    "Module {} is not loaded at {:#X}, process {} is not the patched process",
+                    patch.module,
+                    patch.address,
+                    self.pid
+                )
+            }
+        }
+
+        for patch in self.patches.iter() {
+            let original = parse_hex(&patch.original)?;
+            let replace = parse_hex(&patch.replace)?;
+            let (data, bytes_read) = read_process_memory(handle, patch.address, replace.len())?;
+            let bytes = &data[..bytes_read];
+
+            if bytes == replace.as_slice() {
+                println!(
+                    "[+] {} is applied ({} at {:#X})",
+                    patch.name, patch.module, patch.address
+                );
+                summary.processed();
+            } else if bytes == original.as_slice() {
+                println!(
+                    "[-] {} was reverted ({} at {:#X})",
+                    patch.name, patch.module, patch.address
+                );
+                summary.failed();
+            } else {
+                println!(
+                    "[?] {} was overwritten ({} at {:#X}): {}",
+                    patch.name,
+                    patch.module,
+                    patch.address,
+                    BytePattern::from(bytes)
+                );
+                summary.failed();
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+use std::path::Path;
+
+use anyhow::Result;
+use fs_lib::{
+    PatchType, Platform,
+    buffer::BufferExtension,
+    hash::{self, Algorithm},
+    pattern::{PatternDefinition, select_patterns},
+};
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Style, Stylize},
+    text::Line,
+    widgets::{Block, Clear, List, ListItem, ListState, Paragraph},
+};
+
+use fs_patch::pe::{code_sections, strip_certificate, update_checksum};
+
+use crate::{Cmd, Patch, detect_platform, find_patch, format_location, platform_items};
+
+struct Row {
+    patch_type: PatchType,
+    patch: Option<Patch>,
+    /// Whether the patch should be applied, toggled by the user
+    enabled: bool,
+}
+
+impl Row {
+    fn is_applied(&self) -> bool {
+        self.patch.as_ref().is_some_and(|patch| patch.is_applied)
+    }
+
+    fn is_changed(&self) -> bool {
+        self.patch.is_some() && self.enabled != self.is_applied()
+    }
+}
+
+struct App<'a> {
+    cli: &'a Cmd,
+    file: &'a Path,
+    file_buffer: Vec<u8>,
+    patterns: &'a [PatternDefinition],
+    platform: Platform,
+    sha256: String,
+    /// The pattern file has patterns keyed by the hash of this executable
+    is_keyed: bool,
+    rows: Vec<Row>,
+    list: ListState,
+    confirm: bool,
+    status: String,
+}
+
+impl<'a> App<'a> {
+    fn new(cli: &'a Cmd, file: &'a Path, patterns: &'a [PatternDefinition]) -> Result<Self> {
+        let file_buffer = Vec::read_from_file(file)?;
+        let sha256 = hash::hash_buffer(&file_buffer, Algorithm::Sha256);
+        let selected = select_patterns(patterns, &sha256);
+
+        let platform = match cli.platform {
+            Some(platform) => platform,
+            None => detect_platform(&file_buffer, &selected)?,
+        };
+
+        let mut app = Self {
+            cli,
+            file,
+            file_buffer,
+            patterns,
+            platform,
+            is_keyed: selected.iter().any(|item| item.sha256.is_some()),
+            sha256,
+            rows: Vec::new(),
+            list: ListState::default().with_selected(Some(0)),
+            confirm: false,
+            status: String::new(),
+        };
+
+        app.load()?;
+
+        Ok(app)
+    }
+
+    /// Locates the patches of the platform in the current buffer
+    fn load(&mut self) -> Result<()> {
+        let selected = select_patterns(self.patterns, &self.sha256);
+        let sections = code_sections(&self.file_buffer);
+
+        self.rows = platform_items(&selected, &self.platform)?
+            .into_iter()
+            .map(|item| {
+                let patch = find_patch(&self.file_buffer, &sections, item);
+
+                Row {
+                    patch_type: item.patch_type,
+                    enabled: patch.as_ref().is_some_and(|patch| patch.is_applied),
+                    patch,
+                }
+            })
+            .collect();
+
+        Ok(())
+    }
+
+    /// Not found patches can't be toggled, neither can ambiguous ones
+    /// without --first-match
+    fn state(&self, row: &Row) -> String {
+        match &row.patch {
+            None => "not found".to_string(),
+            Some(patch) if patch.matches > 1 && !self.cli.first_match => {
+                format!("not unique, {} matches", patch.matches)
+            }
+            Some(patch) if patch.is_applied => "applied".to_string(),
+            Some(_) => "not applied".to_string(),
+        }
+    }
+
+    fn toggle(&mut self) {
+        let Some(index) = self.list.selected() else {
+            return;
+        };
+
+        let first_match = self.cli.first_match;
+        let row = &mut self.rows[index];
+
+        match &row.patch {
+            None => self.status = format!("{:?} was not found", row.patch_type),
+            Some(patch) if patch.matches > 1 && !first_match => {
+                self.status = format!(
+                    "{:?} matches more than once, restart with --first-match to patch the first match",
+                    row.patch_type
+                )
+            }
+            Some(_) => {
+                row.enabled = !row.enabled;
+                self.status.clear();
+            }
+        }
+    }
+
+    fn change_count(&self) -> usize {
+        self.rows.iter().filter(|row| row.is_changed()).count()
+    }
+
+    /// Writes the toggled patches to the executable and reloads the state
+    fn apply(&mut self) -> Result<()> {
+        let mut count = 0;
+
+        for row in self.rows.iter().filter(|row| row.is_changed()) {
+            let Some(patch) = &row.patch else {
+                continue;
+            };
+
+            match row.enabled {
+                true => self.file_buffer.replace_bytes(&patch.replace, patch.offset),
+                false => self.file_buffer.replace_bytes(&patch.find, patch.offset),
+            }
+
+            count += 1;
+        }
+
+        if self.cli.fix_checksum {
+            strip_certificate(&mut self.file_buffer)?;
+            update_checksum(&mut self.file_buffer)?;
+        }
+
+        self.file_buffer.write_to_file(self.file)?;
+        self.load()?;
+        self.status = format!("{} changes written to {}", count, self.file.display());
+
+        Ok(())
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, list, footer] = Layout::vertical([
+            Constraint::Length(5),
+            Constraint::Min(3),
+            Constraint::Length(3),
+        ])
+        .areas(frame.area());
+
+        let patterns = match self.is_keyed {
+            true => "for this executable",
+            false => "general",
+        };
+
+        let info = Paragraph::new(vec![
+            Line::from(format!("Executable: {}", self.file.display())),
+            Line::from(format!(
+                "Platform:   {:?} ({} patterns)",
+                self.platform, patterns
+            )),
+            Line::from(format!("SHA-256:    {}", self.sha256)),
+        ])
+        .block(Block::bordered().title(" fs-patch "));
+
+        frame.render_widget(info, header);
+
+        let items: Vec<ListItem> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let location = row
+                    .patch
+                    .as_ref()
+                    .map(|patch| format_location(patch.offset, patch.rva))
+                    .unwrap_or_default();
+
+                let mark = match (row.enabled, row.is_changed()) {
+                    (true, true) => "[+]",
+                    (false, true) => "[-]",
+                    (true, false) => "[x]",
+                    (false, false) => "[ ]",
+                };
+
+                ListItem::new(format!(
+                    "{} {:<16}{:<24}{}",
+                    mark,
+                    format!("{:?}", row.patch_type),
+                    self.state(row),
+                    location
+                ))
+            })
+            .collect();
+
+        let patches = List::new(items)
+            .block(Block::bordered().title(" Patches "))
+            .highlight_style(Style::new().reversed());
+
+        frame.render_stateful_widget(patches, list, &mut self.list);
+
+        let help = match self.status.is_empty() {
+            true => format!(
+                "up/down select  space toggle  a apply ({} changes)  q quit",
+                self.change_count()
+            ),
+            false => self.status.clone(),
+        };
+
+        frame.render_widget(Paragraph::new(help).block(Block::bordered()), footer);
+
+        if self.confirm {
+            let area = popup_area(frame.area(), 60, 5);
+            let question = Paragraph::new(vec![
+                Line::from(format!(
+                    "Write {} changes to the executable?",
+                    self.change_count()
+                )),
+                Line::from(""),
+                Line::from("y apply  n cancel"),
+            ])
+            .block(Block::bordered().title(" Confirm "));
+
+            frame.render_widget(Clear, area);
+            frame.render_widget(question, area);
+        }
+    }
+
+    /// Returns false to quit
+    fn handle_key(&mut self, key: KeyCode) -> Result<bool> {
+        if self.confirm {
+            match key {
+                KeyCode::Char('y') => {
+                    self.confirm = false;
+
+                    if let Err(e) = self.apply() {
+                        self.status = format!("Error: {:#}", e);
+                    }
+                }
+                KeyCode::Char('n') | KeyCode::Esc => {
+                    self.confirm = false;
+                    self.status = "Cancelled".to_string();
+                }
+                _ => {}
+            }
+
+            return Ok(true);
+        }
+
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Up | KeyCode::Char('k') => self.list.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.list.select_next(),
+            KeyCode::Char(' ') | KeyCode::Enter => self.toggle(),
+            KeyCode::Char('a') => match self.change_count() {
+                0 => self.status = "Nothing to change".to_string(),
+                _ => self.confirm = true,
+            },
+            _ => {}
+        }
+
+        Ok(true)
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            // Windows reports key releases too
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !self.handle_key(key.code)?
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn popup_area(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Text UI listing the patches of an executable, toggled patches are
+/// written after confirmation
+pub fn run(cli: &Cmd, file: &Path, patterns: &[PatternDefinition]) -> Result<()> {
+    let mut app = App::new(cli, file, patterns)?;
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+
+    ratatui::restore();
+
+    result
+}
@@ -0,0 +1,3 @@
+//! PE helpers of fs-patch, shared with fs-ffi so both patch the same bytes
+
+pub mod pe;
@@ -1,31 +1,57 @@
+mod interactive;
+mod patch_file;
+mod submit;
+
 use std::{
     path::{Path, PathBuf},
+    process::ExitCode,
     vec,
 };
 
 use anyhow::{Result, bail};
 use argh::FromArgs;
-use fs_lib::{EXECUTABLE_PATTERNS, PatchType, Platform, buffer::BufferExtension};
+use fs_lib::{
+    Platform,
+    buffer::BufferExtension,
+    elevate::{check_write_access, handle_access_denied},
+    hash::{self, Algorithm},
+    list_files_with_extension,
+    pattern::{
+        BytePattern, OffsetPatch, PatternDefinition, PatternFile, export_patterns,
+        load_pattern_file, select_patterns,
+    },
+    report::{self, Summary},
+};
+use fs_patch::pe::{CodeSection, code_sections, find_pattern, strip_certificate, update_checksum};
+
+use crate::{
+    patch_file::{PatchFile, PatchFormat, export_patch, load_patch_file},
+    submit::write_report,
+};
 
 #[allow(dead_code)]
 struct Patch {
     pub offset: usize,
+    pub rva: Option<usize>,
     pub find: Vec<u8>,
     pub replace: Vec<u8>,
-    pub patch_type: PatchType,
+    /// Patch type of a pattern or name of an offset patch
+    pub name: String,
     pub is_applied: bool,
+    /// Number of locations the pattern matched, only the first is patched
+    pub matches: usize,
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// Patch executable
 pub struct Cmd {
-    /// path to executable
+    /// path to executable(s) or folder containing executables
     #[argh(positional)]
-    input: PathBuf,
+    input: Vec<PathBuf>,
 
-    /// platform: steam, giants (default: steam)
-    #[argh(option, default = "Platform::Steam")]
-    platform: Platform,
+    /// platform: steam, giants (default: auto-detect)
+    #[argh(option)]
+    platform: Option<Platform>,
 
     /// revert (applied) patches
     #[argh(switch, short = 'r')]
@@ -35,143 +61,715 @@ pub struct Cmd {
     #[argh(switch, short = 's')]
     scan: bool,
 
+    /// with --scan, write the found patterns as a pattern file for this
+    /// executable: matched bytes, replacement and sha256
+    #[argh(option)]
+    emit_patches: Option<PathBuf>,
+
     /// check executable for active/inactive patches
     #[argh(switch, short = 'c')]
     check: bool,
+
+    /// load patterns from file instead of the built-in table
+    #[argh(option)]
+    patterns: Option<PathBuf>,
+
+    /// apply a JSON or IPS patch, or the [offset-patch] entries of a pattern
+    /// file, instead of searching patterns. Original bytes are verified before
+    /// writing, except for IPS.
+    #[argh(option)]
+    patch_file: Option<PathBuf>,
+
+    /// print patterns in pattern file format and exit
+    #[argh(switch)]
+    export_patterns: bool,
+
+    /// remove the code signature and recompute the PE checksum after patching
+    #[argh(switch)]
+    fix_checksum: bool,
+
+    /// patch the first match when a pattern matches more than once
+    #[argh(switch)]
+    first_match: bool,
+
+    /// relaunch as administrator (UAC prompt) when an executable can't be
+    /// written, e.g. in Program Files (Windows)
+    #[argh(switch)]
+    elevate: bool,
+
+    /// choose patches to apply or revert in a text UI
+    #[argh(switch, short = 'i')]
+    interactive: bool,
+
+    #[argh(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand)]
+enum Command {
+    SubmitReport(SubmitReportCmd),
+    Export(ExportCmd),
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Write the patches for an executable as a JSON or IPS patch, to share
+/// them for one build without sharing the patched executable
+#[argh(subcommand, name = "export")]
+struct ExportCmd {
+    /// patch format: json, ips (default json)
+    #[argh(option, default = "PatchFormat::Json")]
+    format: PatchFormat,
+
+    /// platform: steam, giants (default: auto-detect)
+    #[argh(option)]
+    platform: Option<Platform>,
+
+    /// path to executable
+    #[argh(positional)]
+    input: PathBuf,
+
+    /// path to patch file
+    #[argh(positional)]
+    output: PathBuf,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Write the bytes around near matches of each pattern, to help author
+/// patterns for builds none of them match (no paths or user data)
+#[argh(subcommand, name = "submit-report")]
+struct SubmitReportCmd {
+    /// bytes written before and after each match (default 32)
+    #[argh(option, default = "32")]
+    context: usize,
+
+    /// path to executable
+    #[argh(positional)]
+    input: PathBuf,
+
+    /// path to report file (optional, default stdout)
+    #[argh(positional)]
+    output: Option<PathBuf>,
 }
 
-fn scan_executable<P: AsRef<Path>>(file: P) -> Result<()> {
-    let file_buffer = std::fs::read(&file)?;
+/// Returns file offset, RVA and captures of every match
+fn format_location(offset: usize, rva: Option<usize>) -> String {
+    match rva {
+        Some(rva) => format!("offset {}, RVA 0x{:X}", offset, rva),
+        None => format!("offset {}", offset),
+    }
+}
+
+/// Prints the location of each pattern and returns the found patterns with
+/// the matched bytes, pinned to the executable hash, and their location
+fn scan_executable(
+    file_buffer: &[u8],
+    patterns: &[&PatternDefinition],
+    sha256: &str,
+) -> Result<Vec<(PatternDefinition, String)>> {
+    let sections = code_sections(file_buffer);
+    let mut platform = None;
+    let mut found = Vec::new();
+
+    if sections.is_none() {
+        println!("[!] Not a PE image, searching entire file");
+    }
+
+    for item in patterns {
+        if platform != Some(item.platform) {
+            platform = Some(item.platform);
+            println!("\nPlatform: {:?}", item.platform);
+        }
 
-    for (platform, items) in EXECUTABLE_PATTERNS.iter() {
-        println!("\nPlatform: {:?}", platform);
+        let matches = find_pattern(file_buffer, &sections, &item.find);
 
-        for item in items.iter() {
-            if let Some(offset) = file_buffer.find_bytes(&item.find) {
-                println!("[+] {:?}", item.patch_type);
-                println!("    offset: {}", offset);
-                println!("    expect: {}", item.find.to_hex_string());
-                println!("    replace: {}", item.replace.to_hex_string());
-            } else {
-                println!("[-] Could not locate pattern {:?}", item.patch_type);
-                println!("    bytes: {}", item.find.to_hex_string());
+        if let Some((offset, rva, captures)) = matches.first() {
+            let (offset, rva) = (*offset, *rva);
+
+            println!("[+] {:?}", item.patch_type);
+            println!("    offset: {}", offset);
+
+            if let Some(rva) = rva {
+                println!("    rva: 0x{:X}", rva);
+            }
+
+            if matches.len() > 1 {
+                println!("[!] Pattern is not unique, {} matches", matches.len());
             }
+
+            let replace = item.replace.render(file_buffer, offset, captures);
+
+            println!("    expect: {}", item.find);
+            println!("    replace: {}", replace.to_hex_string());
+
+            let definition = PatternDefinition {
+                platform: item.platform,
+                patch_type: item.patch_type,
+                find: BytePattern::from(&file_buffer[offset..offset + item.find.len()]),
+                replace: BytePattern::from(replace.as_slice()),
+                sha256: Some(sha256.to_string()),
+            };
+
+            found.push((definition, format_location(offset, rva)));
+        } else {
+            println!("[-] Could not locate pattern {:?}", item.patch_type);
+            println!("    bytes: {}", item.find);
         }
     }
 
-    Ok(())
+    Ok(found)
 }
 
-fn find_valid_patches(file_buffer: &Vec<u8>, platform: &Platform) -> Result<Vec<Patch>> {
-    let Some(items) = EXECUTABLE_PATTERNS.get(&platform) else {
-        bail!("No patch items found")
+/// Pattern file with the found patterns, their locations are listed in the
+/// leading comment
+fn emit_patches(file: &Path, sha256: &str, found: Vec<(PatternDefinition, String)>) -> String {
+    let mut output = format!(
+        "# Scanned {}\n# sha256 {}\n",
+        file.file_name().unwrap_or_default().to_string_lossy(),
+        sha256
+    );
+
+    for (definition, location) in found.iter() {
+        output.push_str(&format!(
+            "# {}.{:?}: {}\n",
+            definition.platform, definition.patch_type, location
+        ));
+    }
+
+    let pattern_file = PatternFile {
+        patterns: found
+            .into_iter()
+            .map(|(definition, _)| definition)
+            .collect(),
+        ..Default::default()
     };
 
+    output + "\n" + &export_patterns(&pattern_file)
+}
+
+/// Locates a pattern, unpatched or patched. None if neither matches.
+fn find_patch(
+    file_buffer: &[u8],
+    sections: &Option<Vec<CodeSection>>,
+    item: &PatternDefinition,
+) -> Option<Patch> {
+    let mut matches = find_pattern(file_buffer, sections, &item.find);
+    let mut is_applied = false;
+
+    if matches.is_empty() {
+        matches = find_pattern(file_buffer, sections, &item.replace);
+        is_applied = true;
+    }
+
+    let (offset, rva, captures) = matches.first()?;
+
+    Some(Patch {
+        offset: *offset,
+        rva: *rva,
+        find: item.find.render(file_buffer, *offset, captures),
+        replace: item.replace.render(file_buffer, *offset, captures),
+        name: format!("{:?}", item.patch_type),
+        is_applied,
+        matches: matches.len(),
+    })
+}
+
+/// Offset patches for an executable, fails if the bytes at an offset are
+/// neither the original nor the replacement
+fn find_offset_patches(
+    file_buffer: &[u8],
+    offset_patches: &[OffsetPatch],
+    sha256: &str,
+) -> Result<Vec<Patch>> {
+    let sections = code_sections(file_buffer);
     let mut result: Vec<Patch> = vec![];
 
-    for item in items.iter() {
-        if let Some(offset) = file_buffer.find_bytes(&item.find) {
-            result.push(Patch {
-                offset,
-                find: item.find.clone(),
-                replace: item.replace.clone(),
-                patch_type: item.patch_type.clone(),
-                is_applied: false,
-            });
-        } else if let Some(offset) = file_buffer.find_bytes(&item.replace) {
-            result.push(Patch {
-                offset,
-                find: item.find.clone(),
-                replace: item.replace.clone(),
-                patch_type: item.patch_type.clone(),
-                is_applied: true,
-            });
+    // Hashes are of the unpatched executable, restore applied patches first
+    let mut unpatched = file_buffer.to_vec();
+
+    for item in offset_patches {
+        if file_buffer.get(item.offset..item.offset + item.replace.len()) == Some(&item.replace) {
+            unpatched.replace_bytes(&item.original, item.offset);
+        }
+    }
+
+    let unpatched_sha256 = hash::hash_buffer(&unpatched, Algorithm::Sha256);
+
+    let items = offset_patches.iter().filter(|item| {
+        item.sha256
+            .as_ref()
+            .is_none_or(|hash| hash == sha256 || *hash == unpatched_sha256)
+    });
+
+    for item in items {
+        let name = item.display_name();
+
+        let Some(bytes) = file_buffer.get(item.offset..item.offset + item.original.len()) else {
+            bail!(
+                "{}: offset {} is past the end of the file",
+                name,
+                item.offset
+            )
+        };
+
+        let is_applied = if bytes == item.replace.as_slice() {
+            true
+        } else if bytes == item.original.as_slice() {
+            false
         } else {
+            bail!(
+                "{}: unexpected bytes at offset {}, expected {}, found {}",
+                name,
+                item.offset,
+                BytePattern::from(item.original.as_slice()),
+                BytePattern::from(bytes)
+            )
+        };
+
+        let rva = sections.as_ref().and_then(|sections| {
+            sections
+                .iter()
+                .find(|section| {
+                    (section.offset..section.offset + section.size).contains(&item.offset)
+                })
+                .map(|section| section.rva + item.offset - section.offset)
+        });
+
+        result.push(Patch {
+            offset: item.offset,
+            rva,
+            find: item.original.clone(),
+            replace: item.replace.clone(),
+            name,
+            is_applied,
+            matches: 1,
+        });
+    }
+
+    if result.is_empty() {
+        bail!("No offset patches for this executable (sha256 {})", sha256)
+    }
+
+    Ok(result)
+}
+
+/// Patterns of a platform
+fn platform_items<'a>(
+    patterns: &[&'a PatternDefinition],
+    platform: &Platform,
+) -> Result<Vec<&'a PatternDefinition>> {
+    let items: Vec<&PatternDefinition> = patterns
+        .iter()
+        .filter(|item| item.platform == *platform)
+        .copied()
+        .collect();
+
+    if items.is_empty() {
+        bail!("No patch items found for platform {:?}", platform)
+    }
+
+    Ok(items)
+}
+
+fn find_valid_patches(
+    file_buffer: &[u8],
+    patterns: &[&PatternDefinition],
+    platform: &Platform,
+) -> Result<Vec<Patch>> {
+    let sections = code_sections(file_buffer);
+    let mut result: Vec<Patch> = vec![];
+
+    for item in platform_items(patterns, platform)? {
+        let Some(patch) = find_patch(file_buffer, &sections, item) else {
             bail!("No valid offsets found for patch {:?}", item.patch_type)
-        }
+        };
+
+        result.push(patch);
     }
 
     Ok(result)
 }
 
-fn patch_executable<P: AsRef<Path>>(file: P, platform: &Platform) -> Result<(Vec<u8>, bool)> {
-    let mut file_buffer = Vec::read_from_file(file)?;
-    let patches = find_valid_patches(&file_buffer, platform)?;
+/// Returns the first platform where every pattern matches, either patched or unpatched
+fn detect_platform(file_buffer: &[u8], patterns: &[&PatternDefinition]) -> Result<Platform> {
+    let mut platforms: Vec<Platform> = patterns.iter().map(|item| item.platform).collect();
+    platforms.sort();
+    platforms.dedup();
+
+    for platform in platforms {
+        if find_valid_patches(file_buffer, patterns, &platform).is_ok() {
+            return Ok(platform);
+        }
+    }
 
+    bail!("Unable to detect platform, no matching patterns found")
+}
+
+fn patch_executable(file_buffer: &mut Vec<u8>, patches: Vec<Patch>) -> Result<bool> {
     let mut is_modified = false;
 
     for patch in patches {
         if patch.is_applied {
-            println!("[*] {:?} is already applied", patch.patch_type);
+            println!(
+                "[*] {} is already applied ({})",
+                patch.name,
+                format_location(patch.offset, patch.rva)
+            );
         } else {
             file_buffer.replace_bytes(&patch.replace, patch.offset);
-            println!("[+] Applied {:?}", patch.patch_type);
+            println!(
+                "[+] Applied {} ({})",
+                patch.name,
+                format_location(patch.offset, patch.rva)
+            );
             is_modified = true;
         }
     }
 
-    Ok((file_buffer, is_modified))
+    Ok(is_modified)
 }
 
-fn patch_revert_executable<P: AsRef<Path>>(
-    file: P,
-    platform: &Platform,
-) -> Result<(Vec<u8>, bool)> {
-    let mut file_buffer = Vec::read_from_file(file)?;
-    let patches = find_valid_patches(&file_buffer, platform)?;
-
+fn patch_revert_executable(file_buffer: &mut Vec<u8>, patches: Vec<Patch>) -> Result<bool> {
     let mut is_modified = false;
 
     for patch in patches {
         if patch.is_applied {
             file_buffer.replace_bytes(&patch.find, patch.offset);
-            println!("[-] Reverted {:?}", patch.patch_type);
+            println!(
+                "[-] Reverted {} ({})",
+                patch.name,
+                format_location(patch.offset, patch.rva)
+            );
             is_modified = true;
         } else {
-            println!("[*] {:?} is already reverted", patch.patch_type);
+            println!(
+                "[*] {} is already reverted ({})",
+                patch.name,
+                format_location(patch.offset, patch.rva)
+            );
         }
     }
 
-    Ok((file_buffer, is_modified))
+    Ok(is_modified)
 }
 
-fn check_executable<P: AsRef<Path>>(file: P, platform: &Platform) -> Result<()> {
-    let file_buffer = Vec::read_from_file(file)?;
-    let patches = find_valid_patches(&file_buffer, platform)?;
-
+fn check_executable(patches: Vec<Patch>) -> Result<()> {
     for patch in patches {
         if patch.is_applied {
-            println!("[+] Patch is active {:?}", patch.patch_type);
+            println!(
+                "[+] Patch is active {} ({})",
+                patch.name,
+                format_location(patch.offset, patch.rva)
+            );
         } else {
-            println!("[-] Patch is not active {:?}", patch.patch_type);
+            println!(
+                "[-] Patch is not active {} ({})",
+                patch.name,
+                format_location(patch.offset, patch.rva)
+            );
         }
     }
 
     Ok(())
 }
 
-fn main() -> Result<()> {
-    let cli: Cmd = argh::from_env();
+fn fix_checksum(file_buffer: &mut Vec<u8>) -> Result<()> {
+    if strip_certificate(file_buffer)? {
+        println!("[!] Removed code signature, the executable is no longer signed");
+    }
 
-    if cli.scan {
-        return scan_executable(&cli.input);
+    let checksum = update_checksum(file_buffer)?;
+    println!("[*] Updated PE checksum: 0x{:08X}", checksum);
+
+    Ok(())
+}
+
+/// Collects executables from the positional inputs, folders are expanded to
+/// the .exe files they contain
+fn list_executables(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    if inputs.is_empty() {
+        bail!(report::usage("No input executable given"))
+    }
+
+    let mut files = Vec::new();
+
+    for input in inputs {
+        if input.is_dir() {
+            let mut folder_files = list_files_with_extension(input, "exe", false, false)?;
+            folder_files.sort();
+            files.extend(folder_files);
+        } else {
+            files.push(input.clone());
+        }
     }
 
+    if files.is_empty() {
+        bail!("No executables found")
+    }
+
+    Ok(files)
+}
+
+/// Checks, applies or reverts the patches and writes the executable if it
+/// changed, `label` is appended to the returned status
+fn update_executable(
+    cli: &Cmd,
+    file: &PathBuf,
+    mut file_buffer: Vec<u8>,
+    patches: Vec<Patch>,
+    label: &str,
+) -> Result<String> {
     if cli.check {
-        return check_executable(&cli.input, &cli.platform);
+        check_executable(patches)?;
+        return Ok(format!("checked ({})", label));
     }
 
-    let (file_buffer, is_modified) = match cli.revert {
-        true => patch_revert_executable(&cli.input, &cli.platform)?,
-        false => patch_executable(&cli.input, &cli.platform)?,
+    let is_modified = match cli.revert {
+        true => patch_revert_executable(&mut file_buffer, patches)?,
+        false => patch_executable(&mut file_buffer, patches)?,
     };
 
     if is_modified {
-        file_buffer.write_to_file(&cli.input)?;
-        println!("\nExecutable updated: {}", &cli.input.display());
+        if cli.fix_checksum {
+            fix_checksum(&mut file_buffer)?;
+        }
+
+        file_buffer.write_to_file(file)?;
+        println!("\nExecutable updated: {}", file.display());
+        Ok(format!("updated ({})", label))
     } else {
         println!("\nNo changes required");
+        Ok(format!("no changes required ({})", label))
     }
+}
 
-    Ok(())
+fn process_executable(
+    cli: &Cmd,
+    file: &PathBuf,
+    patterns: &[PatternDefinition],
+    patch_file: Option<&PatchFile>,
+) -> Result<String> {
+    let file_buffer = Vec::read_from_file(file)?;
+    let sha256 = hash::hash_buffer(&file_buffer, Algorithm::Sha256);
+
+    if let Some(patch_file) = patch_file {
+        let offset_patches = patch_file.offset_patches(&file_buffer)?;
+        let patches = find_offset_patches(&file_buffer, &offset_patches, &sha256)?;
+        return update_executable(cli, file, file_buffer, patches, "patch file");
+    }
+
+    let patterns = select_patterns(patterns, &sha256);
+
+    if patterns.iter().any(|item| item.sha256.is_some()) {
+        println!("[*] Using patterns for this executable (sha256 {})", sha256);
+    }
+
+    if cli.scan {
+        let found = scan_executable(&file_buffer, &patterns, &sha256)?;
+
+        if let Some(output) = &cli.emit_patches {
+            if found.is_empty() {
+                bail!("No patterns found, nothing to write")
+            }
+
+            emit_patches(file, &sha256, found)
+                .into_bytes()
+                .write_to_file(output)?;
+            println!("\nPatterns written to {}", output.display());
+        }
+
+        return Ok("scanned".to_string());
+    }
+
+    if code_sections(&file_buffer).is_none() {
+        println!("[!] Not a PE image, searching entire file");
+    }
+
+    let platform = match cli.platform {
+        Some(platform) => platform,
+        None => {
+            let platform = detect_platform(&file_buffer, &patterns)?;
+            println!("Detected platform: {:?}", platform);
+            platform
+        }
+    };
+
+    let patches = find_valid_patches(&file_buffer, &patterns, &platform)?;
+
+    // Writing to the wrong match would corrupt the executable
+    for patch in patches.iter().filter(|patch| patch.matches > 1) {
+        println!(
+            "[!] {} pattern is not unique, {} matches, first at {}",
+            patch.name,
+            patch.matches,
+            format_location(patch.offset, patch.rva)
+        );
+
+        if !cli.check && !cli.first_match {
+            bail!(
+                "Pattern for {} matches more than once, use --first-match to patch the first match",
+                patch.name
+            )
+        }
+    }
+
+    update_executable(cli, file, file_buffer, patches, &format!("{:?}", platform))
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    let pattern_file = match &cli.patterns {
+        Some(file) => load_pattern_file(file)?,
+        None => PatternFile::builtin(),
+    };
+
+    if cli.export_patterns {
+        print!("{}", export_patterns(&pattern_file));
+        return Ok(summary);
+    }
+
+    let patterns = pattern_file.patterns;
+
+    if let Some(Command::SubmitReport(command)) = &cli.command {
+        let report = write_report(
+            &Vec::read_from_file(&command.input)?,
+            &patterns,
+            command.context,
+        );
+
+        match &command.output {
+            Some(output) => {
+                report.into_bytes().write_to_file(output)?;
+                println!("{}", output.display());
+            }
+            None => print!("{}", report),
+        }
+
+        summary.processed();
+        return Ok(summary);
+    }
+
+    if let Some(Command::Export(command)) = &cli.command {
+        let file_buffer = Vec::read_from_file(&command.input)?;
+        let sha256 = hash::hash_buffer(&file_buffer, Algorithm::Sha256);
+        let patterns = select_patterns(&patterns, &sha256);
+
+        let platform = match command.platform {
+            Some(platform) => platform,
+            None => detect_platform(&file_buffer, &patterns)?,
+        };
+
+        let patches = find_valid_patches(&file_buffer, &patterns, &platform)?;
+
+        if let Some(patch) = patches.iter().find(|patch| patch.matches > 1) {
+            bail!(
+                "Pattern for {} matches more than once, the patch would not be exact",
+                patch.name
+            )
+        }
+
+        // The patch is for the unpatched build
+        let mut unpatched = file_buffer;
+
+        for patch in patches.iter().filter(|patch| patch.is_applied) {
+            unpatched.replace_bytes(&patch.find, patch.offset);
+        }
+
+        let sha256 = hash::hash_buffer(&unpatched, Algorithm::Sha256);
+
+        export_patch(command.format, &patches, &sha256, platform)?
+            .write_to_file(&command.output)?;
+        println!(
+            "{} ({:?}, sha256 {})",
+            command.output.display(),
+            platform,
+            sha256
+        );
+
+        summary.processed();
+        return Ok(summary);
+    }
+
+    let files = list_executables(&cli.input)?;
+
+    if cli.emit_patches.is_some() && (!cli.scan || files.len() != 1) {
+        bail!(report::usage(
+            "Emit patches takes one executable and requires --scan"
+        ))
+    }
+
+    let patch_file = match &cli.patch_file {
+        Some(patch_file) => {
+            if cli.scan || cli.interactive {
+                bail!(report::usage(
+                    "Patch file can't be combined with --scan or --interactive"
+                ))
+            }
+
+            let patch_file = load_patch_file(patch_file)?;
+
+            if patch_file.is_ips() && cli.revert {
+                bail!("IPS patches can't be reverted, they don't contain the original bytes")
+            }
+
+            Some(patch_file)
+        }
+        None => None,
+    };
+
+    // Fail before any work instead of on the first write
+    if !cli.scan && !cli.check {
+        for file in files.iter() {
+            check_write_access(file).map_err(|e| handle_access_denied(e, cli.elevate))?;
+        }
+    }
+
+    if cli.interactive {
+        if files.len() != 1 || cli.scan || cli.check || cli.revert {
+            bail!(report::usage(
+                "Interactive mode takes one executable and no --scan, --check or --revert"
+            ))
+        }
+
+        interactive::run(&cli, &files[0], &patterns)?;
+        summary.processed();
+        return Ok(summary);
+    }
+
+    if files.len() == 1 {
+        process_executable(&cli, &files[0], &patterns, patch_file.as_ref())?;
+        summary.processed();
+        return Ok(summary);
+    }
+
+    let mut results = Vec::new();
+
+    for file in files.iter() {
+        println!("\n=== {} ===", file.display());
+
+        match process_executable(&cli, file, &patterns, patch_file.as_ref()) {
+            Ok(status) => {
+                results.push((file, status));
+                summary.processed();
+            }
+            Err(e) => {
+                results.push((file, format!("failed: {}", e)));
+                summary.error(file.display(), &e);
+            }
+        }
+    }
+
+    println!("\nResults:");
+
+    for (file, status) in results {
+        println!("  {}: {}", file.display(), status);
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
 }
@@ -0,0 +1,254 @@
+use std::{path::Path, str::FromStr};
+
+use anyhow::{Context, Result, bail};
+use fs_lib::{
+    Platform,
+    buffer::BufferExtension,
+    pattern::{BytePattern, OffsetPatch, parse_pattern_file},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::Patch;
+
+/// Largest offset of a classic IPS record, larger offsets are written as IPS32
+const IPS_MAX_OFFSET: usize = 0xFFFFFF;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatchFormat {
+    Json,
+    Ips,
+}
+
+impl FromStr for PatchFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(PatchFormat::Json),
+            "ips" => Ok(PatchFormat::Ips),
+            _ => Err(format!("Unknown patch format: {} (json, ips)", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonPatchFile {
+    /// SHA-256 of the unpatched executable
+    sha256: String,
+    platform: String,
+    patches: Vec<JsonPatch>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonPatch {
+    name: String,
+    offset: usize,
+    original: String,
+    replace: String,
+}
+
+/// Patch file given with --patch-file
+pub enum PatchFile {
+    /// JSON patch or [offset-patch] sections, verified by original bytes
+    Offsets(Vec<OffsetPatch>),
+    /// IPS records as (offset, bytes), without original bytes or hash
+    Ips(Vec<(usize, Vec<u8>)>),
+}
+
+impl PatchFile {
+    /// Offset patches for an executable. IPS records take the current bytes
+    /// as original, so they can be applied but not reverted.
+    pub fn offset_patches(&self, file_buffer: &[u8]) -> Result<Vec<OffsetPatch>> {
+        let records = match self {
+            PatchFile::Offsets(patches) => return Ok(patches.clone()),
+            PatchFile::Ips(records) => records,
+        };
+
+        records
+            .iter()
+            .enumerate()
+            .map(|(index, (offset, bytes))| {
+                let Some(original) = file_buffer.get(*offset..*offset + bytes.len()) else {
+                    bail!(
+                        "IPS record {}: offset {} is past the end of the file",
+                        index + 1,
+                        offset
+                    )
+                };
+
+                Ok(OffsetPatch {
+                    name: Some(format!("IPS record {}", index + 1)),
+                    offset: *offset,
+                    original: original.to_vec(),
+                    replace: bytes.clone(),
+                    sha256: None,
+                })
+            })
+            .collect()
+    }
+
+    pub fn is_ips(&self) -> bool {
+        matches!(self, PatchFile::Ips(_))
+    }
+}
+
+fn parse_hex(value: &str) -> Result<Vec<u8>> {
+    value
+        .split_whitespace()
+        .map(|b| u8::from_str_radix(b, 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .with_context(|| format!("Invalid hex bytes '{}'", value))
+}
+
+fn parse_json(buffer: &[u8]) -> Result<Vec<OffsetPatch>> {
+    let file: JsonPatchFile = serde_json::from_slice(buffer)?;
+
+    file.patches
+        .into_iter()
+        .map(|patch| {
+            let original = parse_hex(&patch.original)?;
+            let replace = parse_hex(&patch.replace)?;
+
+            if original.len() != replace.len() {
+                bail!(
+                    "{}: 'original' is {} bytes but 'replace' is {} bytes",
+                    patch.name,
+                    original.len(),
+                    replace.len()
+                )
+            }
+
+            Ok(OffsetPatch {
+                name: Some(patch.name),
+                offset: patch.offset,
+                original,
+                replace,
+                sha256: Some(file.sha256.to_lowercase()),
+            })
+        })
+        .collect()
+}
+
+/// Parses IPS and IPS32 patches, including RLE records
+fn parse_ips(buffer: &[u8]) -> Result<Vec<(usize, Vec<u8>)>> {
+    let (offset_size, footer): (usize, &[u8]) = match &buffer[..5] {
+        b"IPS32" => (4, b"EEOF"),
+        _ => (3, b"EOF"),
+    };
+
+    let mut records = Vec::new();
+    let mut position = 5;
+
+    let read = |position: usize, size: usize| -> Result<&[u8]> {
+        match buffer.get(position..position + size) {
+            Some(bytes) => Ok(bytes),
+            None => bail!("Truncated IPS patch at byte {}", position),
+        }
+    };
+
+    let be = |bytes: &[u8]| bytes.iter().fold(0, |value, b| (value << 8) | *b as usize);
+
+    loop {
+        let offset = read(position, offset_size)?;
+
+        if offset == footer {
+            break;
+        }
+
+        let offset = be(offset);
+        let size = be(read(position + offset_size, 2)?);
+        position += offset_size + 2;
+
+        if size == 0 {
+            let run = read(position, 3)?;
+            records.push((offset, vec![run[2]; be(&run[..2])]));
+            position += 3;
+        } else {
+            records.push((offset, read(position, size)?.to_vec()));
+            position += size;
+        }
+    }
+
+    Ok(records)
+}
+
+/// Loads a JSON or IPS patch, or the [offset-patch] sections of a pattern file
+pub fn load_patch_file(file: &Path) -> Result<PatchFile> {
+    let buffer = Vec::read_from_file(file)?;
+
+    let patch_file = if buffer.starts_with(b"PATCH") || buffer.starts_with(b"IPS32") {
+        parse_ips(&buffer).map(PatchFile::Ips)
+    } else if buffer.trim_ascii_start().starts_with(b"{") {
+        parse_json(&buffer).map(PatchFile::Offsets)
+    } else {
+        parse_pattern_file(&String::from_utf8_lossy(&buffer))
+            .map(|pattern_file| PatchFile::Offsets(pattern_file.offset_patches))
+    };
+
+    patch_file.with_context(|| format!("{}", file.display()))
+}
+
+fn export_json(patches: &[Patch], sha256: &str, platform: Platform) -> Result<Vec<u8>> {
+    let file = JsonPatchFile {
+        sha256: sha256.to_string(),
+        platform: platform.to_string(),
+        patches: patches
+            .iter()
+            .map(|patch| JsonPatch {
+                name: patch.name.clone(),
+                offset: patch.offset,
+                original: BytePattern::from(patch.find.as_slice()).to_string(),
+                replace: BytePattern::from(patch.replace.as_slice()).to_string(),
+            })
+            .collect(),
+    };
+
+    Ok(serde_json::to_vec_pretty(&file)?)
+}
+
+/// IPS patch, IPS32 if an offset doesn't fit in 24 bits
+fn export_ips(patches: &[Patch]) -> Result<Vec<u8>> {
+    let is_ips32 = patches
+        .iter()
+        .any(|patch| patch.offset + patch.replace.len() > IPS_MAX_OFFSET);
+
+    let (mut output, footer, offset_size) = match is_ips32 {
+        true => (b"IPS32".to_vec(), b"EEOF".as_slice(), 4),
+        false => (b"PATCH".to_vec(), b"EOF".as_slice(), 3),
+    };
+
+    for patch in patches {
+        let offset = u32::try_from(patch.offset)?.to_be_bytes();
+
+        // An offset equal to the footer would end the patch early
+        if offset[4 - offset_size..] == *footer {
+            bail!(
+                "{} is at offset {}, which IPS can't address, use --format json",
+                patch.name,
+                patch.offset
+            )
+        }
+
+        output.extend_from_slice(&offset[4 - offset_size..]);
+        output.extend_from_slice(&u16::try_from(patch.replace.len())?.to_be_bytes());
+        output.extend_from_slice(&patch.replace);
+    }
+
+    output.extend_from_slice(footer);
+
+    Ok(output)
+}
+
+/// Patch file for the executable with the given SHA-256, IPS has no room
+/// for the hash
+pub fn export_patch(
+    format: PatchFormat,
+    patches: &[Patch],
+    sha256: &str,
+    platform: Platform,
+) -> Result<Vec<u8>> {
+    match format {
+        PatchFormat::Json => export_json(patches, sha256, platform),
+        PatchFormat::Ips => export_ips(patches),
+    }
+}
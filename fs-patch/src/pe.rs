@@ -0,0 +1,163 @@
+use anyhow::{Result, bail};
+use fs_lib::{
+    buffer::BufferExtension,
+    pattern::{BytePattern, Captures},
+};
+use goblin::pe::{
+    PE,
+    section_table::{IMAGE_SCN_CNT_CODE, IMAGE_SCN_MEM_EXECUTE},
+};
+
+const SECURITY_DIRECTORY_INDEX: usize = 4;
+
+/// File range of a PE section containing code
+pub struct CodeSection {
+    pub offset: usize,
+    pub size: usize,
+    pub rva: usize,
+}
+
+/// Returns the executable sections, or None if the file is not a PE image in
+/// which case the entire file is searched
+pub fn code_sections(file_buffer: &[u8]) -> Option<Vec<CodeSection>> {
+    let pe = PE::parse(file_buffer).ok()?;
+
+    let sections = pe
+        .sections
+        .iter()
+        .filter(|s| s.characteristics & (IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_CNT_CODE) != 0)
+        .map(|s| {
+            let offset = (s.pointer_to_raw_data as usize).min(file_buffer.len());
+            let size = (s.size_of_raw_data as usize).min(file_buffer.len() - offset);
+
+            CodeSection {
+                offset,
+                size,
+                rva: s.virtual_address as usize,
+            }
+        })
+        .collect();
+
+    Some(sections)
+}
+
+/// Matches of a pattern as (file offset, RVA, captures), in the code
+/// sections of a PE image or the entire file
+pub fn find_pattern(
+    file_buffer: &[u8],
+    sections: &Option<Vec<CodeSection>>,
+    pattern: &BytePattern,
+) -> Vec<(usize, Option<usize>, Captures)> {
+    let Some(sections) = sections else {
+        return pattern
+            .find_all(file_buffer)
+            .into_iter()
+            .map(|(offset, captures)| (offset, None, captures))
+            .collect();
+    };
+
+    sections
+        .iter()
+        .flat_map(|section| {
+            let data = &file_buffer[section.offset..section.offset + section.size];
+
+            pattern
+                .find_all(data)
+                .into_iter()
+                .map(|(offset, captures)| {
+                    (
+                        section.offset + offset,
+                        Some(section.rva + offset),
+                        captures,
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Offsets of the checksum field and the security data directory entry
+fn header_offsets(file_buffer: &Vec<u8>) -> Result<(usize, usize)> {
+    if file_buffer.len() < 0x40 || file_buffer[0..2] != *b"MZ" {
+        bail!("Not a PE image")
+    }
+
+    let pe_offset = file_buffer.read_u32(0x3C) as usize;
+    let optional_header = pe_offset + 24;
+
+    if file_buffer.len() < optional_header + 2
+        || file_buffer[pe_offset..pe_offset + 4] != *b"PE\0\0"
+    {
+        bail!("Not a PE image")
+    }
+
+    let (rva_count_offset, directories) = match file_buffer.read_u16(optional_header) {
+        0x10B => (optional_header + 92, optional_header + 96),
+        0x20B => (optional_header + 108, optional_header + 112),
+        magic => bail!("Unknown optional header magic 0x{:X}", magic),
+    };
+
+    if file_buffer.len() < directories + (SECURITY_DIRECTORY_INDEX + 1) * 8
+        || (file_buffer.read_u32(rva_count_offset) as usize) <= SECURITY_DIRECTORY_INDEX
+    {
+        bail!("PE image has no security directory entry")
+    }
+
+    Ok((
+        optional_header + 64,
+        directories + SECURITY_DIRECTORY_INDEX * 8,
+    ))
+}
+
+/// Removes the Authenticode certificate table, returns true if the image was signed
+pub fn strip_certificate(file_buffer: &mut Vec<u8>) -> Result<bool> {
+    let (_, security_directory) = header_offsets(file_buffer)?;
+
+    // The security directory holds a file offset instead of an RVA
+    let offset = file_buffer.read_u32(security_directory) as usize;
+    let size = file_buffer.read_u32(security_directory + 4) as usize;
+
+    if offset == 0 || size == 0 {
+        return Ok(false);
+    }
+
+    if offset + size > file_buffer.len() {
+        bail!("Certificate table is outside of the file")
+    }
+
+    if offset + size == file_buffer.len() {
+        file_buffer.truncate(offset);
+    } else {
+        file_buffer[offset..offset + size].fill(0);
+    }
+
+    file_buffer.replace_bytes(&[0; 8], security_directory);
+
+    Ok(true)
+}
+
+/// Recomputes the optional header checksum the same way as `CheckSumMappedFile`
+pub fn update_checksum(file_buffer: &mut Vec<u8>) -> Result<u32> {
+    let (checksum_offset, _) = header_offsets(file_buffer)?;
+    let mut sum: u64 = 0;
+
+    for (i, chunk) in file_buffer.chunks(2).enumerate() {
+        if i * 2 == checksum_offset || i * 2 == checksum_offset + 2 {
+            continue;
+        }
+
+        let word = match chunk {
+            [lo, hi] => u16::from_le_bytes([*lo, *hi]),
+            [lo] => *lo as u16,
+            _ => 0,
+        };
+
+        sum += word as u64;
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+
+    let checksum = ((sum & 0xFFFF) + file_buffer.len() as u64) as u32;
+
+    file_buffer.replace_bytes(&checksum.to_le_bytes(), checksum_offset);
+
+    Ok(checksum)
+}
@@ -0,0 +1,208 @@
+use std::collections::HashSet;
+
+use fs_lib::{
+    buffer::find_masked,
+    hash::{self, Algorithm},
+    pattern::{BytePattern, PatternByte, PatternDefinition},
+};
+
+use fs_patch::pe::code_sections;
+
+/// Exact pattern bytes searched to find candidates
+const ANCHOR_SIZE: usize = 4;
+
+/// Candidates reported per pattern
+const MAX_CANDIDATES: usize = 3;
+
+/// Bytes per line of the context dump
+const LINE_SIZE: usize = 16;
+
+/// Location where part of a pattern matches
+struct Candidate {
+    offset: usize,
+    rva: Option<usize>,
+    /// Matching exact bytes of the pattern
+    score: usize,
+}
+
+/// File range searched for candidates, code sections of PE images
+struct Range {
+    offset: usize,
+    size: usize,
+    rva: Option<usize>,
+}
+
+fn exact_count(pattern: &BytePattern) -> usize {
+    pattern
+        .0
+        .iter()
+        .filter(|b| matches!(b, PatternByte::Byte(_)))
+        .count()
+}
+
+fn score_at(buffer: &[u8], pattern: &BytePattern, offset: usize) -> usize {
+    let Some(window) = buffer.get(offset..offset + pattern.len()) else {
+        return 0;
+    };
+
+    pattern
+        .0
+        .iter()
+        .zip(window)
+        .filter(|(pattern_byte, value)| matches!(pattern_byte, PatternByte::Byte(b) if b == *value))
+        .count()
+}
+
+/// Runs of exact bytes of the pattern, split into anchors as (index, bytes).
+/// The end of each run is an anchor too, so a change at its start is found.
+fn anchors(pattern: &BytePattern) -> Vec<(usize, Vec<u8>)> {
+    let mut anchors = Vec::new();
+    let mut runs: Vec<(usize, Vec<u8>)> = Vec::new();
+
+    for (index, pattern_byte) in pattern.0.iter().enumerate() {
+        match (pattern_byte, runs.last_mut()) {
+            (PatternByte::Byte(b), Some((start, run))) if *start + run.len() == index => {
+                run.push(*b)
+            }
+            (PatternByte::Byte(b), _) => runs.push((index, vec![*b])),
+            _ => {}
+        }
+    }
+
+    for (start, run) in runs.iter().filter(|(_, run)| run.len() >= ANCHOR_SIZE) {
+        let mut offsets: Vec<usize> = (0..=run.len() - ANCHOR_SIZE).step_by(ANCHOR_SIZE).collect();
+        offsets.push(run.len() - ANCHOR_SIZE);
+        offsets.dedup();
+
+        for offset in offsets {
+            anchors.push((start + offset, run[offset..offset + ANCHOR_SIZE].to_vec()));
+        }
+    }
+
+    anchors
+}
+
+/// Best partial matches of a pattern, at least half of its exact bytes must
+/// match
+fn find_candidates(buffer: &[u8], ranges: &[Range], pattern: &BytePattern) -> Vec<Candidate> {
+    let minimum = exact_count(pattern).div_ceil(2).max(1);
+    let mut seen = HashSet::new();
+    let mut candidates = Vec::new();
+
+    for range in ranges {
+        let data = &buffer[range.offset..range.offset + range.size];
+
+        for (index, anchor) in anchors(pattern) {
+            let mut from = 0;
+
+            while let Some(found) = find_masked(data, &anchor, &[0xFF; ANCHOR_SIZE], from) {
+                from = found + 1;
+
+                let Some(offset) = found.checked_sub(index) else {
+                    continue;
+                };
+
+                if !seen.insert(range.offset + offset) {
+                    continue;
+                }
+
+                let score = score_at(data, pattern, offset);
+
+                if score >= minimum {
+                    candidates.push(Candidate {
+                        offset: range.offset + offset,
+                        rva: range.rva.map(|rva| rva + offset),
+                        score,
+                    });
+                }
+            }
+        }
+    }
+
+    candidates.sort_by(|a, b| b.score.cmp(&a.score).then(a.offset.cmp(&b.offset)));
+    candidates.truncate(MAX_CANDIDATES);
+    candidates
+}
+
+fn write_candidate(
+    output: &mut String,
+    buffer: &[u8],
+    pattern: &BytePattern,
+    candidate: &Candidate,
+    context: usize,
+) {
+    let location = match candidate.rva {
+        Some(rva) => format!("offset {}, RVA 0x{:X}", candidate.offset, rva),
+        None => format!("offset {}", candidate.offset),
+    };
+
+    output.push_str(&format!(
+        "#   {}/{} bytes at {}\n",
+        candidate.score,
+        exact_count(pattern),
+        location
+    ));
+
+    let end = (candidate.offset + pattern.len()).min(buffer.len());
+
+    output.push_str(&format!(
+        "#     found:   {}\n",
+        BytePattern::from(&buffer[candidate.offset..end])
+    ));
+
+    let start = candidate.offset.saturating_sub(context);
+    let end = (end + context).min(buffer.len());
+
+    output.push_str(&format!("#     context: offset {}\n", start));
+
+    for line in buffer[start..end].chunks(LINE_SIZE) {
+        output.push_str(&format!("#       {}\n", BytePattern::from(line)));
+    }
+}
+
+/// Report of the byte neighborhoods where each pattern (or its patched
+/// bytes) almost matches, to author patterns for an unknown build. Only
+/// bytes of the executable are included, no paths or user data.
+pub fn write_report(buffer: &[u8], patterns: &[PatternDefinition], context: usize) -> String {
+    let ranges: Vec<Range> = match code_sections(buffer) {
+        Some(sections) => sections
+            .into_iter()
+            .map(|section| Range {
+                offset: section.offset,
+                size: section.size,
+                rva: Some(section.rva),
+            })
+            .collect(),
+        None => vec![Range {
+            offset: 0,
+            size: buffer.len(),
+            rva: None,
+        }],
+    };
+
+    let mut output = format!(
+        "# fs-patch report\n# sha256: {}\n# size: {}\n",
+        hash::hash_buffer(buffer, Algorithm::Sha256),
+        buffer.len()
+    );
+
+    for item in patterns {
+        output.push_str(&format!("\n# [{}.{:?}]\n", item.platform, item.patch_type));
+
+        for (name, pattern) in [("find", &item.find), ("replace", &item.replace)] {
+            let candidates = find_candidates(buffer, &ranges, pattern);
+
+            output.push_str(&format!("#   {}: {}\n", name, pattern));
+
+            if candidates.is_empty() {
+                output.push_str("#   no candidates\n");
+            }
+
+            for candidate in candidates.iter() {
+                write_candidate(&mut output, buffer, pattern, candidate, context);
+            }
+        }
+    }
+
+    output
+}
@@ -0,0 +1,143 @@
+//! `fs_utils` Python module, built with `maturin build --release` in this
+//! folder
+
+use std::path::PathBuf;
+
+use fs_lib::{
+    archive::ArchiveFs,
+    buffer::BufferExtension,
+    codec,
+    path::sanitize_entry_path,
+    shapes,
+    xml::{self, FormatOptions},
+};
+use pyo3::{create_exception, exceptions::PyException, prelude::*, types::PyBytes};
+
+create_exception!(fs_utils, FsError, PyException, "Error raised by fs_utils");
+
+fn to_py_err(e: anyhow::Error) -> PyErr {
+    FsError::new_err(format!("{:#}", e))
+}
+
+/// Read-only .gar/.dlc archive, entries are decrypted when read
+#[pyclass(unsendable)]
+struct Archive {
+    archive: ArchiveFs,
+}
+
+#[pymethods]
+impl Archive {
+    #[new]
+    fn new(path: PathBuf) -> PyResult<Self> {
+        let archive = ArchiveFs::open(path).map_err(to_py_err)?;
+
+        Ok(Self { archive })
+    }
+
+    /// Files inside a folder, "" is the root
+    #[pyo3(signature = (folder = "", recursive = true))]
+    fn list(&self, folder: &str, recursive: bool) -> Vec<String> {
+        self.archive
+            .list_files(folder, recursive)
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    /// Content of an entry, e.g. "dataS/scripts/main.lua"
+    fn read<'py>(&self, py: Python<'py>, entry: &str) -> PyResult<Bound<'py, PyBytes>> {
+        let buffer = self.archive.read(entry).map_err(to_py_err)?;
+
+        Ok(PyBytes::new(py, &buffer))
+    }
+
+    /// Extracts the files of a folder, returns the number of files
+    #[pyo3(signature = (output, folder = ""))]
+    fn extract(&self, output: PathBuf, folder: &str) -> PyResult<usize> {
+        let files = self.archive.list_files(folder, true);
+
+        for name in files.iter() {
+            let buffer = self.archive.read(name).map_err(to_py_err)?;
+            let file = output.join(sanitize_entry_path(name).map_err(to_py_err)?);
+
+            buffer.write_to_file(file).map_err(to_py_err)?;
+        }
+
+        Ok(files.len())
+    }
+}
+
+/// Decodes a LuaJIT (FS22) or Luau (FS25) .l64 file into plain bytecode
+#[pyfunction]
+fn decode_l64<'py>(py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyBytes>> {
+    let mut buffer = data.to_vec();
+
+    if codec::is_luajit(&buffer) {
+        if codec::is_luajit_encoded(&buffer) {
+            codec::decode_luajit(&mut buffer, &[]).map_err(to_py_err)?;
+        }
+    } else {
+        let Some(header) = codec::detect(&buffer, &[]) else {
+            return Err(FsError::new_err("Unsupported/unknown bytecode"));
+        };
+
+        codec::decode(&mut buffer, &header).map_err(to_py_err)?;
+    }
+
+    Ok(PyBytes::new(py, &buffer))
+}
+
+/// Formats XML like fs-xml-format
+#[pyfunction]
+#[pyo3(signature = (data, indent = "    ", escape = true, eol = "lf", wrap_attributes = None))]
+fn format_xml<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    indent: &str,
+    escape: bool,
+    eol: &str,
+    wrap_attributes: Option<&str>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let options = FormatOptions {
+        indent: indent.to_string(),
+        escape,
+        eol: eol.parse().map_err(FsError::new_err)?,
+        wrap_attributes: wrap_attributes
+            .map(str::parse)
+            .transpose()
+            .map_err(FsError::new_err)?,
+    };
+
+    let output = xml::format_xml(data, &options).map_err(to_py_err)?;
+
+    Ok(PyBytes::new(py, &output))
+}
+
+#[pyfunction]
+fn is_shapes_locked(data: &[u8]) -> PyResult<bool> {
+    shapes::is_locked(data).map_err(to_py_err)
+}
+
+/// Unlocks a .i3d.shapes file, unlocked files are returned unchanged
+#[pyfunction]
+fn unlock_shapes<'py>(py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyBytes>> {
+    let mut buffer = data.to_vec();
+
+    if shapes::is_locked(&buffer).map_err(to_py_err)? {
+        shapes::unlock(&mut buffer).map_err(to_py_err)?;
+    }
+
+    Ok(PyBytes::new(py, &buffer))
+}
+
+#[pymodule]
+fn fs_utils(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("FsError", m.py().get_type::<FsError>())?;
+    m.add_class::<Archive>()?;
+    m.add_function(wrap_pyfunction!(decode_l64, m)?)?;
+    m.add_function(wrap_pyfunction!(format_xml, m)?)?;
+    m.add_function(wrap_pyfunction!(is_shapes_locked, m)?)?;
+    m.add_function(wrap_pyfunction!(unlock_shapes, m)?)?;
+
+    Ok(())
+}
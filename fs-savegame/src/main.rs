@@ -0,0 +1,334 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    buffer::BufferExtension,
+    list_files_with_extension,
+    report::{self, Summary},
+    xml::{self, FormatOptions, Selector},
+};
+
+const CAREER_FILE_NAME: &str = "careerSavegame.xml";
+const FARMS_FILE_NAME: &str = "farms.xml";
+const ENVIRONMENT_FILE_NAME: &str = "environment.xml";
+
+/// Values shown by `list`, (label, selector) in careerSavegame.xml
+const SUMMARY: [(&str, &str); 5] = [
+    ("Name", "settings/savegameName"),
+    ("Map", "settings/mapTitle"),
+    ("Saved", "settings/saveDateFormatted"),
+    ("Money", "statistics/money"),
+    ("Play time", "statistics/playTime"),
+];
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Inspect, format and edit savegame XML files
+pub struct Cmd {
+    #[argh(subcommand)]
+    command: Command,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand)]
+enum Command {
+    List(ListCmd),
+    Print(PrintCmd),
+    Format(FormatCmd),
+    Set(SetCmd),
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Show savegame summary and XML files
+#[argh(subcommand, name = "list")]
+struct ListCmd {
+    /// path to savegame folder (e.g. savegame1)
+    #[argh(positional)]
+    savegame: PathBuf,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Print formatted XML file
+#[argh(subcommand, name = "print")]
+struct PrintCmd {
+    /// path to XML file
+    #[argh(positional)]
+    file: PathBuf,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Re-format XML files in place
+#[argh(subcommand, name = "format")]
+struct FormatCmd {
+    /// path to savegame folder or XML file
+    #[argh(positional)]
+    input: PathBuf,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Change values, modified files are backed up as .bak
+#[argh(subcommand, name = "set")]
+struct SetCmd {
+    /// farm money (farms.xml, careerSavegame.xml for farm 1)
+    #[argh(option)]
+    money: Option<f64>,
+
+    /// farm id used by --money (default 1)
+    #[argh(option, default = "1")]
+    farm: u32,
+
+    /// current day (environment.xml)
+    #[argh(option)]
+    day: Option<u32>,
+
+    /// any value as file:selector=value, e.g.
+    /// careerSavegame.xml:settings/savegameName=Farm or
+    /// farms.xml:farm[farmId=2]@loan=0
+    #[argh(option, from_str_fn(parse_value))]
+    value: Vec<Value>,
+
+    /// path to savegame folder (e.g. savegame1)
+    #[argh(positional)]
+    savegame: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Value {
+    file: String,
+    selector: Selector,
+    value: String,
+}
+
+fn parse_value(s: &str) -> Result<Value, String> {
+    let Some((file, rest)) = s.split_once(':') else {
+        return Err(format!("Missing file name: {}", s));
+    };
+
+    // First = outside of predicates like farm[farmId=1]
+    let mut depth = 0;
+    let Some(position) = rest.find(|c| {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
+        }
+        c == '=' && depth == 0
+    }) else {
+        return Err(format!("Missing value: {}", s));
+    };
+
+    let (selector, value) = (&rest[..position], &rest[position + 1..]);
+
+    Ok(Value {
+        file: file.to_string(),
+        selector: selector.parse()?,
+        value: value.to_string(),
+    })
+}
+
+fn list(cmd: &ListCmd, summary: &Summary) -> Result<()> {
+    let career_file = cmd.savegame.join(CAREER_FILE_NAME);
+
+    if !career_file.is_file() {
+        bail!(report::usage(format!(
+            "Not a savegame folder: {}",
+            cmd.savegame.display()
+        )))
+    }
+
+    let career = xml::parse(&Vec::read_from_file(&career_file)?)?;
+
+    for (label, path) in SUMMARY {
+        if let Some(value) = career.find(path).and_then(|element| element.text()) {
+            println!("{:<12}{}", label, value);
+        }
+    }
+
+    let farms_file = cmd.savegame.join(FARMS_FILE_NAME);
+
+    if farms_file.is_file() {
+        let farms = xml::parse(&Vec::read_from_file(&farms_file)?)?;
+
+        for farm in farms.children("farm") {
+            println!(
+                "{:<12}{}, money {}",
+                format!("Farm {}", farm.attr("farmId").unwrap_or("?")),
+                farm.attr("name").unwrap_or_default(),
+                farm.attr("money").unwrap_or("?"),
+            );
+        }
+    }
+
+    println!();
+
+    let mut files = list_files_with_extension(&cmd.savegame, "xml", false, false)?;
+    files.sort();
+
+    for file in files {
+        let size = std::fs::metadata(&file)?.len();
+        let root = match xml::parse(&Vec::read_from_file(&file)?) {
+            Ok(root) => format!("<{}>", root.name),
+            Err(e) => format!("invalid: {}", e),
+        };
+
+        println!(
+            "{:<32}{:>10}  {}",
+            file.file_name().unwrap_or_default().to_string_lossy(),
+            size,
+            root
+        );
+        summary.processed();
+    }
+
+    Ok(())
+}
+
+fn print(cmd: &PrintCmd, summary: &Summary) -> Result<()> {
+    let buffer = Vec::read_from_input(&cmd.file)?;
+
+    xml::format_xml(&buffer, &FormatOptions::default())?.write_to_output("-")?;
+    summary.processed();
+
+    Ok(())
+}
+
+fn format_file(file: &Path, summary: &Summary) -> Result<()> {
+    let buffer = Vec::read_from_file(file)?;
+    let output = xml::format_xml(&buffer, &FormatOptions::default())?;
+
+    if output == buffer {
+        summary.skipped();
+        return Ok(());
+    }
+
+    output.write_to_file(file)?;
+    println!("{}", file.display());
+    summary.processed();
+
+    Ok(())
+}
+
+fn format(cmd: &FormatCmd, summary: &Summary) -> Result<()> {
+    let mut files = match cmd.input.is_dir() {
+        true => list_files_with_extension(&cmd.input, "xml", false, false)?,
+        false => vec![cmd.input.clone()],
+    };
+    files.sort();
+
+    for file in files {
+        if let Err(e) = format_file(&file, summary) {
+            summary.error(file.display(), &e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies all values of one file, nothing is written unless every selector
+/// matched and the result parses
+fn set_file_values(file: &Path, values: &[&Value]) -> Result<()> {
+    let buffer = Vec::read_from_file(file)?;
+    let selectors: Vec<(Selector, String)> = values
+        .iter()
+        .map(|value| (value.selector.clone(), value.value.clone()))
+        .collect();
+
+    let (output, counts) = xml::set_values(&buffer, &FormatOptions::default(), &selectors)?;
+
+    for (value, count) in values.iter().zip(counts) {
+        if count == 0 {
+            bail!("{}: no match for {}", file.display(), value.selector)
+        }
+    }
+
+    xml::parse(&output)?;
+
+    let mut backup = file.as_os_str().to_owned();
+    backup.push(".bak");
+    buffer.write_to_file(PathBuf::from(backup))?;
+    output.write_to_file(file)?;
+
+    for value in values {
+        println!("{}: {} = {}", file.display(), value.selector, value.value);
+    }
+
+    Ok(())
+}
+
+fn set(cmd: &SetCmd, summary: &Summary) -> Result<()> {
+    let mut values = cmd.value.clone();
+
+    let mut add = |file: &str, selector: &str, value: String| -> Result<()> {
+        values.push(Value {
+            file: file.to_string(),
+            selector: selector.parse().map_err(anyhow::Error::msg)?,
+            value,
+        });
+
+        Ok(())
+    };
+
+    if let Some(money) = cmd.money {
+        add(
+            FARMS_FILE_NAME,
+            &format!("farm[farmId={}]@money", cmd.farm),
+            money.to_string(),
+        )?;
+
+        // Shown in the savegame list
+        if cmd.farm == 1 {
+            add(CAREER_FILE_NAME, "statistics/money", money.to_string())?;
+        }
+    }
+
+    if let Some(day) = cmd.day {
+        add(ENVIRONMENT_FILE_NAME, "currentDay", day.to_string())?;
+    }
+
+    if values.is_empty() {
+        bail!(report::usage(
+            "Nothing to set, use --money, --day or --value"
+        ))
+    }
+
+    let mut files: BTreeMap<&str, Vec<&Value>> = BTreeMap::new();
+
+    for value in values.iter() {
+        files.entry(&value.file).or_default().push(value);
+    }
+
+    for (file, values) in files {
+        let file = cmd.savegame.join(file);
+
+        if !file.is_file() {
+            bail!("File not found: {}", file.display())
+        }
+
+        set_file_values(&file, &values)?;
+        summary.processed();
+    }
+
+    Ok(())
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    match &cli.command {
+        Command::List(cmd) => list(cmd, &summary)?,
+        Command::Print(cmd) => print(cmd, &summary)?,
+        Command::Format(cmd) => format(cmd, &summary)?,
+        Command::Set(cmd) => set(cmd, &summary)?,
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
@@ -0,0 +1,239 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    buffer::BufferExtension,
+    list_files_with_extension,
+    path::PathExtension,
+    report::{self, Summary},
+};
+use serde::{Deserialize, Serialize};
+
+/// Folder inside the tree holding the install record and backups
+const INSTALL_DIR: &str = ".fs-script-install";
+const RECORD_FILE_NAME: &str = "record.json";
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Install compiled .l64 scripts into an unpacked game tree, originals are
+/// backed up and can be restored
+pub struct Cmd {
+    /// undo all installs in the tree
+    #[argh(switch)]
+    restore: bool,
+
+    /// path of the file inside the tree (single file input, optional)
+    #[argh(option, short = 't')]
+    target: Option<PathBuf>,
+
+    /// suppress output
+    #[argh(switch, short = 's')]
+    silent: bool,
+
+    /// enter directory symlinks and junctions of the tree and input folder
+    #[argh(switch)]
+    follow_symlinks: bool,
+
+    /// path to unpacked tree (e.g. dataS)
+    #[argh(positional)]
+    tree: PathBuf,
+
+    /// path to .l64 file or folder mirroring the tree layout
+    #[argh(positional)]
+    input: Option<PathBuf>,
+}
+
+/// Installed files by path relative to the tree, true if the original was
+/// backed up, false if the file was added
+#[derive(Serialize, Deserialize, Default)]
+struct InstallRecord {
+    files: BTreeMap<String, bool>,
+}
+
+impl InstallRecord {
+    fn path(tree: &Path) -> PathBuf {
+        tree.join(INSTALL_DIR).join(RECORD_FILE_NAME)
+    }
+
+    fn load(tree: &Path) -> Result<Self> {
+        let path = Self::path(tree);
+
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+
+        Ok(serde_json::from_slice(&Vec::read_from_file(&path)?)?)
+    }
+
+    fn save(&self, tree: &Path) -> Result<()> {
+        serde_json::to_vec_pretty(self)?.write_to_file(Self::path(tree))
+    }
+}
+
+fn relative_key(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+fn backup_path(tree: &Path, key: &str) -> PathBuf {
+    tree.join(INSTALL_DIR).join("backup").join(key)
+}
+
+/// Finds the single script in the tree with the same file name
+fn find_target(tree: &PathBuf, file: &Path, follow_symlinks: bool) -> Result<PathBuf> {
+    let matches: Vec<PathBuf> = list_files_with_extension(tree, "l64", true, follow_symlinks)?
+        .into_iter()
+        .filter(|path| path.file_name() == file.file_name())
+        .filter(|path| !path.starts_with(tree.join(INSTALL_DIR)))
+        .collect();
+
+    match matches.as_slice() {
+        [target] => tree.get_relative_path(target),
+        [] => bail!(
+            "{} not found in {}, use --target to set the path",
+            file.display(),
+            tree.display()
+        ),
+        _ => bail!(
+            "{} found {} times in {}, use --target to select one:\n{}",
+            file.display(),
+            matches.len(),
+            tree.display(),
+            matches
+                .iter()
+                .map(|path| format!("  {}", path.display()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+    }
+}
+
+/// Returns (file, path relative to tree) pairs
+fn plan_install(cli: &Cmd, input: &PathBuf) -> Result<Vec<(PathBuf, PathBuf)>> {
+    if input.is_dir() {
+        if cli.target.is_some() {
+            bail!(report::usage(
+                "--target is only supported for single file input"
+            ))
+        }
+
+        return list_files_with_extension(input, "l64", true, cli.follow_symlinks)?
+            .into_iter()
+            .map(|file| Ok((file.clone(), input.get_relative_path(&file)?)))
+            .collect();
+    }
+
+    if !input.is_file() {
+        bail!(report::usage(format!(
+            "Input not found: {}",
+            input.display()
+        )))
+    }
+
+    let target = match &cli.target {
+        Some(target) => target.clone(),
+        None => find_target(&cli.tree, input, cli.follow_symlinks)?,
+    };
+
+    Ok(vec![(input.clone(), target)])
+}
+
+fn install(cli: &Cmd, input: &PathBuf, summary: &Summary) -> Result<()> {
+    let files = plan_install(cli, input)?;
+    let mut record = InstallRecord::load(&cli.tree)?;
+
+    for (file, target) in files {
+        let key = relative_key(&target);
+        let target_file = cli.tree.join(&target);
+
+        // Keep the first backup when installing over a previous install
+        if !record.files.contains_key(&key) {
+            let has_original = target_file.is_file();
+
+            if has_original {
+                Vec::read_from_file(&target_file)?.write_to_file(backup_path(&cli.tree, &key))?;
+            }
+
+            record.files.insert(key.clone(), has_original);
+
+            // Record each file before changing the tree so a failure can
+            // still be restored
+            record.save(&cli.tree)?;
+        }
+
+        Vec::read_from_file(&file)?.write_to_file(&target_file)?;
+
+        if !cli.silent {
+            println!("{} -> {}", file.display(), target_file.display());
+        }
+
+        summary.processed();
+    }
+
+    Ok(())
+}
+
+fn restore(cli: &Cmd, summary: &Summary) -> Result<()> {
+    let record = InstallRecord::load(&cli.tree)?;
+
+    if record.files.is_empty() {
+        bail!("Nothing to restore in {}", cli.tree.display())
+    }
+
+    for (key, has_original) in record.files.iter() {
+        let target_file = cli.tree.join(key);
+
+        match has_original {
+            true => {
+                Vec::read_from_file(backup_path(&cli.tree, key))?.write_to_file(&target_file)?;
+
+                if !cli.silent {
+                    println!("Restored {}", target_file.display());
+                }
+            }
+            false => {
+                if target_file.is_file() {
+                    std::fs::remove_file(&target_file)?;
+                }
+
+                if !cli.silent {
+                    println!("Removed {}", target_file.display());
+                }
+            }
+        }
+
+        summary.processed();
+    }
+
+    std::fs::remove_dir_all(cli.tree.join(INSTALL_DIR))?;
+
+    Ok(())
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    if !cli.tree.is_dir() {
+        bail!(report::usage(format!(
+            "Tree folder not found: {}",
+            cli.tree.display()
+        )))
+    }
+
+    match (&cli.input, cli.restore) {
+        (None, true) => restore(&cli, &summary)?,
+        (Some(input), false) => install(&cli, input, &summary)?,
+        (Some(_), true) => bail!(report::usage("--restore doesn't take an input")),
+        (None, false) => bail!(report::usage("Missing input, or use --restore")),
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
@@ -1,11 +1,29 @@
-use std::path::{Path, PathBuf};
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
 /**
 fs-shapes-unlock [-r|--recursive] <file|folder> [<output>]
 */
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
 use argh::FromArgs;
-use fs_lib::{buffer::BufferExtension, list_files_with_extension, path::PathExtension};
+use fs_lib::{
+    batch::Batch,
+    buffer::BufferExtension,
+    cache::Cache,
+    list_input_files,
+    path::{OutputMapper, check_unique_outputs, is_glob_pattern},
+    report::{self, Summary, Timings},
+    shapes::{HEADER_SIZE, is_locked, unlock},
+    zip_input::ZipInput,
+};
+use rayon::{
+    ThreadPoolBuilder,
+    iter::{IntoParallelRefIterator, ParallelIterator},
+};
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// Unlock .i3d.shapes files
@@ -14,11 +32,35 @@ pub struct Cmd {
     #[argh(switch, short = 'r')]
     recursive: bool,
 
+    /// enter directory symlinks and junctions in recursive mode
+    #[argh(switch)]
+    follow_symlinks: bool,
+
     /// suppress output
     #[argh(switch, short = 's')]
     silent: bool,
 
-    /// path to input file/folder
+    /// set thread pool size when processing folders (0 = auto)
+    #[argh(option, default = "0")]
+    num_threads: u8,
+
+    /// print the duration of each stage
+    #[argh(switch)]
+    timings: bool,
+
+    /// process all files, even if unchanged since previous run
+    #[argh(switch, short = 'f')]
+    force: bool,
+
+    /// print planned input -> output mapping without processing files
+    #[argh(switch)]
+    dry_run: bool,
+
+    /// output file pattern, e.g. "{dir}/{stem}_unlocked.{ext}"
+    #[argh(option)]
+    output_pattern: Option<String>,
+
+    /// path to input file/folder or glob pattern
     #[argh(positional)]
     input: PathBuf,
 
@@ -27,87 +69,168 @@ pub struct Cmd {
     output: Option<PathBuf>,
 }
 
+/// Reads only the header first, so unlocked files are skipped without
+/// loading them
 fn unlock_shapes_file<P: AsRef<Path>>(file: P, output_file: P) -> Result<()> {
-    let mut buffer = Vec::read_from_file(&file)?;
+    let mut header = [0; HEADER_SIZE];
+    File::open(&file)?
+        .read_exact(&mut header)
+        .context("Truncated header")?;
 
-    if !is_locked(&buffer)? {
+    if !is_locked(&header)? {
         return Ok(());
     }
 
+    let mut buffer = Vec::read_from_file(&file)?;
+
     unlock(&mut buffer)?;
 
     buffer.write_to_file(&output_file)
 }
 
-fn is_locked(buffer: &Vec<u8>) -> Result<bool> {
-    match buffer[0] {
-        0x05 | 0x06 | 0x07 | 0x09 | 0x0A => Ok(buffer[1] != 0 || buffer[3] != 0),
-        0x00 | 0x01 => Ok(buffer[2] != 0),
-        _ => bail!("Unknown format"),
+/// Unlocks a single file input, returns the lines to print
+fn process_file_input(cli: &Cmd) -> Result<Vec<String>> {
+    let output: PathBuf = match (cli.output.clone(), &cli.output_pattern) {
+        (Some(output), _) => output.components().collect(),
+        (None, Some(_)) => {
+            OutputMapper::beside(&cli.input, cli.output_pattern.clone())?.map(&cli.input, None)?
+        }
+        (None, None) => cli.input.components().collect(),
+    };
+
+    if cli.dry_run {
+        return Ok(vec![format!(
+            "{} -> {}",
+            cli.input.display(),
+            output.display()
+        )]);
+    }
+
+    unlock_shapes_file(&cli.input, &output)?;
+
+    match cli.silent {
+        true => Ok(Vec::new()),
+        false => Ok(vec![output.display().to_string()]),
+    }
+}
+
+/// Runs one `--jobs-from` job, jobs take a single file
+fn process_job(cli: Cmd) -> Result<Vec<String>> {
+    if cli.input.is_dir() || is_glob_pattern(&cli.input) {
+        bail!("Job input must be a file")
     }
+
+    process_file_input(&cli)
 }
 
-fn unlock(buffer: &mut Vec<u8>) -> Result<()> {
-    match buffer[0] {
-        0x05 | 0x06 | 0x07 | 0x09 | 0x0A => {
-            // FS22, FS25 (0x0A, 0x06, 0x09)
-            buffer[1] = 0;
-            buffer[2] = buffer[2].wrapping_sub(0x0D);
-            buffer[3] = 0;
-        }
-        0x00 | 0x01 => {
-            // Legacy
-            buffer[0] = 0;
-            buffer[1] = buffer[1].wrapping_sub(0x0D);
-            buffer[2] = 0;
-        }
-        _ => bail!("Unknown format"),
+fn run() -> Result<Summary> {
+    if let Some(batch) = Batch::from_env()? {
+        return batch.run(process_job);
+    }
+
+    let mut cli: Cmd = fs_lib::config::from_env();
+    let mut timings = Timings::new(cli.timings);
+    let zip_input = ZipInput::open(&mut cli.input, &mut cli.output)?;
+
+    if zip_input.is_some() {
+        timings.stage("extract zip");
     }
 
-    Ok(())
+    let summary = process(cli, &mut timings)?;
+    timings.stage("process");
+
+    if let Some(zip_input) = zip_input {
+        zip_input.finish()?;
+        timings.stage("repack zip");
+    }
+
+    timings.print();
+
+    Ok(summary)
 }
 
-fn main() -> Result<()> {
-    let cli: Cmd = argh::from_env();
+/// Processes a file, folder or glob input, zip inputs are extracted folders
+fn process(cli: Cmd, timings: &mut Timings) -> Result<Summary> {
+    let summary = Summary::default();
+
+    if cli.input.is_dir() || is_glob_pattern(&cli.input) {
+        let (input_path, files) =
+            list_input_files(&cli.input, r"shapes", cli.recursive, cli.follow_symlinks)?;
+        timings.stage("list files");
 
-    if cli.input.is_dir() {
-        let output_path = cli.output.unwrap_or_else(|| cli.input.clone());
+        let output_path = cli.output.unwrap_or_else(|| input_path.clone());
 
         if output_path.is_file() {
-            bail!("Output path is a file")
+            bail!(report::usage("Output path is a file"))
         }
 
-        let files = list_files_with_extension(&cli.input, r"shapes", cli.recursive)?;
+        ThreadPoolBuilder::new()
+            .num_threads(cli.num_threads.into())
+            .build_global()
+            .unwrap();
+
+        let cache = Cache::load(&output_path, "fs-shapes-unlock", "", cli.force);
 
-        for file in files.iter() {
-            let output_file: PathBuf = file
-                .convert_relative_path(&cli.input, &output_path)?
-                .components()
-                .collect();
+        let mapper = OutputMapper::new(&input_path, &output_path, cli.output_pattern.clone())?;
 
-            if !cli.silent {
-                if output_file != *file {
-                    println!("{} -> {}", file.display(), output_file.display());
-                } else {
-                    println!("{}", file.display());
-                }
+        check_unique_outputs(&files, |file| mapper.map(file, None))?;
+
+        let process_entry = |file: &PathBuf| -> Result<Vec<String>> {
+            let output_file = mapper.map(file, None)?;
+
+            if cli.dry_run {
+                summary.processed();
+                return Ok(vec![format!(
+                    "{} -> {}",
+                    file.display(),
+                    output_file.display()
+                )]);
             }
 
-            unlock_shapes_file(&file, &&output_file)?;
-        }
-    } else {
-        let output: PathBuf = cli
-            .output
-            .unwrap_or(cli.input.clone())
-            .components()
+            if cache.is_fresh(file, &output_file)? {
+                summary.skipped();
+                return Ok(vec![]);
+            }
+
+            unlock_shapes_file(file, &output_file)?;
+            cache.update(file, &output_file)?;
+            summary.processed();
+
+            if cli.silent {
+                return Ok(vec![]);
+            }
+
+            match output_file != *file {
+                true => Ok(vec![format!(
+                    "{} -> {}",
+                    file.display(),
+                    output_file.display()
+                )]),
+                false => Ok(vec![file.display().to_string()]),
+            }
+        };
+
+        let outputs: Vec<_> = files
+            .par_iter()
+            .map(|file| (file.display().to_string(), process_entry(file)))
             .collect();
 
-        unlock_shapes_file(&cli.input, &output)?;
+        summary.print_outputs(outputs);
 
-        if !cli.silent {
-            println!("{}", output.display());
+        if !cli.dry_run {
+            cache.save()?;
         }
+    } else {
+        for line in process_file_input(&cli)? {
+            println!("{}", line);
+        }
+
+        summary.processed();
     }
 
-    Ok(())
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
 }
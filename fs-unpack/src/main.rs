@@ -1,12 +1,20 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use anyhow::{Result, bail};
 use argh::FromArgs;
-use fs_lib::buffer::BufferExtension;
+use fs_lib::{
+    buffer::BufferExtension,
+    path::sanitize_entry_path,
+    report::{self, Summary},
+};
 use gar_lib::GarArchive;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
+/// Written to the output folder when entries fail to extract
+const ERROR_LOG_NAME: &str = "extract-errors.log";
+
 #[derive(FromArgs, PartialEq, Debug)]
 /// Extract .gar/.dlc archive
 struct Cmd {
@@ -14,6 +22,10 @@ struct Cmd {
     #[argh(switch, short = 's')]
     silent: bool,
 
+    /// only extract the entries listed in an extract-errors.log
+    #[argh(option)]
+    retry_failed: Option<PathBuf>,
+
     /// path to .gar/.dlc archive
     #[argh(positional)]
     input: PathBuf,
@@ -23,31 +35,102 @@ struct Cmd {
     output_path: PathBuf,
 }
 
-fn main() -> Result<()> {
-    let cli: Cmd = argh::from_env();
+/// Entry names of an error log, one `<entry>\t<index>\t<reason>` line per
+/// failed entry
+fn read_error_log(file: &Path) -> Result<Vec<String>> {
+    let log = String::from_utf8(Vec::read_from_file(file)?)?;
+
+    Ok(log
+        .lines()
+        .filter_map(|line| line.split('\t').next())
+        .filter(|entry| !entry.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Writes failed entries to the error log with their position in the
+/// archive's file table, or removes a previous log when everything was
+/// extracted
+fn write_error_log(file: &Path, failures: &[(&str, usize, String)]) -> Result<()> {
+    if failures.is_empty() {
+        if file.is_file() {
+            std::fs::remove_file(file)?;
+        }
+
+        return Ok(());
+    }
+
+    let log: String = failures
+        .iter()
+        .map(|(entry, index, reason)| {
+            format!(
+                "{}\t{}\t{}\n",
+                entry,
+                index,
+                reason.replace(['\t', '\n'], " ")
+            )
+        })
+        .collect();
+
+    log.into_bytes().write_to_file(file)
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
 
     let archive = GarArchive::open(&cli.input)
         .map_err(|e| anyhow::anyhow!("{}", e))?;
 
-    let file_count = archive.len();
+    let entry_count = archive.len();
 
-    if file_count == 0 {
+    if entry_count == 0 {
         bail!("No files found in archive");
     }
 
+    let retry = match &cli.retry_failed {
+        Some(log) => Some(read_error_log(log)?),
+        None => None,
+    };
+
+    let files: Vec<(usize, &str)> = archive
+        .files()
+        .enumerate()
+        .filter(|(_, file)| {
+            retry
+                .as_ref()
+                .is_none_or(|entries| entries.iter().any(|e| e == file))
+        })
+        .collect();
+
+    if let Some(entries) = &retry {
+        for entry in entries
+            .iter()
+            .filter(|entry| !files.iter().any(|(_, file)| file == entry))
+        {
+            eprintln!("{}: not found in archive", entry);
+            summary.skipped();
+        }
+    }
+
+    let file_count = files.len();
+
     if !cli.silent {
         println!("Extracting {} files from {}", file_count, cli.input.display());
     }
 
-    let files: Vec<_> = archive.files().collect();
     let extracted = AtomicUsize::new(0);
 
-    files.into_par_iter().try_for_each(|file_name| -> Result<()> {
+    let extract_file = |file_name: &str| -> Result<()> {
+        let file_path: PathBuf = cli
+            .output_path
+            .join(sanitize_entry_path(file_name)?)
+            .components()
+            .collect();
+
         let data = archive.read_file(file_name)
             .map_err(|e| anyhow::anyhow!("{}", e))?;
 
-        let file_path: PathBuf = cli.output_path.join(file_name).components().collect();
-
         data.write_to_file(&file_path)?;
 
         let count = extracted.fetch_add(1, Ordering::Relaxed) + 1;
@@ -56,11 +139,37 @@ fn main() -> Result<()> {
         }
 
         Ok(())
-    })?;
+    };
 
-    if !cli.silent {
-        println!("Done. Extracted {} files.", file_count);
+    let results: Vec<(usize, &str, Result<()>)> = files
+        .into_par_iter()
+        .map(|(index, file_name)| (index, file_name, extract_file(file_name)))
+        .collect();
+
+    let mut failures = Vec::new();
+
+    for (index, file_name, result) in results {
+        summary.add(file_name, &result);
+
+        if let Err(e) = result {
+            failures.push((file_name, index, format!("{:#}", e)));
+        }
+    }
+
+    let log_file = cli.output_path.join(ERROR_LOG_NAME);
+    write_error_log(&log_file, &failures)?;
+
+    if !failures.is_empty() {
+        eprintln!(
+            "{} entries failed, see {} (retry with --retry-failed)",
+            failures.len(),
+            log_file.display()
+        );
     }
 
-    Ok(())
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
 }
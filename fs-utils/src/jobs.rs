@@ -0,0 +1,353 @@
+use std::{
+    collections::BTreeMap,
+    env::consts::EXE_SUFFIX,
+    io::{BufRead, BufReader, Read},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use fs_lib::try_get_command_path;
+use serde_json::{Map, Value, json};
+
+use crate::rpc::{INTERNAL_ERROR, METHOD_NOT_FOUND, RpcError, Writer, notify};
+
+/// Interval of exit checks after a tool closed its output
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Tools that can be started, other programs are never run
+pub const TOOLS: [&str; 24] = [
+    "fs-archive-grep",
+    "fs-decompile",
+    "fs-grep",
+    "fs-hash",
+    "fs-i3d-lint",
+    "fs-l10n",
+    "fs-l64-diff",
+    "fs-l64-info",
+    "fs-log",
+    "fs-luajit-decompile",
+    "fs-luau-compile",
+    "fs-luau-decompile",
+    "fs-luau-diff",
+    "fs-mod-analyze",
+    "fs-mod-unlock",
+    "fs-moddesc-lint",
+    "fs-savegame",
+    "fs-shapes-unlock",
+    "fs-unpack",
+    "fs-verify-scripts",
+    "fs-xml-convert",
+    "fs-xml-flatten",
+    "fs-xml-format",
+    "fs-xml-validate",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Running,
+    /// Exit code 0
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl State {
+    fn name(&self) -> &'static str {
+        match self {
+            State::Running => "running",
+            State::Finished => "finished",
+            State::Failed => "failed",
+            State::Cancelled => "cancelled",
+        }
+    }
+}
+
+struct Job {
+    tool: String,
+    args: Vec<String>,
+    state: State,
+    /// Lines printed to stdout, one per processed file for most tools
+    progress: usize,
+    /// Lines printed to stderr, except the summary
+    errors: Vec<String>,
+    summary: Option<String>,
+    exit_code: Option<i32>,
+    child: Arc<Mutex<Child>>,
+}
+
+impl Job {
+    fn to_json(&self, id: u64) -> Value {
+        json!({
+            "job": id,
+            "tool": self.tool,
+            "args": self.args,
+            "state": self.state.name(),
+            "progress": self.progress,
+            "errors": self.errors,
+            "summary": self.summary,
+            "exitCode": self.exit_code,
+        })
+    }
+}
+
+/// Jobs of all clients, a job keeps running when its client disconnects
+#[derive(Default)]
+pub struct Jobs {
+    next_id: AtomicU64,
+    jobs: Mutex<BTreeMap<u64, Job>>,
+}
+
+/// `{"recursive": true, "indent_size": 2}` -> `--recursive --indent-size 2`,
+/// arrays repeat the option and false/null leave it out
+fn option_args(options: &Map<String, Value>) -> Result<Vec<String>, RpcError> {
+    let mut args = Vec::new();
+
+    for (key, value) in options {
+        let flag = format!("--{}", key.replace('_', "-"));
+
+        let values = match value {
+            Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+
+        for value in values {
+            match value {
+                Value::Bool(true) => args.push(flag.clone()),
+                Value::Bool(false) | Value::Null => {}
+                Value::String(value) => args.extend([flag.clone(), value.clone()]),
+                Value::Number(value) => args.extend([flag.clone(), value.to_string()]),
+                _ => {
+                    return Err(RpcError::invalid_params(format!(
+                        "Option {} must be a boolean, number, string or array",
+                        key
+                    )));
+                }
+            }
+        }
+    }
+
+    Ok(args)
+}
+
+fn string_args(value: Option<&Value>) -> Result<Vec<String>, RpcError> {
+    let Some(value) = value else {
+        return Ok(Vec::new());
+    };
+
+    value
+        .as_array()
+        .and_then(|values| {
+            values
+                .iter()
+                .map(|value| value.as_str().map(String::from))
+                .collect()
+        })
+        .ok_or_else(|| RpcError::invalid_params("args must be an array of strings"))
+}
+
+fn job_id(params: &Value) -> Result<u64, RpcError> {
+    params
+        .get("job")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| RpcError::invalid_params("Missing job id"))
+}
+
+fn unknown_job(id: u64) -> RpcError {
+    RpcError::invalid_params(format!("Unknown job: {}", id))
+}
+
+impl Jobs {
+    pub fn call(
+        self: &Arc<Self>,
+        method: &str,
+        params: &Value,
+        writer: &Writer,
+    ) -> Result<Value, RpcError> {
+        match method {
+            "tools.list" => Ok(json!(TOOLS)),
+            "job.start" => self.start(params, writer),
+            "job.status" => self.status(job_id(params)?),
+            "job.list" => Ok(self.list()),
+            "job.cancel" => self.cancel(job_id(params)?),
+            method => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method: {}", method),
+            )),
+        }
+    }
+
+    /// `{"tool": "fs-xml-format", "options": {"recursive": true}, "args": ["mod/"]}`,
+    /// options are passed before the positional args
+    fn start(self: &Arc<Self>, params: &Value, writer: &Writer) -> Result<Value, RpcError> {
+        let Some(tool) = params.get("tool").and_then(Value::as_str) else {
+            return Err(RpcError::invalid_params("Missing tool"));
+        };
+
+        if !TOOLS.contains(&tool) {
+            return Err(RpcError::invalid_params(format!("Unknown tool: {}", tool)));
+        }
+
+        let mut args = match params.get("options") {
+            Some(Value::Object(options)) => option_args(options)?,
+            Some(_) => return Err(RpcError::invalid_params("options must be an object")),
+            None => Vec::new(),
+        };
+
+        args.extend(string_args(params.get("args"))?);
+
+        let internal = |message: String| RpcError::new(INTERNAL_ERROR, message);
+        let file_path: PathBuf = try_get_command_path(&format!("{}{}", tool, EXE_SUFFIX))
+            .map_err(|e| internal(format!("{:#}", e)))?
+            .components()
+            .collect();
+
+        let mut child = Command::new(file_path)
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| internal(format!("Failed to start {}: {}", tool, e)))?;
+
+        let stdout = child.stdout.take().unwrap();
+        let stderr = child.stderr.take().unwrap();
+        let child = Arc::new(Mutex::new(child));
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+
+        self.jobs.lock().unwrap().insert(
+            id,
+            Job {
+                tool: tool.to_string(),
+                args,
+                state: State::Running,
+                progress: 0,
+                errors: Vec::new(),
+                summary: None,
+                exit_code: None,
+                child: child.clone(),
+            },
+        );
+
+        let jobs = self.clone();
+        let writer = writer.clone();
+
+        thread::spawn(move || jobs.watch(id, stdout, stderr, child, writer));
+
+        Ok(json!({ "job": id }))
+    }
+
+    fn update<F: FnOnce(&mut Job)>(&self, id: u64, f: F) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            f(job);
+        }
+    }
+
+    /// Sends stdout lines as `job.progress` and stderr lines as `job.error`
+    /// until the tool exits, then `job.finished`
+    fn watch<O, E>(
+        self: Arc<Self>,
+        id: u64,
+        stdout: O,
+        stderr: E,
+        child: Arc<Mutex<Child>>,
+        writer: Writer,
+    ) where
+        O: Read + Send + 'static,
+        E: Read + Send + 'static,
+    {
+        let errors = {
+            let jobs = self.clone();
+            let writer = writer.clone();
+
+            thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    if line.starts_with("Summary: ") {
+                        jobs.update(id, |job| job.summary = Some(line));
+                        continue;
+                    }
+
+                    jobs.update(id, |job| job.errors.push(line.clone()));
+                    notify(&writer, "job.error", json!({ "job": id, "message": line }));
+                }
+            })
+        };
+
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let mut count = 0;
+
+            self.update(id, |job| {
+                job.progress += 1;
+                count = job.progress;
+            });
+
+            notify(
+                &writer,
+                "job.progress",
+                json!({ "job": id, "line": line, "count": count }),
+            );
+        }
+
+        let _ = errors.join();
+
+        // Polled so job.cancel can take the lock to kill the tool meanwhile
+        let exit_code = loop {
+            match child.lock().unwrap().try_wait() {
+                Ok(Some(status)) => break status.code(),
+                Ok(None) => {}
+                Err(_) => break None,
+            }
+
+            thread::sleep(EXIT_POLL_INTERVAL);
+        };
+        let mut result = Value::Null;
+
+        self.update(id, |job| {
+            job.exit_code = exit_code;
+
+            if job.state == State::Running {
+                job.state = match exit_code {
+                    Some(0) => State::Finished,
+                    _ => State::Failed,
+                };
+            }
+
+            result = job.to_json(id);
+        });
+
+        notify(&writer, "job.finished", result);
+    }
+
+    fn status(&self, id: u64) -> Result<Value, RpcError> {
+        match self.jobs.lock().unwrap().get(&id) {
+            Some(job) => Ok(job.to_json(id)),
+            None => Err(unknown_job(id)),
+        }
+    }
+
+    fn list(&self) -> Value {
+        let jobs = self.jobs.lock().unwrap();
+
+        Value::Array(jobs.iter().map(|(id, job)| job.to_json(*id)).collect())
+    }
+
+    /// Kills the tool, `job.finished` follows once it exited
+    fn cancel(&self, id: u64) -> Result<Value, RpcError> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(&id).ok_or_else(|| unknown_job(id))?;
+
+        if job.state != State::Running {
+            return Ok(json!({ "cancelled": false }));
+        }
+
+        let _ = job.child.lock().unwrap().kill();
+        job.state = State::Cancelled;
+
+        Ok(json!({ "cancelled": true }))
+    }
+}
@@ -0,0 +1,119 @@
+use std::{
+    hash::{BuildHasher, Hasher, RandomState},
+    io::{self, BufReader},
+    net::{Ipv4Addr, TcpListener},
+    process::{self, ExitCode},
+    sync::{Arc, Mutex},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use argh::FromArgs;
+use fs_lib::report::{self, Summary};
+
+use crate::{jobs::Jobs, rpc::Writer};
+
+mod jobs;
+mod rpc;
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Drive the fs-utils tools from GUI frontends and editors
+pub struct Cmd {
+    #[argh(subcommand)]
+    command: Command,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand)]
+enum Command {
+    Serve(ServeCmd),
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Serve JSON-RPC 2.0 requests, one message per line, on a local port or
+/// stdin/stdout
+#[argh(subcommand, name = "serve")]
+struct ServeCmd {
+    /// port on 127.0.0.1 (default 7435)
+    #[argh(option, default = "7435")]
+    port: u16,
+
+    /// serve one client on stdin/stdout instead of a port
+    #[argh(switch)]
+    stdio: bool,
+}
+
+/// Random token that port clients send with `auth` first, so a web page
+/// can't start tools through a request to the local port. RandomState keys
+/// are seeded by the OS.
+fn session_token() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+
+    (0..2)
+        .map(|i| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(nanos);
+            hasher.write_u32(process::id());
+            hasher.write_u8(i);
+            format!("{:016x}", hasher.finish())
+        })
+        .collect()
+}
+
+fn serve(cmd: &ServeCmd) -> Result<()> {
+    let jobs = Arc::new(Jobs::default());
+
+    if cmd.stdio {
+        let writer: Writer = Arc::new(Mutex::new(Box::new(io::stdout())));
+
+        rpc::serve(io::stdin().lock(), writer, jobs, None);
+        return Ok(());
+    }
+
+    // Only local clients, jobs run tools on any path they're given
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, cmd.port))
+        .with_context(|| format!("Failed to listen on port {}", cmd.port))?;
+
+    let token: Arc<str> = session_token().into();
+
+    eprintln!("Listening on {}", listener.local_addr()?);
+    eprintln!("Token: {}", token);
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Connection failed: {}", e);
+                continue;
+            }
+        };
+
+        let reader = BufReader::new(stream.try_clone()?);
+        let writer: Writer = Arc::new(Mutex::new(Box::new(stream)));
+        let jobs = jobs.clone();
+        let token = token.clone();
+
+        thread::spawn(move || rpc::serve(reader, writer, jobs, Some(&token)));
+    }
+
+    Ok(())
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    match &cli.command {
+        Command::Serve(cmd) => serve(cmd)?,
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
@@ -0,0 +1,171 @@
+use std::{
+    io::{BufRead, Write},
+    sync::{Arc, Mutex},
+};
+
+use serde_json::{Value, json};
+
+use crate::jobs::Jobs;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+/// First request of a connection without the session token
+pub const UNAUTHORIZED: i64 = -32001;
+
+/// Connection messages are written to, shared with the job threads
+pub type Writer = Arc<Mutex<Box<dyn Write + Send>>>;
+
+#[derive(Debug)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new<S: Into<String>>(code: i64, message: S) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_params<S: Into<String>>(message: S) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+}
+
+/// Writes one message per line, a closed connection is ignored
+fn send(writer: &Writer, message: &Value) {
+    let mut writer = writer.lock().unwrap();
+
+    let _ = writeln!(writer, "{}", message);
+    let _ = writer.flush();
+}
+
+pub fn notify(writer: &Writer, method: &str, params: Value) {
+    send(
+        writer,
+        &json!({ "jsonrpc": "2.0", "method": method, "params": params }),
+    );
+}
+
+fn respond(writer: &Writer, id: Value, result: Result<Value, RpcError>) {
+    let message = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": e.code, "message": e.message },
+        }),
+    };
+
+    send(writer, &message);
+}
+
+fn handle(
+    request: &Value,
+    writer: &Writer,
+    jobs: &Arc<Jobs>,
+) -> Option<(Value, Result<Value, RpcError>)> {
+    // Requests without id are notifications and get no response
+    let id = request.get("id").cloned();
+
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        let error = RpcError::new(INVALID_REQUEST, "Missing method");
+        return Some((id.unwrap_or(Value::Null), Err(error)));
+    };
+
+    let params = request.get("params").cloned().unwrap_or(json!({}));
+    let result = jobs.call(method, &params, writer);
+
+    id.map(|id| (id, result))
+}
+
+/// `POST / HTTP/1.1`, sent by a browser to a local port
+fn is_http_request_line(line: &str) -> bool {
+    let mut parts = line.split_whitespace();
+
+    matches!(
+        (parts.next(), parts.next(), parts.next()),
+        (Some(method), Some(_), Some(version))
+            if method.bytes().all(|b| b.is_ascii_uppercase()) && version.starts_with("HTTP/")
+    )
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Checks the `auth` request a connection has to start with
+fn authenticate(request: &Value, token: &str) -> Result<Value, RpcError> {
+    let is_auth = request.get("method").and_then(Value::as_str) == Some("auth");
+    let given = request
+        .get("params")
+        .and_then(|params| params.get("token"))
+        .and_then(Value::as_str);
+
+    match given {
+        Some(given) if is_auth && constant_time_eq(given, token) => Ok(json!(true)),
+        _ => Err(RpcError::new(
+            UNAUTHORIZED,
+            "The first request must be auth with the session token",
+        )),
+    }
+}
+
+/// Serves requests of one client until it disconnects. With a `token` the
+/// first request has to be `auth {token}`. The connection is closed on
+/// anything that isn't JSON, e.g. a web page posting to the port.
+pub fn serve<R: BufRead>(reader: R, writer: Writer, jobs: Arc<Jobs>, token: Option<&str>) {
+    let mut is_authenticated = token.is_none();
+
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if is_http_request_line(&line) {
+            break;
+        }
+
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                respond(
+                    &writer,
+                    Value::Null,
+                    Err(RpcError::new(PARSE_ERROR, e.to_string())),
+                );
+                break;
+            }
+        };
+
+        if !is_authenticated {
+            let id = request.get("id").cloned().unwrap_or(Value::Null);
+            let result = authenticate(&request, token.unwrap_or_default());
+
+            is_authenticated = result.is_ok();
+            respond(&writer, id, result);
+
+            match is_authenticated {
+                true => continue,
+                false => break,
+            }
+        }
+
+        if let Some((id, result)) = handle(&request, &writer, &jobs) {
+            respond(&writer, id, result);
+        }
+    }
+}
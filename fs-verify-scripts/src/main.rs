@@ -0,0 +1,184 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    archive::ArchiveFs,
+    hash::{self, Algorithm},
+    list_files,
+    report::{self, Summary},
+};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// Extensions compared when no --extension is given
+const DEFAULT_EXTENSIONS: [&str; 2] = ["l64", "lua"];
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Compare scripts of an unpacked folder against the entries of the original
+/// .gar/.dlc archive
+pub struct Cmd {
+    /// archive folder that matches the unpacked folder, e.g. dataS/scripts
+    /// (default archive root)
+    #[argh(option, short = 'p', default = "String::new()")]
+    prefix: String,
+
+    /// file extension to compare, can be repeated (default l64, lua)
+    #[argh(option, short = 'e')]
+    extension: Vec<String>,
+
+    /// only print modified, missing and extra files
+    #[argh(switch, short = 'q')]
+    quiet: bool,
+
+    /// enter directory symlinks and junctions of the unpacked folder
+    #[argh(switch)]
+    follow_symlinks: bool,
+
+    /// path to original .gar/.dlc archive
+    #[argh(positional)]
+    archive: PathBuf,
+
+    /// path to unpacked folder
+    #[argh(positional)]
+    input: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Modified,
+    /// Only the decompiled .lua exists, bytecode can't be compared with it
+    Decompiled,
+    Missing,
+}
+
+impl Cmd {
+    fn is_script(&self, name: &str) -> bool {
+        let matches = |ext: &str| name.ends_with(&format!(".{}", ext));
+
+        match self.extension.is_empty() {
+            true => DEFAULT_EXTENSIONS.iter().any(|ext| matches(ext)),
+            false => self.extension.iter().any(|ext| matches(ext)),
+        }
+    }
+}
+
+/// Compares an archive entry with the file of the same path in the folder
+fn verify_entry(archive: &ArchiveFs, entry: &str, file: &Path) -> Result<Status> {
+    if !file.is_file() {
+        return match file.with_extension("lua").is_file() {
+            true if entry.ends_with(".l64") => Ok(Status::Decompiled),
+            _ => Ok(Status::Missing),
+        };
+    }
+
+    let expected = hash::hash_buffer(&archive.read(entry)?, Algorithm::Sha256);
+
+    match hash::sha256_file(file)? == expected {
+        true => Ok(Status::Ok),
+        false => Ok(Status::Modified),
+    }
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    if !cli.input.is_dir() {
+        bail!(report::usage(format!(
+            "Unpacked folder not found: {}",
+            cli.input.display()
+        )))
+    }
+
+    let archive = ArchiveFs::open(&cli.archive)?;
+
+    if !cli.prefix.is_empty() && !archive.is_dir(&cli.prefix) {
+        bail!(report::usage(format!(
+            "{}: folder {} not found in archive",
+            cli.archive.display(),
+            cli.prefix
+        )))
+    }
+
+    // `\dataS\scripts\` -> `dataS/scripts/`
+    let prefix: String = cli
+        .prefix
+        .split(['/', '\\'])
+        .filter(|part| !part.is_empty())
+        .map(|part| format!("{}/", part))
+        .collect();
+
+    let entries: Vec<&str> = archive
+        .list_files(&prefix, true)
+        .into_iter()
+        .filter(|entry| cli.is_script(entry))
+        .collect();
+
+    let results: Vec<(&str, Result<Status>)> = entries
+        .into_par_iter()
+        .map(|entry| {
+            let file = cli.input.join(&entry[prefix.len()..]);
+
+            (entry, verify_entry(&archive, entry, &file))
+        })
+        .collect();
+
+    let mut known = BTreeSet::new();
+
+    for (entry, result) in results {
+        let relative = &entry[prefix.len()..];
+
+        known.insert(relative.to_string());
+
+        match result {
+            Ok(Status::Ok) => {
+                if !cli.quiet {
+                    println!("{}: OK", relative);
+                }
+
+                summary.processed();
+            }
+            Ok(Status::Modified) => {
+                println!("{}: MODIFIED", relative);
+                summary.failed();
+            }
+            Ok(Status::Decompiled) => {
+                if !cli.quiet {
+                    println!("{}: DECOMPILED", relative);
+                }
+
+                known.insert(format!("{}.lua", relative.trim_end_matches(".l64")));
+                summary.skipped();
+            }
+            Ok(Status::Missing) => {
+                println!("{}: MISSING", relative);
+                summary.failed();
+            }
+            Err(e) => summary.error(relative, &e),
+        }
+    }
+
+    // Scripts added to the folder, e.g. leftovers of a patch
+    for file in list_files(&cli.input, true, cli.follow_symlinks)? {
+        let relative = file
+            .strip_prefix(&cli.input)?
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        if cli.is_script(&relative) && !known.contains(&relative) {
+            println!("{}: EXTRA", relative);
+            summary.skipped();
+        }
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
@@ -0,0 +1,203 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    str::FromStr,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    buffer::BufferExtension,
+    list_input_files,
+    path::{OutputMapper, check_unique_outputs, is_glob_pattern, is_stdio},
+    report::{self, Summary},
+    xml::{self, Document, FormatOptions},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Xml,
+    Json,
+    Yaml,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "xml" => Ok(Format::Xml),
+            "json" => Ok(Format::Json),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            _ => Err(format!("Unknown format: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl Format {
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Xml => "xml",
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+        }
+    }
+
+    /// Format of a file by extension, stdin and unknown extensions are XML
+    fn from_path(path: &Path) -> Format {
+        path.extension()
+            .and_then(|ext| ext.to_str()?.parse().ok())
+            .unwrap_or(Format::Xml)
+    }
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Convert XML to JSON/YAML and back, keeping attribute order, comments and
+/// CDATA
+pub struct Cmd {
+    /// recursive mode if folder input
+    #[argh(switch, short = 'r')]
+    recursive: bool,
+
+    /// enter directory symlinks and junctions in recursive mode
+    #[argh(switch)]
+    follow_symlinks: bool,
+
+    /// suppress output
+    #[argh(switch, short = 's')]
+    silent: bool,
+
+    /// output format: json, yaml or xml (default json for XML input, xml
+    /// otherwise)
+    #[argh(option, short = 't')]
+    to: Option<Format>,
+
+    /// path to input file/folder or glob pattern (- for stdin)
+    #[argh(positional)]
+    input: PathBuf,
+
+    /// path to output file/folder (optional, - for stdout)
+    #[argh(positional)]
+    output: Option<PathBuf>,
+}
+
+fn convert(buffer: &[u8], from: Format, to: Format) -> Result<Vec<u8>> {
+    let document: Document = match from {
+        Format::Xml => xml::parse_document(buffer)?,
+        Format::Json => serde_json::from_slice(buffer)?,
+        Format::Yaml => serde_yaml::from_slice(buffer)?,
+    };
+
+    match to {
+        Format::Xml => xml::write_document(&document, &FormatOptions::default()),
+        Format::Json => {
+            let mut output = serde_json::to_vec_pretty(&document)?;
+            output.push(b'\n');
+
+            Ok(output)
+        }
+        Format::Yaml => Ok(serde_yaml::to_string(&document)?.into_bytes()),
+    }
+}
+
+fn convert_file(file: &Path, output_file: &Path, to: Format) -> Result<()> {
+    let buffer = Vec::read_from_input(file)?;
+
+    convert(&buffer, Format::from_path(file), to)?.write_to_output(output_file)
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    if cli.input.is_dir() || is_glob_pattern(&cli.input) {
+        let to = cli.to.unwrap_or(Format::Json);
+
+        let extensions: &[&str] = match to {
+            Format::Xml => &["json", "yaml", "yml"],
+            _ => &["xml"],
+        };
+
+        let mut input_path = cli.input.clone();
+        let mut files = Vec::new();
+
+        for extension in extensions {
+            let (path, extension_files) =
+                list_input_files(&cli.input, extension, cli.recursive, cli.follow_symlinks)?;
+
+            input_path = path;
+            files.extend(extension_files);
+        }
+
+        files.sort();
+        files.dedup();
+
+        let output_path = cli.output.clone().unwrap_or_else(|| input_path.clone());
+
+        if output_path.is_file() {
+            bail!(report::usage("Output path is a file"))
+        }
+
+        let mapper = OutputMapper::new(&input_path, &output_path, None)?;
+
+        check_unique_outputs(&files, |file| mapper.map(file, Some(to.extension())))?;
+
+        for file in files.iter() {
+            let result = mapper
+                .map(file, Some(to.extension()))
+                .and_then(|output_file| {
+                    convert_file(file, &output_file, to)?;
+
+                    if !cli.silent {
+                        println!("{} -> {}", file.display(), output_file.display());
+                    }
+
+                    Ok(())
+                });
+
+            summary.add(file.display(), &result);
+        }
+    } else {
+        let from = Format::from_path(&cli.input);
+        let to = match (cli.to, from) {
+            (Some(to), _) => to,
+            (None, Format::Xml) => Format::Json,
+            (None, _) => Format::Xml,
+        };
+
+        if to == from && !is_stdio(&cli.input) {
+            bail!(report::usage(format!(
+                "{} is already {}",
+                cli.input.display(),
+                to
+            )))
+        }
+
+        let output = match cli.output.clone() {
+            Some(output) => output,
+            None if is_stdio(&cli.input) => PathBuf::from("-"),
+            None => cli.input.with_extension(to.extension()),
+        };
+
+        convert_file(&cli.input, &output, to)?;
+
+        if !cli.silent && !is_stdio(&output) {
+            println!("{}", output.display());
+        }
+
+        summary.processed();
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
@@ -0,0 +1,204 @@
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    buffer::BufferExtension,
+    report::{self, Summary},
+    xml::{self, Element, FormatOptions},
+};
+
+const MOD_DESC_FILE_NAME: &str = "modDesc.xml";
+
+/// Attributes referencing another XML file when no --attribute is given
+const DEFAULT_ATTRIBUTES: [&str; 2] = ["filename", "xmlFilename"];
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Inline XML files referenced by filename/xmlFilename attributes into a
+/// single document
+pub struct Cmd {
+    /// attribute holding a referenced file, can be repeated (default
+    /// filename, xmlFilename)
+    #[argh(option, short = 'a')]
+    attribute: Vec<String>,
+
+    /// folder that references are relative to (default folder with
+    /// modDesc.xml above input)
+    #[argh(option, short = 'm')]
+    mod_dir: Option<PathBuf>,
+
+    /// game data folder used for $data references (not inlined if missing)
+    #[argh(option, short = 'd')]
+    data_dir: Option<PathBuf>,
+
+    /// path to input file
+    #[argh(positional)]
+    input: PathBuf,
+
+    /// path to output file (optional, default stdout)
+    #[argh(positional)]
+    output: Option<PathBuf>,
+}
+
+/// Closest folder above the file with a modDesc.xml, or the file folder
+fn find_mod_dir(file: &Path) -> PathBuf {
+    let parent = file.parent().unwrap_or(Path::new("."));
+
+    parent
+        .ancestors()
+        .find(|dir| dir.join(MOD_DESC_FILE_NAME).is_file())
+        .unwrap_or(parent)
+        .to_path_buf()
+}
+
+struct Flatten<'a> {
+    cli: &'a Cmd,
+    summary: &'a Summary,
+    mod_dir: PathBuf,
+    /// Canonical paths of the files being inlined, for cycle detection
+    stack: Vec<PathBuf>,
+}
+
+impl Flatten<'_> {
+    fn is_reference(&self, key: &str) -> bool {
+        match self.cli.attribute.is_empty() {
+            true => DEFAULT_ATTRIBUTES.contains(&key),
+            false => self.cli.attribute.iter().any(|name| name == key),
+        }
+    }
+
+    /// Path for display, relative to the mod folder when inside it
+    fn display(&self, file: &Path) -> String {
+        file.strip_prefix(&self.mod_dir)
+            .unwrap_or(file)
+            .display()
+            .to_string()
+            .replace('\\', "/")
+    }
+
+    /// File of a reference, None for non-XML files and unresolved `$` paths.
+    /// References are relative to the mod folder, the folder of the
+    /// referencing file is tried next.
+    fn resolve(&self, file: &Path, value: &str) -> Option<PathBuf> {
+        if !value.to_lowercase().ends_with(".xml") {
+            return None;
+        }
+
+        let value = value.replace('\\', "/");
+
+        if let Some(path) = value.strip_prefix("$data") {
+            let data_dir = self.cli.data_dir.as_ref()?;
+
+            return Some(data_dir.join(path.trim_start_matches('/')));
+        }
+
+        // $moddir$ etc. point outside the mod folder
+        if value.starts_with('$') {
+            return None;
+        }
+
+        let beside = file.parent().unwrap_or(Path::new("")).join(&value);
+
+        match self.mod_dir.join(&value) {
+            path if path.is_file() || !beside.is_file() => Some(path),
+            _ => Some(beside),
+        }
+    }
+
+    fn flatten_file(&mut self, file: &Path) -> Result<Element> {
+        let mut root = xml::parse(&Vec::read_from_file(file)?)?;
+
+        self.stack.push(file.canonicalize()?);
+        self.flatten_element(&mut root, file)?;
+        self.stack.pop();
+
+        Ok(root)
+    }
+
+    fn flatten_element(&mut self, element: &mut Element, file: &Path) -> Result<()> {
+        for child in element.children.iter_mut() {
+            self.flatten_element(child, file)?;
+        }
+
+        let references: Vec<(String, PathBuf)> = element
+            .attributes
+            .iter()
+            .filter(|(key, _)| self.is_reference(key))
+            .filter_map(|(key, value)| Some((key.clone(), self.resolve(file, value)?)))
+            .collect();
+
+        for (key, reference) in references {
+            let location = format!("{}:{}", self.display(file), element.line);
+
+            if !reference.is_file() {
+                eprintln!("{}: {} not found", location, self.display(&reference));
+                self.summary.skipped();
+                continue;
+            }
+
+            if self.stack.contains(&reference.canonicalize()?) {
+                eprintln!(
+                    "{}: {} includes itself, not inlined",
+                    location,
+                    self.display(&reference)
+                );
+                self.summary.skipped();
+                continue;
+            }
+
+            let mut root = match self.flatten_file(&reference) {
+                Ok(root) => root,
+                Err(e) => bail!("{}: {}", self.display(&reference), e),
+            };
+
+            // "--" is not allowed inside comments
+            let source = self.display(&reference).replace("--", "-");
+            root.comment = Some(format!(" {}: {} ", key, source));
+
+            element.children.push(root);
+            self.summary.processed();
+        }
+
+        Ok(())
+    }
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    if !cli.input.is_file() {
+        bail!(report::usage(format!(
+            "Input file not found: {}",
+            cli.input.display()
+        )))
+    }
+
+    let mod_dir = match &cli.mod_dir {
+        Some(dir) => dir.clone(),
+        None => find_mod_dir(&cli.input),
+    };
+
+    let mut flatten = Flatten {
+        cli: &cli,
+        summary: &summary,
+        mod_dir,
+        stack: Vec::new(),
+    };
+
+    let mut root = flatten.flatten_file(&cli.input)?;
+    root.comment = Some(format!(" Flattened from {} ", flatten.display(&cli.input)));
+
+    let output = cli.output.clone().unwrap_or_else(|| PathBuf::from("-"));
+
+    xml::write_xml(&root, &FormatOptions::default())?.write_to_output(output)?;
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}
@@ -1,15 +1,26 @@
 use std::{
-    fs::File,
-    io::BufWriter,
     path::{Path, PathBuf},
+    process::ExitCode,
     str::FromStr,
 };
 
 use anyhow::{Result, bail};
 use argh::FromArgs;
-use fs_lib::{buffer::BufferExtension, list_files_with_extension, path::PathExtension};
-use xml::ParserConfig;
-use xml::writer::EmitterConfig;
+use fs_lib::{
+    batch::Batch,
+    buffer::BufferExtension,
+    cache::Cache,
+    list_input_files,
+    path::{OutputMapper, check_unique_outputs, is_glob_pattern, is_stdio},
+    report::{self, Summary, Timings},
+    watch::watch_files,
+    xml::{Eol, FormatOptions, WrapAttributes, format_xml},
+    zip_input::ZipInput,
+};
+use rayon::{
+    ThreadPoolBuilder,
+    iter::{IntoParallelIterator, ParallelIterator},
+};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 enum Indent {
@@ -36,10 +47,22 @@ pub struct Cmd {
     #[argh(switch, short = 'r')]
     recursive: bool,
 
+    /// enter directory symlinks and junctions in recursive mode
+    #[argh(switch)]
+    follow_symlinks: bool,
+
     /// suppress output
     #[argh(switch, short = 's')]
     silent: bool,
 
+    /// set thread pool size when processing folders (0 = auto)
+    #[argh(option, default = "0")]
+    num_threads: u8,
+
+    /// print the duration of each stage
+    #[argh(switch)]
+    timings: bool,
+
     /// indent character (space,tab)
     #[argh(option, short = 'c', default = "Indent::Space")]
     indent_char: Indent,
@@ -52,11 +75,40 @@ pub struct Cmd {
     #[argh(switch, short = 'e')]
     disable_escape_characters: bool,
 
-    /// path to input file/folder
+    /// line endings: lf, crlf or auto to keep those of each file (default lf)
+    #[argh(option, default = "Eol::Lf")]
+    eol: Eol,
+
+    /// one attribute per line for start tags wider than a column ("120" or
+    /// "column:120") or with more attributes than a count ("count:3")
+    #[argh(option)]
+    wrap_attributes: Option<WrapAttributes>,
+
+    /// keep running and re-format files in input folder as they change
+    #[argh(switch, short = 'w')]
+    watch: bool,
+
+    /// process all files, even if unchanged since previous run
+    #[argh(switch, short = 'f')]
+    force: bool,
+
+    /// print planned input -> output mapping without processing files
+    #[argh(switch)]
+    dry_run: bool,
+
+    /// only list files that are not formatted, nothing is written
+    #[argh(switch)]
+    check: bool,
+
+    /// output file pattern, e.g. "{dir}/{stem}_formatted.{ext}"
+    #[argh(option)]
+    output_pattern: Option<String>,
+
+    /// path to input file/folder or glob pattern (- for stdin)
     #[argh(positional)]
     input: PathBuf,
 
-    /// path to output file/folder (optional)
+    /// path to output file/folder (optional, - for stdout)
     #[argh(positional)]
     output: Option<PathBuf>,
 }
@@ -70,124 +122,262 @@ fn create_indent_string(indent_type: &Indent, indent_size: u8) -> String {
     indent_char.to_string().repeat(indent_size as usize)
 }
 
-fn format_xml_file<P: AsRef<Path>>(
-    file: P,
-    output_file: P,
-    indent_char: &Indent,
-    indent_size: u8,
-    escape_characters: bool,
-) -> Result<()> {
-    let buffer: Vec<u8> = Vec::read_from_file(&file)?;
-    let input: &[u8] = &buffer;
-
-    let mut reader = ParserConfig::default()
-        .ignore_root_level_whitespace(true)
-        .ignore_comments(false)
-        .cdata_to_characters(false)
-        .coalesce_characters(false)
-        .create_reader(input);
-
-    let output = File::create(output_file)?;
-    let writer = BufWriter::new(output);
-
-    let mut config = EmitterConfig::new()
-        .perform_indent(true)
-        .indent_string(create_indent_string(&indent_char, indent_size))
-        .write_document_declaration(true);
-
-    config.perform_escaping = escape_characters;
-
-    let mut emitter = config.create_writer(writer);
-
-    loop {
-        let reader_event = reader.next()?;
-
-        match reader_event {
-            xml::reader::XmlEvent::EndDocument => break,
-            xml::reader::XmlEvent::StartElement {
-                name,
-                attributes,
-                namespace,
-            } => {
-                let event = xml::writer::XmlEvent::StartElement {
-                    name: name.borrow(),
-                    namespace: namespace.borrow(),
-                    attributes: attributes.iter().map(|attr| attr.borrow()).collect(),
-                };
-                emitter.write(event)?;
-            }
-            xml::reader::XmlEvent::Characters(text) => {
-                let event = xml::writer::XmlEvent::Characters(&text);
-                emitter.write(event)?;
-            }
-            xml::reader::XmlEvent::Comment(text) => {
-                let event = xml::writer::XmlEvent::Comment(&text);
-                emitter.write(event)?;
-            }
-            other => {
-                if let Some(writer_event) = other.as_writer_event() {
-                    emitter.write(writer_event)?;
-                }
+fn format_options(cli: &Cmd) -> FormatOptions {
+    FormatOptions {
+        indent: create_indent_string(&cli.indent_char, cli.indent_size),
+        escape: !cli.disable_escape_characters,
+        eol: cli.eol,
+        wrap_attributes: cli.wrap_attributes,
+    }
+}
+
+fn format_xml_file<P: AsRef<Path>>(file: P, output_file: P, options: &FormatOptions) -> Result<()> {
+    let buffer: Vec<u8> = Vec::read_from_input(&file)?;
+    let output = format_xml(&buffer, options)?;
+
+    output.write_to_output(&output_file)
+}
+
+/// Formats in memory and compares with the file content
+fn is_formatted<P: AsRef<Path>>(file: P, options: &FormatOptions) -> Result<bool> {
+    let buffer: Vec<u8> = Vec::read_from_input(&file)?;
+
+    Ok(format_xml(&buffer, options)? == buffer)
+}
+
+fn check(cli: &Cmd, summary: &Summary) -> Result<()> {
+    let files = match cli.input.is_dir() || is_glob_pattern(&cli.input) {
+        true => list_input_files(&cli.input, r"xml", cli.recursive, cli.follow_symlinks)?.1,
+        false => vec![cli.input.clone()],
+    };
+
+    ThreadPoolBuilder::new()
+        .num_threads(cli.num_threads.into())
+        .build_global()
+        .unwrap();
+
+    let options = format_options(cli);
+
+    let results: Vec<_> = files
+        .into_par_iter()
+        .map(|file| {
+            let result = is_formatted(&file, &options);
+            (file, result)
+        })
+        .collect();
+
+    for (file, result) in results {
+        match result {
+            Ok(true) => summary.processed(),
+            Ok(false) => {
+                println!("{}: not formatted", file.display());
+                summary.failed();
             }
+            Err(e) => summary.error(file.display(), &e),
         }
     }
 
     Ok(())
 }
 
-fn main() -> Result<()> {
-    let cli: Cmd = argh::from_env();
+/// Formats a single file input, returns the lines to print
+fn process_file_input(cli: &Cmd) -> Result<Vec<String>> {
+    let output: PathBuf = match (cli.output.clone(), &cli.output_pattern) {
+        (Some(output), _) => output.components().collect(),
+        (None, Some(_)) if !is_stdio(&cli.input) => {
+            OutputMapper::beside(&cli.input, cli.output_pattern.clone())?.map(&cli.input, None)?
+        }
+        (None, _) => cli.input.components().collect(),
+    };
+
+    if cli.dry_run {
+        return Ok(vec![format!(
+            "{} -> {}",
+            cli.input.display(),
+            output.display()
+        )]);
+    }
+
+    format_xml_file(&cli.input, &output, &format_options(cli))?;
+
+    match cli.silent || is_stdio(&output) {
+        true => Ok(Vec::new()),
+        false => Ok(vec![output.display().to_string()]),
+    }
+}
+
+/// Runs one `--jobs-from` job, jobs take a single file
+fn process_job(cli: Cmd) -> Result<Vec<String>> {
+    if cli.input.is_dir() || is_glob_pattern(&cli.input) || cli.watch || cli.check {
+        bail!("Job input must be a file, watch and check mode aren't supported")
+    }
+
+    process_file_input(&cli)
+}
+
+fn run() -> Result<Summary> {
+    if let Some(batch) = Batch::from_env()? {
+        return batch.run(process_job);
+    }
+
+    let mut cli: Cmd = fs_lib::config::from_env();
+    let mut timings = Timings::new(cli.timings);
+    let zip_input = ZipInput::open(&mut cli.input, &mut cli.output)?;
+
+    if zip_input.is_some() {
+        timings.stage("extract zip");
+    }
+
+    let summary = process(cli, &mut timings)?;
+    timings.stage("process");
+
+    if let Some(zip_input) = zip_input {
+        zip_input.finish()?;
+        timings.stage("repack zip");
+    }
+
+    timings.print();
+
+    Ok(summary)
+}
+
+/// Processes a file, folder or glob input, zip inputs are extracted folders
+fn process(cli: Cmd, timings: &mut Timings) -> Result<Summary> {
+    let summary = Summary::default();
+
+    if cli.watch && !cli.input.is_dir() {
+        bail!(report::usage("Watch mode requires a folder input"))
+    }
+
+    if cli.watch && cli.dry_run {
+        bail!(report::usage("Watch mode can't be combined with dry run"))
+    }
+
+    if cli.check {
+        if cli.watch || cli.output.is_some() {
+            bail!(report::usage(
+                "Check mode can't be combined with watch mode or an output path"
+            ))
+        }
+
+        check(&cli, &summary)?;
+
+        return Ok(summary);
+    }
+
+    if cli.input.is_dir() || is_glob_pattern(&cli.input) {
+        let (input_path, files) =
+            list_input_files(&cli.input, r"xml", cli.recursive, cli.follow_symlinks)?;
+        timings.stage("list files");
 
-    if cli.input.is_dir() {
-        let output_path = cli.output.unwrap_or_else(|| cli.input.clone());
+        let output_path = cli.output.clone().unwrap_or_else(|| input_path.clone());
 
         if output_path.is_file() {
-            bail!("Output path is a file")
+            bail!(report::usage("Output path is a file"))
         }
 
-        let files = list_files_with_extension(&cli.input, r"xml", cli.recursive)?;
+        let mapper = OutputMapper::new(&input_path, &output_path, cli.output_pattern.clone())?;
+        let get_output_file = |file: &PathBuf| mapper.map(file, None);
+
+        check_unique_outputs(&files, get_output_file)?;
+
+        ThreadPoolBuilder::new()
+            .num_threads(cli.num_threads.into())
+            .build_global()
+            .unwrap();
+
+        let options = format_options(&cli);
+
+        // Returns the lines to print, parallel runs print them in input order
+        let process_file = |file: &PathBuf, output_file: &PathBuf| -> Result<Vec<String>> {
+            format_xml_file(file, output_file, &options)?;
+
+            if cli.silent {
+                return Ok(Vec::new());
+            }
+
+            match output_file != file {
+                true => Ok(vec![format!(
+                    "{} -> {}",
+                    file.display(),
+                    output_file.display()
+                )]),
+                false => Ok(vec![file.display().to_string()]),
+            }
+        };
+
+        let cache_options = format!("{:?}", options);
+        let cache = Cache::load(&output_path, "fs-xml-format", &cache_options, cli.force);
+
+        let process_entry = |file: &PathBuf| -> Result<Vec<String>> {
+            let output_file = get_output_file(file)?;
+
+            if cli.dry_run {
+                summary.processed();
+                return Ok(vec![format!(
+                    "{} -> {}",
+                    file.display(),
+                    output_file.display()
+                )]);
+            }
+
+            if cache.is_fresh(file, &output_file)? {
+                summary.skipped();
+                return Ok(Vec::new());
+            }
+
+            let output = process_file(file, &output_file)?;
+            cache.update(file, &output_file)?;
+            summary.processed();
+
+            Ok(output)
+        };
 
-        for file in files.iter() {
-            let output_file: PathBuf = file
-                .convert_relative_path(&cli.input, &output_path)?
-                .components()
-                .collect();
+        let outputs: Vec<_> = files
+            .into_par_iter()
+            .map(|file| {
+                let output = process_entry(&file);
+                (file.display().to_string(), output)
+            })
+            .collect();
+
+        summary.print_outputs(outputs);
+
+        if !cli.dry_run {
+            cache.save()?;
+        }
 
+        if cli.watch {
             if !cli.silent {
-                if output_file != *file {
-                    println!("{} -> {}", file.display(), output_file.display());
-                } else {
-                    println!("{}", file.display());
-                }
+                println!("Watching {} for changes", input_path.display());
             }
 
-            format_xml_file(
-                &file,
-                &&output_file,
-                &cli.indent_char,
-                cli.indent_size,
-                !cli.disable_escape_characters,
+            watch_files(
+                &input_path,
+                r"xml",
+                cli.recursive,
+                cli.follow_symlinks,
+                Some(&output_path),
+                |file| {
+                    for line in process_file(file, &get_output_file(file)?)? {
+                        println!("{}", line);
+                    }
+
+                    Ok(())
+                },
             )?;
         }
     } else {
-        let output: PathBuf = cli
-            .output
-            .unwrap_or(cli.input.clone())
-            .components()
-            .collect();
-
-        format_xml_file(
-            &cli.input,
-            &output,
-            &cli.indent_char,
-            cli.indent_size,
-            !cli.disable_escape_characters,
-        )?;
-
-        if !cli.silent {
-            println!("{}", output.display());
+        for line in process_file_input(&cli)? {
+            println!("{}", line);
         }
+
+        summary.processed();
     }
 
-    Ok(())
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
 }
@@ -0,0 +1,439 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use fs_lib::{
+    buffer::BufferExtension,
+    list_input_files,
+    report::{self, Summary},
+    xml::{self, Element},
+};
+
+const MOD_DESC_FILE_NAME: &str = "modDesc.xml";
+
+/// Map config elements pointing to a list of objects with unique ids
+const ID_LISTS: [(&str, &str); 2] = [("items", "item"), ("placeables", "placeable")];
+
+/// Smallest field polygon area in square meters that isn't reported
+const MIN_FIELD_AREA: f64 = 1.0;
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Check XML files are well-formed, or validate a map mod with --map
+pub struct Cmd {
+    /// recursive mode if folder input
+    #[argh(switch, short = 'r')]
+    recursive: bool,
+
+    /// enter directory symlinks and junctions in recursive mode
+    #[argh(switch)]
+    follow_symlinks: bool,
+
+    /// only list files with issues
+    #[argh(switch, short = 'q')]
+    quiet: bool,
+
+    /// input is a map mod folder: check unique item/placeable ids, field
+    /// polygons and i3d node references of the map config
+    #[argh(switch)]
+    map: bool,
+
+    /// path to input file/folder or glob pattern, mod folder with --map
+    #[argh(positional)]
+    input: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Error,
+    Warning,
+}
+
+#[derive(Debug)]
+struct Issue {
+    level: Level,
+    line: u64,
+    message: String,
+}
+
+#[derive(Default)]
+struct Lint {
+    issues: Vec<Issue>,
+}
+
+impl Lint {
+    fn error(&mut self, line: u64, message: String) {
+        self.issues.push(Issue {
+            level: Level::Error,
+            line,
+            message,
+        });
+    }
+
+    fn warning(&mut self, line: u64, message: String) {
+        self.issues.push(Issue {
+            level: Level::Warning,
+            line,
+            message,
+        });
+    }
+}
+
+/// Issues of one checked file
+struct Report {
+    file: PathBuf,
+    issues: Vec<Issue>,
+}
+
+impl Report {
+    fn new(file: &Path, mut lint: Lint) -> Self {
+        lint.issues.sort_by_key(|issue| (issue.level, issue.line));
+
+        Self {
+            file: file.to_path_buf(),
+            issues: lint.issues,
+        }
+    }
+
+    fn unreadable(file: &Path, error: anyhow::Error) -> Self {
+        let mut lint = Lint::default();
+        lint.error(0, format!("unable to parse: {}", error));
+
+        Self::new(file, lint)
+    }
+}
+
+/// File referenced by an attribute or child element, None for `$data` paths
+fn reference(element: &Element, name: &str) -> Option<String> {
+    let value = element
+        .attr(name)
+        .or_else(|| element.child(name).and_then(|child| child.text()))?;
+
+    match value.is_empty() || value.starts_with('$') {
+        true => None,
+        false => Some(value.replace('\\', "/")),
+    }
+}
+
+/// Map configs are either relative to the mod folder or the config folder
+fn resolve(mod_dir: &Path, file: &Path, value: &str) -> PathBuf {
+    let path = mod_dir.join(value);
+
+    match path.is_file() {
+        true => path,
+        false => file.parent().unwrap_or(mod_dir).join(value),
+    }
+}
+
+fn check_ids(lint: &mut Lint, root: &Element, name: &str) {
+    let mut ids: BTreeMap<&str, u64> = BTreeMap::new();
+
+    for element in root.descendants().into_iter().filter(|e| e.name == name) {
+        let Some(id) = element.attr("id") else {
+            continue;
+        };
+
+        if let Some(first_line) = ids.insert(id, element.line) {
+            lint.error(
+                element.line,
+                format!(
+                    "duplicate {} id {} (first on line {})",
+                    name, id, first_line
+                ),
+            );
+        }
+    }
+}
+
+fn check_object_files(lint: &mut Lint, root: &Element, mod_dir: &Path) {
+    for element in root.descendants() {
+        if let Some(filename) = reference(element, "filename")
+            && !mod_dir.join(&filename).is_file()
+        {
+            lint.error(element.line, format!("missing file: {}", filename));
+        }
+    }
+}
+
+fn translation(node: &Element) -> (f64, f64) {
+    let values: Vec<f64> = node
+        .attr("translation")
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter_map(|value| value.parse().ok())
+        .collect();
+
+    match values[..] {
+        [x, _, z] => (x, z),
+        _ => (0.0, 0.0),
+    }
+}
+
+/// Area of the polygon on the ground plane (shoelace formula)
+fn polygon_area(points: &[(f64, f64)]) -> f64 {
+    let sum: f64 = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|((x1, z1), (x2, z2))| x1 * z2 - x2 * z1)
+        .sum();
+
+    sum.abs() / 2.0
+}
+
+/// Fields are children of a `fields` transform group, each with the corner
+/// points as children of `polygonPoints`
+fn check_fields(lint: &mut Lint, scene: &Element) {
+    let fields = scene
+        .descendants()
+        .into_iter()
+        .filter(|node| node.attr("name") == Some("fields"))
+        .flat_map(|fields| fields.children.iter());
+
+    for field in fields {
+        let name = field.attr("name").unwrap_or("?");
+
+        let Some(polygon) = field
+            .children
+            .iter()
+            .find(|node| node.attr("name") == Some("polygonPoints"))
+        else {
+            lint.error(field.line, format!("field {} has no polygonPoints", name));
+            continue;
+        };
+
+        let points: Vec<(f64, f64)> = polygon.children.iter().map(translation).collect();
+
+        if points.len() < 3 {
+            lint.error(
+                polygon.line,
+                format!("field {} has {} polygon points", name, points.len()),
+            );
+            continue;
+        }
+
+        let area = polygon_area(&points);
+
+        if area < MIN_FIELD_AREA {
+            lint.error(
+                polygon.line,
+                format!("field {} polygon has no area ({:.2} m²)", name, area),
+            );
+        }
+
+        for (index, pair) in points.windows(2).enumerate() {
+            if pair[0] == pair[1] {
+                lint.warning(
+                    polygon.children[index + 1].line,
+                    format!("field {} has duplicate polygon point {}", name, index + 2),
+                );
+            }
+        }
+    }
+}
+
+/// Giants index path, e.g. `0>2|1`: scene root index, then child indices
+fn resolve_index<'a>(scene: &'a Element, index: &str) -> Option<&'a Element> {
+    let (root, path) = index.split_once('>')?;
+    let mut node = scene.children.get(root.parse::<usize>().ok()?)?;
+
+    for child in path.split('|').filter(|child| !child.is_empty()) {
+        node = node.children.get(child.parse::<usize>().ok()?)?;
+    }
+
+    Some(node)
+}
+
+fn is_index_path(value: &str) -> bool {
+    value.contains('>')
+        && value
+            .chars()
+            .all(|c| c.is_ascii_digit() || "|>".contains(c))
+}
+
+fn check_node_references(lint: &mut Lint, config: &Element, scene: &Element) {
+    for element in config.descendants() {
+        for (key, value) in element.attributes.iter() {
+            if is_index_path(value) && resolve_index(scene, value).is_none() {
+                lint.error(
+                    element.line,
+                    format!("{}=\"{}\" not found in map i3d", key, value),
+                );
+            }
+        }
+    }
+}
+
+/// Node references of the map config are reported to config_lint
+fn validate_i3d(file: &Path, config: &Element, config_lint: &mut Lint) -> Report {
+    let root = match Vec::read_from_file(file).and_then(|buffer| xml::parse(&buffer)) {
+        Ok(root) => root,
+        Err(e) => return Report::unreadable(file, e),
+    };
+
+    let mut lint = Lint::default();
+
+    match root.child("Scene") {
+        Some(scene) => {
+            check_fields(&mut lint, scene);
+            check_node_references(config_lint, config, scene);
+        }
+        None => lint.error(root.line, "i3d has no <Scene>".to_string()),
+    }
+
+    Report::new(file, lint)
+}
+
+fn validate_map_config(mod_dir: &Path, file: &Path) -> Vec<Report> {
+    let root = match Vec::read_from_file(file).and_then(|buffer| xml::parse(&buffer)) {
+        Ok(root) => root,
+        Err(e) => return vec![Report::unreadable(file, e)],
+    };
+
+    let mut lint = Lint::default();
+    let mut reports = Vec::new();
+
+    for (list, name) in ID_LISTS {
+        let Some(element) = root.child(list) else {
+            continue;
+        };
+
+        let Some(filename) = reference(element, "filename") else {
+            continue;
+        };
+
+        let list_file = resolve(mod_dir, file, &filename);
+
+        let list_root = match Vec::read_from_file(&list_file).and_then(|b| xml::parse(&b)) {
+            Ok(list_root) => list_root,
+            Err(e) => {
+                lint.error(element.line, format!("unable to read {}: {}", filename, e));
+                continue;
+            }
+        };
+
+        let mut list_lint = Lint::default();
+        check_ids(&mut list_lint, &list_root, name);
+        check_object_files(&mut list_lint, &list_root, mod_dir);
+
+        reports.push(Report::new(&list_file, list_lint));
+    }
+
+    match reference(&root, "filename") {
+        Some(i3d) => reports.push(validate_i3d(
+            &resolve(mod_dir, file, &i3d),
+            &root,
+            &mut lint,
+        )),
+        None => lint.warning(root.line, "map has no i3d filename".to_string()),
+    }
+
+    reports.insert(0, Report::new(file, lint));
+    reports
+}
+
+fn validate_map(mod_dir: &Path) -> Result<Vec<Report>> {
+    let mod_desc = xml::parse(&Vec::read_from_file(mod_dir.join(MOD_DESC_FILE_NAME))?)?;
+
+    let configs: Vec<String> = mod_desc
+        .children("maps")
+        .flat_map(|maps| maps.children("map"))
+        .filter_map(|map| reference(map, "configFilename"))
+        .collect();
+
+    if configs.is_empty() {
+        bail!(report::usage(format!(
+            "No <maps> entries in {}",
+            mod_dir.join(MOD_DESC_FILE_NAME).display()
+        )))
+    }
+
+    Ok(configs
+        .iter()
+        .flat_map(|config| validate_map_config(mod_dir, &mod_dir.join(config)))
+        .collect())
+}
+
+fn validate_files(cli: &Cmd) -> Result<Vec<Report>> {
+    let mut files = match cli.input.is_file() {
+        true => vec![cli.input.clone()],
+        false => list_input_files(&cli.input, "xml", cli.recursive, cli.follow_symlinks)?.1,
+    };
+    files.sort();
+
+    if files.is_empty() {
+        bail!(report::usage(format!(
+            "No .xml files found in {}",
+            cli.input.display()
+        )))
+    }
+
+    Ok(files
+        .iter()
+        .map(
+            |file| match Vec::read_from_file(file).and_then(|b| xml::parse(&b)) {
+                Ok(_) => Report::new(file, Lint::default()),
+                Err(e) => Report::unreadable(file, e),
+            },
+        )
+        .collect())
+}
+
+fn print_issues(name: &str, issues: &[Issue]) {
+    let errors = issues.iter().filter(|i| i.level == Level::Error).count();
+    let warnings = issues.len() - errors;
+
+    if issues.is_empty() {
+        println!("{}: ok", name);
+        return;
+    }
+
+    println!("{}: {} errors, {} warnings", name, errors, warnings);
+
+    for issue in issues {
+        let level = match issue.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        };
+
+        println!("  {:<8}{:>5}  {}", level, issue.line, issue.message);
+    }
+}
+
+fn run() -> Result<Summary> {
+    let cli: Cmd = fs_lib::config::from_env();
+    let summary = Summary::default();
+
+    let reports = match cli.map {
+        true => {
+            let mod_dir = match cli.input.is_file() {
+                true => cli.input.parent().unwrap_or(Path::new("")).to_path_buf(),
+                false => cli.input.clone(),
+            };
+
+            validate_map(&mod_dir)?
+        }
+        false => validate_files(&cli)?,
+    };
+
+    for report in reports {
+        let has_errors = report.issues.iter().any(|i| i.level == Level::Error);
+
+        if !cli.quiet || !report.issues.is_empty() {
+            print_issues(&report.file.display().to_string(), &report.issues);
+        }
+
+        match has_errors {
+            true => summary.failed(),
+            false => summary.processed(),
+        }
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    report::finish(run())
+}